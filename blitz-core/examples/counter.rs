@@ -89,12 +89,12 @@ impl Driver for Counter {
 #[tokio::main]
 async fn main() {
     render(
-        |rdom, _| {
+        |rdom, _, _| {
             let mut rdom = rdom.write().unwrap();
             let root = rdom.root_id();
             Counter::create(rdom.get_mut(root).unwrap())
         },
-        Config,
+        Config::default(),
     )
     .await;
 }
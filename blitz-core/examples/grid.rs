@@ -165,12 +165,12 @@ impl Driver for Test {
 #[tokio::main]
 async fn main() {
     render(
-        |rdom, _| {
+        |rdom, _, _| {
             let mut rdom = rdom.write().unwrap();
             let root = rdom.root_id();
             Test::create(rdom.get_mut(root).unwrap())
         },
-        Config,
+        Config::default(),
     )
     .await;
 }
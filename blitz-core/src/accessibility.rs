@@ -0,0 +1,486 @@
+//! The accessibility tree that screen readers like VoiceOver and NVDA read, built with accesskit.
+//!
+//! Every element and text node of the dom becomes an accesskit node with a role from its tag, a name from its text or its `aria-label`, `alt` or `title` attribute and its bounds in the window. After each frame only the subtrees around the nodes that changed are built again, and only the accesskit nodes that differ from the last update are pushed to the platform adapter of the window. Nodes that moved without changing are sent with their new bounds. Elements with `display: none` or `aria-hidden="true"` are left out with their children. Nodes with `visibility: hidden` are left out too, but their children are walked because they can set `visibility: visible` again.
+//!
+//! Focus goes both ways: the focused element is sent with every update, and a screen reader asking to focus a node moves the focus of the document through [`WindowHandle::focus`].
+
+use std::num::NonZeroU128;
+use std::sync::{Arc, Mutex};
+
+use accesskit::{
+    Action, ActionHandler, ActionRequest, Affine, CheckedState, Node, NodeId as AccessNodeId, Rect,
+    Role, Tree, TreeUpdate,
+};
+use dioxus_native_core::prelude::*;
+use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
+use rustc_hash::{FxHashMap, FxHashSet};
+use taffy::prelude::*;
+use tao::window::Window;
+
+use crate::application::DirtyNodes;
+use crate::checkbox::{is_checked, CheckKind, Checkable};
+use crate::focus::Focus;
+use crate::layout::TaffyLayout;
+use crate::render::get_abs_pos;
+use crate::util::attribute;
+use crate::visibility::is_visible;
+use crate::window::WindowHandle;
+
+/// Converts a dom node id to an accesskit node id. accesskit ids can't be zero, so they are shifted by one.
+fn access_id(id: NodeId) -> AccessNodeId {
+    AccessNodeId(NonZeroU128::new(id.inner() as u128 + 1).unwrap())
+}
+
+/// Converts an accesskit node id back to the dom node id it was made from.
+fn dom_id(id: AccessNodeId) -> Option<NodeId> {
+    NodeId::from_inner((id.0.get() - 1) as u64)
+}
+
+/// The accessibility tree that was last sent to the platform. It is shared with the adapter, which reads the whole tree from it when an assistive technology connects.
+#[derive(Clone, Default)]
+pub(crate) struct AccessibilityTree(Arc<Mutex<SentTree>>);
+
+#[derive(Default)]
+struct SentTree {
+    nodes: FxHashMap<AccessNodeId, Arc<Node>>,
+    root: Option<AccessNodeId>,
+    focus: Option<AccessNodeId>,
+    scale_factor: f64,
+}
+
+impl AccessibilityTree {
+    /// Builds the parts of the tree that changed since the last update. `dirty` are the nodes that changed in the frame, and `scale_factor` converts the layout in css pixels to the device pixels of the window. Returns `None` if nothing changed.
+    pub(crate) fn update(
+        &self,
+        rdom: &RealDom,
+        taffy: &Taffy,
+        viewport_size: &Size<u32>,
+        scale_factor: f64,
+        focused: Option<NodeId>,
+        dirty: &DirtyNodes,
+    ) -> Option<TreeUpdate> {
+        let mut sent = self.0.lock().unwrap();
+        let focus = focused
+            .filter(|focused| rdom.get(*focused).is_some())
+            .map(access_id);
+        let root = access_id(rdom.root_id());
+
+        let subtrees = match dirty {
+            DirtyNodes::Some(dirty)
+                if sent.root == Some(root) && sent.scale_factor == scale_factor =>
+            {
+                subtree_roots(rdom, &sent.nodes, dirty)
+            }
+            _ => None,
+        };
+        let mut previous = FxHashMap::default();
+        let mut built = Vec::new();
+        let rebuilt = subtrees.and_then(|roots| {
+            roots.into_iter().try_for_each(|id| {
+                take_subtree(&mut sent.nodes, access_id(id), &mut previous);
+                let added = add_node(rdom.get(id)?, taffy, viewport_size, &mut built);
+                // the children of the parent change if the root of the subtree was hidden
+                (added == [access_id(id)]).then_some(())
+            })
+        });
+        let candidates: FxHashSet<AccessNodeId> = match rebuilt {
+            Some(()) => {
+                let mut candidates: FxHashSet<_> = built.iter().map(|(id, _)| *id).collect();
+                sent.nodes.extend(built);
+                // nodes outside the rebuilt subtrees move when the layout around them changes
+                for (id, node) in sent.nodes.iter_mut() {
+                    let Some(bounds) = dom_id(*id)
+                        .and_then(|id| rdom.get(id))
+                        .and_then(|node| bounds(node, taffy, viewport_size))
+                    else {
+                        continue;
+                    };
+                    if node.bounds != Some(bounds) {
+                        previous.entry(*id).or_insert_with(|| node.clone());
+                        Arc::make_mut(node).bounds = Some(bounds);
+                        candidates.insert(*id);
+                    }
+                }
+                candidates
+            }
+            None => {
+                previous.extend(std::mem::take(&mut sent.nodes));
+                let nodes = build_nodes(rdom, taffy, viewport_size, scale_factor);
+                let candidates = nodes.iter().map(|(id, _)| *id).collect();
+                sent.nodes = nodes.into_iter().collect();
+                candidates
+            }
+        };
+        sent.root = Some(root);
+        sent.scale_factor = scale_factor;
+
+        let nodes: Vec<_> = candidates
+            .into_iter()
+            .filter_map(|id| {
+                let node = sent.nodes.get(&id)?;
+                (previous.get(&id) != Some(node)).then(|| (id, node.clone()))
+            })
+            .collect();
+        if nodes.is_empty() && focus == sent.focus {
+            return None;
+        }
+        sent.focus = focus;
+        Some(TreeUpdate {
+            nodes,
+            tree: None,
+            focus,
+        })
+    }
+
+    /// The whole tree as it was last sent.
+    fn full_update(&self) -> TreeUpdate {
+        let sent = self.0.lock().unwrap();
+        TreeUpdate {
+            nodes: sent
+                .nodes
+                .iter()
+                .map(|(id, node)| (*id, node.clone()))
+                .collect(),
+            tree: sent.root.map(Tree::new),
+            focus: sent.focus,
+        }
+    }
+}
+
+/// Finds the subtrees that are built again for the dirty nodes: the closest ancestor of each node that is in the tree, so changes to the children of a node and nodes that become visible are picked up. Subtrees inside another one are left out. Returns `None` if the whole tree has to be built again.
+fn subtree_roots(
+    rdom: &RealDom,
+    nodes: &FxHashMap<AccessNodeId, Arc<Node>>,
+    dirty: &FxHashSet<NodeId>,
+) -> Option<Vec<NodeId>> {
+    let in_tree = |node: &NodeRef| nodes.contains_key(&access_id(node.id()));
+    let mut roots = FxHashSet::default();
+    for id in dirty {
+        // removed nodes are dropped by building their old parent again, which is dirty too
+        let Some(node) = rdom.get(*id) else {
+            continue;
+        };
+        let mut current = node.parent()?;
+        while !in_tree(&current) {
+            current = current.parent()?;
+        }
+        if current.id() == rdom.root_id() {
+            return None;
+        }
+        roots.insert(current.id());
+    }
+    Some(
+        roots
+            .iter()
+            .copied()
+            .filter(|id| {
+                let mut current = rdom.get(*id).and_then(|node| node.parent());
+                while let Some(node) = current {
+                    if roots.contains(&node.id()) {
+                        return false;
+                    }
+                    current = node.parent();
+                }
+                true
+            })
+            .collect(),
+    )
+}
+
+/// Moves a node and everything under it from the tree to `removed`.
+fn take_subtree(
+    nodes: &mut FxHashMap<AccessNodeId, Arc<Node>>,
+    id: AccessNodeId,
+    removed: &mut FxHashMap<AccessNodeId, Arc<Node>>,
+) {
+    let mut stack = vec![id];
+    while let Some(id) = stack.pop() {
+        if let Some(node) = nodes.remove(&id) {
+            stack.extend(node.children.iter().copied());
+            removed.insert(id, node);
+        }
+    }
+}
+
+/// Builds the accesskit nodes of the whole document.
+fn build_nodes(
+    rdom: &RealDom,
+    taffy: &Taffy,
+    viewport_size: &Size<u32>,
+    scale_factor: f64,
+) -> Vec<(AccessNodeId, Arc<Node>)> {
+    let root = rdom.get(rdom.root_id()).unwrap();
+    let mut nodes = Vec::new();
+    let children = add_children(root, taffy, viewport_size, &mut nodes);
+    nodes.push((
+        access_id(root.id()),
+        Arc::new(Node {
+            role: Role::Window,
+            transform: Some(Box::new(Affine::scale(scale_factor))),
+            children,
+            ..Default::default()
+        }),
+    ));
+    nodes
+}
+
+/// The bounds of a node in the css pixels of the window.
+fn bounds(node: NodeRef, taffy: &Taffy, viewport_size: &Size<u32>) -> Option<Rect> {
+    let layout = taffy.layout(node.get::<TaffyLayout>()?.node?).ok()?;
+    let pos = get_abs_pos(*layout, taffy, node, viewport_size);
+    Some(Rect::new(
+        pos.x,
+        pos.y,
+        pos.x + layout.size.width as f64,
+        pos.y + layout.size.height as f64,
+    ))
+}
+
+/// Adds the accesskit nodes of a node and its children. Returns the ids that go in the children of the parent: the id of the node, the ids of its children if it has `visibility: hidden`, or nothing if it is left out with its children.
+fn add_node(
+    node: NodeRef,
+    taffy: &Taffy,
+    viewport_size: &Size<u32>,
+    nodes: &mut Vec<(AccessNodeId, Arc<Node>)>,
+) -> Vec<AccessNodeId> {
+    let Some(taffy_node) = node.get::<TaffyLayout>().and_then(|layout| layout.node) else {
+        return Vec::new();
+    };
+    if taffy
+        .style(taffy_node)
+        .ok()
+        .is_none_or(|style| style.display == Display::None)
+        || attribute(node, "aria-hidden").as_deref() == Some("true")
+    {
+        return Vec::new();
+    }
+    if !is_visible(node) {
+        // descendants can be made visible again, so they take the place of the hidden node
+        return add_children(node, taffy, viewport_size, nodes);
+    }
+    add_visible_node(node, taffy, viewport_size, nodes)
+        .into_iter()
+        .collect()
+}
+
+/// Adds the accesskit nodes of the children of a node and returns the ids that go in its children.
+fn add_children(
+    node: NodeRef,
+    taffy: &Taffy,
+    viewport_size: &Size<u32>,
+    nodes: &mut Vec<(AccessNodeId, Arc<Node>)>,
+) -> Vec<AccessNodeId> {
+    node.children()
+        .into_iter()
+        .flat_map(|child| add_node(child, taffy, viewport_size, nodes))
+        .collect()
+}
+
+/// Adds the accesskit node of a node that is painted, and its children.
+fn add_visible_node(
+    node: NodeRef,
+    taffy: &Taffy,
+    viewport_size: &Size<u32>,
+    nodes: &mut Vec<(AccessNodeId, Arc<Node>)>,
+) -> Option<AccessNodeId> {
+    let bounds = bounds(node, taffy, viewport_size)?;
+
+    let access_node = match &*node.node_type() {
+        NodeType::Text(text) => Node {
+            role: Role::StaticText,
+            bounds: Some(bounds),
+            name: Some(text.text.trim().into()),
+            ..Default::default()
+        },
+        NodeType::Element(element) => {
+            let role = role(node, &element.tag);
+            let name = attribute(node, "aria-label")
+                .or_else(|| attribute(node, "alt"))
+                .or_else(|| attribute(node, "title"))
+                .or_else(|| {
+                    // controls and headings are named by the text inside them
+                    matches!(
+                        role,
+                        Role::Button | Role::Link | Role::Heading | Role::Label | Role::Cell
+                    )
+                    .then(|| text_content(node))
+                });
+            let children = add_children(node, taffy, viewport_size, nodes);
+            Node {
+                role,
+                bounds: Some(bounds),
+                children,
+                name: name.map(Into::into),
+                value: (role == Role::TextField)
+                    .then(|| attribute(node, "value").unwrap_or_default().into()),
+                focusable: node.get::<Focus>().is_some_and(|focus| focus.focusable()),
+                checked_state: matches!(role, Role::CheckBox | Role::RadioButton).then(|| {
+                    if is_checked(node) {
+                        CheckedState::True
+                    } else {
+                        CheckedState::False
+                    }
+                }),
+                ..Default::default()
+            }
+        }
+        _ => return None,
+    };
+    let id = access_id(node.id());
+    nodes.push((id, Arc::new(access_node)));
+    Some(id)
+}
+
+/// The role of an element from its tag.
+fn role(node: NodeRef, tag: &str) -> Role {
+    match tag {
+        "button" => Role::Button,
+        "a" => Role::Link,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => Role::Heading,
+        "p" => Role::Paragraph,
+        "img" => Role::Image,
+        "ul" | "ol" => Role::List,
+        "li" => Role::ListItem,
+        "table" => Role::Table,
+        "tr" => Role::Row,
+        "td" => Role::Cell,
+        "th" => Role::ColumnHeader,
+        "nav" => Role::Navigation,
+        "main" => Role::Main,
+        "article" => Role::Article,
+        "form" => Role::Form,
+        "label" => Role::Label,
+        "dialog" => Role::Dialog,
+        "meter" => Role::Meter,
+        "textarea" => Role::TextField,
+        "input" => {
+            let kind = node.get::<Checkable>().and_then(|checkable| checkable.kind);
+            match kind {
+                Some(CheckKind::Checkbox) => Role::CheckBox,
+                Some(CheckKind::Radio) => Role::RadioButton,
+                None => match attribute(node, "type").as_deref().map(str::trim) {
+                    Some("button" | "submit" | "reset") => Role::Button,
+                    _ => Role::TextField,
+                },
+            }
+        }
+        _ => Role::GenericContainer,
+    }
+}
+
+/// Joins the text of the text nodes inside a node.
+fn text_content(node: NodeRef) -> String {
+    let mut text = String::new();
+    let mut stack = vec![node];
+    while let Some(node) = stack.pop() {
+        if let NodeType::Text(text_node) = &*node.node_type() {
+            if !text.is_empty() {
+                text.push(' ');
+            }
+            text.push_str(text_node.text.trim());
+        }
+        stack.extend(node.children().into_iter().rev());
+    }
+    text
+}
+
+/// Moves the focus of the document when a screen reader asks to focus a node.
+struct FocusHandler(Mutex<WindowHandle>);
+
+impl ActionHandler for FocusHandler {
+    fn do_action(&self, request: ActionRequest) {
+        if request.action != Action::Focus {
+            return;
+        }
+        if let Some(node) = dom_id(request.target) {
+            self.0.lock().unwrap().focus(node);
+        }
+    }
+}
+
+/// Connects the accessibility tree to the platform accessibility API of a window. Platforms without an adapter ignore the updates.
+pub(crate) struct AccessibilityAdapter {
+    #[cfg(target_os = "windows")]
+    adapter: accesskit_windows::SubclassingAdapter,
+    #[cfg(target_os = "macos")]
+    adapter: accesskit_macos::SubclassingAdapter,
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    adapter: Option<accesskit_unix::Adapter>,
+}
+
+impl AccessibilityAdapter {
+    /// Creates the adapter of a window. `tree` is read when an assistive technology first connects.
+    #[allow(unused_variables)]
+    pub(crate) fn new(window: &Window, tree: AccessibilityTree, handle: WindowHandle) -> Self {
+        let action_handler = Box::new(FocusHandler(Mutex::new(handle)));
+        let source = move || tree.full_update();
+        match window.raw_window_handle() {
+            #[cfg(target_os = "windows")]
+            RawWindowHandle::Win32(window) => Self {
+                adapter: accesskit_windows::SubclassingAdapter::new(
+                    accesskit_windows::HWND(window.hwnd as _),
+                    source,
+                    action_handler,
+                ),
+            },
+            #[cfg(target_os = "macos")]
+            RawWindowHandle::AppKit(window) => Self {
+                // the view lives as long as the window, which outlives the adapter
+                adapter: unsafe {
+                    accesskit_macos::SubclassingAdapter::new(window.ns_view, source, action_handler)
+                },
+            },
+            #[cfg(any(
+                target_os = "linux",
+                target_os = "dragonfly",
+                target_os = "freebsd",
+                target_os = "netbsd",
+                target_os = "openbsd"
+            ))]
+            _ => Self {
+                adapter: accesskit_unix::Adapter::new(
+                    String::new(),
+                    "blitz".into(),
+                    env!("CARGO_PKG_VERSION").into(),
+                    source,
+                    action_handler,
+                ),
+            },
+            #[cfg(any(target_os = "windows", target_os = "macos"))]
+            _ => unreachable!("tao only creates windows with the native handle of the platform"),
+            #[cfg(not(any(
+                target_os = "windows",
+                target_os = "macos",
+                target_os = "linux",
+                target_os = "dragonfly",
+                target_os = "freebsd",
+                target_os = "netbsd",
+                target_os = "openbsd"
+            )))]
+            _ => Self {},
+        }
+    }
+
+    /// Sends the changed nodes of the tree to the platform.
+    #[allow(unused_variables)]
+    pub(crate) fn update(&self, update: TreeUpdate) {
+        #[cfg(any(target_os = "windows", target_os = "macos"))]
+        self.adapter.update(update).raise();
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "dragonfly",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd"
+        ))]
+        if let Some(adapter) = &self.adapter {
+            adapter.update(update);
+        }
+    }
+}
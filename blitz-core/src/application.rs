@@ -1,32 +1,71 @@
-use quadtree_rs::area::AreaBuilder;
-use quadtree_rs::Quadtree;
 use rustc_hash::FxHashSet;
-use shipyard::Component;
+use std::num::NonZeroU32;
 use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockWriteGuard};
-use taffy::geometry::Point;
-use taffy::prelude::Layout;
-use tao::{dpi::PhysicalSize, event_loop::EventLoopProxy, window::Window};
+use std::time::Instant;
+use tao::{
+    dpi::{LogicalPosition, LogicalSize, PhysicalSize},
+    event::{Event, WindowEvent},
+    event_loop::EventLoopProxy,
+    window::Window,
+};
 use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::sync::oneshot;
 use vello::{
-    peniko::Color,
+    kurbo::{Affine, Rect},
+    peniko::{Color, Fill, Image, Mix},
     util::{RenderContext, RenderSurface},
-    RenderParams, Scene, SceneBuilder,
+    RenderParams, Scene, SceneBuilder, SceneFragment,
 };
 use vello::{Renderer as VelloRenderer, RendererOptions};
 
 use crate::{
+    accessibility::{AccessibilityAdapter, AccessibilityTree},
+    background::BackgroundImage,
+    checkbox::Checkable,
+    container::Container,
+    cursor::Cursor,
+    diagnostics::{style_warnings, StyleDiagnostics},
     events::{BlitzEventHandler, DomEvent},
     focus::{Focus, FocusState},
-    image::LoadedImage,
-    layout::TaffyLayout,
-    mouse::MouseEffected,
+    generated_content::{update_generated_content, ContentStyle},
+    hit_test::hit_test,
+    image::{LoadedImage, ObjectFit},
+    layout::{
+        remeasure_text, update_baseline_struts, update_intrinsic_sizes, update_text_wrapping,
+        ComputedLayout, TaffyLayout,
+    },
+    meter::Gauge,
+    mouse::{MouseEffected, PseudoClasses},
+    position::Positioning,
     prevent_default::PreventDefault,
-    render::render,
-    style::{BackgroundColor, Border, FontSize, ForgroundColor},
-    text::TextContext,
+    print::{page_ranges, PageBreak},
+    render::{get_abs_pos, render},
+    scroll::{
+        adjust_scroll_anchors, scroll_into_view, select_scroll_anchors, OverflowAnchor,
+        ScrollAlignment, ScrollAnimations, SmoothScroll,
+    },
+    stacking::{Opacity, ZIndex},
+    style::{
+        update_font_sizes, AccentColor, BackgroundClip, BackgroundColor, Border, BoxShadow, Filter,
+        FontFamily, FontSize, ForgroundColor, Outline, Overflow, RootFontSize, TextShadow,
+    },
+    table::{update_row_spans, update_table_layout, TableStyle},
+    text::{
+        text_style::{
+            LineHeight, TextAlign, TextDecoration, TextOverflow, TextSpacing, TextTransform,
+            WhiteSpace,
+        },
+        FontContext, FontFile, TextContext,
+    },
+    text_input::Editable,
+    transform::Transform,
+    util::ViewportSize,
+    visibility::Visibility,
+    window::{PageSize, WindowCommand, WindowHandle},
+    writing_mode::WritingMode,
     Redraw, TaoEvent,
 };
-use crate::{image::ImageContext, Driver};
+use crate::{image::ImageContext, Config, Driver, PausedInput};
 use dioxus_native_core::{prelude::*, FxDashSet};
 use taffy::{
     prelude::{AvailableSpace, Size},
@@ -41,35 +80,153 @@ pub struct ApplicationState {
     surface: RenderSurface,
     wgpu_renderer: VelloRenderer,
     event_handler: BlitzEventHandler,
-    quadtree: Quadtree<u64, NodeId>,
+    commands: UnboundedReceiver<WindowCommand>,
+    pending_captures: Vec<PendingCapture>,
+    debug_redraw_regions: bool,
+    /// The number of frames rendered, used to vary the color of the redraw regions.
+    frame_count: usize,
+    paused: bool,
+    paused_input: PausedInput,
+    resume_on_focus: bool,
+    /// Input received while paused with [`PausedInput::Queue`]. It is replayed when rendering resumes.
+    queued_input: Vec<TaoEvent<'static>>,
+    /// Where the IME candidate window was last placed.
+    ime_position: Option<LogicalPosition<f64>>,
+    /// The number of device pixels per css pixel of the monitor the window is on.
+    scale_factor: f64,
+    scroll_animations: ScrollAnimations,
+    /// The scroll containers that were scrolled by the app since the last frame.
+    scrolled: FxHashSet<NodeId>,
+    /// The element that had focus after the last event. Focused elements are scrolled into view.
+    last_focused: Option<NodeId>,
+    /// The cursor that was last applied to the window.
+    cursor: Option<Cursor>,
+    /// The image of a `url()` cursor and where its top left corner is drawn.
+    custom_cursor: Option<(Arc<Image>, vello::kurbo::Point)>,
+    /// If the custom cursor moved or changed since the last frame.
+    custom_cursor_changed: bool,
+    /// The accessibility tree that was last sent to the platform.
+    accessibility_tree: AccessibilityTree,
+    accessibility: AccessibilityAdapter,
+}
+
+/// An offscreen frame that is waiting for the GPU to finish copying it into a buffer.
+pub(crate) struct PendingFrame {
+    buffer: wgpu::Buffer,
+    ready: std::sync::mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+}
+
+pub(crate) enum FrameState {
+    Pending,
+    Ready(::image::RgbaImage),
+    Failed,
+}
+
+impl PendingFrame {
+    pub fn read(&self) -> FrameState {
+        match self.ready.try_recv() {
+            Ok(Ok(())) => {
+                let row_bytes = (self.width * 4) as usize;
+                let mut pixels = Vec::with_capacity(row_bytes * self.height as usize);
+                {
+                    let data = self.buffer.slice(..).get_mapped_range();
+                    // strip the padding from the end of each row
+                    for row in data.chunks(self.padded_bytes_per_row as usize) {
+                        pixels.extend_from_slice(&row[..row_bytes]);
+                    }
+                }
+                self.buffer.unmap();
+                match ::image::RgbaImage::from_raw(self.width, self.height, pixels) {
+                    Some(image) => FrameState::Ready(image),
+                    None => FrameState::Failed,
+                }
+            }
+            Ok(Err(_)) => FrameState::Failed,
+            Err(_) => FrameState::Pending,
+        }
+    }
+}
+
+enum CaptureSender {
+    Screenshot(oneshot::Sender<::image::RgbaImage>),
+    Print(oneshot::Sender<Vec<::image::RgbaImage>>),
+}
+
+/// A screenshot or printout that is waiting for all of its frames to be copied back from the GPU.
+struct PendingCapture {
+    frames: Vec<PendingFrame>,
+    images: Vec<::image::RgbaImage>,
+    sender: CaptureSender,
+}
+
+impl PendingCapture {
+    /// Read every frame that is ready in order. Returns false if a frame could not be read.
+    fn read_ready_frames(&mut self) -> bool {
+        while let Some(frame) = self.frames.get(self.images.len()) {
+            match frame.read() {
+                FrameState::Pending => break,
+                FrameState::Ready(image) => self.images.push(image),
+                FrameState::Failed => return false,
+            }
+        }
+        true
+    }
+
+    fn is_complete(&self) -> bool {
+        self.images.len() == self.frames.len()
+    }
+
+    fn send(self) {
+        match self.sender {
+            CaptureSender::Screenshot(sender) => {
+                if let Some(image) = self.images.into_iter().next() {
+                    let _ = sender.send(image);
+                }
+            }
+            CaptureSender::Print(sender) => {
+                let _ = sender.send(self.images);
+            }
+        }
+    }
 }
 
 impl ApplicationState {
     /// Create a new window state and spawn a vdom thread.
     pub async fn new<R: Driver>(
-        spawn_renderer: impl FnOnce(&Arc<RwLock<RealDom>>, &Arc<Mutex<Taffy>>) -> R + Send + 'static,
+        spawn_renderer: impl FnOnce(&Arc<RwLock<RealDom>>, &Arc<Mutex<Taffy>>, &WindowHandle) -> R
+            + Send
+            + 'static,
         window: &Window,
         proxy: EventLoopProxy<Redraw>,
+        config: &Config,
     ) -> Self {
-        let inner_size = window.inner_size();
-
-        let mut rdom = RealDom::new([
-            MouseEffected::to_type_erased(),
-            TaffyLayout::to_type_erased(),
-            ForgroundColor::to_type_erased(),
-            BackgroundColor::to_type_erased(),
-            Border::to_type_erased(),
-            Focus::to_type_erased(),
-            PreventDefault::to_type_erased(),
-            LoadedImage::to_type_erased(),
-            FontSize::to_type_erased(),
-        ]);
+        let scale_factor = window.scale_factor();
+        // the document is laid out in css pixels
+        let inner_size = window.inner_size().to_logical(scale_factor);
+
+        let mut rdom = create_rdom(config);
 
         let focus_state = FocusState::create(&mut rdom);
 
-        let dom = DomManager::spawn(rdom, inner_size, spawn_renderer, proxy);
+        let (command_sender, commands) = unbounded_channel();
+        let handle = WindowHandle::new(proxy.clone(), command_sender);
+        let accessibility_handle = handle.clone();
+
+        let fonts = FontContext::default();
+        let dom = DomManager::spawn(
+            rdom,
+            inner_size,
+            spawn_renderer,
+            proxy,
+            handle,
+            fonts.clone(),
+            config.font_files.clone(),
+        );
 
-        let event_handler = BlitzEventHandler::new(focus_state);
+        let event_handler = BlitzEventHandler::new(focus_state, config.key_repeat);
 
         let mut render_context = RenderContext::new().unwrap();
         let size = window.inner_size();
@@ -84,7 +241,24 @@ impl ApplicationState {
         )
         .unwrap();
 
-        let text_context = TextContext::default();
+        let text_context = TextContext::new(fonts);
+
+        let viewport_size = Size {
+            width: inner_size.width,
+            height: inner_size.height,
+        };
+        // the adapter reads the tree when an assistive technology connects, so it is built before the first frame
+        let accessibility_tree = AccessibilityTree::default();
+        accessibility_tree.update(
+            &dom.rdom(),
+            &dom.taffy(),
+            &viewport_size,
+            scale_factor,
+            None,
+            &DirtyNodes::All,
+        );
+        let accessibility =
+            AccessibilityAdapter::new(window, accessibility_tree.clone(), accessibility_handle);
 
         ApplicationState {
             dom,
@@ -93,15 +267,115 @@ impl ApplicationState {
             wgpu_renderer,
             surface,
             event_handler,
-            quadtree: Quadtree::new(20),
+            commands,
+            pending_captures: Vec::new(),
+            debug_redraw_regions: config.debug_redraw_regions,
+            frame_count: 0,
+            paused: false,
+            paused_input: config.paused_input,
+            resume_on_focus: config.resume_on_focus,
+            queued_input: Vec::new(),
+            ime_position: None,
+            scale_factor,
+            scroll_animations: ScrollAnimations::default(),
+            scrolled: FxHashSet::default(),
+            last_focused: None,
+            cursor: None,
+            custom_cursor: None,
+            custom_cursor_changed: false,
+            accessibility_tree,
+            accessibility,
         }
     }
 
-    pub fn render(&mut self) {
+    /// Places the IME candidate window below the focused element so it doesn't cover the text being composed.
+    pub fn update_ime_position(&mut self, window: &Window) {
+        let Some(focused) = self.event_handler.focused() else {
+            return;
+        };
+        let size = self.dom.size();
+        let viewport_size = Size {
+            width: size.width,
+            height: size.height,
+        };
+        let position = {
+            let rdom = self.dom.rdom();
+            let taffy = self.dom.taffy();
+            let Some(node) = rdom.get(focused) else {
+                return;
+            };
+            let Some(taffy_node) = node.get::<TaffyLayout>().and_then(|layout| layout.node) else {
+                return;
+            };
+            let layout = taffy.layout(taffy_node).unwrap();
+            let pos = get_abs_pos(*layout, &taffy, node, &viewport_size);
+            LogicalPosition::new(pos.x, pos.y + layout.size.height as f64)
+        };
+        if self.ime_position != Some(position) {
+            self.ime_position = Some(position);
+            window.set_ime_position(position);
+        }
+    }
+
+    /// Shows the cursor of the hovered element. Image cursors are drawn with the scene, so they are only placed here.
+    pub fn update_cursor(&mut self, window: &Window) {
+        let cursor = self
+            .event_handler
+            .hovered()
+            .and_then(|hovered| {
+                let rdom = self.dom.rdom();
+                let node = rdom.get(hovered)?;
+                let cursor = node.get::<Cursor>()?;
+                Some((*cursor).clone())
+            })
+            .unwrap_or_default();
+        let custom_cursor = cursor.image.as_ref().map(|image| {
+            (
+                image.image.clone(),
+                self.event_handler.cursor_position() - image.hotspot,
+            )
+        });
+        if self.custom_cursor != custom_cursor {
+            self.custom_cursor = custom_cursor;
+            self.custom_cursor_changed = true;
+        }
+        if self.cursor.as_ref() != Some(&cursor) {
+            match (&cursor.image, cursor.keyword) {
+                (None, Some(icon)) => {
+                    window.set_cursor_visible(true);
+                    window.set_cursor_icon(icon);
+                }
+                // the system cursor is hidden for `none` and under image cursors
+                _ => window.set_cursor_visible(false),
+            }
+            self.cursor = Some(cursor);
+        }
+    }
+
+    /// Builds the scene of the window in device pixels. The document is drawn in css pixels and scaled by the scale factor.
+    fn build_scene(&mut self, dirty: Option<&DirtyNodes>) -> Scene {
+        let mut fragment = SceneFragment::new();
+        {
+            let mut builder = SceneBuilder::for_fragment(&mut fragment);
+            self.dom.render(&mut self.text_context, &mut builder);
+            if let Some(dirty) = dirty {
+                if let Some((image, position)) = &self.custom_cursor {
+                    builder.draw_image(image, Affine::translate(position.to_vec2()));
+                }
+                if self.debug_redraw_regions {
+                    self.draw_redraw_regions(&mut builder, dirty);
+                }
+            }
+        }
         let mut scene = Scene::new();
         let mut builder = SceneBuilder::for_scene(&mut scene);
-        self.dom.render(&mut self.text_context, &mut builder);
-        // builder.finish();
+        builder.append(&fragment, Some(Affine::scale(self.scale_factor)));
+        scene
+    }
+
+    pub fn render(&mut self, dirty: &DirtyNodes) {
+        let scene = self.build_scene(Some(dirty));
+        self.frame_count += 1;
         let surface_texture = self
             .surface
             .surface
@@ -123,99 +397,345 @@ impl ApplicationState {
             .expect("failed to render to surface");
         surface_texture.present();
         device.device.poll(wgpu::Maintain::Wait);
+        self.update_accessibility(dirty);
+    }
 
-        // After we render, we need to update the quadtree to reflect the new positions of the nodes
-        self.update_quadtree();
-    }
-
-    // TODO: Once we implement a custom tree for Taffy we can call this when the layout actually changes for each node instead of the diffing approach this currently uses
-    fn update_quadtree(&mut self) {
-        #[derive(Component)]
-        struct QuadtreeId(u64);
-
-        fn add_to_quadtree(
-            node_id: NodeId,
-            parent_location: Point<f32>,
-            taffy: &Taffy,
-            rdom: &mut RealDom,
-            quadtree: &mut Quadtree<u64, NodeId>,
-        ) {
-            if let Some(node) = rdom.get(node_id) {
-                if let Some((size, location)) = {
-                    let layout = node.get::<TaffyLayout>();
-                    layout.and_then(|l| {
-                        if let Ok(Layout { size, location, .. }) = taffy.layout(l.node.unwrap()) {
-                            Some((size, location))
-                        } else {
-                            None
-                        }
-                    })
-                } {
-                    let location = Point {
-                        x: location.x + parent_location.x,
-                        y: location.y + parent_location.y,
+    /// Sends the parts of the accessibility tree that changed in the frame that was just rendered and the focused element to the screen reader.
+    fn update_accessibility(&mut self, dirty: &DirtyNodes) {
+        let size = self.dom.size();
+        let viewport_size = Size {
+            width: size.width,
+            height: size.height,
+        };
+        let update = self.accessibility_tree.update(
+            &self.dom.rdom(),
+            &self.dom.taffy(),
+            &viewport_size,
+            self.scale_factor,
+            self.event_handler.focused(),
+            dirty,
+        );
+        if let Some(update) = update {
+            self.accessibility.update(update);
+        }
+    }
+
+    /// Draw a translucent rectangle over every node that was redrawn this frame. The color changes every frame so repeated redraws of the same node stand out.
+    fn draw_redraw_regions(&self, builder: &mut SceneBuilder, dirty: &DirtyNodes) {
+        const COLORS: [Color; 4] = [
+            Color::rgba8(255, 0, 255, 64),
+            Color::rgba8(0, 255, 255, 64),
+            Color::rgba8(255, 255, 0, 64),
+            Color::rgba8(0, 255, 0, 64),
+        ];
+        let color = COLORS[self.frame_count % COLORS.len()];
+        match dirty {
+            DirtyNodes::All => {
+                let size = self.dom.size();
+                let window = Rect::new(0.0, 0.0, size.width as f64, size.height as f64);
+                builder.fill(Fill::NonZero, Affine::IDENTITY, color, None, &window);
+            }
+            DirtyNodes::Some(nodes) => {
+                let size = self.dom.size();
+                let viewport_size = Size {
+                    width: size.width,
+                    height: size.height,
+                };
+                let rdom = self.dom.rdom();
+                let taffy = self.dom.taffy();
+                for id in nodes {
+                    let Some(node) = rdom.get(*id) else {
+                        continue;
                     };
+                    let Some(taffy_node) = node.get::<TaffyLayout>().and_then(|layout| layout.node)
+                    else {
+                        continue;
+                    };
+                    let layout = taffy.layout(taffy_node).unwrap();
+                    let pos = get_abs_pos(*layout, &taffy, node, &viewport_size);
+                    let region = Rect::new(
+                        pos.x,
+                        pos.y,
+                        pos.x + layout.size.width as f64,
+                        pos.y + layout.size.height as f64,
+                    );
+                    builder.fill(Fill::NonZero, Affine::IDENTITY, color, None, &region);
+                }
+            }
+        }
+    }
 
-                    let mut qtree_id = None;
-                    let area = AreaBuilder::default()
-                        .anchor((location.x as u64, location.y as u64).into())
-                        .dimensions((size.width as u64, size.height as u64))
-                        .build()
-                        .unwrap();
-                    match node.get::<QuadtreeId>() {
-                        Some(id) => {
-                            let id = id.0;
-                            if let Some(entry) = quadtree.get(id) {
-                                let old_area = entry.area();
-                                // If the area has changed, we need to update the quadtree
-                                if old_area != area {
-                                    quadtree.delete_by_handle(id);
-                                    qtree_id = quadtree.insert(area, node_id);
-                                }
-                            } else {
-                                // If the node is not in the quadtree, we need to add it
-                                qtree_id = quadtree.insert(area, node_id);
-                            }
-                        }
-                        None => {
-                            // If the node is not in the quadtree, we need to add it
-                            qtree_id = quadtree.insert(area, node_id);
-                        }
-                    }
-                    // Repeat for all children
-                    for child in node.child_ids() {
-                        add_to_quadtree(child, location, taffy, rdom, quadtree);
-                    }
-                    // If the node was added or updated, we need to update the node's quadtree id
-                    if let Some(id) = qtree_id {
-                        let mut node = rdom.get_mut(node_id).unwrap();
-                        node.insert(QuadtreeId(id));
+    /// Build a scene that shows the part of the document between `top` and `bottom`.
+    fn build_page_scene(&mut self, top: f64, bottom: f64, width: u32) -> Scene {
+        let mut fragment = SceneFragment::new();
+        let mut fragment_builder = SceneBuilder::for_fragment(&mut fragment);
+        self.dom
+            .render(&mut self.text_context, &mut fragment_builder);
+
+        let mut scene = Scene::new();
+        let mut builder = SceneBuilder::for_scene(&mut scene);
+        // content below the page break belongs on the next page
+        let page = Rect::new(0.0, 0.0, width as f64, bottom - top);
+        builder.push_layer(Mix::Clip, 1.0, Affine::IDENTITY, &page);
+        builder.append(&fragment, Some(Affine::translate((0.0, -top))));
+        builder.pop_layer();
+        scene
+    }
+
+    /// Handle any commands sent from a [`WindowHandle`].
+    pub fn handle_commands(&mut self) {
+        while let Ok(command) = self.commands.try_recv() {
+            match command {
+                WindowCommand::Screenshot(sender) => self.screenshot(sender),
+                WindowCommand::Print(page_size, sender) => self.print(page_size, sender),
+                WindowCommand::Pause => self.paused = true,
+                WindowCommand::Resume => self.resume(),
+                WindowCommand::ScrollIntoView(node, alignment) => {
+                    self.scroll_into_view(node, alignment)
+                }
+                WindowCommand::PushFocusTrap(container) => self
+                    .event_handler
+                    .push_focus_trap(&mut self.dom.rdom(), container),
+                WindowCommand::PopFocusTrap => {
+                    self.event_handler.pop_focus_trap(&mut self.dom.rdom())
+                }
+                WindowCommand::SetPointerCapture(node) => {
+                    self.event_handler.set_pointer_capture(node)
+                }
+                WindowCommand::ReleasePointerCapture => {
+                    self.event_handler.release_pointer_capture()
+                }
+                WindowCommand::StyleWarnings(sender) => {
+                    let _ = sender.send(style_warnings(&self.dom.rdom()));
+                }
+                WindowCommand::HitTest(x, y, sender) => {
+                    let size = self.dom.size();
+                    let viewport_size = Size {
+                        width: size.width,
+                        height: size.height,
+                    };
+                    let hit = hit_test(
+                        &self.dom.rdom(),
+                        &self.dom.taffy(),
+                        &viewport_size,
+                        vello::kurbo::Point::new(x, y),
+                    );
+                    let _ = sender.send(hit);
+                }
+                WindowCommand::RegisterFont(file) => self.dom.load_font(file),
+                WindowCommand::Focus(node) => {
+                    self.event_handler.focus(&mut self.dom.rdom(), node);
+                    if self.event_handler.take_restyle() {
+                        self.dom.update_state();
                     }
                 }
             }
         }
+    }
+
+    fn scroll_into_view(&mut self, node: NodeId, alignment: ScrollAlignment) {
         let mut rdom = self.dom.rdom();
         let taffy = self.dom.taffy();
-        add_to_quadtree(
-            rdom.root_id(),
-            Point::ZERO,
-            &taffy,
+        let scrolled = scroll_into_view(
             &mut rdom,
-            &mut self.quadtree,
+            &taffy,
+            &mut self.scroll_animations,
+            node,
+            alignment,
         );
+        self.scrolled.extend(scrolled);
+    }
+
+    /// Steps the running smooth scrolls. Returns true while any of them are running so the event loop keeps drawing frames.
+    pub fn animate_scroll(&mut self) -> bool {
+        if self.paused || !self.scroll_animations.is_running() {
+            return false;
+        }
+        let scrolled = {
+            let mut rdom = self.dom.rdom();
+            self.scroll_animations.step(&mut rdom, Instant::now())
+        };
+        self.scrolled.extend(scrolled);
+        self.scroll_animations.is_running()
+    }
+
+    /// Render the current frame offscreen. The copy is finished in [`Self::poll_captures`] so the event loop is never blocked waiting for the GPU.
+    fn screenshot(&mut self, sender: oneshot::Sender<::image::RgbaImage>) {
+        let scene = self.build_scene(None);
+        // if the frame can't be rendered, dropping the sender lets the caller know
+        let Ok(frame) = self.render_offscreen(
+            &scene,
+            self.surface.config.width,
+            self.surface.config.height,
+        ) else {
+            return;
+        };
+        self.pending_captures.push(PendingCapture {
+            frames: vec![frame],
+            images: Vec::new(),
+            sender: CaptureSender::Screenshot(sender),
+        });
+    }
+
+    /// Render the document into pages.
+    ///
+    /// The document keeps the layout of the window, it is only split vertically at the page height and at forced page breaks.
+    fn print(&mut self, page_size: PageSize, sender: oneshot::Sender<Vec<::image::RgbaImage>>) {
+        let pages = {
+            let rdom = self.dom.rdom();
+            let taffy = self.dom.taffy();
+            page_ranges(&rdom, &taffy, page_size.height as f64)
+        };
+        let mut frames = Vec::with_capacity(pages.len());
+        for (top, bottom) in pages {
+            let scene = self.build_page_scene(top, bottom, page_size.width);
+            match self.render_offscreen(&scene, page_size.width, page_size.height) {
+                Ok(frame) => frames.push(frame),
+                // dropping the sender lets the caller know
+                Err(_) => return,
+            }
+        }
+        self.pending_captures.push(PendingCapture {
+            frames,
+            images: Vec::new(),
+            sender: CaptureSender::Print(sender),
+        });
+    }
+
+    /// Render a scene to an offscreen texture with the device of the window.
+    fn render_offscreen(
+        &mut self,
+        scene: &Scene,
+        width: u32,
+        height: u32,
+    ) -> vello::Result<PendingFrame> {
+        let device = &self.render_context.devices[self.surface.dev_id];
+        render_offscreen(
+            &device.device,
+            &device.queue,
+            &mut self.wgpu_renderer,
+            scene,
+            width,
+            height,
+        )
+    }
+
+    pub fn has_pending_captures(&self) -> bool {
+        !self.pending_captures.is_empty()
+    }
+
+    /// Send any screenshots or printouts the GPU has finished copying to the CPU.
+    pub fn poll_captures(&mut self) {
+        if self.pending_captures.is_empty() {
+            return;
+        }
+        let device = &self.render_context.devices[self.surface.dev_id].device;
+        device.poll(wgpu::Maintain::Poll);
+
+        let mut still_pending = Vec::new();
+        for mut capture in self.pending_captures.drain(..) {
+            // if a frame could not be read, dropping the sender lets the caller know
+            if capture.read_ready_frames() {
+                if capture.is_complete() {
+                    capture.send();
+                } else {
+                    still_pending.push(capture);
+                }
+            }
+        }
+        self.pending_captures = still_pending;
     }
 
     pub fn set_size(&mut self, size: PhysicalSize<u32>) {
         // the window size is zero when minimized which causes the renderer to panic
         if size.width > 0 && size.height > 0 {
-            self.dom.set_size(size);
+            self.dom.set_size(size.to_logical(self.scale_factor));
             self.render_context
                 .resize_surface(&mut self.surface, size.width, size.height);
         }
     }
 
+    /// Called when the window moves to a monitor with a different scale factor. The css size of the window changes with it, so the document is laid out again.
+    pub fn set_scale_factor(&mut self, scale_factor: f64, size: PhysicalSize<u32>) {
+        self.scale_factor = scale_factor;
+        self.set_size(size);
+    }
+
     pub fn clean(&mut self) -> DirtyNodes {
-        self.event_handler.clean().or(self.dom.clean())
+        self.event_handler
+            .clean()
+            .or(self.dom.clean())
+            .or(DirtyNodes::Some(std::mem::take(&mut self.scrolled)))
+            .or(if std::mem::take(&mut self.custom_cursor_changed) {
+                DirtyNodes::All
+            } else {
+                DirtyNodes::Some(FxHashSet::default())
+            })
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    fn resume(&mut self) {
+        if !self.paused {
+            return;
+        }
+        self.paused = false;
+        for event in std::mem::take(&mut self.queued_input) {
+            self.send_event(&event);
+        }
+    }
+
+    /// Holds back input while rendering is paused. Returns the event if it should be processed now.
+    pub fn filter_paused<'a>(&mut self, event: TaoEvent<'a>) -> Option<TaoEvent<'a>> {
+        if !self.paused {
+            return Some(event);
+        }
+        if let Event::WindowEvent {
+            event: WindowEvent::Focused(true),
+            ..
+        } = &event
+        {
+            if self.resume_on_focus {
+                self.resume();
+                return Some(event);
+            }
+        }
+        if !is_input(&event) {
+            return Some(event);
+        }
+        if self.paused_input == PausedInput::Queue {
+            if let Some(event) = event.to_static() {
+                self.queued_input.push(event);
+            }
+        }
+        None
+    }
+
+    /// Sends a repeat of the held key if it is due. Returns when the next repeat is due so the event loop can wake up for it.
+    pub fn repeat_held_key(&mut self) -> Option<Instant> {
+        if self.paused {
+            return None;
+        }
+        if self.event_handler.next_key_repeat()? <= Instant::now() {
+            let evts;
+            {
+                let rdom = &mut self.dom.rdom();
+                self.event_handler.repeat_held_key(rdom);
+                evts = self.event_handler.drain_events();
+            }
+            self.dom.send_events(evts);
+        }
+        self.event_handler.next_key_repeat()
+    }
+
+    /// Blinks the caret of the focused text field. Returns when it blinks next so the event loop can wake up for it.
+    pub fn blink_caret(&mut self) -> Option<Instant> {
+        if self.paused {
+            return None;
+        }
+        self.event_handler.blink_caret(&mut self.dom.rdom())
     }
 
     pub fn send_event(&mut self, event: &TaoEvent) {
@@ -228,68 +748,210 @@ impl ApplicationState {
         {
             let rdom = &mut self.dom.rdom();
             let taffy = &self.dom.taffy();
-            self.event_handler
-                .register_event(event, rdom, taffy, &size, &self.quadtree);
+            self.event_handler.register_event(
+                event,
+                rdom,
+                taffy,
+                &size,
+                self.scale_factor,
+                &self.text_context,
+            );
             evts = self.event_handler.drain_events();
         }
         self.dom.send_events(evts);
+        // pseudo classes are attributes, so the passes that read them run on the dom thread
+        if self.event_handler.take_restyle() {
+            self.dom.update_state();
+        }
+
+        // keep the focused element in view when focus moves
+        let focused = self.event_handler.focused();
+        if focused != self.last_focused {
+            self.last_focused = focused;
+            if let Some(focused) = focused {
+                self.scroll_into_view(focused, ScrollAlignment::Nearest);
+            }
+        }
     }
 }
 
+/// Render a scene to an offscreen texture and start copying it back to the CPU. Fails if the renderer can't render the scene, for example if the texture is too large for the device.
+pub(crate) fn render_offscreen(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    renderer: &mut VelloRenderer,
+    scene: &Scene,
+    width: u32,
+    height: u32,
+) -> vello::Result<PendingFrame> {
+    let size = wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("offscreen"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    renderer.render_to_texture(
+        device,
+        queue,
+        scene,
+        &view,
+        &RenderParams {
+            base_color: Color::WHITE,
+            width,
+            height,
+        },
+    )?;
+
+    // rows in the buffer must be aligned to COPY_BYTES_PER_ROW_ALIGNMENT
+    let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let padded_bytes_per_row = (width * 4 + alignment - 1) / alignment * alignment;
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("offscreen"),
+        size: (padded_bytes_per_row * height) as u64,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("offscreen"),
+    });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(padded_bytes_per_row),
+                rows_per_image: None,
+            },
+        },
+        size,
+    );
+    queue.submit(Some(encoder.finish()));
+
+    let (ready_sender, ready) = std::sync::mpsc::channel();
+    buffer
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, move |result| {
+            let _ = ready_sender.send(result);
+        });
+
+    Ok(PendingFrame {
+        buffer,
+        ready,
+        width,
+        height,
+        padded_bytes_per_row,
+    })
+}
+
+/// Create a dom with every pass the renderer reads.
+pub(crate) fn create_rdom(config: &Config) -> RealDom {
+    let mut passes = vec![
+        MouseEffected::to_type_erased(),
+        PseudoClasses::to_type_erased(),
+        TaffyLayout::to_type_erased(),
+        ForgroundColor::to_type_erased(),
+        BackgroundColor::to_type_erased(),
+        BackgroundImage::to_type_erased(),
+        BackgroundClip::to_type_erased(),
+        Border::to_type_erased(),
+        Outline::to_type_erased(),
+        Focus::to_type_erased(),
+        PreventDefault::to_type_erased(),
+        LoadedImage::to_type_erased(),
+        ObjectFit::to_type_erased(),
+        FontSize::to_type_erased(),
+        LineHeight::to_type_erased(),
+        TextSpacing::to_type_erased(),
+        TextTransform::to_type_erased(),
+        WhiteSpace::to_type_erased(),
+        Visibility::to_type_erased(),
+        FontFamily::to_type_erased(),
+        TableStyle::to_type_erased(),
+        Filter::to_type_erased(),
+        BoxShadow::to_type_erased(),
+        TextShadow::to_type_erased(),
+        Cursor::to_type_erased(),
+        Gauge::to_type_erased(),
+        Checkable::to_type_erased(),
+        Editable::to_type_erased(),
+        TextDecoration::to_type_erased(),
+        TextAlign::to_type_erased(),
+        TextOverflow::to_type_erased(),
+        WritingMode::to_type_erased(),
+        ContentStyle::to_type_erased(),
+        Overflow::to_type_erased(),
+        OverflowAnchor::to_type_erased(),
+        SmoothScroll::to_type_erased(),
+        Transform::to_type_erased(),
+        Positioning::to_type_erased(),
+        Opacity::to_type_erased(),
+        ZIndex::to_type_erased(),
+        PageBreak::to_type_erased(),
+        AccentColor::to_type_erased(),
+        Container::to_type_erased(),
+    ];
+    if config.style_diagnostics {
+        passes.push(StyleDiagnostics::to_type_erased());
+    }
+    RealDom::new(passes)
+}
+
 #[allow(clippy::too_many_arguments)]
 async fn spawn_dom<R: Driver>(
     rdom: Arc<RwLock<RealDom>>,
     taffy: Arc<Mutex<Taffy>>,
-    size: Arc<Mutex<PhysicalSize<u32>>>,
-    spawn_renderer: impl FnOnce(&Arc<RwLock<RealDom>>, &Arc<Mutex<Taffy>>) -> R,
+    size: Arc<Mutex<LogicalSize<u32>>>,
+    spawn_renderer: impl FnOnce(&Arc<RwLock<RealDom>>, &Arc<Mutex<Taffy>>, &WindowHandle) -> R,
     proxy: EventLoopProxy<Redraw>,
     mut event_receiver: UnboundedReceiver<DomEvent>,
     mut redraw_receiver: UnboundedReceiver<()>,
     vdom_dirty: Arc<FxDashSet<NodeId>>,
+    handle: WindowHandle,
+    fonts: FontContext,
+    font_files: Vec<FontFile>,
+    font_sender: UnboundedSender<Option<String>>,
+    mut font_receiver: UnboundedReceiver<Option<String>>,
 ) -> Option<()> {
-    let text_context = Arc::new(Mutex::new(TextContext::default()));
-    let mut renderer = spawn_renderer(&rdom, &taffy);
-    let mut last_size;
-    let image_context = ImageContext::default();
+    let text_context = Arc::new(Mutex::new(TextContext::new(fonts.clone())));
+    // Fonts load in the background and text is measured again when each font is ready
+    for file in font_files {
+        let font_sender = font_sender.clone();
+        fonts.load(file, move |family| {
+            let _ = font_sender.send(Some(family));
+        });
+    }
+    // every text can use an installed font, so all of it is measured again when they are indexed
+    fonts.index_system_fonts(move || {
+        let _ = font_sender.send(None);
+    });
+    let mut renderer = spawn_renderer(&rdom, &taffy, &handle);
+    let mut layout_state = LayoutState::default();
 
     // initial render
     {
         let mut rdom = rdom.write().ok()?;
         let root_id = rdom.root_id();
         renderer.update(rdom.get_mut(root_id)?);
-        let mut ctx = SendAnyMap::new();
-        ctx.insert(taffy.clone());
-        ctx.insert(image_context.clone());
-        ctx.insert(text_context.clone());
-        // update the state of the real dom
-        let (to_rerender, _) = rdom.update_state(ctx);
-        let size = size.lock().unwrap();
-
-        let width = size.width as f32;
-        let height = size.height as f32;
-        let size = Size {
-            width: AvailableSpace::Definite(width),
-            height: AvailableSpace::Definite(height),
-        };
-
-        last_size = size;
-
-        let mut locked_taffy = taffy.lock().unwrap();
-
-        // the root node fills the entire area
-        let root_node = rdom.get(rdom.root_id()).unwrap();
-        let root_taffy_node = root_node.get::<TaffyLayout>().unwrap().node.unwrap();
-
-        let mut style = *locked_taffy.style(root_taffy_node).unwrap();
-        style.size = Size {
-            width: Dimension::Points(width),
-            height: Dimension::Points(height),
-        };
-        locked_taffy.set_style(root_taffy_node, style).unwrap();
-        locked_taffy.compute_layout(root_taffy_node, size).unwrap();
-        for k in to_rerender.into_iter() {
-            vdom_dirty.insert(k);
-        }
+        update_and_layout(
+            &mut renderer,
+            &mut rdom,
+            &taffy,
+            &text_context,
+            viewport_size(&size)?,
+            &mut layout_state,
+            &vdom_dirty,
+        )?;
         proxy.send_event(Redraw).unwrap();
     }
 
@@ -303,45 +965,35 @@ async fn spawn_dom<R: Driver>(
                 let mut rdom = rdom.write().ok()?;
                 renderer.handle_event(rdom.get_mut(element)?, name, data, bubbles);
             }
+            Some(family) = font_receiver.recv() => {
+                let mut rdom = rdom.write().ok()?;
+                layout_state.remeasured_text.extend(remeasure_text(&mut rdom, &taffy, &text_context, |node| {
+                    family.as_ref().is_none_or(|family| {
+                        node.get::<FontFamily>().unwrap().names.iter().any(|name| name == family)
+                    })
+                }));
+            }
         }
 
         let mut rdom = rdom.write().ok()?;
+        // the anchors are picked before the dom changes so scroll containers keep the content in view in place
+        let scroll_anchors = select_scroll_anchors(&rdom, &taffy.lock().unwrap());
         // render after the event has been handled
         let root_id = rdom.root_id();
         renderer.update(rdom.get_mut(root_id)?);
 
-        let mut ctx = SendAnyMap::new();
-        ctx.insert(taffy.clone());
-        ctx.insert(text_context.clone());
-
-        // update the real dom
-        let (to_rerender, _) = rdom.update_state(ctx);
-
-        let size = size.lock().ok()?;
-
-        let width = size.width as f32;
-        let height = size.height as f32;
-        let size = Size {
-            width: AvailableSpace::Definite(width),
-            height: AvailableSpace::Definite(height),
-        };
-        if !to_rerender.is_empty() || last_size != size {
-            last_size = size;
-            let mut taffy = taffy.lock().unwrap();
-            let root_node = rdom.get(rdom.root_id()).unwrap();
-            let root_node_layout = root_node.get::<TaffyLayout>().unwrap();
-            let root_taffy_node = root_node_layout.node.unwrap();
-            let mut style = *taffy.style(root_taffy_node).unwrap();
-            let new_size = Size {
-                width: Dimension::Points(width),
-                height: Dimension::Points(height),
-            };
-            if style.size != new_size {
-                style.size = new_size;
-                taffy.set_style(root_taffy_node, style).unwrap();
-            }
-            taffy.compute_layout(root_taffy_node, size).unwrap();
-            for k in to_rerender.into_iter() {
+        let laid_out = update_and_layout(
+            &mut renderer,
+            &mut rdom,
+            &taffy,
+            &text_context,
+            viewport_size(&size)?,
+            &mut layout_state,
+            &vdom_dirty,
+        )?;
+        if laid_out {
+            let scrolled = adjust_scroll_anchors(&mut rdom, &taffy.lock().unwrap(), scroll_anchors);
+            for k in scrolled.into_iter() {
                 vdom_dirty.insert(k);
             }
 
@@ -350,24 +1002,192 @@ async fn spawn_dom<R: Driver>(
     }
 }
 
+/// What the last layout was computed with, so the passes that depend on it run again when it changes.
+#[derive(Default)]
+pub(crate) struct LayoutState {
+    image_context: ImageContext,
+    viewport: Option<ViewportSize>,
+    root_font_size: Option<RootFontSize>,
+    /// Text that was measured again because a font it uses finished loading.
+    pub remeasured_text: FxHashSet<NodeId>,
+}
+
+/// Brings the state of the dom and its layout up to date and lets the driver respond to the layout. The window and [`render_to_image`](crate::render_to_image) both lay out documents with it. The nodes that need to be redrawn are added to `dirty`.
+///
+/// Returns true if the layout was computed again.
+pub(crate) fn update_and_layout<R: Driver>(
+    renderer: &mut R,
+    rdom: &mut RealDom,
+    taffy: &Arc<Mutex<Taffy>>,
+    text_context: &Arc<Mutex<TextContext>>,
+    viewport: ViewportSize,
+    state: &mut LayoutState,
+    dirty: &FxDashSet<NodeId>,
+) -> Option<bool> {
+    if !update_layout(rdom, taffy, text_context, viewport, state, dirty)? {
+        return Some(false);
+    }
+    after_layout(renderer, rdom, taffy, text_context, viewport, state, dirty)?;
+    Some(true)
+}
+
+/// Runs the state passes and the passes that build on them, then computes the layout if anything changed or the viewport was resized. Returns true if the layout was computed.
+fn update_layout(
+    rdom: &mut RealDom,
+    taffy: &Arc<Mutex<Taffy>>,
+    text_context: &Arc<Mutex<TextContext>>,
+    viewport: ViewportSize,
+    state: &mut LayoutState,
+    dirty: &FxDashSet<NodeId>,
+) -> Option<bool> {
+    let mut ctx = SendAnyMap::new();
+    ctx.insert(taffy.clone());
+    ctx.insert(state.image_context.clone());
+    ctx.insert(text_context.clone());
+    ctx.insert(viewport);
+    let root_font_size = RootFontSize::compute(rdom, &viewport.0);
+    ctx.insert(root_font_size);
+
+    // update the state of the real dom
+    let (mut to_rerender, _) = rdom.update_state(ctx);
+    to_rerender.extend(state.remeasured_text.drain());
+    let resized = state.viewport != Some(viewport);
+    // font sizes that use viewport units change when the window is resized and sizes in rem when the root font size changes
+    if resized || state.root_font_size != Some(root_font_size) {
+        state.viewport = Some(viewport);
+        state.root_font_size = Some(root_font_size);
+        let resized_text = update_font_sizes(rdom, &viewport.0, &root_font_size);
+        if !resized_text.is_empty() {
+            to_rerender.extend(remeasure_text(rdom, taffy, text_context, |node| {
+                resized_text.contains(&node.id())
+            }));
+            to_rerender.extend(resized_text);
+        }
+    }
+    if to_rerender.is_empty() && !resized {
+        return Some(false);
+    }
+    if !to_rerender.is_empty() {
+        to_rerender.extend(update_generated_content(rdom, taffy, text_context));
+        to_rerender.extend(update_intrinsic_sizes(rdom, taffy));
+        to_rerender.extend(update_table_layout(rdom, taffy));
+        to_rerender.extend(update_baseline_struts(rdom, taffy));
+    }
+
+    {
+        let mut taffy = taffy.lock().unwrap();
+        // the root node fills the entire viewport
+        let root_taffy_node = rdom.get(rdom.root_id())?.get::<TaffyLayout>()?.node?;
+        let mut style = *taffy.style(root_taffy_node).unwrap();
+        let new_size = Size {
+            width: Dimension::Points(viewport.0.width as f32),
+            height: Dimension::Points(viewport.0.height as f32),
+        };
+        if style.size != new_size {
+            style.size = new_size;
+            taffy.set_style(root_taffy_node, style).unwrap();
+        }
+        taffy
+            .compute_layout(root_taffy_node, available_space(viewport))
+            .unwrap();
+    }
+    for k in to_rerender.into_iter() {
+        dirty.insert(k);
+    }
+    Some(true)
+}
+
+/// Let the driver respond to the computed layout before the frame is painted. Any changes it makes are applied and laid out again, so measuring elements doesn't lag a frame behind.
+fn after_layout<R: Driver>(
+    renderer: &mut R,
+    rdom: &mut RealDom,
+    taffy: &Arc<Mutex<Taffy>>,
+    text_context: &Arc<Mutex<TextContext>>,
+    viewport: ViewportSize,
+    state: &mut LayoutState,
+    dirty: &FxDashSet<NodeId>,
+) -> Option<()> {
+    {
+        let taffy = taffy.lock().unwrap();
+        renderer.after_layout(&ComputedLayout::new(rdom, &taffy, viewport.0));
+    }
+
+    let root_id = rdom.root_id();
+    renderer.update(rdom.get_mut(root_id)?);
+    update_layout(rdom, taffy, text_context, viewport, state, dirty)?;
+
+    // text is wrapped and rows are spanned with the sizes from the layout, so the layout is computed again when they change
+    let wrapped = update_text_wrapping(rdom, taffy, text_context);
+    relayout(rdom, taffy, viewport, wrapped, dirty)?;
+    let row_spans = update_row_spans(rdom, taffy);
+    relayout(rdom, taffy, viewport, row_spans, dirty)
+}
+
+/// Computes the layout again if any nodes changed and marks them to be redrawn.
+fn relayout(
+    rdom: &RealDom,
+    taffy: &Arc<Mutex<Taffy>>,
+    viewport: ViewportSize,
+    changed: FxHashSet<NodeId>,
+    dirty: &FxDashSet<NodeId>,
+) -> Option<()> {
+    if changed.is_empty() {
+        return Some(());
+    }
+    let root_taffy_node = rdom.get(rdom.root_id())?.get::<TaffyLayout>()?.node?;
+    taffy
+        .lock()
+        .unwrap()
+        .compute_layout(root_taffy_node, available_space(viewport))
+        .unwrap();
+    for k in changed.into_iter() {
+        dirty.insert(k);
+    }
+    Some(())
+}
+
+/// The space the root node is laid out in.
+fn available_space(viewport: ViewportSize) -> Size<AvailableSpace> {
+    Size {
+        width: AvailableSpace::Definite(viewport.0.width as f32),
+        height: AvailableSpace::Definite(viewport.0.height as f32),
+    }
+}
+
+fn viewport_size(size: &Mutex<LogicalSize<u32>>) -> Option<ViewportSize> {
+    let size = size.lock().ok()?;
+    Some(ViewportSize(Size {
+        width: size.width,
+        height: size.height,
+    }))
+}
+
 /// A wrapper around the RealDom that manages the lifecycle.
 struct DomManager {
     rdom: Arc<RwLock<RealDom>>,
     taffy: Arc<Mutex<Taffy>>,
-    size: Arc<Mutex<PhysicalSize<u32>>>,
+    size: Arc<Mutex<LogicalSize<u32>>>,
     /// The node that need to be redrawn.
     dirty: Arc<FxDashSet<NodeId>>,
     force_redraw: bool,
     event_sender: UnboundedSender<DomEvent>,
     redraw_sender: UnboundedSender<()>,
+    fonts: FontContext,
+    /// Sends the family of each font that finished loading, so the text that uses it is measured again. `None` is sent when the installed fonts are indexed.
+    font_sender: UnboundedSender<Option<String>>,
 }
 
 impl DomManager {
     fn spawn<R: Driver>(
         rdom: RealDom,
-        size: PhysicalSize<u32>,
-        spawn_renderer: impl FnOnce(&Arc<RwLock<RealDom>>, &Arc<Mutex<Taffy>>) -> R + Send + 'static,
+        size: LogicalSize<u32>,
+        spawn_renderer: impl FnOnce(&Arc<RwLock<RealDom>>, &Arc<Mutex<Taffy>>, &WindowHandle) -> R
+            + Send
+            + 'static,
         proxy: EventLoopProxy<Redraw>,
+        handle: WindowHandle,
+        fonts: FontContext,
+        font_files: Vec<FontFile>,
     ) -> Self {
         let rdom: Arc<RwLock<RealDom>> = Arc::new(RwLock::new(rdom));
         let taffy = Arc::new(Mutex::new(Taffy::new()));
@@ -376,9 +1196,11 @@ impl DomManager {
 
         let (event_sender, event_receiver) = unbounded_channel::<DomEvent>();
         let (redraw_sender, redraw_receiver) = unbounded_channel::<()>();
+        let (font_sender, font_receiver) = unbounded_channel::<Option<String>>();
 
         let (rdom_clone, size_clone, dirty_clone, taffy_clone) =
             (rdom.clone(), size.clone(), dirty.clone(), taffy.clone());
+        let (fonts_clone, font_sender_clone) = (fonts.clone(), font_sender.clone());
         // Spawn a thread to run the virtual dom and update the real dom.
         std::thread::spawn(move || {
             tokio::runtime::Builder::new_current_thread()
@@ -394,6 +1216,11 @@ impl DomManager {
                     event_receiver,
                     redraw_receiver,
                     dirty_clone,
+                    handle,
+                    fonts_clone,
+                    font_files,
+                    font_sender_clone,
+                    font_receiver,
                 ));
         });
 
@@ -404,10 +1231,20 @@ impl DomManager {
             dirty,
             event_sender,
             redraw_sender,
+            fonts,
+            font_sender,
             force_redraw: false,
         }
     }
 
+    /// Load a font registered while the app is running.
+    fn load_font(&self, file: FontFile) {
+        let font_sender = self.font_sender.clone();
+        self.fonts.load(file, move |family| {
+            let _ = font_sender.send(Some(family));
+        });
+    }
+
     fn clean(&self) -> DirtyNodes {
         if self.force_redraw {
             DirtyNodes::All
@@ -426,12 +1263,12 @@ impl DomManager {
         self.taffy.lock().unwrap()
     }
 
-    fn set_size(&mut self, size: PhysicalSize<u32>) {
+    fn set_size(&mut self, size: LogicalSize<u32>) {
         *self.size.lock().unwrap() = size;
         self.force_redraw();
     }
 
-    fn size(&self) -> PhysicalSize<u32> {
+    fn size(&self) -> LogicalSize<u32> {
         *self.size.lock().unwrap()
     }
 
@@ -440,6 +1277,11 @@ impl DomManager {
         self.redraw_sender.send(()).unwrap();
     }
 
+    /// Wakes the dom thread to update the state of the real dom after the event handler changed attributes.
+    fn update_state(&self) {
+        let _ = self.redraw_sender.send(());
+    }
+
     fn render(&self, text_context: &mut TextContext, renderer: &mut SceneBuilder) {
         render(
             &self.rdom(),
@@ -482,3 +1324,24 @@ impl DirtyNodes {
         }
     }
 }
+
+/// If the event comes from the user interacting with the window.
+fn is_input(event: &TaoEvent) -> bool {
+    matches!(
+        event,
+        Event::WindowEvent {
+            event: WindowEvent::KeyboardInput { .. }
+                | WindowEvent::ModifiersChanged(_)
+                | WindowEvent::ReceivedImeText(_)
+                | WindowEvent::CursorMoved { .. }
+                | WindowEvent::CursorEntered { .. }
+                | WindowEvent::CursorLeft { .. }
+                | WindowEvent::MouseWheel { .. }
+                | WindowEvent::MouseInput { .. }
+                | WindowEvent::TouchpadPressure { .. }
+                | WindowEvent::AxisMotion { .. }
+                | WindowEvent::Touch(_),
+            ..
+        }
+    )
+}
@@ -0,0 +1,436 @@
+//! Background images.
+//!
+//! `background-image` is a list of layers that are drawn over the background color, with the first layer on top. Each image is sized with `background-size`, placed in the padding box with `background-position` and tiled with `background-repeat`, then clipped like the background color. The other background properties are lists too, and are repeated when they have fewer values than there are images. `url()` images and `conic-gradient()` are drawn, other images like linear and radial gradients are skipped. Images are loaded through the [`ImageContext`] so every element that uses a file shares one decoded image.
+//!
+//! A conic gradient has no size of its own, so it fills the area unless `background-size` says otherwise. It turns clockwise around its `at` position in the tile, starting from the top rotated by its `from` angle. Stops with angles or percentages are placed at that fraction of the full turn.
+
+use std::f64::consts::FRAC_PI_2;
+use std::sync::Arc;
+
+use cssparser::{Parser, ParserInput};
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use lightningcss::properties::background::{
+    BackgroundPosition, BackgroundRepeat, BackgroundRepeatKeyword, BackgroundSize,
+};
+use lightningcss::traits::Parse;
+use lightningcss::values::angle::AnglePercentage;
+use lightningcss::values::gradient::{ConicGradient, Gradient as GradientValue, GradientItem};
+use lightningcss::values::image::Image as ImageValue;
+use lightningcss::values::length::{LengthPercentage, LengthPercentageOrAuto};
+use lightningcss::values::percentage::DimensionPercentage;
+use lightningcss::values::position::{
+    HorizontalPosition, HorizontalPositionKeyword, PositionComponent, VerticalPosition,
+    VerticalPositionKeyword,
+};
+use shipyard::Component;
+use taffy::prelude::Size;
+use vello::kurbo::{Affine, Rect, Shape, Vec2};
+use vello::peniko::{ColorStop, Fill, Gradient, Image, Mix};
+use vello::SceneBuilder;
+
+use crate::image::ImageContext;
+use crate::util::{translate_color, Axis, Resolve, ResolveContext};
+
+/// Tiles smaller than this many pixels are not drawn, there would be too many of them.
+const MIN_TILE_SIZE: f64 = 1.0;
+
+/// An image of `background-image` that can be drawn.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum BackgroundSource {
+    Image(Arc<Image>),
+    ConicGradient(ConicGradient),
+}
+
+impl BackgroundSource {
+    /// Returns the natural size of the image. Gradients don't have one.
+    fn natural_size(&self) -> Option<Vec2> {
+        match self {
+            BackgroundSource::Image(image) => {
+                Some(Vec2::new(image.width as f64, image.height as f64))
+            }
+            BackgroundSource::ConicGradient(_) => None,
+        }
+    }
+}
+
+/// One image of `background-image` and the values of the other background properties for it.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct BackgroundLayer {
+    pub image: BackgroundSource,
+    pub size: BackgroundSize,
+    pub position: BackgroundPosition,
+    pub repeat: BackgroundRepeat,
+}
+
+impl BackgroundLayer {
+    /// Returns the size of one tile of the image in the area it is positioned in. `cover` and `contain` keep the aspect ratio of the image, and so does an explicit size with one `auto` side. Images without a natural size fill the area on the sides that are not given.
+    fn tile_size(&self, area: Rect, context: &ResolveContext) -> Vec2 {
+        let rect = Size {
+            width: area.width() as f32,
+            height: area.height() as f32,
+        };
+        let natural = self.image.natural_size();
+        let mut size = match (&self.size, natural) {
+            // images without a natural size fill the area
+            (BackgroundSize::Cover | BackgroundSize::Contain, None) => area.size().to_vec2(),
+            (BackgroundSize::Cover, Some(natural)) => {
+                natural * (area.width() / natural.x).max(area.height() / natural.y)
+            }
+            (BackgroundSize::Contain, Some(natural)) => {
+                natural * (area.width() / natural.x).min(area.height() / natural.y)
+            }
+            (BackgroundSize::Explicit { width, height }, natural) => {
+                let resolve = |length: &LengthPercentageOrAuto, axis| match length {
+                    LengthPercentageOrAuto::Auto => None,
+                    LengthPercentageOrAuto::LengthPercentage(length) => {
+                        Some(length.resolve(axis, &rect, context))
+                    }
+                };
+                match (resolve(width, Axis::X), resolve(height, Axis::Y), natural) {
+                    (Some(width), Some(height), _) => Vec2::new(width, height),
+                    (Some(width), None, Some(natural)) => {
+                        Vec2::new(width, width * natural.y / natural.x)
+                    }
+                    (None, Some(height), Some(natural)) => {
+                        Vec2::new(height * natural.x / natural.y, height)
+                    }
+                    (None, None, Some(natural)) => natural,
+                    (width, height, None) => Vec2::new(
+                        width.unwrap_or(area.width()),
+                        height.unwrap_or(area.height()),
+                    ),
+                }
+            }
+        };
+        // round scales the tiles so a whole number of them fits
+        if self.repeat.x == BackgroundRepeatKeyword::Round && size.x > 0.0 {
+            size.x = area.width() / (area.width() / size.x).round().max(1.0);
+        }
+        if self.repeat.y == BackgroundRepeatKeyword::Round && size.y > 0.0 {
+            size.y = area.height() / (area.height() / size.y).round().max(1.0);
+        }
+        size
+    }
+
+    /// Returns the top left corner of every tile relative to the area.
+    fn tiles(&self, area: Rect, context: &ResolveContext) -> Vec<Vec2> {
+        let tile = self.tile_size(area, context);
+        if tile.x < MIN_TILE_SIZE || tile.y < MIN_TILE_SIZE {
+            return Vec::new();
+        }
+        let rect = Size {
+            width: area.width() as f32,
+            height: area.height() as f32,
+        };
+        // percentages line up the same point of the image and the area, so they are relative to the free space
+        let free = Size {
+            width: (area.width() - tile.x) as f32,
+            height: (area.height() - tile.y) as f32,
+        };
+        let position = resolve_position(&self.position.x, &self.position.y, &free, context);
+        let xs = tile_offsets(self.repeat.x, position.x, tile.x, rect.width as f64);
+        let ys = tile_offsets(self.repeat.y, position.y, tile.y, rect.height as f64);
+        ys.iter()
+            .flat_map(|y| xs.iter().map(move |x| Vec2::new(*x, *y)))
+            .collect()
+    }
+}
+
+/// Resolves a position to an offset in a box of the given size.
+fn resolve_position(
+    x: &HorizontalPosition,
+    y: &VerticalPosition,
+    size: &Size<f32>,
+    context: &ResolveContext,
+) -> Vec2 {
+    let resolve_offset = |offset: &Option<LengthPercentage>, axis| {
+        offset
+            .as_ref()
+            .map_or(0.0, |offset| offset.resolve(axis, size, context))
+    };
+    let x = match x {
+        PositionComponent::Center => size.width as f64 / 2.0,
+        PositionComponent::Length(length) => length.resolve(Axis::X, size, context),
+        PositionComponent::Side { side, offset } => {
+            let offset = resolve_offset(offset, Axis::X);
+            match side {
+                HorizontalPositionKeyword::Left => offset,
+                HorizontalPositionKeyword::Right => size.width as f64 - offset,
+            }
+        }
+    };
+    let y = match y {
+        PositionComponent::Center => size.height as f64 / 2.0,
+        PositionComponent::Length(length) => length.resolve(Axis::Y, size, context),
+        PositionComponent::Side { side, offset } => {
+            let offset = resolve_offset(offset, Axis::Y);
+            match side {
+                VerticalPositionKeyword::Top => offset,
+                VerticalPositionKeyword::Bottom => size.height as f64 - offset,
+            }
+        }
+    };
+    Vec2::new(x, y)
+}
+
+/// Returns the offsets of the tiles along one axis that are inside the area.
+fn tile_offsets(
+    repeat: BackgroundRepeatKeyword,
+    position: f64,
+    tile: f64,
+    length: f64,
+) -> Vec<f64> {
+    match repeat {
+        BackgroundRepeatKeyword::NoRepeat => vec![position],
+        BackgroundRepeatKeyword::Space => {
+            let count = (length / tile).floor();
+            if count < 2.0 {
+                // a single tile that doesn't fit twice is placed with the position
+                return vec![position];
+            }
+            let gap = (length - count * tile) / (count - 1.0);
+            (0..count as usize)
+                .map(|i| i as f64 * (tile + gap))
+                .collect()
+        }
+        BackgroundRepeatKeyword::Repeat | BackgroundRepeatKeyword::Round => {
+            // the tiles repeat in both directions from the positioned tile
+            let first = position - (position / tile).ceil() * tile;
+            let count = ((length - first) / tile).ceil().max(0.0) as usize;
+            (0..count).map(|i| first + i as f64 * tile).collect()
+        }
+    }
+}
+
+/// The layers of `background-image` that loaded, in the order they are declared.
+#[derive(Clone, PartialEq, Debug, Default, Component)]
+pub(crate) struct BackgroundImage(pub Vec<BackgroundLayer>);
+
+#[partial_derive_state]
+impl State for BackgroundImage {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&[
+            "background-image",
+            "background-size",
+            "background-position",
+            "background-repeat",
+        ]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        ctx: &SendAnyMap,
+    ) -> bool {
+        let mut images = Vec::new();
+        let mut sizes = Vec::new();
+        let mut positions = Vec::new();
+        let mut repeats = Vec::new();
+        for attr in node_view.attributes().into_iter().flatten() {
+            let Some(as_text) = attr.value.as_text() else {
+                continue;
+            };
+            match attr.attribute.name.as_str() {
+                "background-image" => {
+                    let image_ctx: &ImageContext = ctx.get().expect("ImageContext not found");
+                    images = parse_background_image(as_text, image_ctx).unwrap_or_default();
+                }
+                "background-size" => sizes = parse_list(as_text).unwrap_or_default(),
+                "background-position" => positions = parse_list(as_text).unwrap_or_default(),
+                "background-repeat" => repeats = parse_list(as_text).unwrap_or_default(),
+                _ => {}
+            }
+        }
+        // lists that are shorter than the list of images are repeated
+        let value = |values: &[_], i: usize| values.get(i % values.len().max(1)).cloned();
+        let new = BackgroundImage(
+            images
+                .into_iter()
+                .enumerate()
+                .map(|(i, image)| BackgroundLayer {
+                    image,
+                    size: value(&sizes, i).unwrap_or_default(),
+                    position: value(&positions, i).unwrap_or_default(),
+                    repeat: value(&repeats, i).unwrap_or_default(),
+                })
+                .collect(),
+        );
+
+        if *self != new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+/// Parses a list of images and loads the `url()` images. Images that fail to load or can't be drawn are skipped.
+fn parse_background_image(text: &str, image_ctx: &ImageContext) -> Option<Vec<BackgroundSource>> {
+    let images = parse_list::<ImageValue>(text)?;
+    let images = images
+        .into_iter()
+        .filter_map(|image| match image {
+            ImageValue::Url(url) => image_ctx
+                .load_file(url.url.as_ref())
+                .ok()
+                .map(BackgroundSource::Image),
+            ImageValue::Gradient(gradient) => match *gradient {
+                GradientValue::Conic(conic) => Some(BackgroundSource::ConicGradient(conic)),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    Some(images)
+}
+
+/// Returns the color stops of a conic gradient at their fraction of the full turn. Stops without a position are spread evenly between the stops around them, and transition hints are ignored.
+fn conic_stops(items: &[GradientItem<AnglePercentage>]) -> Vec<ColorStop> {
+    let stops: Vec<_> = items
+        .iter()
+        .filter_map(|item| match item {
+            GradientItem::ColorStop(stop) => Some(stop),
+            GradientItem::Hint(_) => None,
+        })
+        .collect();
+    let mut offsets: Vec<Option<f32>> = stops
+        .iter()
+        .map(|stop| stop.position.as_ref().and_then(turn_fraction))
+        .collect();
+    // the first and last stops default to the start and the end of the turn
+    if let Some(first) = offsets.first_mut() {
+        first.get_or_insert(0.0);
+    }
+    if let Some(last) = offsets.last_mut() {
+        last.get_or_insert(1.0);
+    }
+    // a stop before the stops in front of it is moved up to them
+    let mut previous = 0.0f32;
+    for offset in offsets.iter_mut().flatten() {
+        previous = offset.clamp(previous, 1.0);
+        *offset = previous;
+    }
+    let mut start = 0;
+    while let Some(missing) = (start..offsets.len()).find(|i| offsets[*i].is_none()) {
+        // the first and last offsets are always set
+        let before = missing - 1;
+        let after = (missing..offsets.len())
+            .find(|i| offsets[*i].is_some())
+            .unwrap();
+        let (from, to) = (offsets[before].unwrap(), offsets[after].unwrap());
+        for (i, offset) in offsets.iter_mut().enumerate().take(after).skip(missing) {
+            let t = (i - before) as f32 / (after - before) as f32;
+            *offset = Some(from + (to - from) * t);
+        }
+        start = after;
+    }
+    stops
+        .iter()
+        .zip(offsets)
+        .map(|(stop, offset)| ColorStop {
+            offset: offset.unwrap(),
+            color: translate_color(&stop.color),
+        })
+        .collect()
+}
+
+/// Returns the fraction of a full turn of the position of a stop.
+fn turn_fraction(position: &AnglePercentage) -> Option<f32> {
+    match position {
+        DimensionPercentage::Dimension(angle) => Some(angle.to_degrees() / 360.0),
+        DimensionPercentage::Percentage(percentage) => Some(percentage.0),
+        DimensionPercentage::Calc(_) => None,
+    }
+}
+
+/// Draws a conic gradient in a tile. The sweep of vello starts on the x axis, so it is turned a quarter back to start from the top like css.
+fn render_conic_gradient(
+    conic: &ConicGradient,
+    tile: Rect,
+    scene_builder: &mut SceneBuilder,
+    context: &ResolveContext,
+) {
+    let size = Size {
+        width: tile.width() as f32,
+        height: tile.height() as f32,
+    };
+    let center = resolve_position(&conic.position.x, &conic.position.y, &size, context);
+    let gradient = Gradient::new_sweep((0.0, 0.0), 0.0, std::f32::consts::TAU)
+        .with_stops(conic_stops(&conic.items).as_slice());
+    let brush_transform = Affine::translate(tile.origin().to_vec2() + center)
+        * Affine::rotate(conic.angle.to_radians() as f64 - FRAC_PI_2);
+    scene_builder.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        &gradient,
+        Some(brush_transform),
+        &tile,
+    );
+}
+
+/// Parses a comma separated list of values.
+fn parse_list<'i, T: Parse<'i>>(text: &'i str) -> Option<Vec<T>> {
+    let mut input = ParserInput::new(text);
+    let mut parser = Parser::new(&mut input);
+    parser.parse_comma_separated(T::parse).ok()
+}
+
+/// Draws the background images of an element positioned in `origin`, clipped to `clip`.
+pub(crate) fn render_background_images(
+    node: NodeRef,
+    origin: Rect,
+    clip: &impl Shape,
+    scene_builder: &mut SceneBuilder,
+    viewport_size: &Size<u32>,
+) {
+    let Some(background) = node.get::<BackgroundImage>() else {
+        return;
+    };
+    if background.0.is_empty() {
+        return;
+    }
+    let context = ResolveContext::of(node, viewport_size);
+    scene_builder.push_layer(Mix::Clip, 1.0, Affine::IDENTITY, clip);
+    // the first layer is drawn last so it ends up on top
+    for layer in background.0.iter().rev() {
+        let tile = layer.tile_size(origin, &context);
+        for offset in layer.tiles(origin, &context) {
+            let position = origin.origin() + offset;
+            match &layer.image {
+                BackgroundSource::Image(image) => {
+                    let scale = Affine::scale_non_uniform(
+                        tile.x / image.width as f64,
+                        tile.y / image.height as f64,
+                    );
+                    let translate = Affine::translate(position.to_vec2());
+                    scene_builder.draw_image(image, translate * scale);
+                }
+                BackgroundSource::ConicGradient(conic) => {
+                    let tile = Rect::from_origin_size(position, tile.to_size());
+                    render_conic_gradient(conic, tile, scene_builder, &context);
+                }
+            }
+        }
+    }
+    scene_builder.pop_layer();
+}
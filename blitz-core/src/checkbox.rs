@@ -0,0 +1,238 @@
+//! Checkboxes and radio buttons.
+//!
+//! `<input type="checkbox">` and `<input type="radio">` are drawn as a box or a circle that is toggled when they are clicked. The state starts from the `checked` attribute and is kept in [`Checked`] once the user toggles the input, like the `:checked` pseudo class. Checking a radio button unchecks the other radio buttons in the document with the same `name`.
+
+use dioxus_native_core::node::OwnedAttributeValue;
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use rustc_hash::FxHashSet;
+use shipyard::Component;
+use taffy::prelude::Layout;
+use vello::kurbo::{Affine, BezPath, Circle, Point, RoundedRect};
+use vello::peniko::{Cap, Color, Fill, Join, Stroke};
+use vello::SceneBuilder;
+
+use crate::application::DirtyNodes;
+use crate::style::{AccentColor, ForgroundColor, DEFAULT_ACCENT};
+
+const BORDER_COLOR: Color = Color::rgb8(118, 118, 118);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum CheckKind {
+    Checkbox,
+    Radio,
+}
+
+/// The `type`, `name`, `checked` and `disabled` attributes of an input.
+#[derive(Clone, PartialEq, Debug, Default, Component)]
+pub(crate) struct Checkable {
+    /// `None` if the element is not a checkbox or radio button.
+    pub kind: Option<CheckKind>,
+    pub name: Option<String>,
+    /// The `checked` attribute. It sets the state until the user toggles the input.
+    pub default_checked: bool,
+    pub disabled: bool,
+}
+
+#[partial_derive_state]
+impl State for Checkable {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new()
+            .with_tag()
+            .with_attrs(AttributeMaskBuilder::Some(&[
+                "type", "name", "checked", "disabled",
+            ]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let mut new = Checkable::default();
+        if node_view.tag() == Some("input") {
+            for attr in node_view.attributes().into_iter().flatten() {
+                match attr.attribute.name.as_str() {
+                    "type" => {
+                        new.kind = match attr.value.as_text().map(str::trim) {
+                            Some("checkbox") => Some(CheckKind::Checkbox),
+                            Some("radio") => Some(CheckKind::Radio),
+                            _ => None,
+                        }
+                    }
+                    "name" => new.name = attr.value.as_text().map(str::to_string),
+                    // boolean attributes are set by being present, dioxus sets them to "false" to remove them
+                    "checked" => new.default_checked = !is_false(attr.value),
+                    "disabled" => new.disabled = !is_false(attr.value),
+                    _ => {}
+                }
+            }
+        }
+
+        if *self != new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+fn is_false(value: &OwnedAttributeValue) -> bool {
+    value.as_bool() == Some(false) || value.as_text() == Some("false")
+}
+
+/// Set on checkboxes and radio buttons once the user toggles them, like the `:checked` pseudo class.
+#[derive(Component)]
+pub struct Checked(pub bool);
+
+/// Returns true if a checkbox or radio button is checked.
+pub(crate) fn is_checked(node: NodeRef) -> bool {
+    match node.get::<Checked>() {
+        Some(checked) => checked.0,
+        None => node
+            .get::<Checkable>()
+            .is_some_and(|checkable| checkable.default_checked),
+    }
+}
+
+/// Tracks the checkboxes and radio buttons the user toggled.
+///
+/// The state is inserted directly on the inputs and only they are marked dirty for repainting.
+#[derive(Default)]
+pub(crate) struct CheckedState {
+    dirty: FxHashSet<NodeId>,
+}
+
+impl CheckedState {
+    /// Toggles a checkbox or checks a radio button after it is clicked. Returns true if the state changed.
+    pub fn activate(&mut self, rdom: &mut RealDom, id: NodeId) -> bool {
+        let Some(node) = rdom.get(id) else {
+            return false;
+        };
+        let Some(checkable) = node
+            .get::<Checkable>()
+            .map(|checkable| (*checkable).clone())
+        else {
+            return false;
+        };
+        if checkable.disabled {
+            return false;
+        }
+        let checked = is_checked(node);
+        match checkable.kind {
+            Some(CheckKind::Checkbox) => {
+                self.set(rdom, id, !checked);
+                true
+            }
+            // radio buttons can't be unchecked by clicking them
+            Some(CheckKind::Radio) if !checked => {
+                if let Some(name) = &checkable.name {
+                    for other in radio_group(rdom, name) {
+                        if other != id && is_checked(rdom.get(other).unwrap()) {
+                            self.set(rdom, other, false);
+                        }
+                    }
+                }
+                self.set(rdom, id, true);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn set(&mut self, rdom: &mut RealDom, id: NodeId, checked: bool) {
+        rdom.get_mut(id).unwrap().insert(Checked(checked));
+        self.dirty.insert(id);
+    }
+
+    pub fn clean(&mut self) -> DirtyNodes {
+        DirtyNodes::Some(std::mem::take(&mut self.dirty))
+    }
+}
+
+/// Returns the radio buttons with a `name`.
+fn radio_group(rdom: &RealDom, name: &str) -> Vec<NodeId> {
+    let mut group = Vec::new();
+    rdom.traverse_depth_first(|node| {
+        if let Some(checkable) = node.get::<Checkable>() {
+            if checkable.kind == Some(CheckKind::Radio) && checkable.name.as_deref() == Some(name) {
+                group.push(node.id());
+            }
+        }
+    });
+    group
+}
+
+/// Draws a checkbox as a box with a check mark, or a radio button as a circle with a dot. Checked inputs are filled with the `accent-color`.
+pub(crate) fn render_checkable(
+    node: NodeRef,
+    kind: CheckKind,
+    layout: &Layout,
+    pos: Point,
+    scene_builder: &mut SceneBuilder,
+) {
+    let width = layout.size.width as f64;
+    let height = layout.size.height as f64;
+    let checked = is_checked(node);
+    let accent = node
+        .get::<AccentColor>()
+        .and_then(|accent| accent.resolve(&node.get::<ForgroundColor>().unwrap().0))
+        .unwrap_or(DEFAULT_ACCENT);
+    let border = Stroke::new(1.0);
+    match kind {
+        CheckKind::Checkbox => {
+            let shape = RoundedRect::new(
+                pos.x + 0.5,
+                pos.y + 0.5,
+                pos.x + width - 0.5,
+                pos.y + height - 0.5,
+                2.0,
+            );
+            if checked {
+                scene_builder.fill(Fill::NonZero, Affine::IDENTITY, accent, None, &shape);
+                let mut check = BezPath::new();
+                check.move_to((pos.x + width * 0.22, pos.y + height * 0.5));
+                check.line_to((pos.x + width * 0.42, pos.y + height * 0.7));
+                check.line_to((pos.x + width * 0.78, pos.y + height * 0.3));
+                let stroke = Stroke::new((width / 7.0) as f32)
+                    .with_caps(Cap::Round)
+                    .with_join(Join::Round);
+                scene_builder.stroke(&stroke, Affine::IDENTITY, Color::WHITE, None, &check);
+            } else {
+                scene_builder.fill(Fill::NonZero, Affine::IDENTITY, Color::WHITE, None, &shape);
+                scene_builder.stroke(&border, Affine::IDENTITY, BORDER_COLOR, None, &shape);
+            }
+        }
+        CheckKind::Radio => {
+            let center = Point::new(pos.x + width / 2.0, pos.y + height / 2.0);
+            let radius = width.min(height) / 2.0 - 0.5;
+            let circle = Circle::new(center, radius);
+            scene_builder.fill(Fill::NonZero, Affine::IDENTITY, Color::WHITE, None, &circle);
+            let ring_color = if checked { accent } else { BORDER_COLOR };
+            scene_builder.stroke(&border, Affine::IDENTITY, ring_color, None, &circle);
+            if checked {
+                let dot = Circle::new(center, radius * 0.55);
+                scene_builder.fill(Fill::NonZero, Affine::IDENTITY, accent, None, &dot);
+            }
+        }
+    }
+}
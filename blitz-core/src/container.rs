@@ -0,0 +1,294 @@
+use cssparser::{Parser, ParserInput, Token};
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use shipyard::Component;
+use taffy::prelude::*;
+
+use crate::layout::TaffyLayout;
+use crate::writing_mode::{BlockFlow, WritingMode};
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub(crate) enum ContainerType {
+    /// The element is not a size container.
+    #[default]
+    Normal,
+    /// Both the width and the height of the element can be queried.
+    Size,
+    /// Only the inline size of the element can be queried.
+    InlineSize,
+}
+
+/// The containment context of an element set with `container-type` and `container-name`.
+#[derive(Clone, PartialEq, Debug, Default, Component)]
+pub(crate) struct Container {
+    pub container_type: ContainerType,
+    pub names: Vec<String>,
+}
+
+fn parse_container_type(value: &str) -> Option<ContainerType> {
+    match value.trim() {
+        "normal" => Some(ContainerType::Normal),
+        "size" => Some(ContainerType::Size),
+        "inline-size" => Some(ContainerType::InlineSize),
+        _ => None,
+    }
+}
+
+fn parse_container_names(value: &str) -> Vec<String> {
+    match value.trim() {
+        "none" => Vec::new(),
+        names => names.split_whitespace().map(str::to_string).collect(),
+    }
+}
+
+#[partial_derive_state]
+impl State for Container {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&[
+            "container",
+            "container-type",
+            "container-name",
+        ]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let mut new = Container::default();
+        for attr in node_view.attributes().into_iter().flatten() {
+            if let Some(as_text) = attr.value.as_text() {
+                match attr.attribute.name.as_str() {
+                    // container: <container-name> [ / <container-type> ]?
+                    "container" => {
+                        let (names, container_type) = match as_text.split_once('/') {
+                            Some((names, container_type)) => (names, Some(container_type)),
+                            None => (as_text, None),
+                        };
+                        new.names = parse_container_names(names);
+                        new.container_type = container_type
+                            .and_then(parse_container_type)
+                            .unwrap_or_default();
+                    }
+                    "container-type" => {
+                        if let Some(container_type) = parse_container_type(as_text) {
+                            new.container_type = container_type;
+                        }
+                    }
+                    "container-name" => new.names = parse_container_names(as_text),
+                    _ => {}
+                }
+            }
+        }
+
+        if self != &mut new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum SizeFeature {
+    Width,
+    Height,
+    InlineSize,
+    BlockSize,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Comparison {
+    Min,
+    Max,
+    Exact,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct SizeCondition {
+    feature: SizeFeature,
+    comparison: Comparison,
+    value: f32,
+}
+
+/// The condition of an `@container` rule like `sidebar (min-width: 400px) and (max-width: 800px)`.
+///
+/// Conditions are size features with a `min-` or `max-` prefix, or an exact value, joined with `and`. Lengths are in pixels.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ContainerQuery {
+    name: Option<String>,
+    conditions: Vec<SizeCondition>,
+}
+
+impl ContainerQuery {
+    /// Parse a container query. Returns `None` if the query uses syntax that isn't supported.
+    pub fn parse(query: &str) -> Option<Self> {
+        let mut input = ParserInput::new(query);
+        let mut parser = Parser::new(&mut input);
+        let mut name = None;
+        let mut conditions = Vec::new();
+        let mut expect_condition = true;
+        while let Ok(token) = parser.next() {
+            match token.clone() {
+                Token::Ident(ident) if ident.eq_ignore_ascii_case("and") && !expect_condition => {
+                    expect_condition = true;
+                }
+                Token::Ident(ident) if name.is_none() && conditions.is_empty() => {
+                    name = Some(ident.to_string());
+                }
+                Token::ParenthesisBlock if expect_condition => {
+                    let condition = parser
+                        .parse_nested_block(|parser| {
+                            parse_size_condition(parser)
+                                .ok_or_else(|| parser.new_custom_error::<(), ()>(()))
+                        })
+                        .ok()?;
+                    conditions.push(condition);
+                    expect_condition = false;
+                }
+                _ => return None,
+            }
+        }
+        if conditions.is_empty() || expect_condition {
+            return None;
+        }
+        Some(Self { name, conditions })
+    }
+
+    fn matches_size(&self, size: Size<f32>, writing_mode: &WritingMode) -> bool {
+        let horizontal = writing_mode.block_flow == BlockFlow::HorizontalTb;
+        self.conditions.iter().all(|condition| {
+            let value = match (condition.feature, horizontal) {
+                (SizeFeature::Width, _)
+                | (SizeFeature::InlineSize, true)
+                | (SizeFeature::BlockSize, false) => size.width,
+                (SizeFeature::Height, _)
+                | (SizeFeature::InlineSize, false)
+                | (SizeFeature::BlockSize, true) => size.height,
+            };
+            match condition.comparison {
+                Comparison::Min => value >= condition.value,
+                Comparison::Max => value <= condition.value,
+                Comparison::Exact => value == condition.value,
+            }
+        })
+    }
+
+    /// If a container can be queried by this query.
+    fn accepts(&self, container: &Container, writing_mode: &WritingMode) -> bool {
+        if let Some(name) = &self.name {
+            if !container.names.contains(name) {
+                return false;
+            }
+        }
+        let horizontal = writing_mode.block_flow == BlockFlow::HorizontalTb;
+        match container.container_type {
+            ContainerType::Normal => false,
+            ContainerType::Size => true,
+            // only the inline axis can be queried
+            ContainerType::InlineSize => self.conditions.iter().all(|condition| {
+                matches!(
+                    (condition.feature, horizontal),
+                    (SizeFeature::InlineSize, _)
+                        | (SizeFeature::Width, true)
+                        | (SizeFeature::Height, false)
+                )
+            }),
+        }
+    }
+
+    /// Evaluate the query against the nearest ancestor of the node that is a matching container. Queries without a container never match.
+    pub(crate) fn evaluate(&self, node: NodeRef, taffy: &Taffy) -> bool {
+        let mut current = node.parent();
+        while let Some(ancestor) = current {
+            let writing_mode = ancestor
+                .get::<WritingMode>()
+                .map(|mode| *mode)
+                .unwrap_or_default();
+            if let Some(container) = ancestor.get::<Container>() {
+                if self.accepts(&container, &writing_mode) {
+                    return content_size(ancestor, taffy)
+                        .map(|size| self.matches_size(size, &writing_mode))
+                        .unwrap_or(false);
+                }
+            }
+            current = ancestor.parent();
+        }
+        false
+    }
+}
+
+fn parse_size_condition(parser: &mut Parser) -> Option<SizeCondition> {
+    let feature = parser.expect_ident().ok()?.to_string();
+    parser.expect_colon().ok()?;
+    let value = match parser.next().ok()? {
+        Token::Dimension { value, unit, .. } if unit.eq_ignore_ascii_case("px") => *value,
+        Token::Number { value, .. } if *value == 0.0 => 0.0,
+        _ => return None,
+    };
+    let (comparison, feature) = if let Some(feature) = feature.strip_prefix("min-") {
+        (Comparison::Min, feature)
+    } else if let Some(feature) = feature.strip_prefix("max-") {
+        (Comparison::Max, feature)
+    } else {
+        (Comparison::Exact, feature.as_str())
+    };
+    let feature = match feature {
+        "width" => SizeFeature::Width,
+        "height" => SizeFeature::Height,
+        "inline-size" => SizeFeature::InlineSize,
+        "block-size" => SizeFeature::BlockSize,
+        _ => return None,
+    };
+    Some(SizeCondition {
+        feature,
+        comparison,
+        value,
+    })
+}
+
+/// Container queries are evaluated against the content box of the container.
+fn content_size(node: NodeRef, taffy: &Taffy) -> Option<Size<f32>> {
+    let taffy_node = node.get::<TaffyLayout>()?.node?;
+    let layout = taffy.layout(taffy_node).ok()?;
+    let style = taffy.style(taffy_node).ok()?;
+    let size = layout.size;
+    // percentages are resolved against the width of the container
+    let resolve = |dimension: Dimension| match dimension {
+        Dimension::Points(points) => points,
+        Dimension::Percent(percent) => percent * size.width,
+        _ => 0.0,
+    };
+    let horizontal = resolve(style.padding.left)
+        + resolve(style.padding.right)
+        + resolve(style.border.left)
+        + resolve(style.border.right);
+    let vertical = resolve(style.padding.top)
+        + resolve(style.padding.bottom)
+        + resolve(style.border.top)
+        + resolve(style.border.bottom);
+    Some(Size {
+        width: (size.width - horizontal).max(0.0),
+        height: (size.height - vertical).max(0.0),
+    })
+}
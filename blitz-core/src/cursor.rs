@@ -0,0 +1,206 @@
+//! The `cursor` property.
+//!
+//! Keywords map to the cursors of the platform. `auto` is the text cursor over text fields and the arrow everywhere else, and links show the pointing hand unless they set a cursor themselves. tao can't set an image as the cursor, so when the hovered element uses `url()` the system cursor is hidden and the image is drawn over the scene at the pointer instead. Images that fail to load are skipped and the next value in the list is used.
+
+use std::sync::Arc;
+
+use cssparser::{Parser, ParserInput};
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use shipyard::Component;
+use tao::window::CursorIcon;
+use vello::kurbo::Vec2;
+use vello::peniko::Image;
+
+use crate::image::ImageContext;
+use crate::text_input::is_text_input_type;
+
+/// An image used as the cursor.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct CursorImage {
+    pub image: Arc<Image>,
+    /// The point of the image that is placed at the pointer, in image pixels.
+    pub hotspot: Vec2,
+}
+
+/// The cursor shown over an element. It is inherited.
+#[derive(Clone, PartialEq, Debug, Component)]
+pub(crate) struct Cursor {
+    /// The first image in the list that loaded.
+    pub image: Option<CursorImage>,
+    /// The keyword the list falls back to. `None` is `cursor: none`, which hides the cursor.
+    pub keyword: Option<CursorIcon>,
+    /// If the keyword is `auto`, which is resolved from the element it applies to.
+    pub auto: bool,
+}
+
+impl Default for Cursor {
+    fn default() -> Self {
+        Cursor {
+            image: None,
+            keyword: Some(CursorIcon::Default),
+            auto: true,
+        }
+    }
+}
+
+#[partial_derive_state]
+impl State for Cursor {
+    type ChildDependencies = ();
+    type ParentDependencies = (Self,);
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new()
+            .with_tag()
+            .with_attrs(AttributeMaskBuilder::Some(&[
+                "cursor",
+                "href",
+                "type",
+                "contenteditable",
+            ]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        ctx: &SendAnyMap,
+    ) -> bool {
+        let mut new = parent.map(|(parent,)| parent.clone()).unwrap_or_default();
+        let attribute = |name: &str| {
+            node_view
+                .attributes()
+                .into_iter()
+                .flatten()
+                .find(|attr| attr.attribute.name == name)
+                .and_then(|attr| attr.value.as_text().map(str::to_string))
+        };
+        // links point like `a:any-link { cursor: pointer }` in the default stylesheet of browsers
+        if node_view.tag() == Some("a") && attribute("href").is_some() {
+            new = Cursor {
+                image: None,
+                keyword: Some(CursorIcon::Hand),
+                auto: false,
+            };
+        }
+        if let Some(as_text) = attribute("cursor") {
+            let image_ctx: &ImageContext = ctx.get().expect("ImageContext not found");
+            if let Some(cursor) = parse_cursor(&as_text, image_ctx) {
+                new = cursor;
+            }
+        }
+        if new.auto {
+            let text_input = match node_view.tag() {
+                Some("textarea") => true,
+                Some("input") => is_text_input_type(attribute("type").as_deref()),
+                _ => attribute("contenteditable").is_some_and(|value| value != "false"),
+            };
+            new.keyword = Some(if text_input {
+                CursorIcon::Text
+            } else {
+                CursorIcon::Default
+            });
+        }
+
+        if *self != new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+/// Parses `[url(<path>) [<x> <y>]?,]* <keyword>`, loading the images as they are found.
+fn parse_cursor(text: &str, image_ctx: &ImageContext) -> Option<Cursor> {
+    let mut input = ParserInput::new(text);
+    let mut parser = Parser::new(&mut input);
+    let mut image = None;
+    loop {
+        if let Ok(url) =
+            parser.try_parse(|parser| parser.expect_url_or_string().map(|url| url.to_string()))
+        {
+            let x = parser.try_parse(|parser| parser.expect_number()).ok();
+            let y = x.and_then(|_| parser.try_parse(|parser| parser.expect_number()).ok());
+            let hotspot = match (x, y) {
+                (Some(x), Some(y)) => Vec2::new(x as f64, y as f64),
+                _ => Vec2::ZERO,
+            };
+            // later images are only used if the earlier ones fail to load
+            if image.is_none() {
+                if let Ok(loaded) = image_ctx.load_file(url) {
+                    image = Some(CursorImage {
+                        image: loaded,
+                        hotspot,
+                    });
+                }
+            }
+            parser.expect_comma().ok()?;
+            continue;
+        }
+        let keyword = parser.expect_ident().ok()?.to_ascii_lowercase();
+        let auto = keyword == "auto";
+        let keyword = parse_keyword(&keyword)?;
+        return parser.is_exhausted().then_some(Cursor {
+            image,
+            keyword,
+            auto,
+        });
+    }
+}
+
+fn parse_keyword(keyword: &str) -> Option<Option<CursorIcon>> {
+    let icon = match keyword {
+        "none" => return Some(None),
+        "auto" | "default" => CursorIcon::Default,
+        "pointer" => CursorIcon::Hand,
+        "crosshair" => CursorIcon::Crosshair,
+        "move" => CursorIcon::Move,
+        "text" => CursorIcon::Text,
+        "wait" => CursorIcon::Wait,
+        "help" => CursorIcon::Help,
+        "progress" => CursorIcon::Progress,
+        "not-allowed" => CursorIcon::NotAllowed,
+        "context-menu" => CursorIcon::ContextMenu,
+        "cell" => CursorIcon::Cell,
+        "vertical-text" => CursorIcon::VerticalText,
+        "alias" => CursorIcon::Alias,
+        "copy" => CursorIcon::Copy,
+        "no-drop" => CursorIcon::NoDrop,
+        "grab" => CursorIcon::Grab,
+        "grabbing" => CursorIcon::Grabbing,
+        "all-scroll" => CursorIcon::AllScroll,
+        "zoom-in" => CursorIcon::ZoomIn,
+        "zoom-out" => CursorIcon::ZoomOut,
+        "e-resize" => CursorIcon::EResize,
+        "n-resize" => CursorIcon::NResize,
+        "ne-resize" => CursorIcon::NeResize,
+        "nw-resize" => CursorIcon::NwResize,
+        "s-resize" => CursorIcon::SResize,
+        "se-resize" => CursorIcon::SeResize,
+        "sw-resize" => CursorIcon::SwResize,
+        "w-resize" => CursorIcon::WResize,
+        "ew-resize" => CursorIcon::EwResize,
+        "ns-resize" => CursorIcon::NsResize,
+        "nesw-resize" => CursorIcon::NeswResize,
+        "nwse-resize" => CursorIcon::NwseResize,
+        "col-resize" => CursorIcon::ColResize,
+        "row-resize" => CursorIcon::RowResize,
+        _ => return None,
+    };
+    Some(Some(icon))
+}
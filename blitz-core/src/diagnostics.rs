@@ -0,0 +1,143 @@
+//! Warnings about style attributes that are ignored.
+//!
+//! Passes skip values they can't parse, so a typo in a style leaves the element with its default style and no other feedback. When [`Config::with_style_diagnostics`](crate::Config::with_style_diagnostics) is on, every style attribute is also parsed as a CSS declaration. Values that aren't valid for their property and names that aren't CSS properties are printed to stderr once and kept on the node so they can be read with [`WindowHandle::style_warnings`](crate::WindowHandle::style_warnings).
+//!
+//! Only the syntax is checked. A valid value of a property that isn't implemented yet doesn't produce a warning.
+
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use lightningcss::properties::{Property, PropertyId};
+use lightningcss::stylesheet::ParserOptions;
+use shipyard::Component;
+
+/// Why a style attribute is ignored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StyleWarningKind {
+    /// The value isn't valid for the property.
+    InvalidValue,
+    /// The name isn't a CSS property.
+    UnknownProperty,
+}
+
+/// A style attribute of an element that is ignored.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StyleWarning {
+    pub node: NodeId,
+    pub property: String,
+    pub value: String,
+    pub kind: StyleWarningKind,
+}
+
+impl std::fmt::Display for StyleWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self.kind {
+            StyleWarningKind::InvalidValue => "the value is not valid for the property",
+            StyleWarningKind::UnknownProperty => "the property is not a CSS property",
+        };
+        write!(
+            f,
+            "ignoring `{}: {}` on node {:?}: {}",
+            self.property, self.value, self.node, reason
+        )
+    }
+}
+
+/// The warnings for the style attributes of a node. Only registered when diagnostics are on.
+#[derive(Clone, PartialEq, Debug, Default, Component)]
+pub(crate) struct StyleDiagnostics(pub Vec<StyleWarning>);
+
+#[partial_derive_state]
+impl State for StyleDiagnostics {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::All);
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let mut new = StyleDiagnostics::default();
+        for attr in node_view.attributes().into_iter().flatten() {
+            // other attributes like `href` or `type` are not styles
+            if attr.attribute.namespace.as_deref() != Some("style") {
+                continue;
+            }
+            let name = attr.attribute.name.as_str();
+            let value = match attr.value.as_text() {
+                Some(text) => text.to_string(),
+                None => match attr.value.as_float() {
+                    Some(number) => number.to_string(),
+                    None => match attr.value.as_int() {
+                        Some(number) => number.to_string(),
+                        None => continue,
+                    },
+                },
+            };
+            if let Some(kind) = check_declaration(name, &value) {
+                new.0.push(StyleWarning {
+                    node: node_view.node_id(),
+                    property: name.to_string(),
+                    value,
+                    kind,
+                });
+            }
+        }
+
+        if *self != new {
+            // only the warnings that are new are printed
+            for warning in new.0.iter().filter(|warning| !self.0.contains(warning)) {
+                eprintln!("blitz: {warning}");
+            }
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+/// Parses a declaration and returns why it would be ignored, or `None` if it is valid.
+pub(crate) fn check_declaration(name: &str, value: &str) -> Option<StyleWarningKind> {
+    let property = Property::parse_string(PropertyId::from(name), value, ParserOptions::default());
+    match property {
+        // values that contain var() are also unparsed, they can't be checked without the variables
+        Ok(Property::Unparsed(_)) if !value.contains("var(") => {
+            Some(StyleWarningKind::InvalidValue)
+        }
+        Ok(Property::Custom(_)) if !name.starts_with("--") => {
+            Some(StyleWarningKind::UnknownProperty)
+        }
+        Ok(_) => None,
+        Err(_) => Some(StyleWarningKind::InvalidValue),
+    }
+}
+
+/// Returns the warnings of every node in the dom in document order.
+pub(crate) fn style_warnings(rdom: &RealDom) -> Vec<StyleWarning> {
+    let mut warnings = Vec::new();
+    rdom.traverse_depth_first(|node| {
+        if let Some(diagnostics) = node.get::<StyleDiagnostics>() {
+            warnings.extend(diagnostics.0.iter().cloned());
+        }
+    });
+    warnings
+}
@@ -1,5 +1,5 @@
 use keyboard_types::Code;
-use quadtree_rs::Quadtree;
+use rustc_hash::FxHashSet;
 use std::{
     any::Any,
     rc::Rc,
@@ -8,24 +8,31 @@ use std::{
     time::{Duration, Instant},
 };
 use taffy::{prelude::Size, Taffy};
-use tao::event::MouseButton;
-use vello::kurbo::Point;
+use tao::event::{MouseButton, MouseScrollDelta};
+use vello::kurbo::{Point, Vec2};
 
 use dioxus_html::{
-    events::{FocusData, KeyboardData, MouseData, WheelData},
+    events::{CompositionData, FocusData, FormData, KeyboardData, MouseData, WheelData},
     geometry::{euclid::Point2D, ClientPoint, Coordinates, ElementPoint, PagePoint, ScreenPoint},
     input_data::{self, keyboard_types::Modifiers, MouseButtonSet},
 };
 use dioxus_native_core::prelude::*;
 
-use tao::keyboard::Key;
+use tao::{clipboard::Clipboard, event::KeyEvent, keyboard::Key};
 
 use crate::{
     application::DirtyNodes,
+    checkbox::{is_checked, Checkable, CheckedState},
     focus::{Focus, FocusState},
-    mouse::get_hovered,
+    hit_test::{focusable_at, hit_test},
+    mouse::{listening_ancestor, PseudoClassState},
     prevent_default::PreventDefault,
-    RealDom, TaoEvent,
+    scroll::{scroll_at, LINE_HEIGHT},
+    selection::{text_position_at, SelectionState},
+    text::TextContext,
+    text_input::{selected_field_text, EditingState},
+    util::has_tag,
+    KeyRepeat, RealDom, TaoEvent,
 };
 
 const DBL_CLICK_TIME: Duration = Duration::from_millis(500);
@@ -37,6 +44,8 @@ struct CursorState {
     last_pressed_element: Option<NodeId>,
     last_clicked_element: Option<NodeId>,
     hovered: Option<NodeId>,
+    /// The element that captured the pointer. Mouse moves and button events are sent to it instead of the hovered element until the buttons are released.
+    captured: Option<NodeId>,
 }
 
 impl CursorState {
@@ -70,19 +79,47 @@ impl Default for CursorState {
             last_pressed_element: Default::default(),
             last_clicked_element: Default::default(),
             hovered: Default::default(),
+            captured: Default::default(),
         }
     }
 }
 
+/// A key that is held down while [`KeyRepeat::Custom`] is used.
+struct HeldKey {
+    event: KeyEvent,
+    next_repeat: Instant,
+}
+
 struct EventState {
     modifier_state: Modifiers,
     cursor_state: CursorState,
     focus_state: FocusState,
+    pseudo_class_state: PseudoClassState,
+    key_repeat: KeyRepeat,
+    held_key: Option<HeldKey>,
+    /// The scroll containers that were scrolled since the last frame.
+    scrolled: FxHashSet<NodeId>,
+    selection_state: SelectionState,
+    checked_state: CheckedState,
+    editing_state: EditingState,
 }
 
 impl EventState {
     fn clean(&mut self) -> DirtyNodes {
-        self.focus_state.clean()
+        self.focus_state
+            .clean()
+            .or(self.pseudo_class_state.clean())
+            .or(DirtyNodes::Some(std::mem::take(&mut self.scrolled)))
+            .or(self.selection_state.clean())
+            .or(self.checked_state.clean())
+            .or(self.editing_state.clean())
+    }
+
+    /// Returns true if the focus or a pseudo class changed since the last call, so the state of the dom needs to be updated.
+    fn take_restyle(&mut self) -> bool {
+        // both are taken so neither flag is left set
+        let focus = self.focus_state.take_restyle();
+        self.pseudo_class_state.take_restyle() || focus
     }
 }
 
@@ -97,8 +134,10 @@ pub struct DomEvent {
 pub enum EventData {
     Mouse(MouseData),
     Keyboard(KeyboardData),
+    Composition(CompositionData),
     Focus(FocusData),
     Wheel(WheelData),
+    Form(FormData),
 }
 
 impl EventData {
@@ -106,8 +145,10 @@ impl EventData {
         match self {
             EventData::Mouse(data) => Rc::new(data),
             EventData::Keyboard(data) => Rc::new(data),
+            EventData::Composition(data) => Rc::new(data),
             EventData::Focus(data) => Rc::new(data),
             EventData::Wheel(data) => Rc::new(data),
+            EventData::Form(data) => Rc::new(data),
         }
     }
 }
@@ -119,12 +160,19 @@ pub struct BlitzEventHandler {
 }
 
 impl BlitzEventHandler {
-    pub(crate) fn new(focus_state: FocusState) -> Self {
+    pub(crate) fn new(focus_state: FocusState, key_repeat: KeyRepeat) -> Self {
         Self {
             state: EventState {
                 focus_state,
                 modifier_state: Default::default(),
                 cursor_state: Default::default(),
+                pseudo_class_state: Default::default(),
+                key_repeat,
+                held_key: None,
+                scrolled: Default::default(),
+                selection_state: Default::default(),
+                checked_state: Default::default(),
+                editing_state: Default::default(),
             },
             queued_events: Default::default(),
         }
@@ -134,13 +182,266 @@ impl BlitzEventHandler {
         self.state.clean()
     }
 
+    /// Returns true if the handler changed attributes that passes depend on since the last call.
+    pub(crate) fn take_restyle(&mut self) -> bool {
+        self.state.take_restyle()
+    }
+
+    /// Sends the keyboard events for a key press or release to the focused element.
+    fn register_key(&mut self, event: &KeyEvent, rdom: &mut RealDom) {
+        let key = map_key(&event.logical_key);
+        let code = map_code(&event.physical_key);
+
+        let data = Arc::new(EventData::Keyboard(KeyboardData::new(
+            key,
+            code,
+            match event.location {
+                tao::keyboard::KeyLocation::Standard => {
+                    input_data::keyboard_types::Location::Standard
+                }
+                tao::keyboard::KeyLocation::Left => input_data::keyboard_types::Location::Left,
+                tao::keyboard::KeyLocation::Right => input_data::keyboard_types::Location::Right,
+                tao::keyboard::KeyLocation::Numpad => input_data::keyboard_types::Location::Numpad,
+                _ => todo!(),
+            },
+            event.repeat,
+            self.state.modifier_state,
+        )));
+
+        // keypress events are only triggered when a key that has text is pressed
+        if let tao::event::ElementState::Pressed = event.state {
+            if event.text.is_some() {
+                self.queued_events.push(DomEvent {
+                    name: "keypress",
+                    element: *rdom
+                        .get(rdom.root_id())
+                        .unwrap()
+                        .child_ids()
+                        .first()
+                        .unwrap(),
+                    data: data.clone(),
+                    bubbles: true,
+                });
+            }
+            let shortcut = self.state.modifier_state.contains(Modifiers::CONTROL)
+                || self.state.modifier_state.contains(Modifiers::META);
+            if shortcut
+                && matches!(&event.logical_key, Key::Character(c) if c.eq_ignore_ascii_case("c"))
+            {
+                self.copy_selection(rdom);
+            }
+            if let Key::Tab = event.logical_key {
+                self.state
+                    .focus_state
+                    .progress(rdom, !self.state.modifier_state.contains(Modifiers::SHIFT));
+                return;
+            }
+        }
+
+        if let Some(element) = self.state.focus_state.last_focused_id {
+            self.queued_events.push(DomEvent {
+                element,
+                name: match event.state {
+                    tao::event::ElementState::Pressed => "keydown",
+                    tao::event::ElementState::Released => "keyup",
+                    _ => todo!(),
+                },
+                data,
+                bubbles: true,
+            });
+
+            // text fields take the text of the key unless the keydown is prevented
+            let prevent_default = *rdom.get(element).unwrap().get::<PreventDefault>().unwrap();
+            if matches!(event.state, tao::event::ElementState::Pressed)
+                && prevent_default != PreventDefault::KeyDown
+            {
+                let modifiers = self.state.modifier_state;
+                if let Some(value) = self
+                    .state
+                    .editing_state
+                    .key(rdom, element, event, modifiers)
+                {
+                    self.queued_events.push(DomEvent {
+                        element,
+                        name: "input",
+                        data: Arc::new(EventData::Form(FormData {
+                            value,
+                            values: Default::default(),
+                            files: None,
+                        })),
+                        bubbles: true,
+                    });
+                }
+            }
+
+            // Buttons are activated when Enter is pressed or Space is released, checkboxes and radio buttons only with Space
+            let node = rdom.get(element).unwrap();
+            let checkable = node
+                .get::<Checkable>()
+                .is_some_and(|checkable| checkable.kind.is_some());
+            if has_tag(node, "button") || checkable {
+                let prevent_default = *node.get::<PreventDefault>().unwrap();
+                let activated = match (&event.logical_key, event.state) {
+                    (Key::Enter, tao::event::ElementState::Pressed) => {
+                        !checkable && prevent_default != PreventDefault::KeyDown
+                    }
+                    (Key::Space, tao::event::ElementState::Released) => {
+                        prevent_default != PreventDefault::KeyUp
+                    }
+                    _ => false,
+                };
+                if activated {
+                    self.queued_events.push(DomEvent {
+                        element,
+                        name: "click",
+                        data: Arc::new(EventData::Mouse(
+                            self.state.cursor_state.get_event_mouse_data(),
+                        )),
+                        bubbles: true,
+                    });
+                    self.toggle_checked(rdom, element);
+                }
+            }
+        }
+    }
+
+    /// Copies the selected text to the clipboard. The selection in the focused text field is copied if it has one. Nothing is copied if the focused element prevents the default of keydown.
+    fn copy_selection(&self, rdom: &RealDom) {
+        let focused = self
+            .state
+            .focus_state
+            .last_focused_id
+            .and_then(|focused| rdom.get(focused));
+        if focused.is_some_and(|focused| {
+            *focused.get::<PreventDefault>().unwrap() == PreventDefault::KeyDown
+        }) {
+            return;
+        }
+        let text = focused
+            .and_then(selected_field_text)
+            .unwrap_or_else(|| self.state.selection_state.selected_text(rdom));
+        if !text.is_empty() {
+            Clipboard::new().write_text(text);
+        }
+    }
+
+    /// Toggles a checkbox or radio button after it is clicked, unless the click prevents the default. Sends `input` and `change` events with the new state if it changed.
+    fn toggle_checked(&mut self, rdom: &mut RealDom, element: NodeId) {
+        let prevent_default = *rdom.get(element).unwrap().get::<PreventDefault>().unwrap();
+        if prevent_default == PreventDefault::Click
+            || !self.state.checked_state.activate(rdom, element)
+        {
+            return;
+        }
+        let data = Arc::new(EventData::Form(FormData {
+            value: is_checked(rdom.get(element).unwrap()).to_string(),
+            values: Default::default(),
+            files: None,
+        }));
+        for name in ["input", "change"] {
+            self.queued_events.push(DomEvent {
+                element,
+                name,
+                data: data.clone(),
+                bubbles: true,
+            });
+        }
+    }
+
+    /// Returns the element that has focus.
+    pub(crate) fn focused(&self) -> Option<NodeId> {
+        self.state.focus_state.last_focused_id
+    }
+
+    /// Moves the focus to `element` if it can be focused and is inside the active focus trap.
+    pub(crate) fn focus(&mut self, rdom: &mut RealDom, element: NodeId) {
+        let focusable = rdom.get(element).is_some_and(|node| {
+            node.get::<Focus>().unwrap().focusable()
+                && self.state.focus_state.in_trap(rdom, element)
+        });
+        if focusable {
+            self.state.focus_state.set_focus(rdom, element);
+        }
+    }
+
+    /// Keeps focus inside `container` until [`Self::pop_focus_trap`] is called.
+    pub(crate) fn push_focus_trap(&mut self, rdom: &mut RealDom, container: NodeId) {
+        self.state.focus_state.push_trap(rdom, container);
+    }
+
+    pub(crate) fn pop_focus_trap(&mut self, rdom: &mut RealDom) {
+        self.state.focus_state.pop_trap(rdom);
+    }
+
+    /// Sends mouse events to `element` until the buttons are released, even if the pointer leaves it. The capture is ignored if no button is held.
+    pub(crate) fn set_pointer_capture(&mut self, element: NodeId) {
+        if !self.state.cursor_state.buttons.is_empty() {
+            self.state.cursor_state.captured = Some(element);
+        }
+    }
+
+    pub(crate) fn release_pointer_capture(&mut self) {
+        self.state.cursor_state.captured = None;
+    }
+
+    /// Returns the element mouse events are sent to: the element that captured the pointer if it still exists, or the element under the cursor.
+    fn mouse_target(&self, rdom: &RealDom) -> Option<NodeId> {
+        self.state
+            .cursor_state
+            .captured
+            .filter(|captured| rdom.get(*captured).is_some())
+            .or(self.state.cursor_state.hovered)
+    }
+
+    /// Returns the topmost element under the cursor, even if it doesn't listen for mouse events.
+    pub(crate) fn hovered(&self) -> Option<NodeId> {
+        self.state.pseudo_class_state.hit()
+    }
+
+    /// Returns the position of the cursor in the window.
+    pub(crate) fn cursor_position(&self) -> Point {
+        let position = self.state.cursor_state.position.client();
+        Point::new(position.x, position.y)
+    }
+
+    /// Blinks the caret of the focused text field. Returns when it blinks next.
+    pub(crate) fn blink_caret(&mut self, rdom: &mut RealDom) -> Option<Instant> {
+        let focused = self.state.focus_state.last_focused_id;
+        self.state.editing_state.blink(rdom, focused)
+    }
+
+    /// Returns when the held key should repeat next.
+    pub(crate) fn next_key_repeat(&self) -> Option<Instant> {
+        self.state.held_key.as_ref().map(|held| held.next_repeat)
+    }
+
+    /// Sends a repeat of the held key if it is due.
+    pub(crate) fn repeat_held_key(&mut self, rdom: &mut RealDom) {
+        let KeyRepeat::Custom { interval, .. } = self.state.key_repeat else {
+            return;
+        };
+        let Some(held) = &mut self.state.held_key else {
+            return;
+        };
+        let now = Instant::now();
+        if held.next_repeat > now {
+            return;
+        }
+        // skip the repeats that were missed instead of sending them all at once
+        held.next_repeat = (held.next_repeat + interval).max(now);
+        let mut event = held.event.clone();
+        event.repeat = true;
+        self.register_key(&event, rdom);
+    }
+
     pub(crate) fn register_event(
         &mut self,
         event: &TaoEvent,
         rdom: &mut RealDom,
         taffy: &Taffy,
         viewport_size: &Size<u32>,
-        quadtree: &Quadtree<u64, NodeId>,
+        scale_factor: f64,
+        text_context: &TextContext,
     ) {
         match event {
             tao::event::Event::NewEvents(_) => (),
@@ -157,75 +458,65 @@ impl BlitzEventHandler {
                     tao::event::WindowEvent::DroppedFile(_) => (),
                     tao::event::WindowEvent::HoveredFile(_) => (),
                     tao::event::WindowEvent::HoveredFileCancelled => (),
-                    tao::event::WindowEvent::ReceivedImeText(_) => (),
-                    tao::event::WindowEvent::Focused(_) => (),
+                    tao::event::WindowEvent::ReceivedImeText(text) => {
+                        // tao only reports the committed text, so the whole composition is sent at once
+                        if let Some(element) = self.state.focus_state.last_focused_id {
+                            for (name, data) in [
+                                ("compositionstart", String::new()),
+                                ("compositionupdate", text.clone()),
+                                ("compositionend", text.clone()),
+                            ] {
+                                self.queued_events.push(DomEvent {
+                                    element,
+                                    name,
+                                    data: Arc::new(EventData::Composition(CompositionData {
+                                        data,
+                                    })),
+                                    bubbles: true,
+                                });
+                            }
+                        }
+                    }
+                    tao::event::WindowEvent::Focused(focused) => {
+                        // the key release is sent to another window
+                        if !focused {
+                            self.state.held_key = None;
+                        }
+                    }
                     tao::event::WindowEvent::KeyboardInput {
                         device_id: _,
                         event,
                         is_synthetic: _,
                         ..
                     } => {
-                        let key = map_key(&event.logical_key);
-                        let code = map_code(&event.physical_key);
-
-                        let data = Arc::new(EventData::Keyboard(KeyboardData::new(
-                            key,
-                            code,
-                            match event.location {
-                                tao::keyboard::KeyLocation::Standard => {
-                                    input_data::keyboard_types::Location::Standard
-                                }
-                                tao::keyboard::KeyLocation::Left => {
-                                    input_data::keyboard_types::Location::Left
-                                }
-                                tao::keyboard::KeyLocation::Right => {
-                                    input_data::keyboard_types::Location::Right
+                        match self.state.key_repeat {
+                            KeyRepeat::System => (),
+                            // repeats are sent by the event loop or not at all
+                            KeyRepeat::Custom { .. } | KeyRepeat::Disabled if event.repeat => {
+                                return
+                            }
+                            KeyRepeat::Custom { delay, .. } => match event.state {
+                                tao::event::ElementState::Pressed => {
+                                    self.state.held_key = Some(HeldKey {
+                                        event: event.clone(),
+                                        next_repeat: Instant::now() + delay,
+                                    });
                                 }
-                                tao::keyboard::KeyLocation::Numpad => {
-                                    input_data::keyboard_types::Location::Numpad
+                                _ => {
+                                    if self
+                                        .state
+                                        .held_key
+                                        .as_ref()
+                                        .map(|held| &held.event.physical_key)
+                                        == Some(&event.physical_key)
+                                    {
+                                        self.state.held_key = None;
+                                    }
                                 }
-                                _ => todo!(),
                             },
-                            event.repeat,
-                            self.state.modifier_state,
-                        )));
-
-                        // keypress events are only triggered when a key that has text is pressed
-                        if let tao::event::ElementState::Pressed = event.state {
-                            if event.text.is_some() {
-                                self.queued_events.push(DomEvent {
-                                    name: "keypress",
-                                    element: *rdom
-                                        .get(rdom.root_id())
-                                        .unwrap()
-                                        .child_ids()
-                                        .first()
-                                        .unwrap(),
-                                    data: data.clone(),
-                                    bubbles: true,
-                                });
-                            }
-                            if let Key::Tab = event.logical_key {
-                                self.state.focus_state.progress(
-                                    rdom,
-                                    !self.state.modifier_state.contains(Modifiers::SHIFT),
-                                );
-                                return;
-                            }
-                        }
-
-                        if let Some(element) = self.state.focus_state.last_focused_id {
-                            self.queued_events.push(DomEvent {
-                                element,
-                                name: match event.state {
-                                    tao::event::ElementState::Pressed => "keydown",
-                                    tao::event::ElementState::Released => "keyup",
-                                    _ => todo!(),
-                                },
-                                data,
-                                bubbles: true,
-                            });
+                            KeyRepeat::Disabled => (),
                         }
+                        self.register_key(event, rdom);
                     }
                     tao::event::WindowEvent::ModifiersChanged(mods) => {
                         let mut modifiers = Modifiers::empty();
@@ -248,8 +539,13 @@ impl BlitzEventHandler {
                         position,
                         ..
                     } => {
+                        // the layout is in css pixels, tao reports device pixels
+                        let position = position.to_logical::<f64>(scale_factor);
                         let pos = Point::new(position.x, position.y);
-                        let hovered = get_hovered(taffy, rdom, viewport_size, pos, quadtree);
+                        let hit = hit_test(rdom, taffy, viewport_size, pos);
+                        // :hover follows the element under the cursor, mouse events go to the element listening for them
+                        self.state.pseudo_class_state.set_hovered(rdom, hit);
+                        let hovered = hit.and_then(|hit| listening_ancestor(rdom, hit));
                         let (mouse_x, mouse_y) = (pos.x as i32, pos.y as i32);
                         let screen_point = ScreenPoint::new(mouse_x as f64, mouse_y as f64);
                         let client_point = ClientPoint::new(mouse_x as f64, mouse_y as f64);
@@ -265,6 +561,7 @@ impl BlitzEventHandler {
                             self.state.cursor_state.buttons,
                             self.state.modifier_state,
                         );
+                        let move_data = Arc::new(EventData::Mouse(data.clone()));
                         match (hovered, self.state.cursor_state.hovered) {
                             (Some(hovered), Some(old_hovered)) => {
                                 if hovered != old_hovered {
@@ -304,6 +601,21 @@ impl BlitzEventHandler {
                             (None, None) => (),
                         }
                         self.state.cursor_state.position = position;
+                        if let Some(target) = self.mouse_target(rdom) {
+                            self.queued_events.push(DomEvent {
+                                element: target,
+                                name: "mousemove",
+                                data: move_data,
+                                bubbles: true,
+                            });
+                        }
+                        if self.state.selection_state.is_dragging() {
+                            if let Some(position) =
+                                text_position_at(rdom, taffy, text_context, viewport_size, pos)
+                            {
+                                self.state.selection_state.extend(rdom, position);
+                            }
+                        }
                     }
                     tao::event::WindowEvent::CursorEntered { device_id: _ } => {}
                     tao::event::WindowEvent::CursorLeft { device_id: _ } => {
@@ -318,20 +630,79 @@ impl BlitzEventHandler {
                             });
                             self.state.cursor_state.hovered = None;
                         }
+                        self.state.pseudo_class_state.set_hovered(rdom, None);
                     }
                     tao::event::WindowEvent::MouseWheel {
                         device_id: _,
-                        delta: _,
+                        delta,
                         phase: _,
                         ..
-                    } => (),
+                    } => {
+                        // positive deltas move the content down, which scrolls towards the start
+                        let delta = match delta {
+                            MouseScrollDelta::LineDelta(x, y) => {
+                                Vec2::new(-*x as f64, -*y as f64) * LINE_HEIGHT
+                            }
+                            MouseScrollDelta::PixelDelta(delta) => {
+                                Vec2::new(-delta.x, -delta.y) / scale_factor
+                            }
+                            _ => Vec2::ZERO,
+                        };
+                        let cursor = self.state.cursor_state.position.client();
+                        if let Some(scrolled) = scroll_at(
+                            rdom,
+                            taffy,
+                            viewport_size,
+                            Point::new(cursor.x, cursor.y),
+                            delta,
+                        ) {
+                            self.state.scrolled.insert(scrolled);
+                        }
+                    }
                     tao::event::WindowEvent::MouseInput {
                         device_id: _,
                         state,
                         button,
                         ..
                     } => {
-                        if let Some(hovered) = self.state.cursor_state.hovered {
+                        if *button == MouseButton::Left {
+                            match state {
+                                tao::event::ElementState::Pressed => {
+                                    let cursor = self.state.cursor_state.position.client();
+                                    let position = text_position_at(
+                                        rdom,
+                                        taffy,
+                                        text_context,
+                                        viewport_size,
+                                        Point::new(cursor.x, cursor.y),
+                                    );
+                                    self.state.selection_state.start(rdom, position);
+                                    // pressing an element focuses it or its nearest focusable ancestor, even without listeners
+                                    let focusable = focusable_at(
+                                        rdom,
+                                        taffy,
+                                        viewport_size,
+                                        Point::new(cursor.x, cursor.y),
+                                    );
+                                    if let Some(focusable) = focusable {
+                                        let prevented = *rdom
+                                            .get(focusable)
+                                            .unwrap()
+                                            .get::<PreventDefault>()
+                                            .unwrap()
+                                            == PreventDefault::MouseDown;
+                                        if !prevented
+                                            && self.state.focus_state.in_trap(rdom, focusable)
+                                        {
+                                            self.state.focus_state.set_focus(rdom, focusable);
+                                        }
+                                    }
+                                }
+                                _ => self.state.selection_state.end(),
+                            }
+                        }
+                        // button events go to the element that captured the pointer even if it isn't under the cursor
+                        if let Some(target) = self.mouse_target(rdom) {
                             let button = match button {
                                 MouseButton::Left => input_data::MouseButton::Primary,
                                 MouseButton::Middle => input_data::MouseButton::Auxiliary,
@@ -369,48 +740,55 @@ impl BlitzEventHandler {
                             )));
 
                             let prevent_default = {
-                                let hovered_node = rdom.get(hovered).unwrap();
-                                let default = *hovered_node.get::<PreventDefault>().unwrap();
+                                let target_node = rdom.get(target).unwrap();
+                                let default = *target_node.get::<PreventDefault>().unwrap();
                                 default
                             };
                             match state {
                                 tao::event::ElementState::Pressed => {
                                     self.queued_events.push(DomEvent {
-                                        element: hovered,
+                                        element: target,
                                         name: "mousedown",
                                         data,
                                         bubbles: true,
                                     });
-                                    self.state.cursor_state.last_pressed_element = Some(hovered);
+                                    self.state.cursor_state.last_pressed_element = Some(target);
+                                    self.state.pseudo_class_state.set_active(rdom, Some(target));
                                 }
                                 tao::event::ElementState::Released => {
                                     self.queued_events.push(DomEvent {
-                                        element: hovered,
+                                        element: target,
                                         name: "mouseup",
                                         data: data.clone(),
                                         bubbles: true,
                                     });
+                                    self.state.pseudo_class_state.set_active(rdom, None);
+                                    // the capture ends once every button is released
+                                    if self.state.cursor_state.buttons.is_empty() {
+                                        self.state.cursor_state.captured = None;
+                                    }
 
                                     // click events only trigger if the mouse button is pressed and released on the same element
                                     if self.state.cursor_state.last_pressed_element.take()
-                                        == Some(hovered)
+                                        == Some(target)
                                     {
                                         self.queued_events.push(DomEvent {
-                                            element: hovered,
+                                            element: target,
                                             name: "click",
                                             data: data.clone(),
                                             bubbles: true,
                                         });
+                                        self.toggle_checked(rdom, target);
 
                                         if let Some(last_clicked) =
                                             self.state.cursor_state.last_click.take()
                                         {
                                             if self.state.cursor_state.last_clicked_element
-                                                == Some(hovered)
+                                                == Some(target)
                                                 && last_clicked.elapsed() < DBL_CLICK_TIME
                                             {
                                                 self.queued_events.push(DomEvent {
-                                                    element: hovered,
+                                                    element: target,
                                                     name: "dblclick",
                                                     data,
                                                     bubbles: true,
@@ -418,8 +796,7 @@ impl BlitzEventHandler {
                                             }
                                         }
 
-                                        self.state.cursor_state.last_clicked_element =
-                                            Some(hovered);
+                                        self.state.cursor_state.last_clicked_element = Some(target);
                                         self.state.cursor_state.last_click = Some(Instant::now());
                                     }
                                 }
@@ -427,14 +804,14 @@ impl BlitzEventHandler {
                             }
                             if prevent_default != PreventDefault::MouseUp
                                 && rdom
-                                    .get(hovered)
+                                    .get(target)
                                     .unwrap()
                                     .get::<Focus>()
                                     .unwrap()
-                                    .level
                                     .focusable()
+                                && self.state.focus_state.in_trap(rdom, target)
                             {
-                                self.state.focus_state.set_focus(rdom, hovered);
+                                self.state.focus_state.set_focus(rdom, target);
                             }
                         }
                     }
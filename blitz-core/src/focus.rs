@@ -1,4 +1,11 @@
-use crate::{application::DirtyNodes, prevent_default::PreventDefault, RealDom};
+use crate::{
+    application::DirtyNodes,
+    layout::TaffyLayout,
+    mouse::{set_pseudo_class, FOCUS_ATTRIBUTE},
+    prevent_default::PreventDefault,
+    visibility::is_visible,
+    RealDom,
+};
 
 use std::{cmp::Ordering, num::NonZeroU16};
 
@@ -10,6 +17,7 @@ use dioxus_native_core_macro::partial_derive_state;
 use once_cell::sync::Lazy;
 use rustc_hash::FxHashSet;
 use shipyard::Component;
+use taffy::style::Display;
 
 #[derive(Component)]
 pub struct Focused(pub bool);
@@ -56,7 +64,17 @@ impl Ord for FocusLevel {
 
 #[derive(Clone, PartialEq, Debug, Default, Component)]
 pub(crate) struct Focus {
+    /// Where Tab stops at the element. Elements that are [`FocusLevel::Unfocusable`] are skipped.
     pub level: FocusLevel,
+    /// If the element has a negative `tabindex`. It can be focused by clicking it or with [`WindowHandle::focus`](crate::WindowHandle::focus), but Tab skips it.
+    pub scripted: bool,
+}
+
+impl Focus {
+    /// Returns true if the element can be focused by clicking it or by the app, even if Tab skips it.
+    pub fn focusable(&self) -> bool {
+        self.level.focusable() || self.scripted
+    }
 }
 
 #[partial_derive_state]
@@ -65,6 +83,7 @@ impl State for Focus {
     type ParentDependencies = ();
     type NodeDependencies = ();
     const NODE_MASK: NodeMaskBuilder<'static> = NodeMaskBuilder::new()
+        .with_tag()
         .with_attrs(AttributeMaskBuilder::Some(FOCUS_ATTRIBUTES))
         .with_listeners();
 
@@ -76,16 +95,17 @@ impl State for Focus {
         _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
         _: &SendAnyMap,
     ) -> bool {
-        let new = Focus {
-            level: if let Some(a) = node_view
-                .attributes()
-                .and_then(|mut iter| iter.find(|a| a.attribute.name == "tabindex"))
-            {
-                if let Some(index) = a
-                    .value
+        let tabindex = node_view
+            .attributes()
+            .and_then(|mut iter| iter.find(|a| a.attribute.name == "tabindex"))
+            .map(|a| {
+                a.value
                     .as_int()
-                    .or_else(|| a.value.as_text().and_then(|v| v.parse::<i64>().ok()))
-                {
+                    .or_else(|| a.value.as_text().and_then(|v| v.trim().parse::<i64>().ok()))
+            });
+        let new = Focus {
+            level: if let Some(tabindex) = tabindex {
+                if let Some(index) = tabindex {
                     match index.cmp(&0) {
                         Ordering::Less => FocusLevel::Unfocusable,
                         Ordering::Equal => FocusLevel::Focusable,
@@ -96,6 +116,24 @@ impl State for Focus {
                 } else {
                     FocusLevel::Unfocusable
                 }
+            } else if matches!(node_view.tag(), Some("button" | "input" | "textarea")) {
+                // disabled controls can't be focused
+                if node_view
+                    .attributes()
+                    .into_iter()
+                    .flatten()
+                    .any(|a| a.attribute.name == "disabled")
+                {
+                    FocusLevel::Unfocusable
+                } else {
+                    FocusLevel::Focusable
+                }
+            } else if node_view.attributes().into_iter().flatten().any(|a| {
+                a.attribute.name == "contenteditable"
+                    && a.value.as_bool() != Some(false)
+                    && a.value.as_text() != Some("false")
+            }) {
+                FocusLevel::Focusable
             } else if node_view
                 .listeners()
                 .into_iter()
@@ -106,6 +144,7 @@ impl State for Focus {
             } else {
                 FocusLevel::Unfocusable
             },
+            scripted: matches!(tabindex, Some(Some(index)) if index < 0),
         };
         if *self != new {
             *self = new;
@@ -130,13 +169,17 @@ impl State for Focus {
 
 static FOCUS_EVENTS: Lazy<FxHashSet<&str>> =
     Lazy::new(|| ["keydown", "keypress", "keyup"].into_iter().collect());
-const FOCUS_ATTRIBUTES: &[&str] = &["tabindex"];
+const FOCUS_ATTRIBUTES: &[&str] = &["tabindex", "disabled", "contenteditable"];
 
 pub(crate) struct FocusState {
     pub(crate) focus_iter: PersistantElementIter,
     pub(crate) last_focused_id: Option<NodeId>,
     pub(crate) focus_level: FocusLevel,
     pub(crate) dirty: FxHashSet<NodeId>,
+    /// Containers that keep focus inside them and the element that had focus when each was pushed. Only the last one is active.
+    pub(crate) traps: Vec<(NodeId, Option<NodeId>)>,
+    /// If the focus attribute of an element changed since the state of the dom was last updated.
+    restyle: bool,
 }
 
 impl FocusState {
@@ -147,9 +190,36 @@ impl FocusState {
             last_focused_id: None,
             focus_level: FocusLevel::default(),
             dirty: Default::default(),
+            traps: Vec::new(),
+            restyle: false,
+        }
+    }
+
+    /// Keeps focus inside `container` until the trap is popped. Focus moves to the first focusable element in the container if it is outside of it.
+    pub fn push_trap(&mut self, rdom: &mut RealDom, container: NodeId) {
+        self.traps.push((container, self.last_focused_id));
+        if !self
+            .last_focused_id
+            .is_some_and(|focused| self.in_trap(rdom, focused))
+        {
+            self.progress(rdom, true);
+        }
+    }
+
+    /// Removes the last focus trap and gives focus back to the element that had it before the trap was pushed.
+    pub fn pop_trap(&mut self, rdom: &mut RealDom) {
+        if let Some((_, previous)) = self.traps.pop() {
+            if let Some(previous) = previous.filter(|previous| rdom.get(*previous).is_some()) {
+                self.set_focus(rdom, previous);
+            }
         }
     }
 
+    /// Returns true if the node is inside the active focus trap, or if there is no trap.
+    pub fn in_trap(&self, rdom: &RealDom, id: NodeId) -> bool {
+        rdom.get(id).is_some_and(|node| in_trap(&self.traps, node))
+    }
+
     /// Returns true if the focus has changed.
     pub fn progress(&mut self, rdom: &mut RealDom, forward: bool) {
         if let Some(last) = self.last_focused_id {
@@ -159,6 +229,16 @@ impl FocusState {
                 return;
             }
         }
+        // without anything to focus inside the trap the search below would never end
+        let traps = &self.traps;
+        let mut trap_focusable = false;
+        rdom.traverse_depth_first(|n| {
+            trap_focusable |=
+                n.get::<Focus>().unwrap().level.focusable() && tab_reachable(traps, n);
+        });
+        if !trap_focusable {
+            return;
+        }
         // the id that started focused to track when a loop has happened
         let mut loop_marker_id = self.last_focused_id;
         let focus_level = &mut self.focus_level;
@@ -181,6 +261,7 @@ impl FocusState {
                         if node_level != *focus_level
                             && node_level.focusable()
                             && node_level > *focus_level
+                            && tab_reachable(traps, n)
                         {
                             if let Some(level) = &mut closest_level {
                                 if node_level < *level {
@@ -198,6 +279,7 @@ impl FocusState {
                         if node_level != *focus_level
                             && node_level.focusable()
                             && node_level < *focus_level
+                            && tab_reachable(traps, n)
                         {
                             if let Some(level) = &mut closest_level {
                                 if node_level > *level {
@@ -237,7 +319,10 @@ impl FocusState {
             } else {
                 current_level <= *focus_level
             };
-            if after_previous_focused && current_level.focusable() && current_level == *focus_level
+            if after_previous_focused
+                && current_level.focusable()
+                && current_level == *focus_level
+                && tab_reachable(traps, rdom.get(new_id).unwrap())
             {
                 next_focus = Some(new_id);
                 break;
@@ -245,28 +330,41 @@ impl FocusState {
         }
 
         if let Some(id) = next_focus {
-            rdom.get_mut(id).unwrap().insert(Focused(true));
+            self.set_focused(&mut rdom.get_mut(id).unwrap(), true);
             if let Some(old) = self.last_focused_id.replace(id) {
-                self.dirty.insert(old);
-                rdom.get_mut(old).unwrap().insert(Focused(false));
+                self.set_focused(&mut rdom.get_mut(old).unwrap(), false);
             }
             // reset the position to the currently focused element
             while self.focus_iter.next(rdom).id() != id {}
-            self.dirty.insert(id);
         }
     }
 
-    #[allow(unused)]
+    /// Moves the focus to the node, even if Tab would skip it.
     pub(crate) fn set_focus(&mut self, rdom: &mut RealDom, id: NodeId) {
         if let Some(old) = self.last_focused_id.replace(id) {
-            rdom.get_mut(old).unwrap().insert(Focused(false));
+            // the old element may have been removed
+            if let Some(mut old_node) = rdom.get_mut(old) {
+                self.set_focused(&mut old_node, false);
+            }
         }
         let mut node = rdom.get_mut(id).unwrap();
-        node.insert(Focused(true));
+        self.set_focused(&mut node, true);
         self.focus_level = node.get::<Focus>().unwrap().level;
         // reset the position to the currently focused element
         while self.focus_iter.next(rdom).id() != id {}
-        self.dirty.insert(id);
+    }
+
+    /// Sets the focus of an element and the attribute that styles depending on `:focus` read.
+    fn set_focused(&mut self, node: &mut NodeMut, focused: bool) {
+        node.insert(Focused(focused));
+        set_pseudo_class(node, FOCUS_ATTRIBUTE, focused);
+        self.dirty.insert(node.id());
+        self.restyle = true;
+    }
+
+    /// Returns true once after the focus moved, so the state of the dom can be updated.
+    pub fn take_restyle(&mut self) -> bool {
+        std::mem::take(&mut self.restyle)
     }
 
     pub fn clean(&mut self) -> DirtyNodes {
@@ -274,3 +372,42 @@ impl FocusState {
         DirtyNodes::Some(dirty)
     }
 }
+
+/// Returns true if Tab can move the focus to the node: it is inside the active focus trap, it isn't hidden with `visibility` and neither it nor its ancestors have `display: none`.
+fn tab_reachable(traps: &[(NodeId, Option<NodeId>)], node: NodeRef) -> bool {
+    if !is_visible(node) {
+        return false;
+    }
+    let mut current = Some(node);
+    while let Some(node) = current {
+        let hidden = node
+            .get::<TaffyLayout>()
+            .is_some_and(|layout| layout.style.display == Display::None);
+        if hidden {
+            return false;
+        }
+        current = node.parent();
+    }
+    in_trap(traps, node)
+}
+
+/// Returns true if the node is inside the last focus trap, or if there is no trap. Traps whose container was removed are ignored.
+fn in_trap(traps: &[(NodeId, Option<NodeId>)], node: NodeRef) -> bool {
+    let rdom = node.real_dom();
+    let Some(container) = traps
+        .iter()
+        .rev()
+        .map(|(container, _)| *container)
+        .find(|container| rdom.get(*container).is_some())
+    else {
+        return true;
+    };
+    let mut current = Some(node);
+    while let Some(node) = current {
+        if node.id() == container {
+            return true;
+        }
+        current = node.parent();
+    }
+    false
+}
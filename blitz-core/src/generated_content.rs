@@ -0,0 +1,790 @@
+//! Generated content for the `::before` and `::after` pseudo elements.
+//!
+//! There is no stylesheet to select pseudo elements with, so their content is set with the `before-content` and `after-content` attributes which accept the same values as the `content` property.
+//! Counters and quote nesting depend on every element before a node in document order, so unlike the other styles they are resolved in a walk over the tree after the state passes have run.
+//!
+//! `<q>` elements get `open-quote` and `close-quote` by default.
+//!
+//! The `placeholder` of an empty `<input>` or `<textarea>` is laid out as a generated box after `::before`. Its color is set with the `placeholder-color` attribute in place of `::placeholder`. The `value` of the field is laid out the same way in place of the placeholder, with a bullet for every character of password inputs.
+
+use std::sync::{Arc, Mutex};
+
+use cssparser::{ParseError, Parser, ParserInput, Token};
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use lightningcss::traits::Parse;
+use lightningcss::values::color::CssColor;
+use rustc_hash::FxHashSet;
+use shipyard::Component;
+use taffy::prelude::*;
+
+use crate::layout::TaffyLayout;
+use crate::style::{FontFamily, FontSize, DEFAULT_FONT_SIZE};
+use crate::text::text_style::{LineHeight, TextSpacing, TextTransform};
+use crate::text::TextContext;
+use crate::text_input::is_text_input_type;
+use crate::RealDom;
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum ContentItem {
+    String(String),
+    Counter {
+        name: String,
+        style: CounterStyle,
+    },
+    Counters {
+        name: String,
+        separator: String,
+        style: CounterStyle,
+    },
+    OpenQuote,
+    CloseQuote,
+    /// Increases the quote depth without inserting a quote.
+    NoOpenQuote,
+    /// Decreases the quote depth without inserting a quote.
+    NoCloseQuote,
+}
+
+/// The value of the `quotes` property.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub(crate) enum Quotes {
+    /// Quotes for the language of the element.
+    #[default]
+    Auto,
+    None,
+    /// Open and close quotes for each nesting level.
+    Pairs(Vec<(String, String)>),
+}
+
+impl Quotes {
+    fn parse(text: &str) -> Option<Self> {
+        match text.trim() {
+            "auto" => return Some(Quotes::Auto),
+            "none" => return Some(Quotes::None),
+            _ => {}
+        }
+        let mut input = ParserInput::new(text);
+        let mut parser = Parser::new(&mut input);
+        let mut pairs = Vec::new();
+        while !parser.is_exhausted() {
+            let open = parser.expect_string().ok()?.to_string();
+            let close = parser.expect_string().ok()?.to_string();
+            pairs.push((open, close));
+        }
+        Some(Quotes::Pairs(pairs))
+    }
+
+    /// The quote marks for each nesting level.
+    fn pairs(&self, lang: &str) -> Vec<(String, String)> {
+        match self {
+            Quotes::Auto => locale_quotes(lang)
+                .iter()
+                .map(|(open, close)| (open.to_string(), close.to_string()))
+                .collect(),
+            Quotes::None => Vec::new(),
+            Quotes::Pairs(pairs) => pairs.clone(),
+        }
+    }
+}
+
+/// The outer and inner quote marks used by a language. Languages that aren't listed use English quotes.
+fn locale_quotes(lang: &str) -> [(&'static str, &'static str); 2] {
+    let language = lang.split(['-', '_']).next().unwrap_or_default();
+    match language.to_ascii_lowercase().as_str() {
+        "de" | "cs" | "sk" | "sl" | "lt" | "bg" | "is" => {
+            [("\u{201e}", "\u{201c}"), ("\u{201a}", "\u{2018}")]
+        }
+        "fr" | "es" | "it" | "pt" | "ru" | "uk" | "be" | "el" | "ca" | "no" | "nb" => {
+            [("\u{ab}", "\u{bb}"), ("\u{201c}", "\u{201d}")]
+        }
+        "pl" | "hu" | "ro" | "nl" => [("\u{201e}", "\u{201d}"), ("\u{ab}", "\u{bb}")],
+        "sv" | "fi" => [("\u{201d}", "\u{201d}"), ("\u{2019}", "\u{2019}")],
+        "ja" | "zh" => [("\u{300c}", "\u{300d}"), ("\u{300e}", "\u{300f}")],
+        _ => [("\u{201c}", "\u{201d}"), ("\u{2018}", "\u{2019}")],
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub(crate) enum CounterStyle {
+    #[default]
+    Decimal,
+    DecimalLeadingZero,
+    LowerAlpha,
+    UpperAlpha,
+    LowerRoman,
+    UpperRoman,
+    None,
+}
+
+impl CounterStyle {
+    fn parse(ident: &str) -> Option<Self> {
+        match ident {
+            "decimal" => Some(CounterStyle::Decimal),
+            "decimal-leading-zero" => Some(CounterStyle::DecimalLeadingZero),
+            "lower-alpha" | "lower-latin" => Some(CounterStyle::LowerAlpha),
+            "upper-alpha" | "upper-latin" => Some(CounterStyle::UpperAlpha),
+            "lower-roman" => Some(CounterStyle::LowerRoman),
+            "upper-roman" => Some(CounterStyle::UpperRoman),
+            "none" => Some(CounterStyle::None),
+            _ => None,
+        }
+    }
+
+    pub fn format(&self, value: i32) -> String {
+        match self {
+            CounterStyle::Decimal => value.to_string(),
+            CounterStyle::DecimalLeadingZero => {
+                if (0..10).contains(&value) {
+                    format!("0{value}")
+                } else {
+                    value.to_string()
+                }
+            }
+            CounterStyle::LowerAlpha => alphabetic(value),
+            CounterStyle::UpperAlpha => alphabetic(value).to_uppercase(),
+            CounterStyle::LowerRoman => roman(value),
+            CounterStyle::UpperRoman => roman(value).to_uppercase(),
+            CounterStyle::None => String::new(),
+        }
+    }
+}
+
+/// Alphabetic counters are only defined for positive values, other values fall back to decimal.
+fn alphabetic(value: i32) -> String {
+    if value < 1 {
+        return value.to_string();
+    }
+    let mut value = value as u32;
+    let mut letters = Vec::new();
+    while value > 0 {
+        value -= 1;
+        letters.push(char::from(b'a' + (value % 26) as u8));
+        value /= 26;
+    }
+    letters.iter().rev().collect()
+}
+
+/// Roman numerals are only defined for 1 to 3999, other values fall back to decimal.
+fn roman(value: i32) -> String {
+    if !(1..4000).contains(&value) {
+        return value.to_string();
+    }
+    const NUMERALS: [(i32, &str); 13] = [
+        (1000, "m"),
+        (900, "cm"),
+        (500, "d"),
+        (400, "cd"),
+        (100, "c"),
+        (90, "xc"),
+        (50, "l"),
+        (40, "xl"),
+        (10, "x"),
+        (9, "ix"),
+        (5, "v"),
+        (4, "iv"),
+        (1, "i"),
+    ];
+    let mut value = value;
+    let mut result = String::new();
+    for (numeral_value, numeral) in NUMERALS {
+        while value >= numeral_value {
+            result.push_str(numeral);
+            value -= numeral_value;
+        }
+    }
+    result
+}
+
+/// The counter and content properties of an element.
+#[derive(Clone, PartialEq, Debug, Default, Component)]
+pub(crate) struct ContentStyle {
+    pub counter_reset: Vec<(String, i32)>,
+    pub counter_increment: Vec<(String, i32)>,
+    pub counter_set: Vec<(String, i32)>,
+    pub before: Option<Vec<ContentItem>>,
+    pub after: Option<Vec<ContentItem>>,
+    /// The `quotes` property. `None` inherits the quotes of the parent.
+    pub quotes: Option<Quotes>,
+    /// The `lang` attribute used to pick quotes for `quotes: auto`. `None` inherits the language of the parent.
+    pub lang: Option<String>,
+    /// The placeholder text of a text field that doesn't have a value.
+    pub placeholder: Option<String>,
+    /// The color of the placeholder. `None` draws it with the text color at reduced opacity.
+    pub placeholder_color: Option<CssColor>,
+    /// The text drawn for the value of a text field.
+    pub value: Option<String>,
+}
+
+#[partial_derive_state]
+impl State for ContentStyle {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new()
+            .with_tag()
+            .with_attrs(AttributeMaskBuilder::Some(&[
+                "counter-reset",
+                "counter-increment",
+                "counter-set",
+                "before-content",
+                "after-content",
+                "quotes",
+                "lang",
+                "placeholder",
+                "placeholder-color",
+                "value",
+                "type",
+            ]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let mut new = ContentStyle::default();
+        if node_view.tag() == Some("q") {
+            new.before = Some(vec![ContentItem::OpenQuote]);
+            new.after = Some(vec![ContentItem::CloseQuote]);
+        }
+
+        let text_field = match node_view.tag() {
+            Some("input") => is_text_input_type(
+                node_view
+                    .attributes()
+                    .into_iter()
+                    .flatten()
+                    .find(|attr| attr.attribute.name == "type")
+                    .and_then(|attr| attr.value.as_text()),
+            ),
+            Some("textarea") => true,
+            _ => false,
+        };
+        let mut password = false;
+        let mut value = None;
+        for attr in node_view.attributes().into_iter().flatten() {
+            if let Some(as_text) = attr.value.as_text() {
+                match attr.attribute.name.as_str() {
+                    "counter-reset" => new.counter_reset = parse_counter_list(as_text, 0),
+                    "counter-increment" => new.counter_increment = parse_counter_list(as_text, 1),
+                    "counter-set" => new.counter_set = parse_counter_list(as_text, 0),
+                    "before-content" => new.before = parse_content(as_text),
+                    "after-content" => new.after = parse_content(as_text),
+                    "quotes" => {
+                        if let Some(quotes) = Quotes::parse(as_text) {
+                            new.quotes = Some(quotes);
+                        }
+                    }
+                    "lang" => new.lang = Some(as_text.to_string()),
+                    "placeholder" if text_field => new.placeholder = Some(as_text.to_string()),
+                    "placeholder-color" => {
+                        if let Ok(color) = CssColor::parse_string(as_text) {
+                            new.placeholder_color = Some(color);
+                        }
+                    }
+                    "value" => value = Some(as_text),
+                    "type" => password = as_text.trim() == "password",
+                    _ => {}
+                }
+            }
+        }
+        // the placeholder disappears once the field has a value
+        if let Some(value) = value.filter(|value| text_field && !value.is_empty()) {
+            new.placeholder = None;
+            new.value = Some(if password {
+                "\u{2022}".repeat(value.chars().count())
+            } else {
+                value.to_string()
+            });
+        }
+
+        if self != &mut new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+/// Parses a list of counter names each followed by an optional integer like `chapter 1 section`. Counters without a value use `default_value`.
+fn parse_counter_list(text: &str, default_value: i32) -> Vec<(String, i32)> {
+    let mut input = ParserInput::new(text);
+    let mut parser = Parser::new(&mut input);
+    let mut counters: Vec<(String, i32)> = Vec::new();
+    while let Ok(token) = parser.next() {
+        match token.clone() {
+            Token::Ident(name) if counters.is_empty() && name.eq_ignore_ascii_case("none") => {
+                return Vec::new()
+            }
+            Token::Ident(name) => counters.push((name.to_string(), default_value)),
+            Token::Number {
+                int_value: Some(value),
+                ..
+            } => {
+                if let Some((_, counter_value)) = counters.last_mut() {
+                    *counter_value = value;
+                }
+            }
+            _ => return Vec::new(),
+        }
+    }
+    counters
+}
+
+/// Parses the value of the `content` property. Returns `None` for `none` and `normal`, which don't generate a box.
+fn parse_content(text: &str) -> Option<Vec<ContentItem>> {
+    let mut input = ParserInput::new(text);
+    let mut parser = Parser::new(&mut input);
+    let mut items = Vec::new();
+    while let Ok(token) = parser.next() {
+        match token.clone() {
+            Token::Ident(ident)
+                if ident.eq_ignore_ascii_case("none") || ident.eq_ignore_ascii_case("normal") =>
+            {
+                return None
+            }
+            Token::QuotedString(string) => items.push(ContentItem::String(string.to_string())),
+            Token::Ident(ident) if ident.eq_ignore_ascii_case("open-quote") => {
+                items.push(ContentItem::OpenQuote)
+            }
+            Token::Ident(ident) if ident.eq_ignore_ascii_case("close-quote") => {
+                items.push(ContentItem::CloseQuote)
+            }
+            Token::Ident(ident) if ident.eq_ignore_ascii_case("no-open-quote") => {
+                items.push(ContentItem::NoOpenQuote)
+            }
+            Token::Ident(ident) if ident.eq_ignore_ascii_case("no-close-quote") => {
+                items.push(ContentItem::NoCloseQuote)
+            }
+            Token::Function(name) if name.eq_ignore_ascii_case("counter") => {
+                let item = parser.parse_nested_block(|parser| {
+                    let name = parser.expect_ident()?.to_string();
+                    let style = parse_counter_style(parser);
+                    Ok::<_, ParseError<()>>(ContentItem::Counter { name, style })
+                });
+                items.push(item.ok()?);
+            }
+            Token::Function(name) if name.eq_ignore_ascii_case("counters") => {
+                let item = parser.parse_nested_block(|parser| {
+                    let name = parser.expect_ident()?.to_string();
+                    parser.expect_comma()?;
+                    let separator = parser.expect_string()?.to_string();
+                    let style = parse_counter_style(parser);
+                    Ok::<_, ParseError<()>>(ContentItem::Counters {
+                        name,
+                        separator,
+                        style,
+                    })
+                });
+                items.push(item.ok()?);
+            }
+            _ => return None,
+        }
+    }
+    Some(items)
+}
+
+/// Parses the optional `, <counter-style>` at the end of `counter()` and `counters()`.
+fn parse_counter_style(parser: &mut Parser) -> CounterStyle {
+    parser
+        .try_parse(|parser| -> Result<CounterStyle, ()> {
+            parser.expect_comma().map_err(|_| ())?;
+            let ident = parser.expect_ident().map_err(|_| ())?.to_string();
+            CounterStyle::parse(&ident).ok_or(())
+        })
+        .unwrap_or_default()
+}
+
+/// A laid out `::before` or `::after` box.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct GeneratedBox {
+    pub text: String,
+    pub node: Node,
+}
+
+/// The resolved generated content of an element.
+#[derive(Clone, PartialEq, Debug, Default, Component)]
+pub(crate) struct GeneratedContent {
+    pub before: Option<GeneratedBox>,
+    pub placeholder: Option<GeneratedBox>,
+    pub value: Option<GeneratedBox>,
+    pub after: Option<GeneratedBox>,
+}
+
+struct CounterInstance {
+    name: String,
+    value: i32,
+}
+
+struct GeneratedContentUpdater<'a> {
+    taffy: &'a mut Taffy,
+    text_context: &'a mut TextContext,
+    /// The counters in scope, innermost last.
+    counters: Vec<CounterInstance>,
+    /// The quotes and language of the ancestors of the current element, innermost last.
+    quote_scopes: Vec<(Quotes, String)>,
+    /// The number of quotes that are open at this point in the document.
+    quote_depth: usize,
+    changed: FxHashSet<NodeId>,
+}
+
+/// Resolves counters and generated content in document order and inserts the generated boxes into the layout tree. Returns the nodes whose generated content changed.
+pub(crate) fn update_generated_content(
+    rdom: &mut RealDom,
+    taffy: &Arc<Mutex<Taffy>>,
+    text_context: &Arc<Mutex<TextContext>>,
+) -> FxHashSet<NodeId> {
+    let mut taffy = taffy.lock().unwrap();
+    let mut text_context = text_context.lock().unwrap();
+    let mut updater = GeneratedContentUpdater {
+        taffy: &mut *taffy,
+        text_context: &mut *text_context,
+        counters: Vec::new(),
+        quote_scopes: Vec::new(),
+        quote_depth: 0,
+        changed: FxHashSet::default(),
+    };
+    let root_id = rdom.root_id();
+    updater.update_node(rdom, root_id);
+    updater.changed
+}
+
+impl GeneratedContentUpdater<'_> {
+    fn update_node(&mut self, rdom: &mut RealDom, id: NodeId) {
+        let node = rdom.get(id).unwrap();
+        if !matches!(&*node.node_type(), NodeType::Element(_)) {
+            return;
+        }
+        let style = node.get::<ContentStyle>().map(|style| (*style).clone());
+        let font_size = node
+            .get::<FontSize>()
+            .map(|font_size| font_size.0)
+            .unwrap_or(DEFAULT_FONT_SIZE);
+        let family = node
+            .get::<FontFamily>()
+            .map(|family| (*family).clone())
+            .unwrap_or_default();
+        let line_height = node
+            .get::<LineHeight>()
+            .and_then(|line_height| line_height.resolve(font_size));
+        let spacing = node
+            .get::<TextSpacing>()
+            .map(|spacing| *spacing)
+            .unwrap_or_default();
+        let transform = node
+            .get::<TextTransform>()
+            .map(|transform| *transform)
+            .unwrap_or_default();
+        let children: Vec<NodeId> = node
+            .children()
+            .into_iter()
+            .map(|child| child.id())
+            .collect();
+        let style = style.unwrap_or_default();
+
+        for (name, value) in &style.counter_reset {
+            self.counters.push(CounterInstance {
+                name: name.clone(),
+                value: *value,
+            });
+        }
+        for (name, value) in &style.counter_increment {
+            self.counter_mut(name).value += value;
+        }
+        for (name, value) in &style.counter_set {
+            self.counter_mut(name).value = *value;
+        }
+
+        let (parent_quotes, parent_lang) = self.quote_scopes.last().cloned().unwrap_or_default();
+        let lang = style.lang.clone().unwrap_or(parent_lang);
+        let quotes = style.quotes.clone().unwrap_or(parent_quotes);
+        let quote_pairs = quotes.pairs(&lang);
+        self.quote_scopes.push((quotes, lang));
+
+        let before_text = style.before.as_ref().map(|items| {
+            transform
+                .apply(&self.resolve(items, &quote_pairs))
+                .into_owned()
+        });
+
+        // counters created by descendants go out of scope at the end of the element
+        let scope = self.counters.len();
+        for child in &children {
+            self.update_node(rdom, *child);
+        }
+        self.counters.truncate(scope);
+        self.quote_scopes.pop();
+
+        let after_text = style.after.as_ref().map(|items| {
+            transform
+                .apply(&self.resolve(items, &quote_pairs))
+                .into_owned()
+        });
+
+        let mut node = rdom.get_mut(id).unwrap();
+        let old = node
+            .get::<GeneratedContent>()
+            .map(|content| (*content).clone())
+            .unwrap_or_default();
+        let new = GeneratedContent {
+            before: self.layout_box(
+                old.before.as_ref(),
+                before_text,
+                &family,
+                font_size,
+                line_height,
+                spacing,
+            ),
+            placeholder: self.layout_box(
+                old.placeholder.as_ref(),
+                style
+                    .placeholder
+                    .as_deref()
+                    .map(|text| transform.apply(text).into_owned()),
+                &family,
+                font_size,
+                line_height,
+                spacing,
+            ),
+            value: self.layout_box(
+                old.value.as_ref(),
+                style
+                    .value
+                    .as_deref()
+                    .map(|text| transform.apply(text).into_owned()),
+                &family,
+                font_size,
+                line_height,
+                spacing,
+            ),
+            after: self.layout_box(
+                old.after.as_ref(),
+                after_text,
+                &family,
+                font_size,
+                line_height,
+                spacing,
+            ),
+        };
+        if new != old {
+            self.changed.insert(id);
+            node.insert(new.clone());
+        }
+
+        // The layout pass only knows about the real children, so the generated boxes are added around them
+        let node = rdom.get(id).unwrap();
+        if let Some(taffy_node) = node.get::<TaffyLayout>().and_then(|layout| layout.node) {
+            let mut child_layout: Vec<Node> = new
+                .before
+                .iter()
+                .chain(&new.placeholder)
+                .chain(&new.value)
+                .map(|b| b.node)
+                .collect();
+            for child in node.children() {
+                if let Some(child_node) = child.get::<TaffyLayout>().and_then(|layout| layout.node)
+                {
+                    child_layout.push(child_node);
+                }
+            }
+            child_layout.extend(new.after.iter().map(|b| b.node));
+            if self.taffy.children(taffy_node).unwrap() != child_layout {
+                self.taffy.set_children(taffy_node, &child_layout).unwrap();
+                self.changed.insert(id);
+            }
+        }
+    }
+
+    /// Returns the innermost counter with the name. Using a counter that isn't in scope creates it on the current element.
+    fn counter_mut(&mut self, name: &str) -> &mut CounterInstance {
+        match self
+            .counters
+            .iter()
+            .rposition(|counter| counter.name == name)
+        {
+            Some(index) => &mut self.counters[index],
+            None => {
+                self.counters.push(CounterInstance {
+                    name: name.to_string(),
+                    value: 0,
+                });
+                self.counters.last_mut().unwrap()
+            }
+        }
+    }
+
+    /// Resolves content to text. Quotes use the pair for the current nesting level, or the innermost pair if quotes are nested deeper than there are pairs.
+    fn resolve(&mut self, items: &[ContentItem], quote_pairs: &[(String, String)]) -> String {
+        let mut text = String::new();
+        let quote_pair = |depth: usize| quote_pairs.get(depth).or(quote_pairs.last());
+        for item in items {
+            match item {
+                ContentItem::OpenQuote => {
+                    if let Some((open, _)) = quote_pair(self.quote_depth) {
+                        text += open;
+                    }
+                    self.quote_depth += 1;
+                }
+                ContentItem::CloseQuote => {
+                    // closing a quote that was never opened doesn't insert anything
+                    if self.quote_depth > 0 {
+                        self.quote_depth -= 1;
+                        if let Some((_, close)) = quote_pair(self.quote_depth) {
+                            text += close;
+                        }
+                    }
+                }
+                ContentItem::NoOpenQuote => self.quote_depth += 1,
+                ContentItem::NoCloseQuote => self.quote_depth = self.quote_depth.saturating_sub(1),
+                ContentItem::String(string) => text += string,
+                ContentItem::Counter { name, style } => {
+                    let value = self
+                        .counters
+                        .iter()
+                        .rfind(|counter| &counter.name == name)
+                        .map(|counter| counter.value)
+                        .unwrap_or(0);
+                    text += &style.format(value);
+                }
+                ContentItem::Counters {
+                    name,
+                    separator,
+                    style,
+                } => {
+                    let values: Vec<String> = self
+                        .counters
+                        .iter()
+                        .filter(|counter| &counter.name == name)
+                        .map(|counter| style.format(counter.value))
+                        .collect();
+                    if values.is_empty() {
+                        text += &style.format(0);
+                    } else {
+                        text += &values.join(separator);
+                    }
+                }
+            }
+        }
+        text
+    }
+
+    /// Creates, resizes or removes the layout node of a generated box.
+    fn layout_box(
+        &mut self,
+        old: Option<&GeneratedBox>,
+        text: Option<String>,
+        family: &FontFamily,
+        font_size: f32,
+        line_height: Option<f64>,
+        spacing: TextSpacing,
+    ) -> Option<GeneratedBox> {
+        let Some(text) = text else {
+            if let Some(old) = old {
+                self.taffy.remove(old.node).unwrap();
+            }
+            return None;
+        };
+        let fonts = family.resolve(self.text_context);
+        let text_layout = self.text_context.layout(
+            &fonts,
+            &family.features,
+            font_size,
+            &text,
+            line_height,
+            spacing,
+            None,
+        );
+        let style = Style {
+            size: Size {
+                width: Dimension::Points(text_layout.width() as f32),
+                height: Dimension::Points(text_layout.height() as f32),
+            },
+            ..Default::default()
+        };
+        let node = match old {
+            Some(old) => {
+                if *self.taffy.style(old.node).unwrap() != style {
+                    self.taffy.set_style(old.node, style).unwrap();
+                }
+                old.node
+            }
+            None => self.taffy.new_leaf(style).unwrap(),
+        };
+        Some(GeneratedBox { text, node })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Adds an element with the given attributes to `parent`.
+    fn element(
+        rdom: &mut RealDom,
+        parent: NodeId,
+        tag: &str,
+        attributes: &[(&str, &str)],
+    ) -> NodeId {
+        let id = rdom
+            .create_node(NodeType::Element(ElementNode {
+                tag: tag.to_string(),
+                attributes: attributes
+                    .iter()
+                    .map(|(name, value)| ((*name, "style").into(), value.to_string().into()))
+                    .collect(),
+                ..Default::default()
+            }))
+            .id();
+        rdom.get_mut(parent).unwrap().add_child(id);
+        id
+    }
+
+    #[test]
+    fn nested_quotes_alternate() {
+        let mut rdom: RealDom = RealDom::new(vec![ContentStyle::to_type_erased()]);
+        let root = rdom.root_id();
+        let outer = element(&mut rdom, root, "q", &[]);
+        let inner = element(&mut rdom, outer, "q", &[]);
+        let innermost = element(&mut rdom, inner, "q", &[]);
+        let custom = element(&mut rdom, root, "q", &[("quotes", "'<' '>' '[' ']'")]);
+        let custom_inner = element(&mut rdom, custom, "q", &[]);
+        rdom.update_state(SendAnyMap::new());
+        let taffy = Arc::new(Mutex::new(Taffy::new()));
+        let text_context = Arc::new(Mutex::new(TextContext::default()));
+        update_generated_content(&mut rdom, &taffy, &text_context);
+
+        let quotes = |id: NodeId| {
+            let node = rdom.get(id).unwrap();
+            let content = node.get::<GeneratedContent>().unwrap();
+            (
+                content.before.as_ref().unwrap().text.clone(),
+                content.after.as_ref().unwrap().text.clone(),
+            )
+        };
+        let pair = |open: &str, close: &str| (open.to_string(), close.to_string());
+        assert_eq!(quotes(outer), pair("\u{201c}", "\u{201d}"));
+        assert_eq!(quotes(inner), pair("\u{2018}", "\u{2019}"));
+        // deeper quotes keep using the innermost pair
+        assert_eq!(quotes(innermost), pair("\u{2018}", "\u{2019}"));
+        // the quotes property is inherited by the nested quote
+        assert_eq!(quotes(custom), pair("<", ">"));
+        assert_eq!(quotes(custom_inner), pair("[", "]"));
+    }
+}
@@ -0,0 +1,116 @@
+//! Rendering a document to an image without a window.
+//!
+//! The dom is built, laid out and painted once on the calling thread, then the frame is rendered to an offscreen texture and read back. Nothing depends on timing: the installed fonts are indexed and the fonts registered in the [`Config`] are loaded one after another before the first layout and images are decoded synchronously, so the same document at the same size always produces the same pixels on the same machine.
+
+use std::sync::{Arc, Mutex, RwLock};
+
+use ::image::RgbaImage;
+use dioxus_native_core::prelude::*;
+use dioxus_native_core::FxDashSet;
+use taffy::{prelude::Size, Taffy};
+use tao::dpi::LogicalSize;
+use vello::{Renderer as VelloRenderer, RendererOptions, Scene, SceneBuilder};
+
+use crate::application::{
+    create_rdom, render_offscreen, update_and_layout, FrameState, LayoutState,
+};
+use crate::render::render;
+use crate::text::{FontContext, TextContext};
+use crate::util::ViewportSize;
+use crate::window::WindowHandle;
+use crate::{Config, Driver};
+
+/// Render a document to an image of the given size without opening a window.
+///
+/// `spawn_renderer` builds the document like in [`render`](fn@crate::render). The driver sees the first layout in [`Driver::after_layout`], but async work like timers is never polled, so the image shows the first frame. The [`WindowHandle`] passed to it is not connected to a window and ignores commands.
+///
+/// Returns `None` if no GPU adapter is available or the frame can't be read back.
+pub async fn render_to_image<R: Driver>(
+    spawn_renderer: impl FnOnce(&Arc<RwLock<RealDom>>, &Arc<Mutex<Taffy>>, &WindowHandle) -> R,
+    width: u32,
+    height: u32,
+    cfg: Config,
+) -> Option<RgbaImage> {
+    let rdom = Arc::new(RwLock::new(create_rdom(&cfg)));
+    let taffy = Arc::new(Mutex::new(Taffy::new()));
+    let fonts = FontContext::default();
+    load_fonts(&fonts, &cfg);
+    let text_context = Arc::new(Mutex::new(TextContext::new(fonts)));
+    let mut renderer = spawn_renderer(&rdom, &taffy, &WindowHandle::headless());
+
+    {
+        let mut rdom = rdom.write().ok()?;
+        let root_id = rdom.root_id();
+        renderer.update(rdom.get_mut(root_id)?);
+        // nothing is redrawn incrementally, every node is painted
+        let dirty = FxDashSet::default();
+        update_and_layout(
+            &mut renderer,
+            &mut rdom,
+            &taffy,
+            &text_context,
+            ViewportSize(Size { width, height }),
+            &mut LayoutState::default(),
+            &dirty,
+        )?;
+    }
+
+    let mut scene = Scene::new();
+    {
+        let mut builder = SceneBuilder::for_scene(&mut scene);
+        render(
+            &rdom.read().ok()?,
+            &taffy.lock().unwrap(),
+            &mut text_context.lock().unwrap(),
+            &mut builder,
+            LogicalSize::new(width, height),
+        );
+    }
+
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions::default())
+        .await?;
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("headless"),
+                features: wgpu::Features::empty(),
+                limits: wgpu::Limits::default(),
+            },
+            None,
+        )
+        .await
+        .ok()?;
+    let mut vello_renderer = VelloRenderer::new(
+        &device,
+        &RendererOptions {
+            surface_format: None,
+        },
+    )
+    .ok()?;
+    let frame =
+        render_offscreen(&device, &queue, &mut vello_renderer, &scene, width, height).ok()?;
+    device.poll(wgpu::Maintain::Wait);
+    match frame.read() {
+        FrameState::Ready(image) => Some(image),
+        FrameState::Pending | FrameState::Failed => None,
+    }
+}
+
+/// Index the installed fonts, then load the fonts registered in the config one at a time, so the faces of a family are always in the same order.
+fn load_fonts(fonts: &FontContext, cfg: &Config) {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    fonts.index_system_fonts(move || {
+        let _ = sender.send(());
+    });
+    let _ = receiver.recv();
+    for file in cfg.font_files.iter().cloned() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        fonts.load(file, move |_| {
+            let _ = sender.send(());
+        });
+        // the sender is dropped without sending if the file can't be read
+        let _ = receiver.recv();
+    }
+}
@@ -0,0 +1,122 @@
+//! Finding the element under a point.
+//!
+//! The document is walked in the reverse of the order it is painted in, so the first element whose border box contains the point is the one drawn on top. Elements in a stacking context are visited by the element that paints them in `z-index` order, transformed elements are tested with the point mapped back through their transform, and content that is cut off by an overflow clip can't be hit outside of the clip.
+
+use dioxus_native_core::prelude::*;
+use taffy::{prelude::Size, Taffy};
+use vello::kurbo::{Point, Shape, Vec2};
+
+use crate::focus::Focus;
+use crate::layout::TaffyLayout;
+use crate::meter::Gauge;
+use crate::position::position_offset;
+use crate::render::{get_overflow_clip, get_shape};
+use crate::scroll::scroll_offset;
+use crate::stacking::{creates_stacking_context, stacked_descendants, StackedElement};
+use crate::style::Overflow;
+use crate::transform::{paint_transform, translation};
+use crate::visibility::is_visible;
+
+/// Returns the topmost element whose border box contains the point.
+pub(crate) fn hit_test(
+    rdom: &RealDom,
+    taffy: &Taffy,
+    viewport_size: &Size<u32>,
+    point: Point,
+) -> Option<NodeId> {
+    let root = rdom.get(rdom.root_id())?;
+    hit_node(taffy, root, Point::ZERO, viewport_size, point)
+}
+
+/// Returns the element under the point or its nearest ancestor that can be focused.
+pub(crate) fn focusable_at(
+    rdom: &RealDom,
+    taffy: &Taffy,
+    viewport_size: &Size<u32>,
+    point: Point,
+) -> Option<NodeId> {
+    let mut node = rdom.get(hit_test(rdom, taffy, viewport_size, point)?)?;
+    loop {
+        if node.get::<Focus>().is_some_and(|focus| focus.focusable()) {
+            return Some(node.id());
+        }
+        node = node.parent()?;
+    }
+}
+
+/// Tests a node placed relative to `location` like in `render_node`.
+fn hit_node(
+    taffy: &Taffy,
+    node: NodeRef,
+    location: Point,
+    viewport_size: &Size<u32>,
+    point: Point,
+) -> Option<NodeId> {
+    // text is part of the element it is in
+    if !matches!(&*node.node_type(), NodeType::Element(_)) {
+        return None;
+    }
+    let taffy_node = node.get::<TaffyLayout>()?.node?;
+    let layout = taffy.layout(taffy_node).ok()?;
+    let pos = location
+        + Vec2::new(layout.location.x as f64, layout.location.y as f64)
+        + translation(node, layout, viewport_size)
+        + position_offset(taffy, node, layout, viewport_size);
+    // everything the element paints is drawn with its transform
+    let point = match paint_transform(node, layout, pos, viewport_size) {
+        Some(transform) => transform.inverse() * point,
+        None => point,
+    };
+
+    let overflow = *node.get::<Overflow>().unwrap();
+    let clipped = (overflow.clips_x() || overflow.clips_y())
+        && !get_overflow_clip(layout, node, pos, &overflow, viewport_size).contains(point);
+    if !clipped {
+        let content_pos = pos - scroll_offset(node);
+        let stacked = if creates_stacking_context(node) || node.parent().is_none() {
+            stacked_descendants(taffy, node, content_pos, viewport_size)
+        } else {
+            Vec::new()
+        };
+        let (below, above) =
+            stacked.split_at(stacked.partition_point(|element| element.z_index < 0));
+        if let Some(hit) = hit_stacked(taffy, node, above, viewport_size, point) {
+            return Some(hit);
+        }
+        // the content of a gauge is only a fallback and is never painted
+        if !node.get::<Gauge>().is_some_and(|gauge| gauge.is_gauge()) {
+            for child in node.children().into_iter().rev() {
+                if creates_stacking_context(child) {
+                    continue;
+                }
+                if let Some(hit) = hit_node(taffy, child, content_pos, viewport_size, point) {
+                    return Some(hit);
+                }
+            }
+        }
+        if let Some(hit) = hit_stacked(taffy, node, below, viewport_size, point) {
+            return Some(hit);
+        }
+    }
+
+    // hidden elements can't be hit, but their visible descendants can
+    (is_visible(node) && get_shape(layout, node, viewport_size, pos).contains(point))
+        .then(|| node.id())
+}
+
+/// Tests the elements of a stacking context from the top down, inside the overflow clips of their ancestors.
+fn hit_stacked(
+    taffy: &Taffy,
+    node: NodeRef,
+    stacked: &[StackedElement],
+    viewport_size: &Size<u32>,
+    point: Point,
+) -> Option<NodeId> {
+    stacked.iter().rev().find_map(|element| {
+        if !element.clips.iter().all(|clip| clip.contains(point)) {
+            return None;
+        }
+        let child = node.real_dom().get(element.node)?;
+        hit_node(taffy, child, element.location, viewport_size, point)
+    })
+}
@@ -8,6 +8,7 @@ use std::{
 use dioxus_native_core::{node::OwnedAttributeValue, prelude::*};
 use dioxus_native_core_macro::partial_derive_state;
 use shipyard::Component;
+use vello::kurbo::{Rect, Vec2};
 use vello::peniko::{Blob, Format, Image};
 
 #[derive(Default, Clone)]
@@ -68,8 +69,16 @@ impl From<image::ImageError> for ImageError {
     }
 }
 
+/// The image of an `<img>` element.
 #[derive(Debug, Default, PartialEq, Clone, Component)]
-pub(crate) struct LoadedImage(pub Option<Arc<Image>>);
+pub(crate) enum LoadedImage {
+    /// The element is not an image or has no `src`.
+    #[default]
+    None,
+    Loaded(Arc<Image>),
+    /// The source could not be read or decoded. A placeholder box is drawn instead.
+    Broken,
+}
 
 #[partial_derive_state]
 impl State for LoadedImage {
@@ -88,22 +97,115 @@ impl State for LoadedImage {
         _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
         ctx: &SendAnyMap,
     ) -> bool {
-        let mut new = None;
-        if let Some(OwnedAttributeValue::Text(image)) =
-            node_view.attributes().and_then(|mut attrs| {
-                attrs
-                    .find(|attr| attr.attribute.name == "src")
-                    .map(|attr| attr.value)
-            })
-        {
-            let image_ctx: &ImageContext = ctx.get().expect("ImageContext not found");
-            new = Some(image_ctx.load_file(image).unwrap());
+        let mut new = LoadedImage::None;
+        if node_view.tag() == Some("img") {
+            if let Some(OwnedAttributeValue::Text(image)) =
+                node_view.attributes().and_then(|mut attrs| {
+                    attrs
+                        .find(|attr| attr.attribute.name == "src")
+                        .map(|attr| attr.value)
+                })
+            {
+                // images are shared with background images and cursors through the same cache
+                let image_ctx: &ImageContext = ctx.get().expect("ImageContext not found");
+                new = match image_ctx.load_file(image) {
+                    Ok(image) => LoadedImage::Loaded(image),
+                    Err(_) => LoadedImage::Broken,
+                };
+            }
         }
-        if self.0 != new {
-            self.0 = new;
+        if *self != new {
+            *self = new;
             true
         } else {
             false
         }
     }
 }
+
+/// How the image of an `<img>` is scaled into its content box with `object-fit`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Component)]
+pub(crate) enum ObjectFit {
+    /// The image is stretched to fill the box.
+    #[default]
+    Fill,
+    /// The image is scaled to fit inside the box and keeps its aspect ratio.
+    Contain,
+    /// The image is scaled to cover the box and keeps its aspect ratio. The parts outside of the box are clipped.
+    Cover,
+    /// The image keeps its natural size.
+    None,
+    /// The image is drawn like `none` or `contain`, whichever is smaller.
+    ScaleDown,
+}
+
+impl ObjectFit {
+    /// Returns the rect the image is drawn into, centered in the content box.
+    pub fn place(&self, image: &Image, content_box: Rect) -> Rect {
+        let natural = Vec2::new(image.width as f64, image.height as f64);
+        let contain = (content_box.width() / natural.x).min(content_box.height() / natural.y);
+        let size = match self {
+            ObjectFit::Fill => return content_box,
+            ObjectFit::Contain => natural * contain,
+            ObjectFit::Cover => {
+                natural * (content_box.width() / natural.x).max(content_box.height() / natural.y)
+            }
+            ObjectFit::None => natural,
+            ObjectFit::ScaleDown => natural * contain.min(1.0),
+        };
+        Rect::from_center_size(content_box.center(), size.to_size())
+    }
+}
+
+#[partial_derive_state]
+impl State for ObjectFit {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&["object-fit"]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let new = match node_view
+            .attributes()
+            .into_iter()
+            .flatten()
+            .next()
+            .and_then(|attr| attr.value.as_text())
+            .map(str::trim)
+        {
+            Some("contain") => ObjectFit::Contain,
+            Some("cover") => ObjectFit::Cover,
+            Some("none") => ObjectFit::None,
+            Some("scale-down") => ObjectFit::ScaleDown,
+            _ => ObjectFit::Fill,
+        };
+
+        if *self != new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
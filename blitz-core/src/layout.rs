@@ -1,25 +1,88 @@
 use std::sync::{Arc, Mutex};
 
+use cssparser::{Parser, ParserInput};
 use dioxus_native_core::layout_attributes::apply_layout_attributes;
 use dioxus_native_core::prelude::*;
 use dioxus_native_core_macro::partial_derive_state;
+use lightningcss::properties::align::GapValue;
+use lightningcss::properties::Property;
+use lightningcss::stylesheet::ParserOptions;
+use lightningcss::traits::Parse;
+use lightningcss::values::calc::{Calc, MathFunction};
+use lightningcss::values::color::CssColor;
+use lightningcss::values::length::{LengthPercentage, LengthPercentageOrAuto, LengthValue};
+use lightningcss::values::percentage::DimensionPercentage;
+use rustc_hash::FxHashSet;
 use shipyard::Component;
 use taffy::prelude::*;
+use vello::peniko::Color;
 
+use crate::container::ContainerQuery;
+use crate::hit_test::hit_test;
 use crate::image::LoadedImage;
-use crate::style::FontSize;
-use crate::text::TextContext;
+use crate::position::Positioning;
+use crate::render::{get_abs_pos, get_content_box_x};
+use crate::stacking::Opacity;
+use crate::style::{
+    BackgroundColor, FontFamily, FontSize, ForgroundColor, RootFontSize, DEFAULT_FONT_SIZE,
+};
+use crate::table::{CaptionSide, TableRole, TableStyle};
+use crate::text::text_style::{LineHeight, TextSpacing, TextTransform, WhiteSpace};
+use crate::text::{FontUnits, TextContext};
+use crate::util::{translate_color, Axis, Resolve, ResolveContext, ViewportSize};
+use crate::visibility::Visibility;
+
+/// The size of the placeholder box of an image that failed to load.
+pub(crate) const BROKEN_IMAGE_SIZE: f32 = 16.0;
 
 // TODO: More layout types. This should default to box layout
 #[derive(Clone, Default, Debug, Component)]
 pub(crate) struct TaffyLayout {
     pub style: Style,
     pub node: Option<Node>,
+    /// The baseline strut of a text node. It is only a child of the text node while its baseline lines up flex items, see [`update_baseline_struts`].
+    pub baseline: Option<Node>,
+    /// Set on table and figure captions. Tables and figures move their captions to this side.
+    pub caption: Option<CaptionSide>,
+    /// Set if `width` is a content keyword. The width is measured by [`update_intrinsic_sizes`].
+    pub intrinsic_width: Option<IntrinsicSize>,
+    /// Set if `min-width` is a content keyword.
+    pub intrinsic_min_width: Option<IntrinsicSize>,
+    /// Set if `max-width` is a content keyword.
+    pub intrinsic_max_width: Option<IntrinsicSize>,
+    /// The size of `ch` and `ex` in the font of the node, for the lengths that are resolved when it is painted.
+    pub font_units: Option<FontUnits>,
+}
+
+/// The keywords that size an element to its content.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum IntrinsicSize {
+    MinContent,
+    MaxContent,
+    /// As wide as the available space, but no narrower than the min-content width and no wider than the max-content width.
+    FitContent,
+}
+
+impl IntrinsicSize {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "min-content" => Some(IntrinsicSize::MinContent),
+            "max-content" => Some(IntrinsicSize::MaxContent),
+            "fit-content" => Some(IntrinsicSize::FitContent),
+            _ => None,
+        }
+    }
 }
 
 impl PartialEq<Self> for TaffyLayout {
     fn eq(&self, other: &Self) -> bool {
-        self.style == other.style && self.node == other.node
+        self.style == other.style
+            && self.node == other.node
+            && self.caption == other.caption
+            && self.intrinsic_width == other.intrinsic_width
+            && self.intrinsic_min_width == other.intrinsic_min_width
+            && self.intrinsic_max_width == other.intrinsic_max_width
+            && self.font_units == other.font_units
     }
 }
 
@@ -27,44 +90,90 @@ impl PartialEq<Self> for TaffyLayout {
 impl State for TaffyLayout {
     type ChildDependencies = (Self,);
     type ParentDependencies = ();
-    type NodeDependencies = (LoadedImage, FontSize);
+    type NodeDependencies = (
+        LoadedImage,
+        FontSize,
+        FontFamily,
+        TableStyle,
+        LineHeight,
+        TextSpacing,
+        TextTransform,
+        WhiteSpace,
+        Positioning,
+        Visibility,
+    );
 
     const NODE_MASK: NodeMaskBuilder<'static> = NodeMaskBuilder::new()
+        .with_tag()
         .with_attrs(AttributeMaskBuilder::All)
         .with_text();
 
     fn update<'a>(
         &mut self,
         node_view: NodeView<()>,
-        (image, fz): <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        (
+            image,
+            fz,
+            family,
+            table,
+            line_height,
+            spacing,
+            transform,
+            white_space,
+            positioning,
+            visibility,
+        ): <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
         _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
         children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
         context: &SendAnyMap,
     ) -> bool {
         let taffy: &Arc<Mutex<Taffy>> = context.get().unwrap();
         let text_context: &Arc<Mutex<TextContext>> = context.get().unwrap();
-        let mut taffy = taffy.lock().unwrap();
+        let viewport_size = context
+            .get::<ViewportSize>()
+            .map(|viewport| viewport.0)
+            .unwrap_or(Size {
+                width: 0,
+                height: 0,
+            });
+        let root_font_size = context
+            .get::<RootFontSize>()
+            .map_or(DEFAULT_FONT_SIZE, |root| root.size);
         let mut changed = false;
+        let font_units = {
+            let text_context = text_context.lock().unwrap();
+            let fonts = family.resolve(&text_context);
+            text_context.get_font_units(&fonts, &family.features, fz.0)
+        };
+        if self.font_units != Some(font_units) {
+            self.font_units = Some(font_units);
+            changed = true;
+        }
+        let mut taffy = taffy.lock().unwrap();
         if let Some(text) = node_view.text() {
             let mut text_context = text_context.lock().unwrap();
-            let font_size = fz.0;
-            let (width, height) = text_context.get_text_size(None, font_size, text);
+            let text = transform.apply(text);
+            let text = white_space.apply(&text);
+            let (style, baseline_style) = text_styles(
+                &mut text_context,
+                &text,
+                family,
+                fz.0,
+                line_height,
+                *spacing,
+            );
 
-            let style = Style {
-                size: Size {
-                    height: Dimension::Points(height as f32),
-
-                    width: Dimension::Points(width as f32),
-                },
-                ..Default::default()
-            };
-
-            if let Some(n) = self.node {
+            if let (Some(n), Some(baseline)) = (self.node, self.baseline) {
                 if self.style != style {
                     taffy.set_style(n, style).unwrap();
                     changed = true;
                 }
+                if *taffy.style(baseline).unwrap() != baseline_style {
+                    taffy.set_style(baseline, baseline_style).unwrap();
+                    changed = true;
+                }
             } else {
+                self.baseline = Some(taffy.new_leaf(baseline_style).unwrap());
                 self.node = Some(taffy.new_leaf(style).unwrap());
                 changed = true;
             }
@@ -77,29 +186,195 @@ impl State for TaffyLayout {
             // gather up all the styles from the attribute list
             let mut style = Style::default();
 
-            // Images default to a fixed size
-            // TODO: The aspect ratio should be preserved when the image is scaled when box layout is implemented
-            if let Some(image) = &image.0 {
-                style = Style::default();
-                style.size = Size {
-                    width: Dimension::Points(image.width as f32),
-                    height: Dimension::Points(image.height as f32),
+            // Images default to their natural size and keep their aspect ratio when only one side is set
+            match &*image {
+                LoadedImage::Loaded(image) => {
+                    style.size = Size {
+                        width: Dimension::Points(image.width as f32),
+                        height: Dimension::Points(image.height as f32),
+                    };
+                    style.aspect_ratio = Some(image.width as f32 / image.height.max(1) as f32);
+                    style.flex_grow = 0.0;
+                    style.flex_shrink = 0.0;
+                }
+                LoadedImage::Broken => {
+                    style.size = Size {
+                        width: Dimension::Points(BROKEN_IMAGE_SIZE),
+                        height: Dimension::Points(BROKEN_IMAGE_SIZE),
+                    };
+                    style.flex_shrink = 0.0;
+                }
+                LoadedImage::None => {}
+            }
+
+            // Figures stack their content with the margins of the default stylesheet
+            if node_view.tag() == Some("figure") {
+                style.flex_direction = FlexDirection::Column;
+                style.margin = Rect {
+                    left: Dimension::Points(40.0),
+                    right: Dimension::Points(40.0),
+                    top: Dimension::Points(16.0),
+                    bottom: Dimension::Points(16.0),
+                };
+            }
+
+            // Buttons center their content inside a small padding and border
+            if node_view.tag() == Some("button") {
+                style.padding = Rect {
+                    left: Dimension::Points(6.0),
+                    right: Dimension::Points(6.0),
+                    top: Dimension::Points(1.0),
+                    bottom: Dimension::Points(1.0),
+                };
+                style.border = Rect {
+                    left: Dimension::Points(2.0),
+                    right: Dimension::Points(2.0),
+                    top: Dimension::Points(2.0),
+                    bottom: Dimension::Points(2.0),
                 };
-                style.flex_grow = 0.0;
+                style.align_items = AlignItems::Center;
+                style.justify_content = JustifyContent::Center;
+                // the label is never clipped by shrinking the button
                 style.flex_shrink = 0.0;
             }
 
+            // Progress bars and meters have a fixed default size like images
+            match node_view.tag() {
+                Some("progress") => {
+                    style.size = Size {
+                        width: Dimension::Points(160.0),
+                        height: Dimension::Points(16.0),
+                    };
+                    style.flex_shrink = 0.0;
+                }
+                // Checkboxes and radio buttons have the size and margins of the default stylesheet
+                Some("input")
+                    if node_view.attributes().into_iter().flatten().any(|attr| {
+                        attr.attribute.name == "type"
+                            && matches!(
+                                attr.value.as_text().map(str::trim),
+                                Some("checkbox" | "radio")
+                            )
+                    }) =>
+                {
+                    style.size = Size {
+                        width: Dimension::Points(13.0),
+                        height: Dimension::Points(13.0),
+                    };
+                    style.margin = Rect {
+                        left: Dimension::Points(4.0),
+                        right: Dimension::Points(3.0),
+                        top: Dimension::Points(3.0),
+                        bottom: Dimension::Points(3.0),
+                    };
+                    style.flex_shrink = 0.0;
+                }
+                Some("meter") => {
+                    style.size = Size {
+                        width: Dimension::Points(80.0),
+                        height: Dimension::Points(16.0),
+                    };
+                    style.flex_shrink = 0.0;
+                }
+                _ => {}
+            }
+
+            let resolve_context = ResolveContext {
+                viewport_size,
+                font_size: fz.0,
+                root_font_size,
+                font_units,
+            };
+            let mut intrinsic_width = None;
+            let (mut intrinsic_min_width, mut intrinsic_max_width) = (None, None);
+            let (mut sets_width, mut sets_height) = (false, false);
             for attr in node_view.attributes().into_iter().flatten() {
                 let name = &attr.attribute.name;
                 let value = attr.value;
+                sets_width |= name == "width";
+                sets_height |= name == "height";
                 if let Some(value) = value.as_text() {
                     apply_layout_attributes(name, value, &mut style);
+                    apply_min_max_size(name, value, &mut style, |length| {
+                        length_dimension(length, &resolve_context)
+                    });
+                    apply_font_relative_length(name, value, &mut style, font_units);
+                    apply_gap(name, value, &mut style, |length| {
+                        length_dimension(length, &resolve_context)
+                    });
+                    if name == "flex-basis" {
+                        if let Some(flex_basis) = parse_flex_basis(value, &resolve_context) {
+                            style.flex_basis = flex_basis;
+                        }
+                    }
+                    match name.as_str() {
+                        "width" => {
+                            intrinsic_width = IntrinsicSize::parse(value);
+                            if intrinsic_width.is_some() {
+                                style.size.width = Dimension::Auto;
+                            }
+                        }
+                        "aspect-ratio" => {
+                            if let Some(ratio) = AspectRatio::parse(value) {
+                                ratio.apply(&mut style, &image);
+                            }
+                        }
+                        "min-width" => intrinsic_min_width = IntrinsicSize::parse(value),
+                        "max-width" => intrinsic_max_width = IntrinsicSize::parse(value),
+                        _ => {}
+                    }
+                }
+            }
+            positioning.apply(&mut style, &resolve_context);
+            // the other side of an image or an element with `aspect-ratio` follows the ratio
+            if style.aspect_ratio.is_some() {
+                match (sets_width, sets_height) {
+                    (true, false) => style.size.height = Dimension::Auto,
+                    (false, true) => style.size.width = Dimension::Auto,
+                    _ => {}
                 }
             }
+            if intrinsic_width != self.intrinsic_width {
+                self.intrinsic_width = intrinsic_width;
+                changed = true;
+            }
+            if intrinsic_min_width != self.intrinsic_min_width
+                || intrinsic_max_width != self.intrinsic_max_width
+            {
+                self.intrinsic_min_width = intrinsic_min_width;
+                self.intrinsic_max_width = intrinsic_max_width;
+                changed = true;
+            }
+
+            // Table boxes are laid out with flexbox, column widths are set by `update_table_layout`
+            table.apply(&mut style);
+            // collapsed rows leave the layout, their cells are still measured for the column widths
+            if visibility.collapses(table.role) {
+                style.display = Display::None;
+            }
+
+            let caption = match table.role {
+                TableRole::Caption => Some(table.caption_side.unwrap_or(CaptionSide::Top)),
+                _ if node_view.tag() == Some("figcaption") => table.caption_side,
+                _ => None,
+            };
+            if caption != self.caption {
+                self.caption = caption;
+                changed = true;
+            }
 
             // Set all direct nodes as our children
+            let mut children: Vec<_> = children.into_iter().map(|(l,)| l).collect();
+            // Captions are moved to their side, the sort is stable so everything else keeps its order
+            if table.role == TableRole::Table || node_view.tag() == Some("figure") {
+                children.sort_by_key(|l| match l.caption {
+                    Some(CaptionSide::Top) => 0,
+                    None => 1,
+                    Some(CaptionSide::Bottom) => 2,
+                });
+            }
             let mut child_layout = vec![];
-            for (l,) in children {
+            for l in children {
                 child_layout.push(l.node.unwrap());
             }
 
@@ -137,3 +412,942 @@ impl State for TaffyLayout {
         myself
     }
 }
+
+/// The style of a text node and of the strut that marks its baseline.
+fn text_styles(
+    text_context: &mut TextContext,
+    text: &str,
+    family: &FontFamily,
+    font_size: f32,
+    line_height: &LineHeight,
+    spacing: TextSpacing,
+) -> (Style, Style) {
+    let fonts = family.resolve(text_context);
+    let text_layout = text_context.layout(
+        &fonts,
+        &family.features,
+        font_size,
+        text,
+        line_height.resolve(font_size),
+        spacing,
+        None,
+    );
+    let (width, height) = (text_layout.width(), text_layout.height());
+
+    let style = Style {
+        size: Size {
+            height: Dimension::Points(height as f32),
+
+            width: Dimension::Points(width as f32),
+        },
+        ..Default::default()
+    };
+    let baseline_style = Style {
+        size: Size {
+            width: Dimension::Points(0.0),
+            height: Dimension::Points(text_layout.baseline as f32),
+        },
+        flex_shrink: 0.0,
+        ..Default::default()
+    };
+    (style, baseline_style)
+}
+
+/// Measures text nodes again when something the layout pass doesn't track changed, like a font finishing loading. Returns the text nodes whose size changed.
+pub(crate) fn remeasure_text(
+    rdom: &mut RealDom,
+    taffy: &Arc<Mutex<Taffy>>,
+    text_context: &Arc<Mutex<TextContext>>,
+    affected_node: impl Fn(NodeRef) -> bool,
+) -> FxHashSet<NodeId> {
+    let mut taffy = taffy.lock().unwrap();
+    let mut text_context = text_context.lock().unwrap();
+    let mut affected = Vec::new();
+    let mut stack = vec![rdom.root_id()];
+    while let Some(id) = stack.pop() {
+        let node = rdom.get(id).unwrap();
+        if let NodeType::Text(TextNode { text, .. }) = &*node.node_type() {
+            if affected_node(node) {
+                let font_family = node.get::<FontFamily>().unwrap();
+                let font_size = node.get::<FontSize>().unwrap().0;
+                let line_height = node.get::<LineHeight>().unwrap();
+                let spacing = *node.get::<TextSpacing>().unwrap();
+                let text = node.get::<TextTransform>().unwrap().apply(text);
+                let text = node.get::<WhiteSpace>().unwrap().apply(&text);
+                let (style, baseline_style) = text_styles(
+                    &mut text_context,
+                    &text,
+                    &font_family,
+                    font_size,
+                    &line_height,
+                    spacing,
+                );
+                affected.push((id, style, baseline_style));
+            }
+        }
+        stack.extend(node.child_ids());
+    }
+
+    let mut changed = FxHashSet::default();
+    for (id, style, baseline_style) in affected {
+        let mut node = rdom.get_mut(id).unwrap();
+        let Some(layout) = node.get::<TaffyLayout>().map(|layout| (*layout).clone()) else {
+            continue;
+        };
+        let (Some(taffy_node), Some(baseline)) = (layout.node, layout.baseline) else {
+            continue;
+        };
+        if layout.style != style {
+            taffy.set_style(taffy_node, style).unwrap();
+            taffy.set_style(baseline, baseline_style).unwrap();
+            node.insert(TaffyLayout { style, ..layout });
+            changed.insert(id);
+        }
+    }
+    changed
+}
+
+/// Wraps the lines of text nodes at the width of the content box of their parent. The width is only known once the layout is computed, so this runs after it and returns the text nodes whose size changed, which need to be laid out again.
+///
+/// The layout pass measures text without wrapping, so text that fits in its parent keeps that size.
+pub(crate) fn update_text_wrapping(
+    rdom: &RealDom,
+    taffy: &Arc<Mutex<Taffy>>,
+    text_context: &Arc<Mutex<TextContext>>,
+) -> FxHashSet<NodeId> {
+    let mut taffy = taffy.lock().unwrap();
+    let text_context = text_context.lock().unwrap();
+    let mut changed = FxHashSet::default();
+    let mut stack = vec![rdom.root_id()];
+    while let Some(id) = stack.pop() {
+        let node = rdom.get(id).unwrap();
+        stack.extend(node.child_ids());
+        let NodeType::Text(TextNode { text, .. }) = &*node.node_type() else {
+            continue;
+        };
+        let (Some(parent), Some(layout)) = (node.parent(), node.get::<TaffyLayout>()) else {
+            continue;
+        };
+        let Some(taffy_node) = layout.node else {
+            continue;
+        };
+        let white_space = *node.get::<WhiteSpace>().unwrap();
+        let mut style = layout.style;
+        let (_, wrap_width) = get_content_box_x(&taffy, parent, vello::kurbo::Point::ZERO);
+        let unwrapped_width = match style.size.width {
+            Dimension::Points(width) => width as f64,
+            _ => 0.0,
+        };
+        if white_space.wraps() && unwrapped_width > wrap_width {
+            let font_family = node.get::<FontFamily>().unwrap();
+            let font_size = node.get::<FontSize>().unwrap().0;
+            let fonts = font_family.resolve(&text_context);
+            let text = node.get::<TextTransform>().unwrap().apply(text);
+            let text = white_space.apply(&text);
+            let text_layout = text_context.layout(
+                &fonts,
+                &font_family.features,
+                font_size,
+                &text,
+                node.get::<LineHeight>().unwrap().resolve(font_size),
+                *node.get::<TextSpacing>().unwrap(),
+                Some(wrap_width),
+            );
+            style.size = Size {
+                width: Dimension::Points(text_layout.width() as f32),
+                height: Dimension::Points(text_layout.height() as f32),
+            };
+        }
+        if *taffy.style(taffy_node).unwrap() != style {
+            taffy.set_style(taffy_node, style).unwrap();
+            changed.insert(id);
+        }
+    }
+    changed
+}
+
+/// Measures the elements with a content keyword for their `width`, `min-width` or `max-width`. The measured size is the border box, so it includes the padding and border of the element. This needs to run before the layout is computed. Returns the elements whose width changed.
+pub(crate) fn update_intrinsic_sizes(
+    rdom: &RealDom,
+    taffy: &Arc<Mutex<Taffy>>,
+) -> FxHashSet<NodeId> {
+    let mut taffy = taffy.lock().unwrap();
+    let mut nodes = Vec::new();
+    let mut stack = vec![rdom.root_id()];
+    while let Some(id) = stack.pop() {
+        let node = rdom.get(id).unwrap();
+        stack.extend(node.child_ids());
+        nodes.push(node);
+    }
+
+    let mut changed = FxHashSet::default();
+    // descendants are measured first so the size of their ancestors includes them
+    for node in nodes.into_iter().rev() {
+        let Some(layout) = node.get::<TaffyLayout>() else {
+            continue;
+        };
+        let Some(taffy_node) = layout.node else {
+            continue;
+        };
+        let keywords = [
+            layout.intrinsic_width,
+            layout.intrinsic_min_width,
+            layout.intrinsic_max_width,
+        ];
+        if keywords.iter().all(Option::is_none) {
+            continue;
+        }
+        let mut measure =
+            |available_width| measure_width(&mut taffy, taffy_node, &layout.style, available_width);
+        let mut style = layout.style;
+        match layout.intrinsic_width {
+            Some(IntrinsicSize::MinContent) => {
+                style.size.width = measure(AvailableSpace::MinContent);
+            }
+            Some(IntrinsicSize::MaxContent) => {
+                style.size.width = measure(AvailableSpace::MaxContent);
+            }
+            Some(IntrinsicSize::FitContent) => {
+                style.min_size.width = measure(AvailableSpace::MinContent);
+                style.max_size.width = measure(AvailableSpace::MaxContent);
+            }
+            None => {}
+        }
+        // a limit of fit-content is the smallest width the content allows for `min-width` and the widest for `max-width`
+        match layout.intrinsic_min_width {
+            Some(IntrinsicSize::MinContent | IntrinsicSize::FitContent) => {
+                style.min_size.width = measure(AvailableSpace::MinContent);
+            }
+            Some(IntrinsicSize::MaxContent) => {
+                style.min_size.width = measure(AvailableSpace::MaxContent);
+            }
+            None => {}
+        }
+        match layout.intrinsic_max_width {
+            Some(IntrinsicSize::MinContent) => {
+                style.max_size.width = measure(AvailableSpace::MinContent);
+            }
+            Some(IntrinsicSize::MaxContent | IntrinsicSize::FitContent) => {
+                style.max_size.width = measure(AvailableSpace::MaxContent);
+            }
+            None => {}
+        }
+        if *taffy.style(taffy_node).unwrap() != style {
+            taffy.set_style(taffy_node, style).unwrap();
+            changed.insert(node.id());
+        }
+    }
+    changed
+}
+
+/// Attaches the baseline strut of the text nodes whose baseline lines up flex items, and detaches it from the others. Returns the text nodes whose children changed.
+///
+/// This is a workaround for taffy not knowing where the baseline of text is. It uses the bottom of the first child as the baseline of a node, or the bottom of the node if it has no children. A text node that takes part in `align-items: baseline` or `align-self: baseline` gets an empty child that ends at the baseline of its first line, so the baselines of text in different font sizes line up. The baseline of an item is the baseline of its first child, so the first text of an aligned item takes part too.
+pub(crate) fn update_baseline_struts(
+    rdom: &RealDom,
+    taffy: &Arc<Mutex<Taffy>>,
+) -> FxHashSet<NodeId> {
+    let mut taffy = taffy.lock().unwrap();
+    let mut changed = FxHashSet::default();
+    // each node with whether its baseline is used to align it
+    let mut stack = vec![(rdom.root_id(), false)];
+    while let Some((id, aligned)) = stack.pop() {
+        let node = rdom.get(id).unwrap();
+        let Some(layout) = node.get::<TaffyLayout>() else {
+            continue;
+        };
+        if let (Some(taffy_node), Some(strut)) = (layout.node, layout.baseline) {
+            let children = if aligned { vec![strut] } else { Vec::new() };
+            if taffy.children(taffy_node).unwrap() != children {
+                taffy.set_children(taffy_node, &children).unwrap();
+                changed.insert(id);
+            }
+            continue;
+        }
+        let aligns_items = layout.style.align_items == AlignItems::Baseline;
+        for (index, child) in node.children().into_iter().enumerate() {
+            let align_self = child
+                .get::<TaffyLayout>()
+                .map_or(AlignSelf::Auto, |child| child.style.align_self);
+            let child_aligned = match align_self {
+                AlignSelf::Auto => aligns_items,
+                align_self => align_self == AlignSelf::Baseline,
+            } || (aligned && index == 0);
+            stack.push((child.id(), child_aligned));
+        }
+    }
+    changed
+}
+
+/// Lays out a node on its own with the style from its attributes and returns the width of its border box.
+fn measure_width(
+    taffy: &mut Taffy,
+    taffy_node: Node,
+    style: &Style,
+    available_width: AvailableSpace,
+) -> Dimension {
+    if taffy.style(taffy_node).unwrap() != style {
+        taffy.set_style(taffy_node, *style).unwrap();
+    }
+    taffy
+        .compute_layout(
+            taffy_node,
+            Size {
+                width: available_width,
+                height: AvailableSpace::MaxContent,
+            },
+        )
+        .unwrap();
+    Dimension::Points(taffy.layout(taffy_node).unwrap().size.width)
+}
+
+/// Sets a layout property in `ch` or `ex` from the metrics of the font of the element.
+fn apply_font_relative_length(name: &str, value: &str, style: &mut Style, font_units: FontUnits) {
+    let Ok(LengthPercentageOrAuto::LengthPercentage(DimensionPercentage::Dimension(length))) =
+        LengthPercentageOrAuto::parse_string(value)
+    else {
+        return;
+    };
+    let dimension = match length {
+        LengthValue::Ch(ch) => Dimension::Points(ch * font_units.ch),
+        LengthValue::Ex(ex) => Dimension::Points(ex * font_units.ex),
+        _ => return,
+    };
+    let property = match name {
+        "width" => &mut style.size.width,
+        "height" => &mut style.size.height,
+        "min-width" => &mut style.min_size.width,
+        "min-height" => &mut style.min_size.height,
+        "max-width" => &mut style.max_size.width,
+        "max-height" => &mut style.max_size.height,
+        "flex-basis" => &mut style.flex_basis,
+        "margin-left" => &mut style.margin.left,
+        "margin-right" => &mut style.margin.right,
+        "margin-top" => &mut style.margin.top,
+        "margin-bottom" => &mut style.margin.bottom,
+        "padding-left" => &mut style.padding.left,
+        "padding-right" => &mut style.padding.right,
+        "padding-top" => &mut style.padding.top,
+        "padding-bottom" => &mut style.padding.bottom,
+        "left" => &mut style.position.left,
+        "right" => &mut style.position.right,
+        "top" => &mut style.position.top,
+        "bottom" => &mut style.position.bottom,
+        _ => return,
+    };
+    *property = dimension;
+}
+
+/// The value of the `aspect-ratio` property.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct AspectRatio {
+    /// Replaced elements like images use their natural ratio if they have one.
+    auto: bool,
+    /// The width divided by the height. A ratio with a zero side is ignored.
+    ratio: Option<f32>,
+}
+
+impl AspectRatio {
+    /// Parses `auto`, `<number>`, `<width> / <height>` or `auto` together with a ratio.
+    fn parse(value: &str) -> Option<Self> {
+        let value = value.trim().to_ascii_lowercase();
+        let mut words: Vec<&str> = value.split_whitespace().collect();
+        let auto = match words.iter().position(|word| *word == "auto") {
+            Some(0) => {
+                words.remove(0);
+                true
+            }
+            Some(last) if last == words.len() - 1 => {
+                words.pop();
+                true
+            }
+            Some(_) => return None,
+            None => false,
+        };
+        if words.is_empty() {
+            return auto.then_some(Self { auto, ratio: None });
+        }
+        let ratio = words.concat();
+        let (width, height) = ratio.split_once('/').unwrap_or((&ratio, "1"));
+        let (width, height) = (parse_ratio_number(width)?, parse_ratio_number(height)?);
+        Some(Self {
+            auto,
+            ratio: (width > 0.0 && height > 0.0).then(|| width / height),
+        })
+    }
+
+    /// Sets the preferred ratio of the element. Images keep their natural ratio for `auto`.
+    fn apply(self, style: &mut Style, image: &LoadedImage) {
+        if self.auto && matches!(image, LoadedImage::Loaded(_)) {
+            return;
+        }
+        // `auto` alone leaves the element without a preferred ratio
+        style.aspect_ratio = self.ratio;
+    }
+}
+
+/// Parses one side of a ratio. Negative numbers are invalid.
+fn parse_ratio_number(value: &str) -> Option<f32> {
+    value
+        .parse::<f32>()
+        .ok()
+        .filter(|number| number.is_finite() && *number >= 0.0)
+}
+
+/// Sets the limits on the size of an element from `min-width`, `min-height`, `max-width` or `max-height`. `auto` and `none` remove the limit. Content keywords are measured by [`update_intrinsic_sizes`] for widths and leave heights unlimited, since the content already decides the height of a block.
+fn apply_min_max_size(
+    name: &str,
+    value: &str,
+    style: &mut Style,
+    resolve: impl Fn(&LengthPercentage) -> Option<Dimension>,
+) {
+    let (property, none) = match name {
+        "min-width" => (&mut style.min_size.width, "auto"),
+        "min-height" => (&mut style.min_size.height, "auto"),
+        "max-width" => (&mut style.max_size.width, "none"),
+        "max-height" => (&mut style.max_size.height, "none"),
+        _ => return,
+    };
+    let value = value.trim();
+    if value.eq_ignore_ascii_case(none) || IntrinsicSize::parse(value).is_some() {
+        *property = Dimension::Auto;
+        return;
+    }
+    if let Ok(LengthPercentageOrAuto::LengthPercentage(length)) =
+        LengthPercentageOrAuto::parse_string(value)
+    {
+        if let Some(dimension) = resolve(&length) {
+            *property = dimension;
+        }
+    }
+}
+
+/// Sets the space between rows and columns from `gap`, `row-gap` or `column-gap`. `normal` is no gap.
+fn apply_gap(
+    name: &str,
+    value: &str,
+    style: &mut Style,
+    resolve: impl Fn(&LengthPercentage) -> Option<Dimension>,
+) {
+    if !matches!(name, "gap" | "row-gap" | "column-gap") {
+        return;
+    }
+    let mut input = ParserInput::new(value);
+    let mut parser = Parser::new(&mut input);
+    let Ok(property) = Property::parse(name.into(), &mut parser, &ParserOptions::default()) else {
+        return;
+    };
+    let resolve = |gap: &GapValue| match gap {
+        GapValue::Normal => Some(Dimension::Points(0.0)),
+        GapValue::LengthPercentage(length) => resolve(length),
+    };
+    // taffy's gap is the space along each axis, so the row gap is its height
+    match property {
+        Property::Gap(gap) => {
+            if let (Some(row), Some(column)) = (resolve(&gap.row), resolve(&gap.column)) {
+                style.gap.height = row;
+                style.gap.width = column;
+            }
+        }
+        Property::RowGap(gap) => {
+            if let Some(row) = resolve(&gap) {
+                style.gap.height = row;
+            }
+        }
+        Property::ColumnGap(gap) => {
+            if let Some(column) = resolve(&gap) {
+                style.gap.width = column;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Converts a length to a dimension. Percentages are left for taffy to resolve against the size of the container and lengths in `em` are relative to the font size of the element.
+fn length_dimension(length: &LengthPercentage, context: &ResolveContext) -> Option<Dimension> {
+    match length {
+        DimensionPercentage::Percentage(percent) => Some(Dimension::Percent(percent.0)),
+        // a taffy dimension can't mix percentages and lengths, and the size of the container isn't known until taffy lays it out
+        DimensionPercentage::Calc(calc) if calc_has_percentage(calc) => None,
+        length => {
+            // without percentages the size of the container doesn't matter
+            let rect = Size {
+                width: 0.0,
+                height: 0.0,
+            };
+            Some(Dimension::Points(
+                length.resolve(Axis::X, &rect, context) as f32
+            ))
+        }
+    }
+}
+
+/// Checks if a `calc()` or another math function refers to the size of the container.
+fn calc_has_percentage(calc: &Calc<DimensionPercentage<LengthValue>>) -> bool {
+    match calc {
+        Calc::Value(value) => match &**value {
+            DimensionPercentage::Dimension(_) => false,
+            DimensionPercentage::Percentage(_) => true,
+            DimensionPercentage::Calc(calc) => calc_has_percentage(calc),
+        },
+        Calc::Number(_) => false,
+        Calc::Sum(a, b) => calc_has_percentage(a) || calc_has_percentage(b),
+        Calc::Product(_, value) => calc_has_percentage(value),
+        Calc::Function(function) => match &**function {
+            MathFunction::Calc(calc) | MathFunction::Abs(calc) | MathFunction::Sign(calc) => {
+                calc_has_percentage(calc)
+            }
+            MathFunction::Min(values) | MathFunction::Max(values) | MathFunction::Hypot(values) => {
+                values.iter().any(calc_has_percentage)
+            }
+            MathFunction::Clamp(min, value, max) => {
+                calc_has_percentage(min) || calc_has_percentage(value) || calc_has_percentage(max)
+            }
+            MathFunction::Round(_, value, interval) => {
+                calc_has_percentage(value) || calc_has_percentage(interval)
+            }
+            MathFunction::Rem(dividend, divisor) | MathFunction::Mod(dividend, divisor) => {
+                calc_has_percentage(dividend) || calc_has_percentage(divisor)
+            }
+        },
+    }
+}
+
+/// Parses `flex-basis` into a dimension taffy can use. Percentages are left for taffy to resolve against the main size of the flex container and the result is clamped by the min and max size of the item.
+fn parse_flex_basis(value: &str, context: &ResolveContext) -> Option<Dimension> {
+    match LengthPercentageOrAuto::parse_string(value).ok()? {
+        LengthPercentageOrAuto::Auto => Some(Dimension::Auto),
+        LengthPercentageOrAuto::LengthPercentage(length) => length_dimension(&length, context),
+    }
+}
+
+/// The layout of an element in window coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ElementLayout {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// A view of the computed layout of the document. This is passed to [`crate::Driver::after_layout`] every time the layout changes.
+pub struct ComputedLayout<'a> {
+    rdom: &'a RealDom,
+    taffy: &'a Taffy,
+    viewport_size: Size<u32>,
+}
+
+impl<'a> ComputedLayout<'a> {
+    pub(crate) fn new(rdom: &'a RealDom, taffy: &'a Taffy, viewport_size: Size<u32>) -> Self {
+        Self {
+            rdom,
+            taffy,
+            viewport_size,
+        }
+    }
+
+    /// Get the layout of a node.
+    pub fn get(&self, id: NodeId) -> Option<ElementLayout> {
+        let node = self.rdom.get(id)?;
+        let taffy_node = node.get::<TaffyLayout>()?.node?;
+        let layout = self.taffy.layout(taffy_node).ok()?;
+        let pos = get_abs_pos(*layout, self.taffy, node, &self.viewport_size);
+        Some(ElementLayout {
+            x: pos.x as f32,
+            y: pos.y as f32,
+            width: layout.size.width,
+            height: layout.size.height,
+        })
+    }
+
+    /// Find the topmost element at a point, the element a click there would land on.
+    pub fn hit_test(&self, x: f32, y: f32) -> Option<NodeId> {
+        hit_test(
+            self.rdom,
+            self.taffy,
+            &self.viewport_size,
+            vello::kurbo::Point::new(x as f64, y as f64),
+        )
+    }
+
+    /// Get the layout of the first element with an `id` attribute that matches.
+    pub fn get_by_id(&self, id: &str) -> Option<ElementLayout> {
+        let node = find_by_id(self.rdom.get(self.rdom.root_id())?, id)?;
+        self.get(node)
+    }
+
+    /// Evaluate a container query for the first element with an `id` attribute that matches. The query is evaluated against the nearest container that is an ancestor of the element.
+    pub fn query_container(&self, id: &str, query: &ContainerQuery) -> bool {
+        self.rdom
+            .get(self.rdom.root_id())
+            .and_then(|root| find_by_id(root, id))
+            .and_then(|node| self.rdom.get(node))
+            .map(|node| query.evaluate(node, self.taffy))
+            .unwrap_or(false)
+    }
+
+    /// Serialize the layout of the whole document to JSON so it can be inspected or diffed offline.
+    ///
+    /// Every node has its `id`, its `tag` or `text`, its `rect` in window coordinates, a few computed `style` values and its `children`. Nodes that are not laid out have no `rect`.
+    pub fn to_json(&self) -> serde_json::Value {
+        self.rdom
+            .get(self.rdom.root_id())
+            .map_or(serde_json::Value::Null, |root| self.node_json(root))
+    }
+
+    fn node_json(&self, node: NodeRef) -> serde_json::Value {
+        let mut json = serde_json::Map::new();
+        json.insert("id".into(), format!("{:?}", node.id()).into());
+        match &*node.node_type() {
+            NodeType::Element(element) => {
+                json.insert("tag".into(), element.tag.clone().into());
+            }
+            NodeType::Text(TextNode { text, .. }) => {
+                json.insert("text".into(), text.clone().into());
+            }
+            _ => {}
+        }
+        if let Some(layout) = self.get(node.id()) {
+            json.insert(
+                "rect".into(),
+                serde_json::json!({
+                    "x": layout.x,
+                    "y": layout.y,
+                    "width": layout.width,
+                    "height": layout.height,
+                }),
+            );
+        }
+
+        let mut style = serde_json::Map::new();
+        if let Some(layout) = node.get::<TaffyLayout>() {
+            style.insert(
+                "display".into(),
+                format!("{:?}", layout.style.display).into(),
+            );
+            style.insert(
+                "flex-direction".into(),
+                format!("{:?}", layout.style.flex_direction).into(),
+            );
+        }
+        if let Some(font_size) = node.get::<FontSize>() {
+            style.insert("font-size".into(), font_size.0.into());
+        }
+        if let Some(color) = node.get::<ForgroundColor>() {
+            style.insert("color".into(), color_json(&color.0).into());
+        }
+        if let Some(color) = node.get::<BackgroundColor>() {
+            style.insert("background-color".into(), color_json(&color.0).into());
+        }
+        if let Some(opacity) = node.get::<Opacity>() {
+            style.insert("opacity".into(), opacity.0.into());
+        }
+        json.insert("style".into(), style.into());
+
+        let children: Vec<_> = node
+            .children()
+            .into_iter()
+            .map(|child| self.node_json(child))
+            .collect();
+        json.insert("children".into(), children.into());
+        json.into()
+    }
+}
+
+fn color_json(color: &CssColor) -> String {
+    let Color { r, g, b, a } = translate_color(color);
+    format!("rgba({r}, {g}, {b}, {a})")
+}
+
+fn find_by_id(node: NodeRef, id: &str) -> Option<NodeId> {
+    if let NodeType::Element(element) = &*node.node_type() {
+        let matches = element
+            .attributes
+            .iter()
+            .any(|(attribute, value)| attribute.name == "id" && value.as_text() == Some(id));
+        if matches {
+            return Some(node.id());
+        }
+    }
+    node.children()
+        .into_iter()
+        .find_map(|child| find_by_id(child, id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::Future;
+    use std::pin::Pin;
+
+    use dioxus_native_core::FxDashSet;
+
+    use crate::application::{create_rdom, update_and_layout, LayoutState};
+    use crate::{Config, Driver, EventData};
+
+    /// A driver that leaves the document alone.
+    struct StaticDocument;
+
+    impl Driver for StaticDocument {
+        fn update(&mut self, _: NodeMut) {}
+
+        fn handle_event(&mut self, _: NodeMut, _: &str, _: Arc<EventData>, _: bool) {}
+
+        fn poll_async(&mut self) -> Pin<Box<dyn Future<Output = ()> + '_>> {
+            Box::pin(async {})
+        }
+    }
+
+    /// Adds an element with the given attributes to `parent`.
+    fn element(
+        rdom: &mut RealDom,
+        parent: NodeId,
+        tag: &str,
+        attributes: &[(&str, &str)],
+    ) -> NodeId {
+        let id = rdom
+            .create_node(NodeType::Element(ElementNode {
+                tag: tag.to_string(),
+                attributes: attributes
+                    .iter()
+                    .map(|(name, value)| ((*name, "style").into(), value.to_string().into()))
+                    .collect(),
+                ..Default::default()
+            }))
+            .id();
+        rdom.get_mut(parent).unwrap().add_child(id);
+        id
+    }
+
+    /// Lays out a document in an 800x600 window like the window does.
+    fn lay_out(rdom: &mut RealDom) -> Arc<Mutex<Taffy>> {
+        let taffy = Arc::new(Mutex::new(Taffy::new()));
+        let text_context = Arc::new(Mutex::new(TextContext::default()));
+        update_and_layout(
+            &mut StaticDocument,
+            rdom,
+            &taffy,
+            &text_context,
+            ViewportSize(Size {
+                width: 800,
+                height: 600,
+            }),
+            &mut LayoutState::default(),
+            &FxDashSet::default(),
+        )
+        .unwrap();
+        taffy
+    }
+
+    /// The top of an element relative to its parent.
+    fn top(rdom: &RealDom, taffy: &Taffy, id: NodeId) -> f32 {
+        let node = rdom
+            .get(id)
+            .unwrap()
+            .get::<TaffyLayout>()
+            .unwrap()
+            .node
+            .unwrap();
+        taffy.layout(node).unwrap().location.y
+    }
+
+    #[test]
+    fn bottom_figure_caption_is_placed_below_the_content() {
+        let mut rdom = create_rdom(&Config::default());
+        let root = rdom.root_id();
+        let figure = element(&mut rdom, root, "figure", &[]);
+        let caption = element(
+            &mut rdom,
+            figure,
+            "figcaption",
+            &[("caption-side", "bottom"), ("height", "20px")],
+        );
+        let content = element(&mut rdom, figure, "div", &[("height", "50px")]);
+        let taffy = lay_out(&mut rdom);
+        let taffy = taffy.lock().unwrap();
+
+        assert_eq!(top(&rdom, &taffy, content), 0.0);
+        assert_eq!(top(&rdom, &taffy, caption), 50.0);
+    }
+
+    /// Lays out a box with the `max-width` inside an 800px wide root. The box contains content that is 1000px wide. Returns the width of the box.
+    fn box_width(max_width: &str) -> f32 {
+        let viewport_size = Size {
+            width: 800,
+            height: 600,
+        };
+        let mut taffy = Taffy::new();
+        let content = taffy
+            .new_leaf(Style {
+                size: Size {
+                    width: Dimension::Points(1000.0),
+                    height: Dimension::Points(20.0),
+                },
+                ..Default::default()
+            })
+            .unwrap();
+        let mut style = Style::default();
+        let context = ResolveContext {
+            viewport_size,
+            font_size: 16.0,
+            root_font_size: 16.0,
+            font_units: FontUnits::approximate(16.0),
+        };
+        apply_min_max_size("max-width", max_width, &mut style, |length| {
+            length_dimension(length, &context)
+        });
+        let container = taffy.new_with_children(style, &[content]).unwrap();
+        let root = taffy
+            .new_with_children(
+                Style {
+                    size: Size {
+                        width: Dimension::Points(800.0),
+                        height: Dimension::Auto,
+                    },
+                    ..Default::default()
+                },
+                &[container],
+            )
+            .unwrap();
+        taffy
+            .compute_layout(
+                root,
+                Size {
+                    width: AvailableSpace::Definite(800.0),
+                    height: AvailableSpace::MaxContent,
+                },
+            )
+            .unwrap();
+        taffy.layout(container).unwrap().size.width
+    }
+
+    #[test]
+    fn max_width_limits_wide_content() {
+        assert_eq!(box_width("200px"), 200.0);
+        assert_eq!(box_width("calc(100px + 100px)"), 200.0);
+        // 25% of the 800px root
+        assert_eq!(box_width("25%"), 200.0);
+        assert!(box_width("none") > 200.0);
+    }
+
+    #[test]
+    fn flex_basis_grows_up_to_max_width() {
+        let context = ResolveContext {
+            viewport_size: Size {
+                width: 800,
+                height: 600,
+            },
+            font_size: 16.0,
+            root_font_size: 16.0,
+            font_units: FontUnits::approximate(16.0),
+        };
+        let mut taffy = Taffy::new();
+        let items: Vec<_> = ["0", "calc(1em + 2rem)"]
+            .into_iter()
+            .map(|flex_basis| {
+                let mut style = Style {
+                    flex_basis: parse_flex_basis(flex_basis, &context).unwrap(),
+                    flex_grow: 1.0,
+                    ..Default::default()
+                };
+                apply_min_max_size("max-width", "100px", &mut style, |length| {
+                    length_dimension(length, &context)
+                });
+                taffy.new_leaf(style).unwrap()
+            })
+            .collect();
+        let root = taffy
+            .new_with_children(
+                Style {
+                    size: Size {
+                        width: Dimension::Points(800.0),
+                        height: Dimension::Auto,
+                    },
+                    ..Default::default()
+                },
+                &items,
+            )
+            .unwrap();
+        taffy
+            .compute_layout(
+                root,
+                Size {
+                    width: AvailableSpace::Definite(800.0),
+                    height: AvailableSpace::MaxContent,
+                },
+            )
+            .unwrap();
+        for item in items {
+            assert_eq!(taffy.layout(item).unwrap().size.width, 100.0);
+        }
+        assert_eq!(
+            parse_flex_basis("2em", &context),
+            Some(Dimension::Points(32.0))
+        );
+        assert_eq!(
+            parse_flex_basis("10vw", &context),
+            Some(Dimension::Points(80.0))
+        );
+    }
+
+    #[test]
+    fn max_width_with_a_percentage_in_calc_is_not_font_relative() {
+        // the size of the container isn't known when the style is computed, so the limit is left unset rather than resolved against the font size
+        assert_eq!(box_width("calc(100% - 2rem)"), box_width("none"));
+        assert_eq!(box_width("min(50%, 300px)"), box_width("none"));
+        assert_eq!(box_width("calc(10em + 40px)"), 200.0);
+    }
+
+    #[test]
+    fn baselines_of_mixed_font_sizes_line_up() {
+        let mut text_context = TextContext::default();
+        let mut taffy = Taffy::new();
+        let family = FontFamily::default();
+        // an item with a text node that has its baseline strut attached, like update_baseline_struts does
+        let mut item = |font_size: f32| {
+            let (style, strut_style) = text_styles(
+                &mut text_context,
+                "Baseline",
+                &family,
+                font_size,
+                &LineHeight::default(),
+                TextSpacing::default(),
+            );
+            let strut = taffy.new_leaf(strut_style).unwrap();
+            let text = taffy.new_with_children(style, &[strut]).unwrap();
+            let item = taffy.new_with_children(Style::default(), &[text]).unwrap();
+            (item, text, strut_style.size.height)
+        };
+        let (big, big_text, big_baseline) = item(32.0);
+        let (small, small_text, small_baseline) = item(12.0);
+        let container = taffy
+            .new_with_children(
+                Style {
+                    align_items: AlignItems::Baseline,
+                    ..Default::default()
+                },
+                &[big, small],
+            )
+            .unwrap();
+        taffy
+            .compute_layout(
+                container,
+                Size {
+                    width: AvailableSpace::Definite(800.0),
+                    height: AvailableSpace::MaxContent,
+                },
+            )
+            .unwrap();
+
+        let baseline = |item, text, strut: Dimension| {
+            let Dimension::Points(strut) = strut else {
+                panic!("the strut has a fixed height");
+            };
+            taffy.layout(item).unwrap().location.y + taffy.layout(text).unwrap().location.y + strut
+        };
+        assert_ne!(big_baseline, small_baseline);
+        let big = baseline(big, big_text, big_baseline);
+        let small = baseline(small, small_text, small_baseline);
+        assert!((big - small).abs() < 0.01, "{big} != {small}");
+    }
+}
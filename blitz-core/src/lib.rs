@@ -1,36 +1,70 @@
 use std::{
+    path::PathBuf,
     pin::Pin,
     sync::{Arc, Mutex, RwLock},
+    time::Duration,
 };
 
-use application::ApplicationState;
+use application::{ApplicationState, DirtyNodes};
 use dioxus_native_core::prelude::*;
+use text::font_face::parse_font_face_rules;
+use text::{parse_unicode_range, FontFile, FontSource};
 
+pub use ::image::RgbaImage;
 use futures_util::Future;
 use taffy::Taffy;
 use tao::{
+    dpi::LogicalSize,
     event::{Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
     window::WindowBuilder,
 };
 
+pub use crate::container::ContainerQuery;
+pub use crate::diagnostics::{StyleWarning, StyleWarningKind};
 #[cfg(feature = "dioxus-bindings")]
 pub use crate::dioxus::*;
 pub use crate::events::EventData;
+pub use crate::headless::render_to_image;
+pub use crate::layout::{ComputedLayout, ElementLayout};
+pub use crate::scroll::ScrollAlignment;
+pub use crate::window::{PageSize, WindowHandle};
 
+mod accessibility;
 mod application;
+mod background;
+mod checkbox;
+mod container;
+mod cursor;
+mod diagnostics;
 #[cfg(feature = "dioxus-bindings")]
 mod dioxus;
 mod events;
 mod focus;
+mod generated_content;
+mod headless;
+mod hit_test;
 mod image;
 mod layout;
+mod meter;
 mod mouse;
+mod position;
 mod prevent_default;
+mod print;
 mod render;
+mod scroll;
+mod selection;
+mod stacking;
 mod style;
+mod supports;
+mod table;
 mod text;
+mod text_input;
+mod transform;
 mod util;
+mod visibility;
+mod window;
+mod writing_mode;
 
 type TaoEvent<'a> = Event<'a, Redraw>;
 
@@ -38,17 +72,192 @@ type TaoEvent<'a> = Event<'a, Redraw>;
 pub struct Redraw;
 
 #[derive(Default)]
-pub struct Config;
+pub struct Config {
+    debug_redraw_regions: bool,
+    paused_input: PausedInput,
+    resume_on_focus: bool,
+    font_files: Vec<FontFile>,
+    key_repeat: KeyRepeat,
+    style_diagnostics: bool,
+    title: Option<String>,
+    inner_size: Option<LogicalSize<f64>>,
+    min_inner_size: Option<LogicalSize<f64>>,
+    max_inner_size: Option<LogicalSize<f64>>,
+    resizable: Option<bool>,
+    maximized: bool,
+}
+
+/// What happens to input received while rendering is paused with [`WindowHandle::pause`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PausedInput {
+    /// Input is ignored.
+    #[default]
+    Drop,
+    /// Input is processed in order once rendering resumes.
+    Queue,
+}
+
+/// How keys that are held down repeat.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeyRepeat {
+    /// Use the repeats sent by the operating system, which follow the user's repeat settings.
+    #[default]
+    System,
+    /// Repeat the held key after `delay` and then every `interval`. Repeats sent by the operating system are ignored.
+    Custom { delay: Duration, interval: Duration },
+    /// Held keys only fire once.
+    Disabled,
+}
+
+impl Config {
+    /// Tint the parts of the window that are redrawn each frame. This makes it easy to spot nodes that are invalidated more often than they should be.
+    pub fn with_debug_redraw_regions(mut self, enabled: bool) -> Self {
+        self.debug_redraw_regions = enabled;
+        self
+    }
+
+    /// Set what happens to input received while rendering is paused.
+    pub fn with_paused_input(mut self, paused_input: PausedInput) -> Self {
+        self.paused_input = paused_input;
+        self
+    }
+
+    /// Register a font file for a `font-family` name.
+    ///
+    /// Fonts are loaded on a background thread so they don't delay the first frame. Text that uses the family is drawn with the fallback font until the file is loaded, then it is measured and drawn again.
+    ///
+    /// Register several files for a family to give it bold and italic faces. Whether a face is bold or italic is read from the font, and text that asks for a style the family doesn't have is emboldened or slanted.
+    ///
+    /// Families that aren't registered are looked up in the fonts installed on the system, and registered files take precedence over installed fonts with the same name.
+    pub fn with_font_file(mut self, family: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        self.font_files
+            .push(FontFile::new(family.into(), FontSource::Path(path.into())));
+        self
+    }
+
+    /// Register a font for a `font-family` name from its data, like a font bundled with `include_bytes!`.
+    ///
+    /// Unlike font files, the font is ready before the first frame is drawn.
+    pub fn with_font_bytes(mut self, family: impl Into<String>, bytes: impl Into<Vec<u8>>) -> Self {
+        self.font_files.push(FontFile::new(
+            family.into(),
+            FontSource::Bytes(Arc::new(bytes.into())),
+        ));
+        self
+    }
+
+    /// Register the fonts of the `@font-face` rules in a stylesheet, like `@font-face { font-family: "MyFont"; src: url(fonts/MyFont-Bold.ttf); font-weight: bold; }`.
+    ///
+    /// Each rule is registered like [`Self::with_font_face`]. The `font-weight` and `font-style` of a rule decide which text the face is picked for instead of the flags in the font. Only `url()` sources are loaded, and they are paths on disk.
+    pub fn with_font_face_rules(mut self, css: &str) -> Self {
+        self.font_files.extend(parse_font_face_rules(css));
+        self
+    }
+
+    /// Register a font file that only covers part of Unicode for a `font-family` name, like an `@font-face` rule with a `unicode-range` such as `U+4E00-9FFF, U+30??`.
+    ///
+    /// A family can have several faces. Each character is drawn with the first face of the family whose range covers it. If the range can't be parsed the face covers every character.
+    pub fn with_font_face(
+        mut self,
+        family: impl Into<String>,
+        path: impl Into<PathBuf>,
+        unicode_range: &str,
+    ) -> Self {
+        let mut file = FontFile::new(family.into(), FontSource::Path(path.into()));
+        file.unicode_range = parse_unicode_range(unicode_range);
+        self.font_files.push(file);
+        self
+    }
+
+    /// Set how keys that are held down repeat.
+    pub fn with_key_repeat(mut self, key_repeat: KeyRepeat) -> Self {
+        self.key_repeat = key_repeat;
+        self
+    }
+
+    /// Report style attributes that are ignored because their value is invalid or the property doesn't exist. Warnings are printed to stderr and can be read with [`WindowHandle::style_warnings`].
+    ///
+    /// Every style attribute is parsed an extra time, so this is meant for debugging.
+    pub fn with_style_diagnostics(mut self, enabled: bool) -> Self {
+        self.style_diagnostics = enabled;
+        self
+    }
+
+    /// Set the title of the window.
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Set the size of the window content in logical pixels when it opens.
+    pub fn with_inner_size(mut self, width: f64, height: f64) -> Self {
+        self.inner_size = Some(LogicalSize::new(width, height));
+        self
+    }
+
+    /// Keep the user from making the window content smaller than a size in logical pixels.
+    pub fn with_min_inner_size(mut self, width: f64, height: f64) -> Self {
+        self.min_inner_size = Some(LogicalSize::new(width, height));
+        self
+    }
+
+    /// Keep the user from making the window content larger than a size in logical pixels.
+    pub fn with_max_inner_size(mut self, width: f64, height: f64) -> Self {
+        self.max_inner_size = Some(LogicalSize::new(width, height));
+        self
+    }
+
+    /// Set whether the user can resize the window. Windows are resizable by default.
+    pub fn with_resizable(mut self, resizable: bool) -> Self {
+        self.resizable = Some(resizable);
+        self
+    }
+
+    /// Open the window maximized.
+    pub fn with_maximized(mut self, maximized: bool) -> Self {
+        self.maximized = maximized;
+        self
+    }
+
+    /// Apply the window settings to a window builder.
+    fn window_builder(&self) -> WindowBuilder {
+        let mut builder = WindowBuilder::new().with_maximized(self.maximized);
+        if let Some(title) = &self.title {
+            builder = builder.with_title(title);
+        }
+        if let Some(size) = self.inner_size {
+            builder = builder.with_inner_size(size);
+        }
+        if let Some(size) = self.min_inner_size {
+            builder = builder.with_min_inner_size(size);
+        }
+        if let Some(size) = self.max_inner_size {
+            builder = builder.with_max_inner_size(size);
+        }
+        if let Some(resizable) = self.resizable {
+            builder = builder.with_resizable(resizable);
+        }
+        builder
+    }
+
+    /// Resume rendering automatically when the window gains focus.
+    pub fn with_resume_on_focus(mut self, enabled: bool) -> Self {
+        self.resume_on_focus = enabled;
+        self
+    }
+}
 
 pub async fn render<R: Driver>(
-    spawn_renderer: impl FnOnce(&Arc<RwLock<RealDom>>, &Arc<Mutex<Taffy>>) -> R + Send + 'static,
-    _cfg: Config,
+    spawn_renderer: impl FnOnce(&Arc<RwLock<RealDom>>, &Arc<Mutex<Taffy>>, &WindowHandle) -> R
+        + Send
+        + 'static,
+    cfg: Config,
 ) {
     let event_loop = EventLoop::with_user_event();
-    let window = WindowBuilder::new().build(&event_loop).unwrap();
+    let window = cfg.window_builder().build(&event_loop).unwrap();
     let mut appliction =
-        ApplicationState::new(spawn_renderer, &window, event_loop.create_proxy()).await;
-    appliction.render();
+        ApplicationState::new(spawn_renderer, &window, event_loop.create_proxy(), &cfg).await;
+    appliction.render(&DirtyNodes::All);
 
     event_loop.run(move |event, _, control_flow| {
         // ControlFlow::Wait pauses the event loop if no events are available to process.
@@ -56,7 +265,13 @@ pub async fn render<R: Driver>(
         // input, and uses significantly less power/CPU time than ControlFlow::Poll.
         *control_flow = ControlFlow::Wait;
 
+        let Some(event) = appliction.filter_paused(event) else {
+            return;
+        };
         appliction.send_event(&event);
+        appliction.update_ime_position(&window);
+        appliction.update_cursor(&window);
+        appliction.poll_captures();
 
         match event {
             Event::WindowEvent {
@@ -71,7 +286,9 @@ pub async fn render<R: Driver>(
                 // You only need to call this if you've determined that you need to redraw, in
                 // applications which do not always need to. Applications that redraw continuously
                 // can just render here instead.
-                window.request_redraw();
+                if !appliction.is_paused() {
+                    window.request_redraw();
+                }
             }
             Event::RedrawRequested(_) => {
                 // Redraw the application.
@@ -80,11 +297,15 @@ pub async fn render<R: Driver>(
                 // this event rather than in MainEventsCleared, since rendering in here allows
                 // the program to gracefully handle redraws requested by the OS.
 
-                if !appliction.clean().is_empty() {
-                    appliction.render();
+                if !appliction.is_paused() {
+                    let dirty = appliction.clean();
+                    if !dirty.is_empty() {
+                        appliction.render(&dirty);
+                    }
                 }
             }
             Event::UserEvent(_redraw) => {
+                appliction.handle_commands();
                 window.request_redraw();
             }
             Event::WindowEvent {
@@ -94,8 +315,35 @@ pub async fn render<R: Driver>(
             } => {
                 appliction.set_size(physical_size);
             }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::ScaleFactorChanged {
+                        scale_factor,
+                        new_inner_size,
+                    },
+                ..
+            } => {
+                appliction.set_scale_factor(scale_factor, *new_inner_size);
+            }
             _ => (),
         }
+
+        // Wake up when the held key should repeat or the caret of the focused text field blinks
+        let next_repeat = appliction.repeat_held_key();
+        let next_blink = appliction.blink_caret();
+        if let Some(wake_up) = next_repeat.into_iter().chain(next_blink).min() {
+            *control_flow = ControlFlow::WaitUntil(wake_up);
+        }
+
+        // Keep drawing frames while a smooth scroll is running
+        if appliction.animate_scroll() {
+            *control_flow = ControlFlow::Poll;
+        }
+
+        // Keep polling until the GPU has finished copying any screenshots or printouts
+        if appliction.has_pending_captures() {
+            *control_flow = ControlFlow::Poll;
+        }
     });
 }
 
@@ -103,4 +351,6 @@ pub trait Driver {
     fn update(&mut self, root: NodeMut);
     fn handle_event(&mut self, node: NodeMut, event: &str, value: Arc<EventData>, bubbles: bool);
     fn poll_async(&mut self) -> Pin<Box<dyn Future<Output = ()> + '_>>;
+    /// Called after the layout is computed and before the frame is painted. Changes made to the dom here are applied before the frame is painted.
+    fn after_layout(&mut self, _layout: &ComputedLayout) {}
 }
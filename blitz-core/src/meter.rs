@@ -0,0 +1,189 @@
+//! The `<progress>` and `<meter>` elements.
+//!
+//! Both are drawn as a track with a bar filled in proportion to their value. Their content is only a fallback for browsers that don't support them, so it isn't drawn.
+
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use shipyard::Component;
+use taffy::prelude::Layout;
+use vello::kurbo::{Affine, Point, Rect, RoundedRect};
+use vello::peniko::{Color, Fill, Mix};
+use vello::SceneBuilder;
+
+use crate::style::{AccentColor, ForgroundColor, DEFAULT_ACCENT};
+
+const TRACK_COLOR: Color = Color::rgb8(239, 239, 239);
+
+/// How full a `<progress>` or `<meter>` element is.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Component)]
+pub(crate) enum Gauge {
+    /// The element is not a gauge.
+    #[default]
+    None,
+    /// A progress bar without a value, the task has no known length.
+    Indeterminate,
+    /// The filled fraction of the bar, between 0 and 1.
+    Value(f64),
+}
+
+impl Gauge {
+    pub fn is_gauge(&self) -> bool {
+        *self != Gauge::None
+    }
+}
+
+#[partial_derive_state]
+impl State for Gauge {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> = NodeMaskBuilder::new()
+        .with_tag()
+        .with_attrs(AttributeMaskBuilder::Some(&["value", "min", "max"]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let (mut value, mut min, mut max) = (None, None, None);
+        for attr in node_view.attributes().into_iter().flatten() {
+            let number = attr
+                .value
+                .as_float()
+                .or_else(|| attr.value.as_int().map(|n| n as f64))
+                .or_else(|| attr.value.as_text().and_then(|v| v.trim().parse().ok()));
+            match attr.attribute.name.as_str() {
+                "value" => value = number,
+                "min" => min = number,
+                "max" => max = number,
+                _ => {}
+            }
+        }
+        let new = match node_view.tag() {
+            Some("progress") => {
+                // a max that is not positive is ignored
+                let max = max.filter(|max| *max > 0.0).unwrap_or(1.0);
+                match value {
+                    Some(value) => Gauge::Value((value / max).clamp(0.0, 1.0)),
+                    None => Gauge::Indeterminate,
+                }
+            }
+            Some("meter") => {
+                let min = min.unwrap_or(0.0);
+                // the max is raised to the min if it is below it
+                let max = max.unwrap_or(1.0).max(min);
+                let value = value.unwrap_or(0.0).clamp(min, max);
+                if max > min {
+                    Gauge::Value((value - min) / (max - min))
+                } else {
+                    Gauge::Value(0.0)
+                }
+            }
+            _ => Gauge::None,
+        };
+
+        if *self != new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+/// Draws the track of a gauge and the bar filled with the `accent-color`.
+pub(crate) fn render_gauge(
+    node: NodeRef,
+    gauge: Gauge,
+    layout: &Layout,
+    pos: Point,
+    scene_builder: &mut SceneBuilder,
+) {
+    let width = layout.size.width as f64;
+    let height = layout.size.height as f64;
+    let radius = height / 2.0;
+    let track = RoundedRect::new(pos.x, pos.y, pos.x + width, pos.y + height, radius);
+    scene_builder.fill(Fill::NonZero, Affine::IDENTITY, TRACK_COLOR, None, &track);
+
+    let Some(bar) = filled_bar(gauge, track.rect()) else {
+        return;
+    };
+    let color = node
+        .get::<AccentColor>()
+        .and_then(|accent| accent.resolve(&node.get::<ForgroundColor>().unwrap().0))
+        .unwrap_or(DEFAULT_ACCENT);
+    // the bar is clipped to the rounded track so its end follows the curve when it is nearly full
+    scene_builder.push_layer(Mix::Clip, 1.0, Affine::IDENTITY, &track);
+    scene_builder.fill(Fill::NonZero, Affine::IDENTITY, color, None, &bar);
+    scene_builder.pop_layer();
+}
+
+/// The part of the track that is filled, from its left edge. Empty and indeterminate gauges have no bar.
+fn filled_bar(gauge: Gauge, track: Rect) -> Option<Rect> {
+    // TODO: animate a stripe across indeterminate progress bars once animations are supported
+    let Gauge::Value(fraction) = gauge else {
+        return None;
+    };
+    if fraction <= 0.0 {
+        return None;
+    }
+    Some(Rect::new(
+        track.x0,
+        track.y0,
+        track.x0 + track.width() * fraction,
+        track.y1,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_at_half_fills_half_the_track() {
+        let mut rdom: RealDom = RealDom::new(vec![Gauge::to_type_erased()]);
+        let root = rdom.root_id();
+        let progress = rdom
+            .create_node(NodeType::Element(ElementNode {
+                tag: "progress".to_string(),
+                attributes: [
+                    ("value".to_string().into(), "50".to_string().into()),
+                    ("max".to_string().into(), "100".to_string().into()),
+                ]
+                .into_iter()
+                .collect(),
+                ..Default::default()
+            }))
+            .id();
+        rdom.get_mut(root).unwrap().add_child(progress);
+        rdom.update_state(SendAnyMap::new());
+        let gauge = *rdom.get(progress).unwrap().get::<Gauge>().unwrap();
+        assert_eq!(gauge, Gauge::Value(0.5));
+
+        // the default size of a progress bar
+        let track = Rect::new(10.0, 20.0, 170.0, 36.0);
+        assert_eq!(
+            filled_bar(gauge, track),
+            Some(Rect::new(10.0, 20.0, 90.0, 36.0))
+        );
+        assert_eq!(filled_bar(Gauge::Value(0.0), track), None);
+        assert_eq!(filled_bar(Gauge::Indeterminate, track), None);
+    }
+}
@@ -1,61 +1,24 @@
 use dioxus_native_core::prelude::*;
 use dioxus_native_core_macro::partial_derive_state;
 use once_cell::sync::Lazy;
-use quadtree_rs::{area::AreaBuilder, Quadtree};
 use rustc_hash::FxHashSet;
 use shipyard::Component;
-use taffy::{prelude::Size, Taffy};
-use vello::kurbo::{Point, Shape};
-
-use crate::{
-    layout::TaffyLayout,
-    render::{get_abs_pos, get_shape},
-};
-
-pub(crate) fn get_hovered(
-    taffy: &Taffy,
-    dom: &RealDom,
-    viewport_size: &Size<u32>,
-    mouse_pos: Point,
-    quadtree: &Quadtree<u64, NodeId>,
-) -> Option<NodeId> {
-    quadtree
-        .query(
-            AreaBuilder::default()
-                .anchor((mouse_pos.x as u64, mouse_pos.y as u64).into())
-                .dimensions((1, 1))
-                .build()
-                .unwrap(),
-        )
-        .find(|entry| {
-            // filter out nodes that are not actually hovered
-            if let Some(node) = dom.get(*entry.value_ref()) {
-                node.get::<MouseEffected>()
-                    .filter(|effected| effected.0)
-                    .is_some()
-                    && check_hovered(taffy, node, viewport_size, mouse_pos)
-            } else {
-                false
-            }
-        })
-        .map(|entry| *entry.value_ref())
-}
 
-pub(crate) fn check_hovered(
-    taffy: &Taffy,
-    node: NodeRef,
-    viewport_size: &Size<u32>,
-    mouse_pos: Point,
-) -> bool {
-    let taffy_node = node.get::<TaffyLayout>().unwrap().node.unwrap();
-    let node_layout = taffy.layout(taffy_node).unwrap();
-    get_shape(
-        node_layout,
-        node,
-        viewport_size,
-        get_abs_pos(*node_layout, taffy, node),
-    )
-    .contains(mouse_pos)
+use crate::{application::DirtyNodes, hit_test::hit_test};
+
+/// Returns the element that receives mouse events for the element under the cursor: the element itself or its nearest ancestor with listeners.
+pub(crate) fn listening_ancestor(dom: &RealDom, id: NodeId) -> Option<NodeId> {
+    let mut node = dom.get(id)?;
+    loop {
+        if node
+            .get::<MouseEffected>()
+            .filter(|effected| effected.0)
+            .is_some()
+        {
+            return Some(node.id());
+        }
+        node = node.parent()?;
+    }
 }
 
 #[derive(Debug, Default, PartialEq, Clone, Component)]
@@ -117,3 +80,187 @@ static MOUSE_EVENTS: Lazy<FxHashSet<&'static str>> = Lazy::new(|| {
     .into_iter()
     .collect()
 });
+
+/// The attribute set on the topmost element under the cursor and all of its ancestors, like the `:hover` pseudo class. Elements are hovered whether or not they have listeners.
+pub(crate) const HOVER_ATTRIBUTE: &str = "dioxus-hover";
+
+/// The attribute set on the pressed element and all of its ancestors while a mouse button is held, like the `:active` pseudo class.
+pub(crate) const ACTIVE_ATTRIBUTE: &str = "dioxus-active";
+
+/// The attribute set on the focused element, like the `:focus` pseudo class.
+pub(crate) const FOCUS_ATTRIBUTE: &str = "dioxus-focus";
+
+/// Sets the attribute that mirrors a pseudo class on an element. The real dom tracks the attribute, so only the passes that read it rerun, on the element and the descendants that depend on it.
+pub(crate) fn set_pseudo_class(node: &mut NodeMut, attribute: &str, matches: bool) {
+    if let NodeTypeMut::Element(mut element) = node.node_type_mut() {
+        element.set_attribute(attribute.to_string(), matches.to_string());
+    }
+}
+
+/// The pseudo classes an element matches. Passes that style elements differently while they are hovered, pressed or focused depend on it.
+///
+/// There is no stylesheet to write `:hover` rules in, so a style attribute prefixed with `hover-`, `focus-` or `active-`, like `hover-background-color`, replaces the property while the element matches the pseudo class.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Component)]
+pub(crate) struct PseudoClasses {
+    pub hover: bool,
+    pub active: bool,
+    pub focus: bool,
+}
+
+impl PseudoClasses {
+    /// Returns how strongly an attribute sets a property: 0 for the property itself and more for the pseudo classes the element matches, with `active` over `hover` over `focus`. Returns `None` if the attribute is for another property or a pseudo class the element doesn't match.
+    pub fn precedence(&self, attribute: &str, property: &str) -> Option<u8> {
+        match attribute.strip_suffix(property)? {
+            "" => Some(0),
+            "focus-" if self.focus => Some(1),
+            "hover-" if self.hover => Some(2),
+            "active-" if self.active => Some(3),
+            _ => None,
+        }
+    }
+}
+
+#[partial_derive_state]
+impl State for PseudoClasses {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&[
+            HOVER_ATTRIBUTE,
+            ACTIVE_ATTRIBUTE,
+            FOCUS_ATTRIBUTE,
+        ]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let mut new = PseudoClasses::default();
+        for attr in node_view.attributes().into_iter().flatten() {
+            let matches = attr.value.as_text() == Some("true");
+            match attr.attribute.name.as_str() {
+                HOVER_ATTRIBUTE => new.hover = matches,
+                ACTIVE_ATTRIBUTE => new.active = matches,
+                FOCUS_ATTRIBUTE => new.focus = matches,
+                _ => {}
+            }
+        }
+
+        if *self != new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+/// Tracks which elements match `:hover` and `:active`.
+///
+/// The state is written to attributes of the nodes that change, so the next state update only reruns the passes that depend on [`PseudoClasses`] for those nodes instead of restyling the tree. The nodes are also marked dirty so they are repainted.
+#[derive(Default)]
+pub(crate) struct PseudoClassState {
+    /// The topmost element under the cursor.
+    hit: Option<NodeId>,
+    hovered: Vec<NodeId>,
+    active: Vec<NodeId>,
+    dirty: FxHashSet<NodeId>,
+    /// If an attribute changed since the state of the dom was last updated.
+    restyle: bool,
+}
+
+impl PseudoClassState {
+    /// Moves `:hover` to the element under the cursor. Nothing changes while the cursor stays over the same element.
+    pub fn set_hovered(&mut self, rdom: &mut RealDom, id: Option<NodeId>) {
+        if id == self.hit {
+            return;
+        }
+        self.hit = id;
+        let new = id.map(|id| ancestors(rdom, id)).unwrap_or_default();
+        update_chain(
+            rdom,
+            &self.hovered,
+            &new,
+            &mut self.dirty,
+            |node, hovered| set_pseudo_class(node, HOVER_ATTRIBUTE, hovered),
+        );
+        self.restyle |= self.hovered != new;
+        self.hovered = new;
+    }
+
+    /// Returns the topmost element under the cursor.
+    pub fn hit(&self) -> Option<NodeId> {
+        self.hit
+    }
+
+    pub fn set_active(&mut self, rdom: &mut RealDom, id: Option<NodeId>) {
+        let new = id.map(|id| ancestors(rdom, id)).unwrap_or_default();
+        update_chain(rdom, &self.active, &new, &mut self.dirty, |node, active| {
+            set_pseudo_class(node, ACTIVE_ATTRIBUTE, active)
+        });
+        self.restyle |= self.active != new;
+        self.active = new;
+    }
+
+    /// Returns true once after an attribute changed, so the state of the dom can be updated.
+    pub fn take_restyle(&mut self) -> bool {
+        std::mem::take(&mut self.restyle)
+    }
+
+    pub fn clean(&mut self) -> DirtyNodes {
+        let dirty = std::mem::take(&mut self.dirty);
+        DirtyNodes::Some(dirty)
+    }
+}
+
+/// Returns the node and all of its ancestors.
+fn ancestors(rdom: &RealDom, id: NodeId) -> Vec<NodeId> {
+    let mut chain = vec![id];
+    let mut current = id;
+    while let Some(parent) = rdom.get(current).and_then(|node| node.parent()) {
+        current = parent.id();
+        chain.push(current);
+    }
+    chain
+}
+
+/// Only touches the nodes that entered or left the chain.
+fn update_chain(
+    rdom: &mut RealDom,
+    old: &[NodeId],
+    new: &[NodeId],
+    dirty: &mut FxHashSet<NodeId>,
+    set: impl Fn(&mut NodeMut, bool),
+) {
+    for id in old.iter().filter(|id| !new.contains(id)) {
+        // the node may have been removed since it was last updated
+        if let Some(mut node) = rdom.get_mut(*id) {
+            set(&mut node, false);
+            dirty.insert(*id);
+        }
+    }
+    for id in new.iter().filter(|id| !old.contains(id)) {
+        if let Some(mut node) = rdom.get_mut(*id) {
+            set(&mut node, true);
+            dirty.insert(*id);
+        }
+    }
+}
@@ -0,0 +1,375 @@
+//! The `position` property and the `top`, `right`, `bottom` and `left` offsets.
+//!
+//! Relatively positioned elements are moved by taffy. Sticky elements are moved while their nearest scroll container is scrolled so they stay inside of its scrollport, but never out of their parent. Absolutely positioned elements are laid out by taffy relative to their parent, then moved into their containing block, the padding box of the nearest positioned ancestor, when they are painted and hit tested. Fixed elements are placed relative to the viewport, so they don't move when their ancestors are scrolled. Percentage offsets resolve against the size of the containing block.
+
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use lightningcss::properties::border::BorderSideWidth;
+use lightningcss::traits::Parse;
+use lightningcss::values::length::{LengthPercentage, LengthPercentageOrAuto};
+use lightningcss::values::percentage::DimensionPercentage;
+use shipyard::Component;
+use taffy::prelude::{Dimension, Layout, PositionType, Rect, Size, Style};
+use taffy::Taffy;
+use vello::kurbo::{Point, Vec2};
+
+use crate::layout::TaffyLayout;
+use crate::render::get_abs_pos;
+use crate::scroll::scroll_offset;
+use crate::style::{Border, Overflow};
+use crate::util::{Axis, Resolve, ResolveContext};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub(crate) enum PositionKind {
+    #[default]
+    Static,
+    Relative,
+    Absolute,
+    Fixed,
+    Sticky,
+}
+
+#[derive(Clone, PartialEq, Debug, Component)]
+pub(crate) struct Positioning {
+    pub kind: PositionKind,
+    pub top: LengthPercentageOrAuto,
+    pub right: LengthPercentageOrAuto,
+    pub bottom: LengthPercentageOrAuto,
+    pub left: LengthPercentageOrAuto,
+}
+
+impl Default for Positioning {
+    fn default() -> Self {
+        Positioning {
+            kind: PositionKind::Static,
+            top: LengthPercentageOrAuto::Auto,
+            right: LengthPercentageOrAuto::Auto,
+            bottom: LengthPercentageOrAuto::Auto,
+            left: LengthPercentageOrAuto::Auto,
+        }
+    }
+}
+
+impl Positioning {
+    /// Returns true if the element is a containing block for absolutely positioned descendants.
+    pub fn is_positioned(&self) -> bool {
+        self.kind != PositionKind::Static
+    }
+
+    /// Sets the position type and offsets taffy lays the element out with. The offsets of static elements are ignored.
+    pub fn apply(&self, style: &mut Style, context: &ResolveContext) {
+        let offset = |offset: &LengthPercentageOrAuto, axis: Axis| match offset {
+            LengthPercentageOrAuto::Auto => Dimension::Auto,
+            LengthPercentageOrAuto::LengthPercentage(DimensionPercentage::Percentage(p)) => {
+                Dimension::Percent(p.0)
+            }
+            LengthPercentageOrAuto::LengthPercentage(length) => {
+                let rect = Size {
+                    width: 0.0,
+                    height: 0.0,
+                };
+                Dimension::Points(length.resolve(axis, &rect, context) as f32)
+            }
+        };
+        match self.kind {
+            PositionKind::Static | PositionKind::Sticky => {
+                style.position_type = PositionType::Relative;
+                style.position = Rect {
+                    left: Dimension::Auto,
+                    right: Dimension::Auto,
+                    top: Dimension::Auto,
+                    bottom: Dimension::Auto,
+                };
+            }
+            PositionKind::Relative | PositionKind::Absolute | PositionKind::Fixed => {
+                style.position_type = match self.kind {
+                    PositionKind::Relative => PositionType::Relative,
+                    _ => PositionType::Absolute,
+                };
+                style.position = Rect {
+                    left: offset(&self.left, Axis::X),
+                    right: offset(&self.right, Axis::X),
+                    top: offset(&self.top, Axis::Y),
+                    bottom: offset(&self.bottom, Axis::Y),
+                };
+            }
+        }
+    }
+}
+
+#[partial_derive_state]
+impl State for Positioning {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&[
+            "position", "top", "right", "bottom", "left",
+        ]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let mut new = Positioning::default();
+        for attr in node_view.attributes().into_iter().flatten() {
+            let Some(as_text) = attr.value.as_text() else {
+                continue;
+            };
+            let offset = || LengthPercentageOrAuto::parse_string(as_text).ok();
+            match attr.attribute.name.as_str() {
+                "position" => {
+                    new.kind = match as_text.trim() {
+                        "relative" => PositionKind::Relative,
+                        "absolute" => PositionKind::Absolute,
+                        "fixed" => PositionKind::Fixed,
+                        "sticky" => PositionKind::Sticky,
+                        _ => PositionKind::Static,
+                    }
+                }
+                "top" => new.top = offset().unwrap_or(new.top),
+                "right" => new.right = offset().unwrap_or(new.right),
+                "bottom" => new.bottom = offset().unwrap_or(new.bottom),
+                "left" => new.left = offset().unwrap_or(new.left),
+                _ => {}
+            }
+        }
+
+        if *self != new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+/// Returns how far an absolutely positioned, fixed or sticky element is moved from where taffy placed it in its parent.
+pub(crate) fn position_offset(
+    taffy: &Taffy,
+    node: NodeRef,
+    layout: &Layout,
+    viewport_size: &Size<u32>,
+) -> Vec2 {
+    let Some(positioning) = node.get::<Positioning>() else {
+        return Vec2::ZERO;
+    };
+    let Some(parent) = node.parent() else {
+        return Vec2::ZERO;
+    };
+    let (origin, size) = match positioning.kind {
+        PositionKind::Fixed => (
+            Point::ZERO,
+            Size {
+                width: viewport_size.width as f32,
+                height: viewport_size.height as f32,
+            },
+        ),
+        PositionKind::Absolute => {
+            let block = containing_block(node);
+            let block_id = block.map_or(node.real_dom().root_id(), |block| block.id());
+            // taffy already placed the element in its parent
+            if block_id == parent.id() {
+                return Vec2::ZERO;
+            }
+            match block {
+                Some(block) => match padding_box(taffy, block, viewport_size) {
+                    Some(padding_box) => padding_box,
+                    None => return Vec2::ZERO,
+                },
+                // the initial containing block is the size of the viewport and placed at the origin of the document
+                None => (
+                    Point::ZERO,
+                    Size {
+                        width: viewport_size.width as f32,
+                        height: viewport_size.height as f32,
+                    },
+                ),
+            }
+        }
+        PositionKind::Sticky => {
+            return sticky_offset(taffy, node, &positioning, layout, viewport_size)
+                .unwrap_or_default()
+        }
+        PositionKind::Static | PositionKind::Relative => return Vec2::ZERO,
+    };
+    let Some(placed) = placed_position(taffy, parent, layout, viewport_size) else {
+        return Vec2::ZERO;
+    };
+    let context = ResolveContext::of(node, viewport_size);
+
+    // an axis without offsets keeps the position taffy gave the element
+    let place = |start: &LengthPercentageOrAuto, end: &LengthPercentageOrAuto, axis: Axis| {
+        let (origin, length, element_length, placed) = match axis {
+            Axis::X => (origin.x, size.width, layout.size.width, placed.x),
+            _ => (origin.y, size.height, layout.size.height, placed.y),
+        };
+        let resolve = |offset: &LengthPercentage| offset.resolve(axis, &size, &context);
+        match (start, end) {
+            (LengthPercentageOrAuto::LengthPercentage(start), _) => origin + resolve(start),
+            (_, LengthPercentageOrAuto::LengthPercentage(end)) => {
+                origin + (length - element_length) as f64 - resolve(end)
+            }
+            _ => placed,
+        }
+    };
+    let x = place(&positioning.left, &positioning.right, Axis::X);
+    let y = place(&positioning.top, &positioning.bottom, Axis::Y);
+    Vec2::new(x - placed.x, y - placed.y)
+}
+
+/// Returns how far a sticky element is moved to stay inside the scrollport of its nearest scroll container while the container is scrolled. The element never leaves its parent. Without a scroll container the offsets move it like relative positioning.
+fn sticky_offset(
+    taffy: &Taffy,
+    node: NodeRef,
+    positioning: &Positioning,
+    layout: &Layout,
+    viewport_size: &Size<u32>,
+) -> Option<Vec2> {
+    let parent = node.parent()?;
+    let parent_layout = taffy.layout(parent.get::<TaffyLayout>()?.node?).ok()?;
+    let context = ResolveContext::of(node, viewport_size);
+    let resolve = |offset: &LengthPercentageOrAuto, axis: Axis, size: &Size<f32>| match offset {
+        LengthPercentageOrAuto::Auto => None,
+        LengthPercentageOrAuto::LengthPercentage(length) => {
+            Some(length.resolve(axis, size, &context))
+        }
+    };
+
+    let Some(container) = scroll_container(node) else {
+        let size = &parent_layout.size;
+        let relative = |start, end, axis| {
+            resolve(start, axis, size)
+                .or_else(|| resolve(end, axis, size).map(|end| -end))
+                .unwrap_or(0.0)
+        };
+        return Some(Vec2::new(
+            relative(&positioning.left, &positioning.right, Axis::X),
+            relative(&positioning.top, &positioning.bottom, Axis::Y),
+        ));
+    };
+
+    let placed = placed_position(taffy, parent, layout, viewport_size)?;
+    let (port_origin, port_size) = padding_box(taffy, container, viewport_size)?;
+    let (block_origin, block_size) = padding_box(taffy, parent, viewport_size)?;
+    let stick = |start: &LengthPercentageOrAuto, end: &LengthPercentageOrAuto, axis: Axis| {
+        let (placed, length, port_start, port_length, block_start, block_length) = match axis {
+            Axis::X => (
+                placed.x,
+                layout.size.width as f64,
+                port_origin.x,
+                port_size.width as f64,
+                block_origin.x,
+                block_size.width as f64,
+            ),
+            _ => (
+                placed.y,
+                layout.size.height as f64,
+                port_origin.y,
+                port_size.height as f64,
+                block_origin.y,
+                block_size.height as f64,
+            ),
+        };
+        // moving toward the end stops at the end of the parent and moving toward the start stops at its start
+        let from_start = resolve(start, axis, &port_size).map(|start| {
+            (port_start + start - placed)
+                .min(block_start + block_length - length - placed)
+                .max(0.0)
+        });
+        let from_end = resolve(end, axis, &port_size).map(|end| {
+            (port_start + port_length - end - length - placed)
+                .max(block_start - placed)
+                .min(0.0)
+        });
+        match (from_start, from_end) {
+            (Some(shift), _) if shift > 0.0 => shift,
+            (_, Some(shift)) => shift,
+            _ => 0.0,
+        }
+    };
+    Some(Vec2::new(
+        stick(&positioning.left, &positioning.right, Axis::X),
+        stick(&positioning.top, &positioning.bottom, Axis::Y),
+    ))
+}
+
+/// Returns where taffy placed an element in window coordinates, before it is moved by its position.
+fn placed_position(
+    taffy: &Taffy,
+    parent: NodeRef,
+    layout: &Layout,
+    viewport_size: &Size<u32>,
+) -> Option<Point> {
+    let parent_layout = taffy.layout(parent.get::<TaffyLayout>()?.node?).ok()?;
+    Some(
+        get_abs_pos(*parent_layout, taffy, parent, viewport_size) - scroll_offset(parent)
+            + Vec2::new(layout.location.x as f64, layout.location.y as f64),
+    )
+}
+
+/// Returns the nearest ancestor that clips its overflow, which is the container a sticky element sticks in.
+fn scroll_container(node: NodeRef) -> Option<NodeRef> {
+    let mut current = node.parent()?;
+    loop {
+        if current
+            .get::<Overflow>()
+            .is_some_and(|overflow| overflow.clips_x() || overflow.clips_y())
+        {
+            return Some(current);
+        }
+        current = current.parent()?;
+    }
+}
+
+/// Returns the nearest positioned ancestor of the element, or `None` if it is placed in the initial containing block.
+fn containing_block(node: NodeRef) -> Option<NodeRef> {
+    let mut current = node.parent()?;
+    loop {
+        if current
+            .get::<Positioning>()
+            .is_some_and(|positioning| positioning.is_positioned())
+        {
+            return Some(current);
+        }
+        current = current.parent()?;
+    }
+}
+
+/// Returns the origin of the padding box of an element in window coordinates and its size.
+fn padding_box(
+    taffy: &Taffy,
+    node: NodeRef,
+    viewport_size: &Size<u32>,
+) -> Option<(Point, Size<f32>)> {
+    let taffy_node = node.get::<TaffyLayout>()?.node?;
+    let layout = taffy.layout(taffy_node).ok()?;
+    let border = node.get::<Border>()?;
+    let context = ResolveContext::of(node, viewport_size);
+    let width = |side: &BorderSideWidth| side.resolve(Axis::Min, &layout.size, &context);
+    let (left, right) = (width(&border.width.left), width(&border.width.right));
+    let (top, bottom) = (width(&border.width.top), width(&border.width.bottom));
+    let origin = get_abs_pos(*layout, taffy, node, viewport_size) + Vec2::new(left, top);
+    let size = Size {
+        width: (layout.size.width as f64 - left - right).max(0.0) as f32,
+        height: (layout.size.height as f64 - top - bottom).max(0.0) as f32,
+    };
+    Some((origin, size))
+}
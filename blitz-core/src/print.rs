@@ -0,0 +1,135 @@
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use shipyard::Component;
+use taffy::Taffy;
+
+use crate::layout::TaffyLayout;
+
+/// Forced page breaks set with `break-before` and `break-after`.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Component)]
+pub(crate) struct PageBreak {
+    pub before: bool,
+    pub after: bool,
+}
+
+/// If the value of `break-before` or `break-after` forces a page break. The legacy `page-break-*` properties use `always` for the same thing.
+fn forces_page_break(value: &str) -> bool {
+    matches!(
+        value.trim(),
+        "page" | "left" | "right" | "recto" | "verso" | "always"
+    )
+}
+
+#[partial_derive_state]
+impl State for PageBreak {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&[
+            "break-before",
+            "break-after",
+            "page-break-before",
+            "page-break-after",
+        ]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let mut new = PageBreak::default();
+        for attr in node_view.attributes().into_iter().flatten() {
+            if let Some(as_text) = attr.value.as_text() {
+                match attr.attribute.name.as_str() {
+                    "break-before" | "page-break-before" => new.before = forces_page_break(as_text),
+                    "break-after" | "page-break-after" => new.after = forces_page_break(as_text),
+                    _ => {}
+                }
+            }
+        }
+
+        if self != &mut new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+/// Splits the document into pages. Returns the top and bottom of each page in document coordinates.
+///
+/// Pages end after `page_height` or at the next forced page break, whichever comes first.
+pub(crate) fn page_ranges(rdom: &RealDom, taffy: &Taffy, page_height: f64) -> Vec<(f64, f64)> {
+    let page_height = page_height.max(1.0);
+    let mut content_height = 0.0;
+    let mut forced_breaks = Vec::new();
+    collect_page_breaks(
+        rdom.get(rdom.root_id()).unwrap(),
+        taffy,
+        0.0,
+        &mut content_height,
+        &mut forced_breaks,
+    );
+    forced_breaks.sort_by(f64::total_cmp);
+
+    let mut pages = Vec::new();
+    let mut top = 0.0;
+    loop {
+        let bottom = forced_breaks
+            .iter()
+            .copied()
+            .find(|y| *y > top && *y < top + page_height)
+            .unwrap_or(top + page_height);
+        pages.push((top, bottom));
+        if bottom >= content_height {
+            break;
+        }
+        top = bottom;
+    }
+    pages
+}
+
+fn collect_page_breaks(
+    node: NodeRef,
+    taffy: &Taffy,
+    parent_y: f64,
+    content_height: &mut f64,
+    forced_breaks: &mut Vec<f64>,
+) {
+    let Some(taffy_node) = node.get::<TaffyLayout>().and_then(|layout| layout.node) else {
+        return;
+    };
+    let layout = taffy.layout(taffy_node).unwrap();
+    let top = parent_y + layout.location.y as f64;
+    let bottom = top + layout.size.height as f64;
+    *content_height = content_height.max(bottom);
+    if let Some(page_break) = node.get::<PageBreak>() {
+        if page_break.before {
+            forced_breaks.push(top);
+        }
+        if page_break.after {
+            forced_breaks.push(bottom);
+        }
+    }
+    for child in node.children() {
+        collect_page_breaks(child, taffy, top, content_height, forced_breaks);
+    }
+}
@@ -1,33 +1,73 @@
 use dioxus_native_core::prelude::*;
+use lightningcss::properties::background::BackgroundClip as BackgroundClipProperty;
+use lightningcss::properties::border::{BorderSideWidth, LineStyle};
+use lightningcss::properties::effects::DropShadow;
+use lightningcss::properties::outline::OutlineStyle;
+use lightningcss::properties::text::{
+    TextAlign as TextAlignProperty, TextDecorationLine, TextDecorationStyle,
+    TextDecorationThickness,
+};
+use lightningcss::values::color::CssColor;
+use taffy::prelude::Dimension;
 use taffy::prelude::Layout;
 use taffy::prelude::Size;
 use taffy::Taffy;
-use tao::dpi::PhysicalSize;
-use vello::kurbo::{Affine, Point, Rect, RoundedRect, Vec2};
-use vello::peniko::{Color, Fill, Stroke};
-use vello::SceneBuilder;
+use tao::dpi::LogicalSize;
+use vello::kurbo::{Affine, BezPath, Point, Rect, RoundedRect, Shape, Vec2};
+use vello::peniko::{BlendMode, Cap, Color, Compose, Fill, Mix, Stroke};
+use vello::{SceneBuilder, SceneFragment};
 
+use crate::background::render_background_images;
+use crate::checkbox::{render_checkable, Checkable};
 use crate::focus::Focused;
-use crate::image::LoadedImage;
+use crate::generated_content::{ContentStyle, GeneratedBox, GeneratedContent};
+use crate::image::{LoadedImage, ObjectFit};
 use crate::layout::TaffyLayout;
+use crate::meter::{render_gauge, Gauge};
+use crate::position::position_offset;
+use crate::scroll::scroll_offset;
+use crate::selection::{TextSelection, SELECTION_COLOR};
+use crate::stacking::{creates_stacking_context, opacity, stacked_descendants, StackedElement};
+use crate::style::AccentColor;
+use crate::style::BackgroundClip;
 use crate::style::BackgroundColor;
 use crate::style::Border;
+use crate::style::BoxShadow;
+use crate::style::Filter;
+use crate::style::FontFamily;
 use crate::style::FontSize;
 use crate::style::ForgroundColor;
+use crate::style::Outline;
+use crate::style::Overflow;
+use crate::style::TextShadow;
 use crate::style::DEFAULT_FONT_SIZE;
-use crate::text::TextContext;
+use crate::table::hides_empty_cell;
+use crate::text::bidi::{line_direction, visual_order};
+use crate::text::text_style::{
+    LineHeight, TextAlign, TextDecoration, TextOverflow, TextSpacing, TextTransform, WhiteSpace,
+};
+use crate::text::{FontMetrics, FontSelection, TextContext, TextLine};
+use crate::text_input::render_caret;
+use crate::transform::{paint_transform, translation};
 use crate::util::Resolve;
-use crate::util::{translate_color, Axis};
+use crate::util::{translate_color, translate_current_color, Axis, ResolveContext};
+use crate::visibility::is_visible;
+use crate::writing_mode::WritingMode;
 use crate::RealDom;
 
-const FOCUS_BORDER_WIDTH: f64 = 6.0;
+/// The width of the default focus ring. It is drawn in the accent color with a white halo around it so it stands out on any background.
+const FOCUS_RING_WIDTH: f64 = 2.0;
+const FOCUS_RING_HALO_WIDTH: f64 = 1.0;
+/// The colors of the placeholder box drawn for images that failed to load.
+const BROKEN_IMAGE_FILL: Color = Color::rgb8(240, 240, 240);
+const BROKEN_IMAGE_BORDER: Color = Color::rgb8(160, 160, 160);
 
 pub(crate) fn render(
     dom: &RealDom,
     taffy: &Taffy,
     text_context: &mut TextContext,
     scene_builder: &mut SceneBuilder,
-    window_size: PhysicalSize<u32>,
+    window_size: LogicalSize<u32>,
 ) {
     let root = &dom.get(dom.root_id()).unwrap();
     let root_node = root.get::<TaffyLayout>().unwrap().node.unwrap();
@@ -63,95 +103,1402 @@ fn render_node(
 ) {
     let taffy_node = node.get::<TaffyLayout>().unwrap().node.unwrap();
     let layout = taffy.layout(taffy_node).unwrap();
-    let pos = location + Vec2::new(layout.location.x as f64, layout.location.y as f64);
+    let pos = location
+        + Vec2::new(layout.location.x as f64, layout.location.y as f64)
+        + translation(node, layout, viewport_size)
+        + position_offset(taffy, node, layout, viewport_size);
     match &*node.node_type() {
-        NodeType::Text(TextNode { text, .. }) => {
-            let text_color = translate_color(&node.get::<ForgroundColor>().unwrap().0);
-            let font_size = if let Some(font_size) = node.get::<FontSize>() {
-                font_size.0
-            } else {
-                DEFAULT_FONT_SIZE
-            };
-            text_context.add(
+        NodeType::Text(_) if !is_visible(node) => {}
+        NodeType::Text(TextNode { text, .. }) => render_text(
+            taffy,
+            node,
+            text,
+            pos,
+            location,
+            None,
+            text_context,
+            scene_builder,
+            viewport_size,
+        ),
+        NodeType::Element(_) => {
+            // translucent elements are drawn into a layer so overlapping descendants don't show through each other
+            // layers nest, so the opacity of ancestors multiplies with the opacity of the element
+            let opacity = opacity(node);
+            // invisible elements keep their layout but nothing in them needs to be painted
+            if opacity <= 0.0 {
+                return;
+            }
+            if opacity < 1.0 {
+                let viewport = Rect::new(
+                    0.0,
+                    0.0,
+                    viewport_size.width as f64,
+                    viewport_size.height as f64,
+                );
+                scene_builder.push_layer(Mix::Normal, opacity, Affine::IDENTITY, &viewport);
+            }
+            // rotations, scales and skews are applied to everything the element paints, including its descendants
+            match paint_transform(node, layout, pos, viewport_size) {
+                Some(transform) => {
+                    let mut fragment = SceneFragment::new();
+                    let mut fragment_builder = SceneBuilder::for_fragment(&mut fragment);
+                    paint_element(
+                        taffy,
+                        node,
+                        layout,
+                        pos,
+                        text_context,
+                        &mut fragment_builder,
+                        viewport_size,
+                    );
+                    scene_builder.append(&fragment, Some(transform));
+                }
+                None => paint_element(
+                    taffy,
+                    node,
+                    layout,
+                    pos,
+                    text_context,
+                    scene_builder,
+                    viewport_size,
+                ),
+            }
+            if opacity < 1.0 {
+                scene_builder.pop_layer();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Paints the shadows of an element, the element and its content.
+fn paint_element(
+    taffy: &Taffy,
+    node: NodeRef,
+    layout: &Layout,
+    pos: Point,
+    text_context: &mut TextContext,
+    scene_builder: &mut SceneBuilder,
+    viewport_size: &Size<u32>,
+) {
+    // hidden elements still paint their descendants, which can be visible again
+    let visible = is_visible(node);
+    if let Some(filter) = node.get::<Filter>().filter(|_| visible) {
+        for shadow in &filter.drop_shadows {
+            render_drop_shadow(
+                taffy,
+                node,
+                layout,
+                pos,
+                text_context,
                 scene_builder,
+                viewport_size,
+                shadow,
+            );
+        }
+    }
+    if visible {
+        render_box_shadows(node, layout, pos, false, scene_builder, viewport_size);
+    }
+    render_element(
+        taffy,
+        node,
+        layout,
+        pos,
+        text_context,
+        scene_builder,
+        viewport_size,
+    );
+}
+
+/// Draws elements that belong to a stacking context inside the overflow clips of their ancestors.
+fn render_stacked(
+    taffy: &Taffy,
+    node: NodeRef,
+    stacked: &[StackedElement],
+    text_context: &mut TextContext,
+    scene_builder: &mut SceneBuilder,
+    viewport_size: &Size<u32>,
+) {
+    for element in stacked {
+        for clip in &element.clips {
+            scene_builder.push_layer(Mix::Clip, 1.0, Affine::IDENTITY, clip);
+        }
+        render_node(
+            taffy,
+            node.real_dom().get(element.node).unwrap(),
+            text_context,
+            scene_builder,
+            element.location,
+            viewport_size,
+        );
+        for _ in &element.clips {
+            scene_builder.pop_layer();
+        }
+    }
+}
+
+/// Returns the background color of an element.
+fn background_color(node: NodeRef) -> Color {
+    current_color(node, &node.get::<BackgroundColor>().unwrap().0)
+}
+
+/// Converts a color of an element. `currentColor` is the `color` the element inherits or sets.
+fn current_color(node: NodeRef, color: &CssColor) -> Color {
+    translate_current_color(color, &node.get::<ForgroundColor>().unwrap().0)
+}
+
+fn render_element(
+    taffy: &Taffy,
+    node: NodeRef,
+    layout: &Layout,
+    pos: Point,
+    text_context: &mut TextContext,
+    scene_builder: &mut SceneBuilder,
+    viewport_size: &Size<u32>,
+) {
+    let shape = get_shape(layout, node, viewport_size, pos);
+    let fill_color = background_color(node);
+    let visible = is_visible(node);
+
+    if visible && !hides_empty_cell(node) {
+        render_background(
+            taffy,
+            node,
+            layout,
+            pos,
+            &shape,
+            fill_color,
+            text_context,
+            scene_builder,
+            viewport_size,
+        );
+        render_border(node, layout, pos, &shape, scene_builder, viewport_size);
+    }
+    if visible {
+        render_box_shadows(node, layout, pos, true, scene_builder, viewport_size);
+        if let Some(kind) = node.get::<Checkable>().and_then(|checkable| checkable.kind) {
+            render_checkable(node, kind, layout, pos, scene_builder);
+        }
+    }
+    let gauge = *node.get::<Gauge>().unwrap();
+    if visible && gauge.is_gauge() {
+        render_gauge(node, gauge, layout, pos, scene_builder);
+    }
+
+    match &*node.get::<LoadedImage>().unwrap() {
+        LoadedImage::Loaded(image) if visible => {
+            let content_box = get_inner_shape(taffy, layout, node, pos, viewport_size, true).rect();
+            let fit = *node.get::<ObjectFit>().unwrap();
+            let placed = fit.place(image, content_box);
+            let scale = Affine::scale_non_uniform(
+                placed.width() / image.width as f64,
+                placed.height() / image.height as f64,
+            );
+            let translate = Affine::translate(placed.origin().to_vec2());
+            // cover and none can overflow the content box
+            scene_builder.push_layer(Mix::Clip, 1.0, Affine::IDENTITY, &content_box);
+            scene_builder.draw_image(image, translate * scale);
+            scene_builder.pop_layer();
+        }
+        LoadedImage::Broken if visible => {
+            let content_box = get_inner_shape(taffy, layout, node, pos, viewport_size, true).rect();
+            let placeholder = content_box.inset(-0.5);
+            let stroke = Stroke::new(1.0);
+            scene_builder.fill(
+                Fill::NonZero,
+                Affine::IDENTITY,
+                BROKEN_IMAGE_FILL,
                 None,
-                font_size,
-                Some(text_color),
-                Affine::translate(pos.to_vec2() + Vec2::new(0.0, font_size as f64)),
+                &placeholder,
+            );
+            scene_builder.stroke(
+                &stroke,
+                Affine::IDENTITY,
+                BROKEN_IMAGE_BORDER,
+                None,
+                &placeholder,
+            );
+        }
+        _ => {}
+    }
+
+    // the generated content and the caret are part of the element
+    let generated = node
+        .get::<GeneratedContent>()
+        .filter(|_| visible)
+        .map(|content| (*content).clone())
+        .unwrap_or_default();
+    let overflow = *node.get::<Overflow>().unwrap();
+    let clipped = overflow.clips_x() || overflow.clips_y();
+    if clipped {
+        let clip = get_overflow_clip(layout, node, pos, &overflow, viewport_size);
+        scene_builder.push_layer(Mix::Clip, 1.0, Affine::IDENTITY, &clip);
+    }
+    // the content of scroll containers moves up and left as it is scrolled
+    let content_pos = pos - scroll_offset(node);
+    // elements in the stacking context of this element are painted by it instead of their parents
+    let stacked = if creates_stacking_context(node) || node.parent().is_none() {
+        stacked_descendants(taffy, node, content_pos, viewport_size)
+    } else {
+        Vec::new()
+    };
+    let (below, above) = stacked.split_at(stacked.partition_point(|element| element.z_index < 0));
+    render_stacked(
+        taffy,
+        node,
+        below,
+        text_context,
+        scene_builder,
+        viewport_size,
+    );
+    if let Some(before) = &generated.before {
+        render_generated_box(
+            taffy,
+            node,
+            before,
+            None,
+            content_pos,
+            text_context,
+            scene_builder,
+        );
+    }
+    if let Some(placeholder) = &generated.placeholder {
+        let color = node
+            .get::<ContentStyle>()
+            .and_then(|style| {
+                style
+                    .placeholder_color
+                    .as_ref()
+                    .map(|color| current_color(node, color))
+            })
+            .unwrap_or_else(|| {
+                let Color { r, g, b, a } =
+                    translate_color(&node.get::<ForgroundColor>().unwrap().0);
+                Color::rgba8(r, g, b, a / 2)
+            });
+        render_generated_box(
+            taffy,
+            node,
+            placeholder,
+            Some(color),
+            content_pos,
+            text_context,
+            scene_builder,
+        );
+    }
+    if let Some(value) = &generated.value {
+        render_generated_box(
+            taffy,
+            node,
+            value,
+            None,
+            content_pos,
+            text_context,
+            scene_builder,
+        );
+    }
+    for child in node.children() {
+        // the content of a gauge is only a fallback
+        if creates_stacking_context(child) || gauge.is_gauge() {
+            continue;
+        }
+        render_node(
+            taffy,
+            child,
+            text_context,
+            scene_builder,
+            content_pos,
+            viewport_size,
+        );
+    }
+    if let Some(after) = &generated.after {
+        render_generated_box(
+            taffy,
+            node,
+            after,
+            None,
+            content_pos,
+            text_context,
+            scene_builder,
+        );
+    }
+    let content_box = get_inner_shape(taffy, layout, node, content_pos, viewport_size, true).rect();
+    if visible {
+        render_caret(
+            taffy,
+            node,
+            &generated,
+            content_pos,
+            content_box,
+            text_context,
+            scene_builder,
+        );
+    }
+    render_stacked(
+        taffy,
+        node,
+        above,
+        text_context,
+        scene_builder,
+        viewport_size,
+    );
+    if clipped {
+        scene_builder.pop_layer();
+    }
+
+    if !visible {
+        return;
+    }
+    // the outline is drawn over the content and doesn't take up space in the layout
+    let outline = node.get::<Outline>().unwrap().clone();
+    let border_box = RoundedRect::from_rect(
+        Rect::from_origin_size(pos, (layout.size.width as f64, layout.size.height as f64)),
+        shape.radii(),
+    );
+    let focused = node.get::<Focused>().filter(|focused| focused.0).is_some();
+    match outline_kind(&outline.style, focused) {
+        Some(OutlineKind::FocusRing) => render_focus_ring(
+            node,
+            layout,
+            &border_box,
+            &outline,
+            scene_builder,
+            viewport_size,
+        ),
+        Some(OutlineKind::Line(line_style)) => render_outline(
+            node,
+            layout,
+            &border_box,
+            &outline,
+            line_style,
+            scene_builder,
+            viewport_size,
+        ),
+        None => {}
+    }
+}
+
+/// How the outline of an element is drawn.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum OutlineKind {
+    /// The default focus ring in the accent color.
+    FocusRing,
+    Line(LineStyle),
+}
+
+/// Focused elements get the focus ring unless they set their own outline style. `outline-style: auto` is the focus ring, so it is only drawn while the element is focused.
+fn outline_kind(style: &Option<OutlineStyle>, focused: bool) -> Option<OutlineKind> {
+    match style {
+        None | Some(OutlineStyle::Auto) => focused.then_some(OutlineKind::FocusRing),
+        Some(OutlineStyle::LineStyle(line_style)) => Some(OutlineKind::Line(*line_style)),
+    }
+}
+
+/// The lengths of the dashes and the gaps between them for dotted and dashed lines of a width. Dots are dashes of length 0 with round caps.
+fn dash_pattern(line_style: LineStyle, width: f64) -> Option<[f32; 2]> {
+    match line_style {
+        LineStyle::Dotted => Some([0.0, width as f32 * 2.0]),
+        LineStyle::Dashed => Some([width as f32 * 3.0, width as f32 * 2.0]),
+        _ => None,
+    }
+}
+
+/// Draws the `auto` outline, which is also the default outline of focused elements. It follows `outline-offset` and is drawn in the accent color.
+fn render_focus_ring(
+    node: NodeRef,
+    layout: &Layout,
+    border_box: &RoundedRect,
+    outline: &Outline,
+    scene_builder: &mut SceneBuilder,
+    viewport_size: &Size<u32>,
+) {
+    let context = ResolveContext::of(node, viewport_size);
+    let offset = outline.offset.resolve(Axis::Min, &layout.size, &context);
+    let color = node
+        .get::<AccentColor>()
+        .and_then(|accent| accent.resolve(&node.get::<ForgroundColor>().unwrap().0))
+        .unwrap_or(Color::rgb(0.0, 0.0, 0.0));
+    let ring = grow_rounded_rect(border_box, offset + FOCUS_RING_WIDTH / 2.0);
+    let halo = grow_rounded_rect(
+        border_box,
+        offset + FOCUS_RING_WIDTH + FOCUS_RING_HALO_WIDTH / 2.0,
+    );
+    scene_builder.stroke(
+        &Stroke::new(FOCUS_RING_HALO_WIDTH as f32),
+        Affine::IDENTITY,
+        Color::WHITE,
+        None,
+        &halo,
+    );
+    scene_builder.stroke(
+        &Stroke::new(FOCUS_RING_WIDTH as f32),
+        Affine::IDENTITY,
+        color,
+        None,
+        &ring,
+    );
+}
+
+/// Strokes the outline of an element outside of its border box.
+fn render_outline(
+    node: NodeRef,
+    layout: &Layout,
+    shape: &RoundedRect,
+    outline: &Outline,
+    line_style: LineStyle,
+    scene_builder: &mut SceneBuilder,
+    viewport_size: &Size<u32>,
+) {
+    let context = ResolveContext::of(node, viewport_size);
+    let width = outline.width.resolve(Axis::Min, &layout.size, &context);
+    let offset = outline.offset.resolve(Axis::Min, &layout.size, &context);
+    if width <= 0.0 {
+        return;
+    }
+    let color = current_color(node, &outline.color);
+    let grow = |distance: f64| grow_rounded_rect(shape, distance);
+    match line_style {
+        LineStyle::None | LineStyle::Hidden => {}
+        LineStyle::Double => {
+            // two lines each a third of the width with a gap between them
+            let line_width = width / 3.0;
+            let stroke = Stroke::new(line_width as f32);
+            for distance in [offset + line_width / 2.0, offset + width - line_width / 2.0] {
+                scene_builder.stroke(&stroke, Affine::IDENTITY, color, None, &grow(distance));
+            }
+        }
+        LineStyle::Dotted | LineStyle::Dashed => {
+            let mut stroke = Stroke::new(width as f32);
+            if line_style == LineStyle::Dotted {
+                stroke = stroke.with_caps(Cap::Round);
+            }
+            if let Some(pattern) = dash_pattern(line_style, width) {
+                stroke = stroke.with_dashes(0.0, pattern);
+            }
+            let path = grow(offset + width / 2.0);
+            scene_builder.stroke(&stroke, Affine::IDENTITY, color, None, &path);
+        }
+        // The 3d styles are drawn as solid lines
+        _ => {
+            let stroke = Stroke::new(width as f32);
+            let path = grow(offset + width / 2.0);
+            scene_builder.stroke(&stroke, Affine::IDENTITY, color, None, &path);
+        }
+    }
+}
+
+/// Fills the border of an element with the width and color of each side. Adjacent sides meet on the line between the outer and inner corner, and the inner corners are rounded by the radius left after the border widths.
+fn render_border(
+    node: NodeRef,
+    layout: &Layout,
+    pos: Point,
+    shape: &RoundedRect,
+    scene_builder: &mut SceneBuilder,
+    viewport_size: &Size<u32>,
+) {
+    let context = ResolveContext::of(node, viewport_size);
+    let border = node.get::<Border>().unwrap();
+    let width = |side: &BorderSideWidth| side.resolve(Axis::Min, &layout.size, &context);
+    let color = |color: &CssColor| current_color(node, color);
+    let sides = [
+        (width(&border.width.top), color(&border.colors.top)),
+        (width(&border.width.right), color(&border.colors.right)),
+        (width(&border.width.bottom), color(&border.colors.bottom)),
+        (width(&border.width.left), color(&border.colors.left)),
+    ];
+    let [top, right, bottom, left] = sides.map(|(width, _)| width.max(0.0));
+    if top + right + bottom + left <= 0.0 {
+        return;
+    }
+
+    let radii = shape.radii();
+    let outer = Rect::new(
+        pos.x,
+        pos.y,
+        pos.x + layout.size.width as f64,
+        pos.y + layout.size.height as f64,
+    );
+    let inner = Rect::new(
+        outer.x0 + left,
+        outer.y0 + top,
+        (outer.x1 - right).max(outer.x0 + left),
+        (outer.y1 - bottom).max(outer.y0 + top),
+    );
+    let mut ring = BezPath::new();
+    ring.extend(RoundedRect::from_rect(outer, radii).path_elements(0.1));
+    ring.extend(
+        RoundedRect::from_rect(
+            inner,
+            (
+                (radii.top_left - left.max(top)).max(0.0),
+                (radii.top_right - right.max(top)).max(0.0),
+                (radii.bottom_right - right.max(bottom)).max(0.0),
+                (radii.bottom_left - left.max(bottom)).max(0.0),
+            ),
+        )
+        .path_elements(0.1),
+    );
+
+    // a border with one color is filled at once so there are no seams at the corners
+    if sides.iter().all(|(_, color)| *color == sides[0].1) {
+        scene_builder.fill(Fill::EvenOdd, Affine::IDENTITY, sides[0].1, None, &ring);
+        return;
+    }
+    let (o, i) = (outer, inner);
+    let regions = [
+        [(o.x0, o.y0), (o.x1, o.y0), (i.x1, i.y0), (i.x0, i.y0)],
+        [(o.x1, o.y0), (o.x1, o.y1), (i.x1, i.y1), (i.x1, i.y0)],
+        [(o.x1, o.y1), (o.x0, o.y1), (i.x0, i.y1), (i.x1, i.y1)],
+        [(o.x0, o.y1), (o.x0, o.y0), (i.x0, i.y0), (i.x0, i.y1)],
+    ];
+    for ((width, color), corners) in sides.into_iter().zip(regions) {
+        if width <= 0.0 {
+            continue;
+        }
+        let mut region = BezPath::new();
+        region.move_to(corners[0]);
+        for corner in &corners[1..] {
+            region.line_to(*corner);
+        }
+        region.close_path();
+        scene_builder.push_layer(Mix::Clip, 1.0, Affine::IDENTITY, &region);
+        scene_builder.fill(Fill::EvenOdd, Affine::IDENTITY, color, None, &ring);
+        scene_builder.pop_layer();
+    }
+}
+
+/// Grows a rounded rect by `distance` on every side, keeping the corners concentric. A negative distance shrinks it.
+fn grow_rounded_rect(shape: &RoundedRect, distance: f64) -> RoundedRect {
+    let radii = shape.radii();
+    let rect = shape.rect();
+    RoundedRect::new(
+        rect.x0 - distance,
+        rect.y0 - distance,
+        (rect.x1 + distance).max(rect.x0 - distance),
+        (rect.y1 + distance).max(rect.y0 - distance),
+        (
+            (radii.top_left + distance).max(0.0),
+            (radii.top_right + distance).max(0.0),
+            (radii.bottom_right + distance).max(0.0),
+            (radii.bottom_left + distance).max(0.0),
+        ),
+    )
+}
+
+/// Draws the outer shadows of the `box-shadow` of an element behind it, or the inset shadows inside its border box.
+///
+/// Shadows are painted from last to first so the first one ends up on top. Blur is approximated with [`blur_samples`] like drop shadows.
+fn render_box_shadows(
+    node: NodeRef,
+    layout: &Layout,
+    pos: Point,
+    inset: bool,
+    scene_builder: &mut SceneBuilder,
+    viewport_size: &Size<u32>,
+) {
+    let context = ResolveContext::of(node, viewport_size);
+    let Some(box_shadow) = node.get::<BoxShadow>() else {
+        return;
+    };
+    if !box_shadow
+        .shadows
+        .iter()
+        .any(|shadow| shadow.inset == inset)
+    {
+        return;
+    }
+    let border_box = RoundedRect::from_rect(
+        Rect::new(
+            pos.x,
+            pos.y,
+            pos.x + layout.size.width as f64,
+            pos.y + layout.size.height as f64,
+        ),
+        get_shape(layout, node, viewport_size, pos).radii(),
+    );
+    if inset {
+        scene_builder.push_layer(Mix::Clip, 1.0, Affine::IDENTITY, &border_box);
+    }
+    for shadow in box_shadow
+        .shadows
+        .iter()
+        .rev()
+        .filter(|shadow| shadow.inset == inset)
+    {
+        let offset = Vec2::new(
+            shadow.x_offset.resolve(Axis::X, &layout.size, &context),
+            shadow.y_offset.resolve(Axis::Y, &layout.size, &context),
+        );
+        let blur = shadow.blur.resolve(Axis::Min, &layout.size, &context);
+        let spread = shadow.spread.resolve(Axis::Min, &layout.size, &context);
+        let color = current_color(node, &shadow.color);
+        // the spread grows outer shadows and shrinks the hole inset shadows are cast around
+        let shape = grow_rounded_rect(&border_box, if inset { -spread } else { spread });
+        let samples = blur_samples(blur);
+        let alpha = sample_alpha(color.a as f32 / 255.0, samples.len());
+        let sample_color = Color {
+            a: (alpha * 255.0).round() as u8,
+            ..color
+        };
+        for sample in samples {
+            let transform = Affine::translate(offset + sample);
+            if inset {
+                // fill the area around the shape that is still inside the clip
+                let reach = blur + spread.abs() + offset.hypot();
+                let mut path = BezPath::new();
+                path.extend(border_box.rect().inset(reach).path_elements(0.1));
+                path.extend(shape.path_elements(0.1));
+                scene_builder.fill(Fill::EvenOdd, transform, sample_color, None, &path);
+            } else {
+                scene_builder.fill(Fill::NonZero, transform, sample_color, None, &shape);
+            }
+        }
+    }
+    if inset {
+        scene_builder.pop_layer();
+    }
+}
+
+/// Fills the background of an element in the area set by `background-clip`. `border_box` is the shape used for the border box.
+#[allow(clippy::too_many_arguments)]
+fn render_background(
+    taffy: &Taffy,
+    node: NodeRef,
+    layout: &Layout,
+    pos: Point,
+    border_box: &RoundedRect,
+    color: Color,
+    text_context: &mut TextContext,
+    scene_builder: &mut SceneBuilder,
+    viewport_size: &Size<u32>,
+) {
+    // background images are positioned from the padding box
+    let padding_box = get_inner_shape(taffy, layout, node, pos, viewport_size, false).rect();
+    match node.get::<BackgroundClip>().unwrap().0 {
+        BackgroundClipProperty::BorderBox | BackgroundClipProperty::Border => {
+            scene_builder.fill(Fill::NonZero, Affine::IDENTITY, color, None, border_box);
+            render_background_images(node, padding_box, border_box, scene_builder, viewport_size);
+        }
+        BackgroundClipProperty::PaddingBox => {
+            let shape = get_inner_shape(taffy, layout, node, pos, viewport_size, false);
+            scene_builder.fill(Fill::NonZero, Affine::IDENTITY, color, None, &shape);
+            render_background_images(node, padding_box, &shape, scene_builder, viewport_size);
+        }
+        BackgroundClipProperty::ContentBox => {
+            let shape = get_inner_shape(taffy, layout, node, pos, viewport_size, true);
+            scene_builder.fill(Fill::NonZero, Affine::IDENTITY, color, None, &shape);
+            render_background_images(node, padding_box, &shape, scene_builder, viewport_size);
+        }
+        // The text is drawn into a layer and the background is composited onto the glyphs with `SrcIn`
+        BackgroundClipProperty::Text => {
+            let viewport = Rect::new(
+                0.0,
+                0.0,
+                viewport_size.width as f64,
+                viewport_size.height as f64,
+            );
+            scene_builder.push_layer(Mix::Normal, 1.0, Affine::IDENTITY, &viewport);
+            render_descendant_text(taffy, node, pos, text_context, scene_builder, viewport_size);
+            scene_builder.push_layer(
+                BlendMode::new(Mix::Normal, Compose::SrcIn),
+                1.0,
+                Affine::IDENTITY,
+                &viewport,
+            );
+            scene_builder.fill(Fill::NonZero, Affine::IDENTITY, color, None, border_box);
+            render_background_images(node, padding_box, border_box, scene_builder, viewport_size);
+            scene_builder.pop_layer();
+            scene_builder.pop_layer();
+        }
+    }
+}
+
+/// Draws only the text inside of an element in an opaque color. This is used as a mask for `background-clip: text`, which should work even if the text itself is transparent.
+fn render_descendant_text(
+    taffy: &Taffy,
+    node: NodeRef,
+    pos: Point,
+    text_context: &mut TextContext,
+    scene_builder: &mut SceneBuilder,
+    viewport_size: &Size<u32>,
+) {
+    for child in node.children() {
+        let taffy_node = child.get::<TaffyLayout>().unwrap().node.unwrap();
+        let layout = taffy.layout(taffy_node).unwrap();
+        let child_pos = pos + Vec2::new(layout.location.x as f64, layout.location.y as f64);
+        match &*child.node_type() {
+            NodeType::Text(TextNode { text, .. }) => render_text(
+                taffy,
+                child,
                 text,
-            )
+                child_pos,
+                pos,
+                Some(Color::BLACK),
+                text_context,
+                scene_builder,
+                viewport_size,
+            ),
+            NodeType::Element(_) => render_descendant_text(
+                taffy,
+                child,
+                child_pos,
+                text_context,
+                scene_builder,
+                viewport_size,
+            ),
+            _ => {}
         }
-        NodeType::Element(_) => {
-            let shape = get_shape(layout, node, viewport_size, pos);
-            let fill_color = translate_color(&node.get::<BackgroundColor>().unwrap().0);
-
-            // Draw a border around focused elements
-            if node.get::<Focused>().filter(|focused| focused.0).is_some() {
-                let stroke_color = Color::rgb(1.0, 1.0, 1.0);
-                let stroke = Stroke::new(FOCUS_BORDER_WIDTH as f32 / 2.0);
-                scene_builder.stroke(&stroke, Affine::IDENTITY, stroke_color, None, &shape);
-                let smaller_rect = shape.rect().inset(-FOCUS_BORDER_WIDTH / 2.0);
-                let smaller_shape = RoundedRect::from_rect(smaller_rect, shape.radii());
-                let stroke_color = Color::rgb(0.0, 0.0, 0.0);
-                scene_builder.stroke(&stroke, Affine::IDENTITY, stroke_color, None, &shape);
+    }
+}
+
+/// Returns the padding box of an element, or the content box if `include_padding` is set. The corners are rounded with the border radius reduced by the distance from the border box.
+fn get_inner_shape(
+    taffy: &Taffy,
+    layout: &Layout,
+    node: NodeRef,
+    pos: Point,
+    viewport_size: &Size<u32>,
+    include_padding: bool,
+) -> RoundedRect {
+    let context = ResolveContext::of(node, viewport_size);
+    let border = node.get::<Border>().unwrap();
+    let axis = Axis::Min;
+    let rect = layout.size;
+    let mut top = border.width.top.resolve(axis, &rect, &context);
+    let mut right = border.width.right.resolve(axis, &rect, &context);
+    let mut bottom = border.width.bottom.resolve(axis, &rect, &context);
+    let mut left = border.width.left.resolve(axis, &rect, &context);
+    if include_padding {
+        let taffy_node = node.get::<TaffyLayout>().unwrap().node.unwrap();
+        let padding = taffy.style(taffy_node).unwrap().padding;
+        // percentage padding is relative to the width
+        let resolve = |dimension: Dimension| match dimension {
+            Dimension::Points(points) => points as f64,
+            Dimension::Percent(percent) => (percent * layout.size.width) as f64,
+            _ => 0.0,
+        };
+        top += resolve(padding.top);
+        right += resolve(padding.right);
+        bottom += resolve(padding.bottom);
+        left += resolve(padding.left);
+    }
+    let inner_radius = |radius: f64, horizontal_inset: f64, vertical_inset: f64| {
+        (radius - horizontal_inset.max(vertical_inset)).max(0.0)
+    };
+    RoundedRect::new(
+        pos.x + left,
+        pos.y + top,
+        pos.x + layout.size.width as f64 - right,
+        pos.y + layout.size.height as f64 - bottom,
+        (
+            inner_radius(
+                border.radius.top_left.0.resolve(axis, &rect, &context),
+                left,
+                top,
+            ),
+            inner_radius(
+                border.radius.top_right.0.resolve(axis, &rect, &context),
+                right,
+                top,
+            ),
+            inner_radius(
+                border.radius.bottom_right.0.resolve(axis, &rect, &context),
+                right,
+                bottom,
+            ),
+            inner_radius(
+                border.radius.bottom_left.0.resolve(axis, &rect, &context),
+                left,
+                bottom,
+            ),
+        ),
+    )
+}
+
+/// Returns the area the children of an element are clipped to. Content is clipped to the padding box on each axis that doesn't have visible overflow.
+pub(crate) fn get_overflow_clip(
+    layout: &Layout,
+    node: NodeRef,
+    pos: Point,
+    overflow: &Overflow,
+    viewport_size: &Size<u32>,
+) -> RoundedRect {
+    let context = ResolveContext::of(node, viewport_size);
+    let border = node.get::<Border>().unwrap();
+    let axis = Axis::Min;
+    let rect = layout.size;
+    let left = border.width.left.resolve(axis, &rect, &context);
+    let right = border.width.right.resolve(axis, &rect, &context);
+    let top = border.width.top.resolve(axis, &rect, &context);
+    let bottom = border.width.bottom.resolve(axis, &rect, &context);
+    // the axes that are not clipped extend over the whole viewport
+    let (x0, x1) = if overflow.clips_x() {
+        (pos.x + left, pos.x + layout.size.width as f64 - right)
+    } else {
+        (0.0, viewport_size.width as f64)
+    };
+    let (y0, y1) = if overflow.clips_y() {
+        (pos.y + top, pos.y + layout.size.height as f64 - bottom)
+    } else {
+        (0.0, viewport_size.height as f64)
+    };
+    let clip = Rect::new(x0, y0, x1.max(x0), y1.max(y0));
+    // the corners are only rounded when the clip follows the padding box on both axes
+    if !(overflow.clips_x() && overflow.clips_y()) {
+        return RoundedRect::from_rect(clip, 0.0);
+    }
+    // the inner corners are rounded by the radius left inside the border
+    let radii = get_shape(layout, node, viewport_size, pos).radii();
+    RoundedRect::from_rect(
+        clip,
+        (
+            (radii.top_left - left.max(top)).max(0.0),
+            (radii.top_right - right.max(top)).max(0.0),
+            (radii.bottom_right - right.max(bottom)).max(0.0),
+            (radii.bottom_left - left.max(bottom)).max(0.0),
+        ),
+    )
+}
+
+/// Draws the text of a generated box with the style of the element it belongs to. `color` overrides the text color.
+fn render_generated_box(
+    taffy: &Taffy,
+    node: NodeRef,
+    generated: &GeneratedBox,
+    color: Option<Color>,
+    pos: Point,
+    text_context: &mut TextContext,
+    scene_builder: &mut SceneBuilder,
+) {
+    let layout = taffy.layout(generated.node).unwrap();
+    let text_color =
+        color.unwrap_or_else(|| translate_color(&node.get::<ForgroundColor>().unwrap().0));
+    let font_size = if let Some(font_size) = node.get::<FontSize>() {
+        font_size.0
+    } else {
+        DEFAULT_FONT_SIZE
+    };
+    let font_family = node.get::<FontFamily>().unwrap();
+    let fonts = font_family.resolve(text_context);
+    let features = &font_family.features;
+    let line_height = node.get::<LineHeight>().unwrap().resolve(font_size);
+    let spacing = *node.get::<TextSpacing>().unwrap();
+    let text_layout = text_context.layout(
+        &fonts,
+        features,
+        font_size,
+        &generated.text,
+        line_height,
+        spacing,
+        None,
+    );
+    let mut baseline = pos.y + layout.location.y as f64 + text_layout.baseline;
+    for line in &text_layout.lines {
+        text_context.add(
+            scene_builder,
+            &fonts,
+            features,
+            font_size,
+            Some(text_color),
+            Affine::translate((pos.x + layout.location.x as f64, baseline)),
+            &line.text,
+            spacing,
+        );
+        baseline += text_layout.line_height;
+    }
+}
+
+/// Draws a `drop-shadow()` filter behind the element.
+///
+/// The shadow follows the alpha of the rendered subtree: the subtree is drawn into a layer and the shadow color is composited onto it with `SrcIn`. The blur is only approximated, see [`blur_samples`].
+#[allow(clippy::too_many_arguments)]
+fn render_drop_shadow(
+    taffy: &Taffy,
+    node: NodeRef,
+    layout: &Layout,
+    pos: Point,
+    text_context: &mut TextContext,
+    scene_builder: &mut SceneBuilder,
+    viewport_size: &Size<u32>,
+    shadow: &DropShadow,
+) {
+    let context = ResolveContext::of(node, viewport_size);
+    let offset = Vec2::new(
+        shadow.x_offset.resolve(Axis::X, &layout.size, &context),
+        shadow.y_offset.resolve(Axis::Y, &layout.size, &context),
+    );
+    let blur = shadow.blur.resolve(Axis::Min, &layout.size, &context);
+    let color = current_color(node, &shadow.color);
+    let opaque_color = Color::rgb8(color.r, color.g, color.b);
+    let viewport = Rect::new(
+        0.0,
+        0.0,
+        viewport_size.width as f64,
+        viewport_size.height as f64,
+    );
+    let samples = blur_samples(blur);
+    let sample_alpha = sample_alpha(color.a as f32 / 255.0, samples.len());
+    for sample in samples {
+        scene_builder.push_layer(Mix::Normal, sample_alpha, Affine::IDENTITY, &viewport);
+        render_element(
+            taffy,
+            node,
+            layout,
+            pos + offset + sample,
+            text_context,
+            scene_builder,
+            viewport_size,
+        );
+        scene_builder.push_layer(
+            BlendMode::new(Mix::Normal, Compose::SrcIn),
+            1.0,
+            Affine::IDENTITY,
+            &viewport,
+        );
+        scene_builder.fill(
+            Fill::NonZero,
+            Affine::IDENTITY,
+            opaque_color,
+            None,
+            &viewport,
+        );
+        scene_builder.pop_layer();
+        scene_builder.pop_layer();
+    }
+}
+
+/// Vello can't blur yet, so blur is approximated by drawing copies spread over the blur radius.
+///
+/// CSS blurs with a gaussian whose standard deviation is half the blur radius. The 3x3 copies are placed one standard deviation apart, so the shadow is softened by about the right amount, but its edge is a few visible steps instead of a smooth falloff and it stops short of the full radius.
+fn blur_samples(blur: f64) -> Vec<Vec2> {
+    if blur <= 0.0 {
+        return vec![Vec2::ZERO];
+    }
+    let step = blur / 2.0;
+    let mut samples = Vec::with_capacity(9);
+    for x in -1..=1 {
+        for y in -1..=1 {
+            samples.push(Vec2::new(x as f64 * step, y as f64 * step));
+        }
+    }
+    samples
+}
+
+/// The alpha each of the overlapping samples needs for a fully covered point to end up at `alpha`.
+fn sample_alpha(alpha: f32, samples: usize) -> f32 {
+    1.0 - (1.0 - alpha).powf(1.0 / samples as f32)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn render_text(
+    taffy: &Taffy,
+    node: NodeRef,
+    text: &str,
+    pos: Point,
+    parent_pos: Point,
+    color: Option<Color>,
+    text_context: &mut TextContext,
+    scene_builder: &mut SceneBuilder,
+    viewport_size: &Size<u32>,
+) {
+    let text_color =
+        color.unwrap_or_else(|| translate_color(&node.get::<ForgroundColor>().unwrap().0));
+    let font_size = if let Some(font_size) = node.get::<FontSize>() {
+        font_size.0
+    } else {
+        DEFAULT_FONT_SIZE
+    };
+    let text_align = *node.get::<TextAlign>().unwrap();
+    let writing_mode = *node.get::<WritingMode>().unwrap();
+    let decoration = node
+        .get::<TextDecoration>()
+        .filter(|decoration| !decoration.line.is_empty());
+    let content_box = match node.parent() {
+        Some(parent) => get_content_box_x(taffy, parent, parent_pos),
+        None => (pos.x, 0.0),
+    };
+
+    let font_family = node.get::<FontFamily>().unwrap();
+    let fonts = font_family.resolve(text_context);
+    let features = &font_family.features;
+    let line_height = node.get::<LineHeight>().unwrap().resolve(font_size);
+    let spacing = *node.get::<TextSpacing>().unwrap();
+    let text = node.get::<TextTransform>().unwrap().apply(text);
+    let white_space = *node.get::<WhiteSpace>().unwrap();
+    let text = white_space.apply(&text);
+    let wrap_width = (white_space.wraps() && node.parent().is_some()).then_some(content_box.1);
+    let text_layout = text_context.layout(
+        &fonts,
+        features,
+        font_size,
+        &text,
+        line_height,
+        spacing,
+        wrap_width,
+    );
+    let selection = node
+        .get::<TextSelection>()
+        .map(|selection| selection.0.clone())
+        .filter(|selection| !selection.is_empty());
+    // every line that overflows a block with an ellipsis is cut off
+    let ellipsis = node.parent().is_some_and(|parent| {
+        parent.get::<TextOverflow>().as_deref() == Some(&TextOverflow::Ellipsis)
+            && parent
+                .get::<Overflow>()
+                .is_some_and(|overflow| overflow.clips_x())
+    });
+    let shadows = text_shadows(taffy, node, text_color, viewport_size);
+    let context = ResolveContext::of(node, viewport_size);
+    let mut baseline = pos.y + text_layout.baseline;
+    // the index of the first character of the line in the text node
+    let mut line_start = 0;
+    for line in &text_layout.lines {
+        let direction = line_direction(
+            &line.text,
+            writing_mode.direction,
+            writing_mode.unicode_bidi,
+        );
+        let alignment = text_align.line_alignment(line.ends_paragraph, direction);
+        let (x, word_spacing) = align_line(line, alignment, pos.x, content_box);
+        let line_len = line.text.chars().count();
+        if let Some(selection) = &selection {
+            let start = selection.start.max(line_start) - line_start;
+            let end = selection
+                .end
+                .min(line_start + line_len)
+                .saturating_sub(line_start);
+            if start < end {
+                let offsets = text_context.caret_offsets(
+                    &fonts,
+                    features,
+                    font_size,
+                    &line.text,
+                    spacing.with_extra_word_spacing(word_spacing),
+                );
+                let top = baseline - text_layout.baseline;
+                let highlight = Rect::new(
+                    x + offsets[start],
+                    top,
+                    x + offsets[end],
+                    top + text_layout.line_height,
+                );
                 scene_builder.fill(
                     Fill::NonZero,
                     Affine::IDENTITY,
-                    fill_color,
+                    SELECTION_COLOR,
                     None,
-                    &smaller_shape,
+                    &highlight,
                 );
-            } else {
-                let stroke_color = translate_color(&node.get::<Border>().unwrap().colors.top);
-                let stroke = Stroke::new(node.get::<Border>().unwrap().width.top.resolve(
-                    Axis::Min,
-                    &layout.size,
-                    viewport_size,
-                ) as f32);
-                scene_builder.stroke(&stroke, Affine::IDENTITY, stroke_color, None, &shape);
-                scene_builder.fill(Fill::NonZero, Affine::IDENTITY, fill_color, None, &shape);
+            }
+        }
+        line_start += line_len + 1;
+        let ellipsized = ellipsis
+            .then(|| {
+                let (content_x, content_width) = content_box;
+                text_context.ellipsize(
+                    &fonts,
+                    features,
+                    font_size,
+                    &line.text,
+                    spacing,
+                    content_x + content_width - x,
+                )
+            })
+            .flatten();
+        // lines are shaped in the order they are drawn
+        let visual = visual_order(
+            ellipsized.as_deref().unwrap_or(&line.text),
+            direction,
+            writing_mode.unicode_bidi,
+        );
+        for (offset, color) in &shadows {
+            text_context.add(
+                scene_builder,
+                &fonts,
+                features,
+                font_size,
+                Some(*color),
+                Affine::translate(Vec2::new(x, baseline) + *offset),
+                &visual,
+                spacing.with_extra_word_spacing(word_spacing),
+            );
+        }
+        text_context.add(
+            scene_builder,
+            &fonts,
+            features,
+            font_size,
+            Some(text_color),
+            Affine::translate((x, baseline)),
+            &visual,
+            spacing.with_extra_word_spacing(word_spacing),
+        );
+        if let Some(decoration) = &decoration {
+            let decoration_color = match &decoration.color {
+                CssColor::CurrentColor => text_color,
+                color => translate_color(color),
             };
+            let width = match &ellipsized {
+                Some(ellipsized) => {
+                    text_context
+                        .get_text_size(&fonts, features, font_size, ellipsized, spacing)
+                        .0
+                }
+                None => {
+                    let spaces = line.text.matches(' ').count();
+                    line.width + word_spacing * spaces as f64
+                }
+            };
+            render_text_decoration(
+                text_context,
+                scene_builder,
+                &fonts,
+                decoration,
+                decoration_color,
+                Point::new(x, baseline),
+                width,
+                font_size,
+                &context,
+            );
+        }
+        baseline += text_layout.line_height;
+    }
+}
 
-            if let Some(image) = node
-                .get::<LoadedImage>()
-                .as_ref()
-                .and_then(|image| image.0.as_ref())
-            {
-                // Scale the image to fit the layout
-                let image_width = image.width as f64;
-                let image_height = image.height as f64;
-                let scale = Affine::scale_non_uniform(
-                    layout.size.width as f64 / image_width,
-                    layout.size.height as f64 / image_height,
-                );
-
-                // Translate the image to the layout's position
-                let translate = Affine::translate(pos.to_vec2());
+/// Resolves the `text-shadow` of a text node into the offset and color of every copy of the text to draw behind it.
+///
+/// Shadows are returned from last to first so the first one ends up on top. Blur is approximated with [`blur_samples`] like box shadows, so a shadow without blur is a single sharp copy.
+fn text_shadows(
+    taffy: &Taffy,
+    node: NodeRef,
+    text_color: Color,
+    viewport_size: &Size<u32>,
+) -> Vec<(Vec2, Color)> {
+    let context = ResolveContext::of(node, viewport_size);
+    let Some(text_shadow) = node.get::<TextShadow>() else {
+        return Vec::new();
+    };
+    let taffy_node = node.get::<TaffyLayout>().unwrap().node.unwrap();
+    let size = taffy.layout(taffy_node).unwrap().size;
+    let mut copies = Vec::new();
+    for shadow in text_shadow.shadows.iter().rev() {
+        let offset = Vec2::new(
+            shadow.x_offset.resolve(Axis::X, &size, &context),
+            shadow.y_offset.resolve(Axis::Y, &size, &context),
+        );
+        let blur = shadow.blur.resolve(Axis::Min, &size, &context);
+        let color = match &shadow.color {
+            CssColor::CurrentColor => text_color,
+            color => translate_color(color),
+        };
+        let samples = blur_samples(blur);
+        let alpha = sample_alpha(color.a as f32 / 255.0, samples.len());
+        let sample_color = Color {
+            a: (alpha * 255.0).round() as u8,
+            ..color
+        };
+        copies.extend(
+            samples
+                .into_iter()
+                .map(|sample| (offset + sample, sample_color)),
+        );
+    }
+    copies
+}
 
-                scene_builder.draw_image(image, translate * scale);
+/// Returns the x position of a line and the extra space to add after each space to justify it.
+///
+/// Left aligned lines stay where the layout placed them, other lines are aligned inside the content box of the parent. `alignment` is resolved with [`TextAlign::line_alignment`] first, so `start` and `end` are already a side.
+pub(crate) fn align_line(
+    line: &TextLine,
+    alignment: TextAlignProperty,
+    text_x: f64,
+    (content_x, content_width): (f64, f64),
+) -> (f64, f64) {
+    let free_space = (content_width - line.width).max(0.0);
+    match alignment {
+        TextAlignProperty::Start | TextAlignProperty::Left | TextAlignProperty::MatchParent => {
+            (text_x, 0.0)
+        }
+        TextAlignProperty::End | TextAlignProperty::Right => (content_x + free_space, 0.0),
+        TextAlignProperty::Center => (content_x + free_space / 2.0, 0.0),
+        TextAlignProperty::Justify | TextAlignProperty::JustifyAll => {
+            let spaces = line.text.matches(' ').count();
+            if spaces == 0 {
+                (text_x, 0.0)
+            } else {
+                (content_x, free_space / spaces as f64)
             }
+        }
+    }
+}
 
-            for child in node.children() {
-                render_node(
-                    taffy,
-                    child,
-                    text_context,
-                    scene_builder,
-                    pos,
-                    viewport_size,
+/// Returns the x position and width of the content box of an element: the layout box without the padding and border.
+pub(crate) fn get_content_box_x(taffy: &Taffy, node: NodeRef, pos: Point) -> (f64, f64) {
+    let taffy_node = node.get::<TaffyLayout>().unwrap().node.unwrap();
+    let layout = taffy.layout(taffy_node).unwrap();
+    let style = taffy.style(taffy_node).unwrap();
+    let width = layout.size.width;
+    let resolve = |dimension: Dimension| match dimension {
+        Dimension::Points(points) => points,
+        Dimension::Percent(percent) => percent * width,
+        _ => 0.0,
+    };
+    let left = resolve(style.padding.left) + resolve(style.border.left);
+    let right = resolve(style.padding.right) + resolve(style.border.right);
+    (pos.x + left as f64, (width - left - right).max(0.0) as f64)
+}
+
+/// Draws every decoration line that is set on a line of text starting at `origin` on the baseline. Multiple lines can be set at once, for example `text-decoration-line: underline overline`.
+#[allow(clippy::too_many_arguments)]
+fn render_text_decoration(
+    text_context: &mut TextContext,
+    scene_builder: &mut SceneBuilder,
+    fonts: &FontSelection,
+    decoration: &TextDecoration,
+    color: Color,
+    origin: Point,
+    width: f64,
+    font_size: f32,
+    context: &ResolveContext,
+) {
+    let metrics = text_context.get_font_metrics(fonts, font_size);
+    let thickness = match &decoration.thickness {
+        TextDecorationThickness::Auto | TextDecorationThickness::FromFont => {
+            metrics.underline_thickness as f64
+        }
+        // percentages are relative to the font size
+        TextDecorationThickness::LengthPercentage(thickness) => thickness.resolve(
+            Axis::Min,
+            &Size {
+                width: font_size,
+                height: font_size,
+            },
+            context,
+        ),
+    };
+    // percentages are relative to the font size
+    let underline_offset = decoration.underline_offset.as_ref().map(|offset| {
+        offset.resolve(
+            Axis::Min,
+            &Size {
+                width: font_size,
+                height: font_size,
+            },
+            context,
+        ) as f32
+    });
+    for (line, offset) in decoration_offsets(decoration, &metrics, thickness, underline_offset) {
+        let y = origin.y - offset as f64;
+        let line_path = |y: f64| {
+            let mut path = BezPath::new();
+            path.move_to((origin.x, y));
+            path.line_to((origin.x + width, y));
+            path
+        };
+        match decoration.style {
+            TextDecorationStyle::Wavy => {
+                let wave = wavy_line(origin.x, origin.x + width, y, thickness);
+                let stroke = Stroke::new(thickness as f32);
+                scene_builder.stroke(&stroke, Affine::IDENTITY, color, None, &wave);
+            }
+            TextDecorationStyle::Dotted => {
+                let stroke = Stroke::new(thickness as f32)
+                    .with_caps(Cap::Round)
+                    .with_dashes(0.0, [0.0, thickness as f32 * 2.0]);
+                scene_builder.stroke(&stroke, Affine::IDENTITY, color, None, &line_path(y));
+            }
+            TextDecorationStyle::Dashed => {
+                let stroke = Stroke::new(thickness as f32)
+                    .with_dashes(0.0, [thickness as f32 * 3.0, thickness as f32 * 2.0]);
+                scene_builder.stroke(&stroke, Affine::IDENTITY, color, None, &line_path(y));
+            }
+            TextDecorationStyle::Double => {
+                // two lines of the full thickness with a gap of the same size, the second one further from the text
+                let second = if line == TextDecorationLine::Underline {
+                    y + thickness * 2.0
+                } else {
+                    y - thickness * 2.0
+                };
+                for y in [y, second] {
+                    let shape = Rect::new(
+                        origin.x,
+                        y - thickness / 2.0,
+                        origin.x + width,
+                        y + thickness / 2.0,
+                    );
+                    scene_builder.fill(Fill::NonZero, Affine::IDENTITY, color, None, &shape);
+                }
+            }
+            TextDecorationStyle::Solid => {
+                let shape = Rect::new(
+                    origin.x,
+                    y - thickness / 2.0,
+                    origin.x + width,
+                    y + thickness / 2.0,
                 );
+                scene_builder.fill(Fill::NonZero, Affine::IDENTITY, color, None, &shape);
             }
         }
-        _ => {}
     }
 }
 
+/// The decoration lines that are set and their offsets above the baseline. `underline_offset` is the resolved `text-underline-offset`.
+fn decoration_offsets(
+    decoration: &TextDecoration,
+    metrics: &FontMetrics,
+    thickness: f64,
+    underline_offset: Option<f32>,
+) -> Vec<(TextDecorationLine, f32)> {
+    // a positive text-underline-offset moves the underline away from the text
+    let underline_offset = match (underline_offset, decoration.underline_under) {
+        (None, false) => metrics.underline_offset,
+        (None, true) => metrics.descent - thickness as f32 / 2.0,
+        (Some(offset), false) => -offset,
+        (Some(offset), true) => metrics.descent - offset,
+    };
+    [
+        (TextDecorationLine::Underline, underline_offset),
+        (TextDecorationLine::Overline, metrics.ascent),
+        (TextDecorationLine::LineThrough, metrics.strikeout_offset),
+    ]
+    .into_iter()
+    .filter(|(line, _)| decoration.line.contains(*line))
+    .collect()
+}
+
+/// Builds a wavy line centered on `y` out of quadratic segments that alternate above and below the line. The size of the waves scales with the thickness of the line.
+fn wavy_line(x_start: f64, x_end: f64, y: f64, thickness: f64) -> BezPath {
+    let thickness = thickness.max(1.0);
+    let amplitude = thickness * 1.5;
+    let half_wavelength = thickness * 3.0;
+    let mut path = BezPath::new();
+    path.move_to((x_start, y));
+    let mut x = x_start;
+    let mut up = true;
+    while x < x_end {
+        let next_x = (x + half_wavelength).min(x_end);
+        // the peak of a quadratic curve is half way to its control point
+        let control_y = if up {
+            y - amplitude * 2.0
+        } else {
+            y + amplitude * 2.0
+        };
+        path.quad_to(((x + next_x) / 2.0, control_y), (next_x, y));
+        x = next_x;
+        up = !up;
+    }
+    path
+}
+
 pub(crate) fn get_shape(
     layout: &Layout,
     node: NodeRef,
     viewport_size: &Size<u32>,
     location: Point,
 ) -> RoundedRect {
+    let context = ResolveContext::of(node, viewport_size);
     let axis = Axis::Min;
     let rect = layout.size;
     let x: f64 = location.x;
@@ -159,27 +1506,10 @@ pub(crate) fn get_shape(
     let width: f64 = layout.size.width.into();
     let height: f64 = layout.size.height.into();
     let border: &Border = &node.get().unwrap();
-    let focused = node.get::<Focused>().filter(|focused| focused.0).is_some();
-    let left_border_width = if focused {
-        FOCUS_BORDER_WIDTH
-    } else {
-        border.width.left.resolve(axis, &rect, viewport_size)
-    };
-    let right_border_width = if focused {
-        FOCUS_BORDER_WIDTH
-    } else {
-        border.width.right.resolve(axis, &rect, viewport_size)
-    };
-    let top_border_width = if focused {
-        FOCUS_BORDER_WIDTH
-    } else {
-        border.width.top.resolve(axis, &rect, viewport_size)
-    };
-    let bottom_border_width = if focused {
-        FOCUS_BORDER_WIDTH
-    } else {
-        border.width.bottom.resolve(axis, &rect, viewport_size)
-    };
+    let left_border_width = border.width.left.resolve(axis, &rect, &context);
+    let right_border_width = border.width.right.resolve(axis, &rect, &context);
+    let top_border_width = border.width.top.resolve(axis, &rect, &context);
+    let bottom_border_width = border.width.bottom.resolve(axis, &rect, &context);
 
     // The stroke is drawn on the outside of the border, so we need to offset the rect by the border width for each side.
     let x_start = x + left_border_width / 2.0;
@@ -193,28 +1523,26 @@ pub(crate) fn get_shape(
         x_end,
         y_end,
         (
-            border.radius.top_left.0.resolve(axis, &rect, viewport_size),
-            border
-                .radius
-                .top_right
-                .0
-                .resolve(axis, &rect, viewport_size),
-            border
-                .radius
-                .bottom_right
-                .0
-                .resolve(axis, &rect, viewport_size),
-            border
-                .radius
-                .bottom_left
-                .0
-                .resolve(axis, &rect, viewport_size),
+            border.radius.top_left.0.resolve(axis, &rect, &context),
+            border.radius.top_right.0.resolve(axis, &rect, &context),
+            border.radius.bottom_right.0.resolve(axis, &rect, &context),
+            border.radius.bottom_left.0.resolve(axis, &rect, &context),
         ),
     )
 }
 
-pub(crate) fn get_abs_pos(layout: Layout, taffy: &Taffy, node: NodeRef) -> Point {
+/// Returns the position of a node in window coordinates, including the scroll offsets and translations of it and its ancestors.
+pub(crate) fn get_abs_pos(
+    layout: Layout,
+    taffy: &Taffy,
+    node: NodeRef,
+    viewport_size: &Size<u32>,
+) -> Point {
+    let own_translation = translation(node, &layout, viewport_size)
+        + position_offset(taffy, node, &layout, viewport_size);
     let mut node_layout = layout.location;
+    node_layout.x += own_translation.x as f32;
+    node_layout.y += own_translation.y as f32;
     let mut current = node.id();
     while let Some(parent) = node.real_dom().get(current).unwrap().parent() {
         let parent_id = parent.id();
@@ -225,8 +1553,103 @@ pub(crate) fn get_abs_pos(layout: Layout, taffy: &Taffy, node: NodeRef) -> Point
         current = parent_id;
         let taffy_node = parent.get::<TaffyLayout>().unwrap().node.unwrap();
         let parent_layout = taffy.layout(taffy_node).unwrap();
-        node_layout.x += parent_layout.location.x;
-        node_layout.y += parent_layout.location.y;
+        let offset = translation(parent, parent_layout, viewport_size)
+            + position_offset(taffy, parent, parent_layout, viewport_size)
+            - scroll_offset(parent);
+        node_layout.x += parent_layout.location.x + offset.x as f32;
+        node_layout.y += parent_layout.location.y + offset.y as f32;
     }
     Point::new(node_layout.x as f64, node_layout.y as f64)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The metrics of a 16px font. Offsets point up from the baseline.
+    const METRICS: FontMetrics = FontMetrics {
+        ascent: 12.0,
+        descent: -4.0,
+        line_height: 20.0,
+        underline_offset: -2.0,
+        underline_thickness: 1.0,
+        strikeout_offset: 4.0,
+        strikeout_thickness: 1.0,
+        x_height: 8.0,
+    };
+
+    #[test]
+    fn underline_and_overline_are_both_drawn() {
+        let decoration = TextDecoration {
+            line: TextDecorationLine::Underline | TextDecorationLine::Overline,
+            ..Default::default()
+        };
+        assert_eq!(
+            decoration_offsets(&decoration, &METRICS, 1.0, None),
+            vec![
+                (TextDecorationLine::Underline, -2.0),
+                (TextDecorationLine::Overline, 12.0)
+            ]
+        );
+        assert!(decoration_offsets(&TextDecoration::default(), &METRICS, 1.0, None).is_empty());
+    }
+
+    #[test]
+    fn positive_underline_offset_lowers_the_underline() {
+        let underline = |decoration: &TextDecoration, offset: Option<f32>| {
+            decoration_offsets(decoration, &METRICS, 1.0, offset)[0].1
+        };
+        let decoration = TextDecoration {
+            line: TextDecorationLine::Underline,
+            ..Default::default()
+        };
+        // offsets point up, so a lower underline has a smaller offset
+        assert_eq!(underline(&decoration, Some(0.0)), 0.0);
+        assert_eq!(underline(&decoration, Some(3.0)), -3.0);
+        assert!(underline(&decoration, Some(3.0)) < underline(&decoration, None));
+        // with `text-underline-position: under` the offset is added below the descenders
+        let under = TextDecoration {
+            underline_under: true,
+            ..decoration
+        };
+        assert_eq!(underline(&under, Some(3.0)), -7.0);
+        assert!(underline(&under, Some(3.0)) < underline(&under, Some(0.0)));
+    }
+
+    #[test]
+    fn focused_dashed_outline_is_dashed() {
+        let dashed = Some(OutlineStyle::LineStyle(LineStyle::Dashed));
+        assert_eq!(
+            outline_kind(&dashed, true),
+            Some(OutlineKind::Line(LineStyle::Dashed))
+        );
+        assert_eq!(dash_pattern(LineStyle::Dashed, 2.0), Some([6.0, 4.0]));
+        assert_eq!(dash_pattern(LineStyle::Solid, 2.0), None);
+    }
+
+    #[test]
+    fn blur_samples_spread_over_half_the_blur_radius() {
+        assert_eq!(blur_samples(0.0), vec![Vec2::ZERO]);
+        let samples = blur_samples(8.0);
+        assert_eq!(samples.len(), 9);
+        // the copies are centered on the shadow and reach one standard deviation, half the radius, on each axis
+        assert_eq!(
+            samples.iter().fold(Vec2::ZERO, |sum, s| sum + *s),
+            Vec2::ZERO
+        );
+        assert_eq!(samples.iter().map(|s| s.x).fold(0.0, f64::max), 4.0);
+        assert_eq!(samples.iter().map(|s| s.y).fold(0.0, f64::min), -4.0);
+        // where every copy overlaps, the shadow has the alpha of its color
+        let alpha = sample_alpha(0.6, samples.len());
+        assert!((1.0 - (1.0 - alpha).powi(9) - 0.6).abs() < 1e-5);
+    }
+
+    #[test]
+    fn auto_outline_is_only_drawn_when_focused() {
+        let auto = Some(OutlineStyle::Auto);
+        assert_eq!(outline_kind(&auto, false), None);
+        assert_eq!(outline_kind(&auto, true), Some(OutlineKind::FocusRing));
+        assert_eq!(outline_kind(&None, false), None);
+        assert_eq!(outline_kind(&None, true), Some(OutlineKind::FocusRing));
+    }
+}
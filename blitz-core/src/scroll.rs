@@ -0,0 +1,541 @@
+//! Scrolling of scroll containers.
+//!
+//! Elements with `overflow: auto` or `overflow: scroll` scroll their content with the mouse wheel or trackpad. Each container keeps its own offset, and the wheel scrolls the innermost container under the cursor until it reaches its end, then the container around it. The offset is stored in a [`ScrollOffset`] on the container and subtracted from the position of its children when rendering and hit testing.
+//!
+//! Containers can also be scrolled from the app with [`crate::WindowHandle::scroll_into_view`], and they are scrolled to keep the focused element in view. With `scroll-behavior: smooth` the offset is animated to the target instead of jumping. The animation stops if the container is scrolled some other way before it finishes.
+//!
+//! When content changes size, the scroll offset of each container is adjusted so the content in view stays in place. The anchor is picked before the layout changes and the offset is corrected after it is computed again. `overflow-anchor: none` on a container opts out, and on an element keeps it from being picked as the anchor.
+
+use std::time::{Duration, Instant};
+
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use rustc_hash::{FxHashMap, FxHashSet};
+use shipyard::Component;
+use taffy::prelude::{Dimension, Layout, Size};
+use taffy::Taffy;
+use vello::kurbo::{Point, Vec2};
+
+use crate::hit_test::hit_test;
+use crate::layout::TaffyLayout;
+use crate::style::Overflow;
+
+/// The distance scrolled for each line of a mouse wheel.
+pub(crate) const LINE_HEIGHT: f64 = 40.0;
+
+/// How long a smooth scroll takes.
+const SMOOTH_SCROLL_DURATION: Duration = Duration::from_millis(300);
+
+/// How far the content of a scroll container is scrolled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Component)]
+pub(crate) struct ScrollOffset(pub Vec2);
+
+/// The `overflow-anchor` property. `false` opts out of scroll anchoring.
+#[derive(Clone, Copy, Debug, PartialEq, Component)]
+pub(crate) struct OverflowAnchor(pub bool);
+
+impl Default for OverflowAnchor {
+    fn default() -> Self {
+        OverflowAnchor(true)
+    }
+}
+
+#[partial_derive_state]
+impl State for OverflowAnchor {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&["overflow-anchor"]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let new = OverflowAnchor(
+            node_view
+                .attributes()
+                .into_iter()
+                .flatten()
+                .filter_map(|attr| attr.value.as_text())
+                .all(|value| value.trim() != "none"),
+        );
+
+        if *self != new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+/// The `scroll-behavior` property. `true` animates scrolling that isn't done by the user.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Component)]
+pub(crate) struct SmoothScroll(pub bool);
+
+#[partial_derive_state]
+impl State for SmoothScroll {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&["scroll-behavior"]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let new = SmoothScroll(
+            node_view
+                .attributes()
+                .into_iter()
+                .flatten()
+                .filter_map(|attr| attr.value.as_text())
+                .any(|value| value.trim() == "smooth"),
+        );
+
+        if *self != new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+/// Returns how far the content of a node is scrolled.
+pub(crate) fn scroll_offset(node: NodeRef) -> Vec2 {
+    node.get::<ScrollOffset>()
+        .map(|offset| offset.0)
+        .unwrap_or_default()
+}
+
+fn layout_of<'a>(taffy: &'a Taffy, node: NodeRef) -> Option<&'a Layout> {
+    let taffy_node = node.get::<TaffyLayout>()?.node?;
+    taffy.layout(taffy_node).ok()
+}
+
+/// Returns the largest scroll offset of a container. The content can be scrolled until the far edge of its furthest descendant, plus the end padding of the container, is at the inner edge of its border.
+fn max_scroll(taffy: &Taffy, node: NodeRef) -> Vec2 {
+    let (Some(layout), Some(taffy_node)) = (
+        layout_of(taffy, node),
+        node.get::<TaffyLayout>().and_then(|layout| layout.node),
+    ) else {
+        return Vec2::ZERO;
+    };
+    let style = taffy.style(taffy_node).unwrap();
+    // percentages of padding and border are relative to the width
+    let resolve = |dimension: Dimension| match dimension {
+        Dimension::Points(points) => points as f64,
+        Dimension::Percent(percent) => (percent * layout.size.width) as f64,
+        _ => 0.0,
+    };
+    let extent = content_extent(taffy, node)
+        + Vec2::new(resolve(style.padding.right), resolve(style.padding.bottom));
+    let visible = Vec2::new(
+        layout.size.width as f64 - resolve(style.border.right),
+        layout.size.height as f64 - resolve(style.border.bottom),
+    );
+    Vec2::new(
+        (extent.x - visible.x).max(0.0),
+        (extent.y - visible.y).max(0.0),
+    )
+}
+
+/// Returns the far corner of the content of a node relative to its border box. Descendants that overflow children without a clip count too.
+fn content_extent(taffy: &Taffy, node: NodeRef) -> Vec2 {
+    let mut extent = Vec2::ZERO;
+    for child in node.children() {
+        let Some(child_layout) = layout_of(taffy, child) else {
+            continue;
+        };
+        let location = Vec2::new(
+            child_layout.location.x as f64,
+            child_layout.location.y as f64,
+        );
+        let mut far = location
+            + Vec2::new(
+                child_layout.size.width as f64,
+                child_layout.size.height as f64,
+            );
+        let clips = child
+            .get::<Overflow>()
+            .is_some_and(|overflow| overflow.clips_x() || overflow.clips_y());
+        if !clips {
+            let inner = location + content_extent(taffy, child);
+            far = Vec2::new(far.x.max(inner.x), far.y.max(inner.y));
+        }
+        extent = Vec2::new(extent.x.max(far.x), extent.y.max(far.y));
+    }
+    extent
+}
+
+/// Clamps a scroll offset to the axes the container scrolls on and the size of its content.
+fn clamp_offset(taffy: &Taffy, node: NodeRef, offset: Vec2) -> Vec2 {
+    let overflow = node
+        .get::<Overflow>()
+        .map(|overflow| *overflow)
+        .unwrap_or_default();
+    let max = max_scroll(taffy, node);
+    Vec2::new(
+        if overflow.clips_x() {
+            offset.x.clamp(0.0, max.x)
+        } else {
+            0.0
+        },
+        if overflow.clips_y() {
+            offset.y.clamp(0.0, max.y)
+        } else {
+            0.0
+        },
+    )
+}
+
+/// Scrolls the innermost scroll container under the cursor that can still move in the direction of `delta`. Once a container reaches its end the scroll continues in the container around it. Returns the container that was scrolled.
+pub(crate) fn scroll_at(
+    rdom: &mut RealDom,
+    taffy: &Taffy,
+    viewport_size: &Size<u32>,
+    cursor: Point,
+    delta: Vec2,
+) -> Option<NodeId> {
+    // the containers around the topmost element under the cursor, innermost first
+    let mut containers = Vec::new();
+    let mut current = rdom.get(hit_test(rdom, taffy, viewport_size, cursor)?);
+    while let Some(node) = current {
+        let overflow = *node.get::<Overflow>().unwrap();
+        if overflow.scrolls_x() || overflow.scrolls_y() {
+            containers.push((node.id(), overflow));
+        }
+        current = node.parent();
+    }
+
+    for (id, overflow) in containers {
+        let node = rdom.get(id).unwrap();
+        let delta = Vec2::new(
+            if overflow.scrolls_x() { delta.x } else { 0.0 },
+            if overflow.scrolls_y() { delta.y } else { 0.0 },
+        );
+        let old = scroll_offset(node);
+        let new = clamp_offset(taffy, node, old + delta);
+        if new != old {
+            rdom.get_mut(id).unwrap().insert(ScrollOffset(new));
+            return Some(id);
+        }
+    }
+    None
+}
+
+/// The content a scroll container keeps in view while the layout changes.
+pub(crate) struct ScrollAnchor {
+    container: NodeId,
+    node: NodeId,
+    /// The position of the anchor in the content of the container before the layout changed.
+    position: Point,
+}
+
+/// Returns the position of a node relative to the content of one of its ancestors.
+fn position_in(rdom: &RealDom, taffy: &Taffy, container: NodeId, id: NodeId) -> Option<Point> {
+    let mut position = Point::ZERO;
+    let mut current = rdom.get(id)?;
+    while current.id() != container {
+        let layout = layout_of(taffy, current)?;
+        position += Vec2::new(layout.location.x as f64, layout.location.y as f64);
+        current = current.parent()?;
+    }
+    Some(position)
+}
+
+/// Picks the anchor of every scrolled container. This must run before the dom or layout change.
+pub(crate) fn select_scroll_anchors(rdom: &RealDom, taffy: &Taffy) -> Vec<ScrollAnchor> {
+    let mut anchors = Vec::new();
+    let mut stack = vec![rdom.root_id()];
+    while let Some(id) = stack.pop() {
+        let node = rdom.get(id).unwrap();
+        stack.extend(node.child_ids());
+        let offset = scroll_offset(node);
+        if offset == Vec2::ZERO || !node.get::<OverflowAnchor>().is_none_or(|anchor| anchor.0) {
+            continue;
+        }
+        if let Some(anchor) = find_anchor(rdom, taffy, node, offset) {
+            if let Some(position) = position_in(rdom, taffy, id, anchor) {
+                anchors.push(ScrollAnchor {
+                    container: id,
+                    node: anchor,
+                    position,
+                });
+            }
+        }
+    }
+    anchors
+}
+
+/// Finds the deepest node that is at least partly in view, preferring nodes that are fully in view.
+fn find_anchor(rdom: &RealDom, taffy: &Taffy, container: NodeRef, offset: Vec2) -> Option<NodeId> {
+    let container_id = container.id();
+    let size = layout_of(taffy, container)?.size;
+    let top = offset.y;
+    let bottom = offset.y + size.height as f64;
+    let mut candidates = container.children();
+    loop {
+        let mut next = None;
+        for child in candidates {
+            if !child.get::<OverflowAnchor>().is_none_or(|anchor| anchor.0) {
+                continue;
+            }
+            let Some(layout) = layout_of(taffy, child) else {
+                continue;
+            };
+            let Some(position) = position_in(rdom, taffy, container_id, child.id()) else {
+                continue;
+            };
+            let child_bottom = position.y + layout.size.height as f64;
+            if child_bottom <= top || position.y >= bottom {
+                continue;
+            }
+            let children = child.children();
+            // nodes that are cut off by the top of the scrollport are too coarse, so look inside them
+            if position.y < top && !children.is_empty() {
+                next = Some(children);
+            } else {
+                return Some(child.id());
+            }
+            break;
+        }
+        candidates = next?;
+    }
+}
+
+/// Moves the scroll offset of each container by how far its anchor moved. Returns the containers that were scrolled.
+pub(crate) fn adjust_scroll_anchors(
+    rdom: &mut RealDom,
+    taffy: &Taffy,
+    anchors: Vec<ScrollAnchor>,
+) -> FxHashSet<NodeId> {
+    let mut changed = FxHashSet::default();
+    for anchor in anchors {
+        let Some(position) = position_in(rdom, taffy, anchor.container, anchor.node) else {
+            // the anchor was removed
+            continue;
+        };
+        let Some(container) = rdom.get(anchor.container) else {
+            continue;
+        };
+        let old = scroll_offset(container);
+        let new = clamp_offset(taffy, container, old + (position - anchor.position));
+        if new != old {
+            rdom.get_mut(anchor.container)
+                .unwrap()
+                .insert(ScrollOffset(new));
+            changed.insert(anchor.container);
+        }
+    }
+    changed
+}
+
+/// Where an element is placed in a scroll container, like the `block` and `inline` options of `scrollIntoView`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScrollAlignment {
+    /// Align the start of the element with the start of the container.
+    #[default]
+    Start,
+    /// Center the element in the container.
+    Center,
+    /// Align the end of the element with the end of the container.
+    End,
+    /// Scroll as little as possible to bring the element into view. Elements that are already in view don't move.
+    Nearest,
+}
+
+impl ScrollAlignment {
+    /// Returns the offset that places an element that spans `start..start + size` in a container that shows `visible` of its content and is scrolled to `offset`.
+    fn offset(self, start: f64, size: f64, visible: f64, offset: f64) -> f64 {
+        match self {
+            ScrollAlignment::Start => start,
+            ScrollAlignment::Center => start + (size - visible) / 2.0,
+            ScrollAlignment::End => start + size - visible,
+            ScrollAlignment::Nearest => {
+                if start < offset {
+                    start
+                } else if start + size > offset + visible {
+                    // elements that are larger than the container keep their start in view
+                    start.min(start + size - visible)
+                } else {
+                    offset
+                }
+            }
+        }
+    }
+}
+
+/// Scrolls every scroll container around an element so the element is in view. Returns the containers that were scrolled right away, containers with `scroll-behavior: smooth` are scrolled by [`ScrollAnimations::step`].
+pub(crate) fn scroll_into_view(
+    rdom: &mut RealDom,
+    taffy: &Taffy,
+    animations: &mut ScrollAnimations,
+    id: NodeId,
+    alignment: ScrollAlignment,
+) -> FxHashSet<NodeId> {
+    let mut targets = Vec::new();
+    {
+        let Some(node) = rdom.get(id) else {
+            return FxHashSet::default();
+        };
+        let Some(layout) = layout_of(taffy, node) else {
+            return FxHashSet::default();
+        };
+        let size = layout.size;
+        // the position of the element relative to the content of the current ancestor
+        let mut position = Point::new(layout.location.x as f64, layout.location.y as f64);
+        let mut current = node;
+        while let Some(parent) = current.parent() {
+            let Some(parent_layout) = layout_of(taffy, parent) else {
+                break;
+            };
+            let overflow = parent
+                .get::<Overflow>()
+                .map(|overflow| *overflow)
+                .unwrap_or_default();
+            if overflow.scrolls_x() || overflow.scrolls_y() {
+                let old = scroll_offset(parent);
+                let x = alignment.offset(
+                    position.x,
+                    size.width as f64,
+                    parent_layout.size.width as f64,
+                    old.x,
+                );
+                let y = alignment.offset(
+                    position.y,
+                    size.height as f64,
+                    parent_layout.size.height as f64,
+                    old.y,
+                );
+                let target = clamp_offset(taffy, parent, Vec2::new(x, y));
+                targets.push((parent.id(), target));
+                position -= target;
+            }
+            position += Vec2::new(
+                parent_layout.location.x as f64,
+                parent_layout.location.y as f64,
+            );
+            current = parent;
+        }
+    }
+
+    let mut changed = FxHashSet::default();
+    for (container, target) in targets {
+        if animations.scroll_to(rdom, container, target) {
+            changed.insert(container);
+        }
+    }
+    changed
+}
+
+struct ScrollAnimation {
+    from: Vec2,
+    to: Vec2,
+    start: Instant,
+    /// The offset set by the last step. If the offset changed since then, the container was scrolled some other way.
+    last: Vec2,
+}
+
+/// The smooth scrolls that are running.
+#[derive(Default)]
+pub(crate) struct ScrollAnimations {
+    running: FxHashMap<NodeId, ScrollAnimation>,
+}
+
+impl ScrollAnimations {
+    /// Scrolls a container to an offset. Returns true if the offset changed right away instead of being animated.
+    fn scroll_to(&mut self, rdom: &mut RealDom, id: NodeId, target: Vec2) -> bool {
+        let Some(node) = rdom.get(id) else {
+            return false;
+        };
+        let old = scroll_offset(node);
+        let smooth = node.get::<SmoothScroll>().is_some_and(|smooth| smooth.0);
+        self.running.remove(&id);
+        if old == target {
+            return false;
+        }
+        if smooth {
+            self.running.insert(
+                id,
+                ScrollAnimation {
+                    from: old,
+                    to: target,
+                    start: Instant::now(),
+                    last: old,
+                },
+            );
+            false
+        } else {
+            rdom.get_mut(id).unwrap().insert(ScrollOffset(target));
+            true
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.running.is_empty()
+    }
+
+    /// Moves every running animation to its offset at `now`. Returns the containers that were scrolled.
+    pub fn step(&mut self, rdom: &mut RealDom, now: Instant) -> FxHashSet<NodeId> {
+        let mut changed = FxHashSet::default();
+        self.running.retain(|id, animation| {
+            let Some(node) = rdom.get(*id) else {
+                return false;
+            };
+            if scroll_offset(node) != animation.last {
+                return false;
+            }
+            let t = (now.saturating_duration_since(animation.start).as_secs_f64()
+                / SMOOTH_SCROLL_DURATION.as_secs_f64())
+            .min(1.0);
+            // ease out so the scroll slows down as it reaches the target
+            let progress = 1.0 - (1.0 - t).powi(3);
+            let offset = animation.from.lerp(animation.to, progress);
+            if offset != animation.last {
+                rdom.get_mut(*id).unwrap().insert(ScrollOffset(offset));
+                animation.last = offset;
+                changed.insert(*id);
+            }
+            t < 1.0
+        });
+        changed
+    }
+}
@@ -0,0 +1,324 @@
+//! Selecting text with the mouse.
+//!
+//! Pressing the primary button on text starts a selection and dragging extends it. The selection covers every text node between its two ends in document order, so it continues across elements with different styles and across lines. Each text node in the selection gets a [`TextSelection`] with the characters it covers, which are highlighted behind the glyphs when the text is rendered.
+//!
+//! Ctrl+C, or Cmd+C on macOS, copies the selected text to the clipboard as it is drawn.
+
+use std::ops::Range;
+
+use dioxus_native_core::prelude::*;
+use rustc_hash::FxHashSet;
+use shipyard::Component;
+use taffy::{prelude::Size, Taffy};
+use vello::kurbo::{Point, Rect};
+use vello::peniko::Color;
+
+use crate::application::DirtyNodes;
+use crate::layout::TaffyLayout;
+use crate::render::{align_line, get_abs_pos, get_content_box_x};
+use crate::scroll::scroll_offset;
+use crate::style::{FontFamily, FontSize};
+use crate::text::text_style::{LineHeight, TextAlign, TextSpacing, TextTransform, WhiteSpace};
+use crate::text::TextContext;
+use crate::util::attribute;
+use crate::writing_mode::WritingMode;
+
+/// Elements that are inline unless they set `display`. Text in them continues the line of the text around them when it is copied.
+const INLINE_ELEMENTS: &[&str] = &[
+    "a", "abbr", "b", "bdi", "bdo", "cite", "code", "data", "dfn", "em", "i", "kbd", "label",
+    "mark", "q", "s", "samp", "small", "span", "strong", "sub", "sup", "time", "u", "var",
+];
+
+/// The color selected text is highlighted with.
+pub(crate) const SELECTION_COLOR: Color = Color::rgba8(0, 120, 215, 96);
+
+/// The characters of a text node that are selected.
+#[derive(Clone, PartialEq, Debug, Component)]
+pub(crate) struct TextSelection(pub Range<usize>);
+
+/// A position between two characters of a text node.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) struct TextPosition {
+    pub node: NodeId,
+    /// The number of characters before the position.
+    pub offset: usize,
+}
+
+/// Tracks the selected text.
+///
+/// Like [`crate::mouse::PseudoClassState`], only the text nodes that enter or leave the selection are updated and marked dirty.
+#[derive(Default)]
+pub(crate) struct SelectionState {
+    anchor: Option<TextPosition>,
+    focus: Option<TextPosition>,
+    /// If the primary button is held after starting a selection.
+    dragging: bool,
+    selected: Vec<NodeId>,
+    dirty: FxHashSet<NodeId>,
+}
+
+impl SelectionState {
+    /// Starts a new selection at a position. Pressing outside of text clears the selection.
+    pub fn start(&mut self, rdom: &mut RealDom, position: Option<TextPosition>) {
+        self.anchor = position;
+        self.focus = position;
+        self.dragging = position.is_some();
+        self.update(rdom);
+    }
+
+    /// Moves the end of the selection while the button is held.
+    pub fn extend(&mut self, rdom: &mut RealDom, position: TextPosition) {
+        if self.dragging && self.focus != Some(position) {
+            self.focus = Some(position);
+            self.update(rdom);
+        }
+    }
+
+    pub fn end(&mut self) {
+        self.dragging = false;
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.dragging
+    }
+
+    fn update(&mut self, rdom: &mut RealDom) {
+        let ranges = match (self.anchor, self.focus) {
+            (Some(anchor), Some(focus)) => selected_ranges(rdom, anchor, focus),
+            _ => Vec::new(),
+        };
+        for id in std::mem::take(&mut self.selected) {
+            if !ranges.iter().any(|(selected, _)| *selected == id) {
+                // the node may have been removed since it was selected
+                if let Some(mut node) = rdom.get_mut(id) {
+                    node.insert(TextSelection(0..0));
+                    self.dirty.insert(id);
+                }
+            }
+        }
+        for (id, range) in ranges {
+            let mut node = rdom.get_mut(id).unwrap();
+            let new = TextSelection(range);
+            if node.get::<TextSelection>().as_deref() != Some(&new) {
+                node.insert(new);
+                self.dirty.insert(id);
+            }
+            self.selected.push(id);
+        }
+    }
+
+    /// Returns the selected text as it is drawn. Text in different blocks is separated by a line break, text in inline elements like `<b>` continues the line.
+    pub fn selected_text(&self, rdom: &RealDom) -> String {
+        let mut selected = String::new();
+        let mut last_block = None;
+        for id in &self.selected {
+            let Some(node) = rdom.get(*id) else {
+                continue;
+            };
+            let NodeType::Text(TextNode { text, .. }) = &*node.node_type() else {
+                continue;
+            };
+            let Some(range) = node
+                .get::<TextSelection>()
+                .map(|selection| selection.0.clone())
+            else {
+                continue;
+            };
+            // the offsets of the selection are counted in the drawn text
+            let text = node.get::<TextTransform>().unwrap().apply(text);
+            let text = node.get::<WhiteSpace>().unwrap().apply(&text);
+            let block = block_container(node);
+            if last_block.is_some() && last_block != Some(block) {
+                selected.push('\n');
+            }
+            last_block = Some(block);
+            selected.extend(text.chars().skip(range.start).take(range.len()));
+        }
+        selected
+    }
+
+    pub fn clean(&mut self) -> DirtyNodes {
+        DirtyNodes::Some(std::mem::take(&mut self.dirty))
+    }
+}
+
+/// Returns the nearest ancestor of a node that isn't inline. Text nodes with the same block container share lines.
+fn block_container(node: NodeRef) -> Option<NodeId> {
+    let mut ancestor = node.parent();
+    while let Some(element) = ancestor {
+        if !is_inline(element) {
+            return Some(element.id());
+        }
+        ancestor = element.parent();
+    }
+    None
+}
+
+/// Returns true if an element is inline from its `display` or its tag.
+fn is_inline(node: NodeRef) -> bool {
+    let tag = match &*node.node_type() {
+        NodeType::Element(element) => element.tag.clone(),
+        _ => return false,
+    };
+    match attribute(node, "display") {
+        Some(display) => display.trim().starts_with("inline"),
+        None => INLINE_ELEMENTS.contains(&tag.as_str()),
+    }
+}
+
+/// Returns the text nodes between two positions in document order and the characters of each that are selected.
+fn selected_ranges(
+    rdom: &RealDom,
+    anchor: TextPosition,
+    focus: TextPosition,
+) -> Vec<(NodeId, Range<usize>)> {
+    let mut text_nodes = Vec::new();
+    let mut stack = vec![rdom.root_id()];
+    while let Some(id) = stack.pop() {
+        let node = rdom.get(id).unwrap();
+        if let NodeType::Text(TextNode { text, .. }) = &*node.node_type() {
+            text_nodes.push((id, text.chars().count()));
+        }
+        // children are pushed in reverse so they are visited in order
+        stack.extend(node.child_ids().into_iter().rev());
+    }
+    let index = |position: TextPosition| text_nodes.iter().position(|(id, _)| *id == position.node);
+    let (Some(anchor_index), Some(focus_index)) = (index(anchor), index(focus)) else {
+        return Vec::new();
+    };
+    let (start, end) = if (anchor_index, anchor.offset) <= (focus_index, focus.offset) {
+        ((anchor_index, anchor), (focus_index, focus))
+    } else {
+        ((focus_index, focus), (anchor_index, anchor))
+    };
+    text_nodes[start.0..=end.0]
+        .iter()
+        .enumerate()
+        .map(|(i, (id, len))| {
+            let from = if i == 0 { start.1.offset } else { 0 };
+            let to = if start.0 + i == end.0 {
+                end.1.offset
+            } else {
+                *len
+            };
+            (*id, from.min(*len)..to.min(*len))
+        })
+        .filter(|(_, range)| !range.is_empty())
+        .collect()
+}
+
+/// Finds the text node under the cursor and the character boundary closest to it.
+pub(crate) fn text_position_at(
+    rdom: &RealDom,
+    taffy: &Taffy,
+    text_context: &TextContext,
+    viewport_size: &Size<u32>,
+    cursor: Point,
+) -> Option<TextPosition> {
+    let mut stack = vec![rdom.root_id()];
+    while let Some(id) = stack.pop() {
+        let node = rdom.get(id).unwrap();
+        stack.extend(node.child_ids());
+        let NodeType::Text(TextNode { text, .. }) = &*node.node_type() else {
+            continue;
+        };
+        let Some(taffy_node) = node.get::<TaffyLayout>().and_then(|layout| layout.node) else {
+            continue;
+        };
+        let layout = taffy.layout(taffy_node).unwrap();
+        let pos = get_abs_pos(*layout, taffy, node, viewport_size);
+        let rect = Rect::new(
+            pos.x,
+            pos.y,
+            pos.x + layout.size.width as f64,
+            pos.y + layout.size.height as f64,
+        );
+        if !rect.contains(cursor) {
+            continue;
+        }
+        let offset = offset_at(rdom, taffy, text_context, viewport_size, node, text, cursor);
+        return Some(TextPosition { node: id, offset });
+    }
+    None
+}
+
+/// Returns the character boundary in a text node that is closest to the cursor. Lines are placed the same way as when the text is rendered.
+fn offset_at(
+    rdom: &RealDom,
+    taffy: &Taffy,
+    text_context: &TextContext,
+    viewport_size: &Size<u32>,
+    node: NodeRef,
+    text: &str,
+    cursor: Point,
+) -> usize {
+    let layout = taffy
+        .layout(node.get::<TaffyLayout>().unwrap().node.unwrap())
+        .unwrap();
+    let pos = get_abs_pos(*layout, taffy, node, viewport_size);
+    let parent = node.parent().map(|parent| parent.id());
+    let content_box = match parent.and_then(|parent| rdom.get(parent)) {
+        Some(parent) => {
+            let parent_layout = taffy
+                .layout(parent.get::<TaffyLayout>().unwrap().node.unwrap())
+                .unwrap();
+            let parent_pos =
+                get_abs_pos(*parent_layout, taffy, parent, viewport_size) - scroll_offset(parent);
+            get_content_box_x(taffy, parent, parent_pos)
+        }
+        None => (pos.x, 0.0),
+    };
+
+    let font_size = node.get::<FontSize>().map_or(16.0, |size| size.0);
+    let text_align = *node.get::<TextAlign>().unwrap();
+    let font_family = node.get::<FontFamily>().unwrap();
+    let fonts = font_family.resolve(text_context);
+    let features = &font_family.features;
+    let line_height = node.get::<LineHeight>().unwrap().resolve(font_size);
+    let spacing = *node.get::<TextSpacing>().unwrap();
+    // offsets are counted in the drawn text, which differs when case mapping changes its length or whitespace collapses
+    let text = node.get::<TextTransform>().unwrap().apply(text);
+    let white_space = *node.get::<WhiteSpace>().unwrap();
+    let text = white_space.apply(&text);
+    let wrap_width = (white_space.wraps() && parent.is_some()).then_some(content_box.1);
+    let text_layout = text_context.layout(
+        &fonts,
+        features,
+        font_size,
+        &text,
+        line_height,
+        spacing,
+        wrap_width,
+    );
+
+    let line_index = (((cursor.y - pos.y) / text_layout.line_height).max(0.0) as usize)
+        .min(text_layout.lines.len().saturating_sub(1));
+    let line_start: usize = text_layout.lines[..line_index]
+        .iter()
+        .map(|line| line.text.chars().count() + 1)
+        .sum();
+    let Some(line) = text_layout.lines.get(line_index) else {
+        return 0;
+    };
+    let direction = node.get::<WritingMode>().unwrap().direction;
+    let alignment = text_align.line_alignment(line.ends_paragraph, direction);
+    let (x, word_spacing) = align_line(line, alignment, pos.x, content_box);
+    let offsets = text_context.caret_offsets(
+        &fonts,
+        features,
+        font_size,
+        &line.text,
+        spacing.with_extra_word_spacing(word_spacing),
+    );
+    // the boundary closest to the cursor
+    let in_line = offsets
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| {
+            (x + **a - cursor.x)
+                .abs()
+                .total_cmp(&(x + **b - cursor.x).abs())
+        })
+        .map_or(0, |(i, _)| i);
+    line_start + in_line
+}
@@ -0,0 +1,317 @@
+//! Stacking contexts and the `opacity` and `z-index` properties.
+//!
+//! Positioned elements with a `z-index`, elements with an `opacity` below 1, a `transform` or a `filter` create a stacking context. They are not painted with the rest of the content of their parent. Instead the nearest ancestor that creates a stacking context paints them sorted by `z-index`: negative ones after its own background and the others after its content. The `z-index` of their descendants only orders them within that context, so it can't move content out from under a translucent or transformed element. Like in CSS, `z-index` is ignored on elements with `position: static`, which is the default.
+
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use lightningcss::traits::Parse;
+use lightningcss::values::alpha::AlphaValue;
+use shipyard::Component;
+use taffy::prelude::Size;
+use taffy::Taffy;
+use vello::kurbo::{Point, RoundedRect, Vec2};
+
+use crate::layout::TaffyLayout;
+use crate::render::get_overflow_clip;
+use crate::scroll::scroll_offset;
+use crate::style::{Filter, Overflow};
+use crate::transform::Transform;
+
+/// The `opacity` property.
+#[derive(Clone, Copy, Debug, PartialEq, Component)]
+pub(crate) struct Opacity(pub f32);
+
+impl Default for Opacity {
+    fn default() -> Self {
+        Opacity(1.0)
+    }
+}
+
+#[partial_derive_state]
+impl State for Opacity {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&["opacity"]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let mut new = Opacity::default();
+        for attr in node_view.attributes().into_iter().flatten() {
+            if let Some(as_text) = attr.value.as_text() {
+                if let Ok(alpha) = AlphaValue::parse_string(as_text) {
+                    new = Opacity(alpha.0.clamp(0.0, 1.0));
+                }
+            }
+        }
+
+        if *self != new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+/// The `z-index` property. `None` is `auto`, or an element that isn't positioned.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Component)]
+pub(crate) struct ZIndex(pub Option<i32>);
+
+#[partial_derive_state]
+impl State for ZIndex {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&["z-index", "position"]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let mut new = ZIndex::default();
+        let mut positioned = false;
+        for attr in node_view.attributes().into_iter().flatten() {
+            match attr.attribute.name.as_str() {
+                "z-index" => {
+                    if let Some(index) = attr
+                        .value
+                        .as_int()
+                        .map(|index| index as i32)
+                        .or_else(|| attr.value.as_text().and_then(|v| v.trim().parse().ok()))
+                    {
+                        new = ZIndex(Some(index));
+                    }
+                }
+                "position" => {
+                    positioned = attr.value.as_text().is_some_and(|position| {
+                        matches!(
+                            position.trim(),
+                            "relative" | "absolute" | "fixed" | "sticky"
+                        )
+                    })
+                }
+                _ => {}
+            }
+        }
+        // the z-index only applies to positioned elements
+        if !positioned {
+            new = ZIndex(None);
+        }
+
+        if *self != new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+/// Returns the opacity of an element.
+pub(crate) fn opacity(node: NodeRef) -> f32 {
+    node.get::<Opacity>().map_or(1.0, |opacity| opacity.0)
+}
+
+/// Returns true if the element is painted as its own stacking context.
+pub(crate) fn creates_stacking_context(node: NodeRef) -> bool {
+    matches!(&*node.node_type(), NodeType::Element(_))
+        && (node.get::<ZIndex>().is_some_and(|z| z.0.is_some())
+            || opacity(node) < 1.0
+            || node.get::<Transform>().is_some_and(|t| !t.is_empty())
+            || node
+                .get::<Filter>()
+                .is_some_and(|f| !f.drop_shadows.is_empty()))
+}
+
+/// An element that is painted by the stacking context it belongs to instead of its parent.
+pub(crate) struct StackedElement {
+    pub z_index: i32,
+    pub node: NodeId,
+    /// The position the element is placed relative to, like the `location` passed to `render_node`.
+    pub location: Point,
+    /// The overflow clips of the ancestors between the element and the root of the stacking context.
+    pub clips: Vec<RoundedRect>,
+}
+
+/// Finds the elements that belong to the stacking context of `node`, sorted by `z-index`. `content_pos` is where the children of `node` are placed.
+pub(crate) fn stacked_descendants(
+    taffy: &Taffy,
+    node: NodeRef,
+    content_pos: Point,
+    viewport_size: &Size<u32>,
+) -> Vec<StackedElement> {
+    let mut stacked = Vec::new();
+    collect_stacked(
+        taffy,
+        node,
+        content_pos,
+        &mut Vec::new(),
+        viewport_size,
+        &mut stacked,
+    );
+    // the sort is stable, so elements with the same z-index are painted in tree order
+    stacked.sort_by_key(|element| element.z_index);
+    stacked
+}
+
+fn collect_stacked(
+    taffy: &Taffy,
+    node: NodeRef,
+    content_pos: Point,
+    clips: &mut Vec<RoundedRect>,
+    viewport_size: &Size<u32>,
+    stacked: &mut Vec<StackedElement>,
+) {
+    for child in node.children() {
+        if creates_stacking_context(child) {
+            stacked.push(StackedElement {
+                z_index: child.get::<ZIndex>().and_then(|z| z.0).unwrap_or(0),
+                node: child.id(),
+                location: content_pos,
+                clips: clips.clone(),
+            });
+            continue;
+        }
+        let Some(taffy_node) = child.get::<TaffyLayout>().and_then(|layout| layout.node) else {
+            continue;
+        };
+        let layout = taffy.layout(taffy_node).unwrap();
+        let pos = content_pos + Vec2::new(layout.location.x as f64, layout.location.y as f64);
+        let overflow = child.get::<Overflow>().map(|overflow| *overflow);
+        let clipped = overflow.filter(|overflow| overflow.clips_x() || overflow.clips_y());
+        if let Some(overflow) = &clipped {
+            clips.push(get_overflow_clip(
+                layout,
+                child,
+                pos,
+                overflow,
+                viewport_size,
+            ));
+        }
+        collect_stacked(
+            taffy,
+            child,
+            pos - scroll_offset(child),
+            clips,
+            viewport_size,
+            stacked,
+        );
+        if clipped.is_some() {
+            clips.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Adds a `div` with the given style attributes to `parent`.
+    fn element(rdom: &mut RealDom, parent: NodeId, style: &[(&str, &str)]) -> NodeId {
+        let id = rdom
+            .create_node(NodeType::Element(ElementNode {
+                tag: "div".to_string(),
+                attributes: style
+                    .iter()
+                    .map(|(name, value)| ((*name, "style").into(), value.to_string().into()))
+                    .collect(),
+                ..Default::default()
+            }))
+            .id();
+        rdom.get_mut(parent).unwrap().add_child(id);
+        id
+    }
+
+    #[test]
+    fn z_index_only_orders_elements_within_their_stacking_context() {
+        let mut rdom: RealDom = RealDom::new(vec![
+            Opacity::to_type_erased(),
+            ZIndex::to_type_erased(),
+            Transform::to_type_erased(),
+            Filter::to_type_erased(),
+        ]);
+        let root = rdom.root_id();
+        let translucent = element(
+            &mut rdom,
+            root,
+            &[
+                ("position", "relative"),
+                ("z-index", "1"),
+                ("opacity", "0.5"),
+            ],
+        );
+        let nested = element(
+            &mut rdom,
+            translucent,
+            &[("position", "relative"), ("z-index", "10")],
+        );
+        let above = element(
+            &mut rdom,
+            root,
+            &[("position", "relative"), ("z-index", "5")],
+        );
+        let transformed = element(&mut rdom, root, &[("transform", "translate(10px)")]);
+        let faded = element(&mut rdom, root, &[("opacity", "0.5")]);
+        let static_z_index = element(&mut rdom, above, &[("z-index", "20")]);
+        rdom.update_state(SendAnyMap::new());
+
+        let taffy = Taffy::new();
+        let stacked = |id: NodeId| {
+            let viewport_size = Size {
+                width: 800,
+                height: 600,
+            };
+            stacked_descendants(&taffy, rdom.get(id).unwrap(), Point::ZERO, &viewport_size)
+                .into_iter()
+                .map(|element| (element.node, element.z_index))
+                .collect::<Vec<_>>()
+        };
+        // the nested element is painted inside the translucent one, so its z-index can't lift it above the element with z-index 5
+        assert_eq!(
+            stacked(root),
+            vec![(transformed, 0), (faded, 0), (translucent, 1), (above, 5)]
+        );
+        assert_eq!(stacked(translucent), vec![(nested, 10)]);
+        // z-index is ignored on static elements
+        assert!(!creates_stacking_context(rdom.get(static_z_index).unwrap()));
+    }
+}
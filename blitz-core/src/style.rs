@@ -2,24 +2,55 @@ use cssparser::{Parser, ParserInput, RGBA};
 use dioxus_native_core::node::OwnedAttributeValue;
 use dioxus_native_core::prelude::*;
 use dioxus_native_core_macro::partial_derive_state;
+use lightningcss::printer::PrinterOptions;
+use lightningcss::properties::background::BackgroundClip as BackgroundClipProperty;
 use lightningcss::properties::border::BorderColor;
 use lightningcss::properties::border::BorderSideWidth;
 use lightningcss::properties::border::BorderWidth;
 use lightningcss::properties::border_radius::BorderRadius;
+use lightningcss::properties::box_shadow::BoxShadow as Shadow;
+use lightningcss::properties::effects::{DropShadow, Filter as FilterFunction, FilterList};
 use lightningcss::properties::font::AbsoluteFontSize;
+use lightningcss::properties::font::Font as FontShorthand;
 use lightningcss::properties::font::RelativeFontSize;
-use lightningcss::traits::Parse;
+use lightningcss::properties::outline::OutlineStyle;
+use lightningcss::properties::overflow::OverflowKeyword;
+use lightningcss::properties::text::TextShadow as TextShadowValue;
+use lightningcss::traits::{Parse, ToCss};
+use lightningcss::values::calc::{Calc, MathFunction};
 use lightningcss::values::color::CssColor;
-use lightningcss::values::length::LengthValue;
+use lightningcss::values::length::{Length, LengthPercentage, LengthValue};
 use lightningcss::values::percentage::DimensionPercentage;
+use lightningcss::values::size::Size2D;
 use lightningcss::{
     properties::font::FontSize as FontSizeProperty, properties::Property, stylesheet::ParserOptions,
 };
+use rustc_hash::FxHashSet;
 use shipyard::Component;
+use taffy::prelude::Size;
+use vello::peniko::Color;
+
+use crate::mouse::PseudoClasses;
+use crate::text::{
+    FontFeatures, FontSelection, FontStyle, FontUnits, GenericFamily, TextContext, TextRendering,
+};
+use crate::util::{translate_current_color, Axis, Resolve, ResolveContext, ViewportSize};
+use crate::writing_mode::{LogicalEdge::*, PhysicalCorner, WritingMode};
 
 #[derive(Clone, PartialEq, Debug, Component)]
 pub(crate) struct BackgroundColor(pub CssColor);
 
+/// The background of a `<button>` that doesn't set `background-color`.
+pub(crate) const BUTTON_BACKGROUND: RGBA = RGBA {
+    red: 239,
+    green: 239,
+    blue: 239,
+    alpha: 255,
+};
+
+/// The color of controls when `accent-color` is `auto`.
+pub(crate) const DEFAULT_ACCENT: Color = Color::rgb8(0, 117, 255);
+
 impl Default for BackgroundColor {
     fn default() -> Self {
         BackgroundColor(CssColor::RGBA(RGBA::new(255, 255, 255, 0)))
@@ -28,12 +59,101 @@ impl Default for BackgroundColor {
 
 #[partial_derive_state]
 impl State for BackgroundColor {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = (PseudoClasses,);
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new()
+            .with_tag()
+            .with_attrs(AttributeMaskBuilder::Some(&[
+                "background-color",
+                "hover-background-color",
+                "focus-background-color",
+                "active-background-color",
+            ]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        (pseudo_classes,): <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let mut new = if node_view.tag() == Some("button") {
+            // buttons that keep the default background are shaded while they are pressed or hovered
+            let background = if pseudo_classes.active {
+                RGBA::new(245, 245, 245, 255)
+            } else if pseudo_classes.hover {
+                RGBA::new(229, 229, 229, 255)
+            } else {
+                BUTTON_BACKGROUND
+            };
+            BackgroundColor(CssColor::RGBA(background))
+        } else {
+            BackgroundColor::default()
+        };
+        let color_attr = node_view
+            .attributes()
+            .into_iter()
+            .flatten()
+            .filter_map(|attr| {
+                let precedence =
+                    pseudo_classes.precedence(&attr.attribute.name, "background-color")?;
+                Some((precedence, attr.value.as_text()?))
+            })
+            .max_by_key(|(precedence, _)| *precedence);
+        if let Some((_, as_text)) = color_attr {
+            let mut value = ParserInput::new(as_text);
+            let mut parser = Parser::new(&mut value);
+            if let Ok(new_color) = CssColor::parse(&mut parser) {
+                new = BackgroundColor(new_color);
+            }
+        }
+
+        if self != &mut new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+/// The area the background is painted in.
+#[derive(Clone, Copy, PartialEq, Debug, Component)]
+pub(crate) struct BackgroundClip(pub BackgroundClipProperty);
+
+impl Default for BackgroundClip {
+    fn default() -> Self {
+        BackgroundClip(BackgroundClipProperty::BorderBox)
+    }
+}
+
+#[partial_derive_state]
+impl State for BackgroundClip {
     type ChildDependencies = ();
     type ParentDependencies = ();
     type NodeDependencies = ();
 
     const NODE_MASK: NodeMaskBuilder<'static> =
-        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&["background-color"]));
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&[
+            "background-clip",
+            "-webkit-background-clip",
+        ]));
 
     fn update<'a>(
         &mut self,
@@ -43,19 +163,23 @@ impl State for BackgroundColor {
         _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
         _: &SendAnyMap,
     ) -> bool {
-        if let Some(color_attr) = node_view.attributes().into_iter().flatten().next() {
-            if let Some(as_text) = color_attr.value.as_text() {
-                let mut value = ParserInput::new(as_text);
-                let mut parser = Parser::new(&mut value);
-                if let Ok(new_color) = CssColor::parse(&mut parser) {
-                    if self.0 != new_color {
-                        *self = Self(new_color);
-                        return true;
-                    }
+        let mut new = BackgroundClip::default();
+        for attr in node_view.attributes().into_iter().flatten() {
+            if let Some(as_text) = attr.value.as_text() {
+                // There is only one background layer, so only the first value is used
+                let first = as_text.split(',').next().unwrap_or_default();
+                if let Ok(clip) = BackgroundClipProperty::parse_string(first) {
+                    new = BackgroundClip(clip);
                 }
             }
         }
-        false
+
+        if self != &mut new {
+            *self = new;
+            true
+        } else {
+            false
+        }
     }
 
     fn create<'a>(
@@ -82,11 +206,84 @@ impl Default for ForgroundColor {
 
 #[partial_derive_state]
 impl State for ForgroundColor {
+    type ChildDependencies = ();
+    type ParentDependencies = (Self,);
+    type NodeDependencies = (PseudoClasses,);
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&[
+            "color",
+            "hover-color",
+            "focus-color",
+            "active-color",
+        ]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        (pseudo_classes,): <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let inherited = parent.map_or_else(Self::default, |(parent,)| parent.clone());
+        let new = node_view
+            .attributes()
+            .into_iter()
+            .flatten()
+            .filter_map(|attr| {
+                let precedence = pseudo_classes.precedence(&attr.attribute.name, "color")?;
+                Some((precedence, attr))
+            })
+            .max_by_key(|(precedence, _)| *precedence)
+            .and_then(|(_, color_attr)| {
+                let mut value = ParserInput::new(color_attr.value.as_text()?);
+                let mut parser = Parser::new(&mut value);
+                CssColor::parse(&mut parser).ok()
+            })
+            // `currentColor` in `color` is the inherited color, so the computed color is never `currentColor`
+            .filter(|color| *color != CssColor::CurrentColor)
+            .map_or(inherited, Self);
+
+        if *self != new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+/// The accent color of controls and focus rings. `None` is the `auto` value which uses the platform color.
+#[derive(Clone, PartialEq, Debug, Default, Component)]
+pub(crate) struct AccentColor(pub Option<CssColor>);
+
+impl AccentColor {
+    pub fn resolve(&self, current_color: &CssColor) -> Option<Color> {
+        self.0
+            .as_ref()
+            .map(|color| translate_current_color(color, current_color))
+    }
+}
+
+#[partial_derive_state]
+impl State for AccentColor {
     type ChildDependencies = ();
     type ParentDependencies = (Self,);
     type NodeDependencies = ();
     const NODE_MASK: NodeMaskBuilder<'static> =
-        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&["color"]));
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&["accent-color"]));
 
     fn update<'a>(
         &mut self,
@@ -96,26 +293,19 @@ impl State for ForgroundColor {
         _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
         _: &SendAnyMap,
     ) -> bool {
-        let new = if let Some(color_attr) = node_view.attributes().into_iter().flatten().next() {
-            if let Some(as_text) = color_attr.value.as_text() {
-                let mut value = ParserInput::new(as_text);
-                let mut parser = Parser::new(&mut value);
-                if let Ok(new_color) = CssColor::parse(&mut parser) {
-                    new_color
-                } else {
-                    return false;
+        let mut new = parent.map(|(parent,)| parent.clone()).unwrap_or_default();
+        if let Some(accent_attr) = node_view.attributes().into_iter().flatten().next() {
+            if let Some(as_text) = accent_attr.value.as_text() {
+                if as_text.trim() == "auto" {
+                    new = AccentColor(None);
+                } else if let Ok(color) = CssColor::parse_string(as_text) {
+                    new = AccentColor(Some(color));
                 }
-            } else {
-                return false;
             }
-        } else if let Some((parent,)) = parent {
-            parent.0.clone()
-        } else {
-            return false;
-        };
+        }
 
-        if self.0 != new {
-            *self = Self(new);
+        if self != &mut new {
+            *self = new;
             true
         } else {
             false
@@ -142,51 +332,108 @@ pub(crate) struct Border {
     pub radius: BorderRadius,
 }
 
+impl Border {
+    fn corner_radius(&mut self, corner: PhysicalCorner) -> &mut Size2D<LengthPercentage> {
+        match corner {
+            PhysicalCorner::TopLeft => &mut self.radius.top_left,
+            PhysicalCorner::TopRight => &mut self.radius.top_right,
+            PhysicalCorner::BottomRight => &mut self.radius.bottom_right,
+            PhysicalCorner::BottomLeft => &mut self.radius.bottom_left,
+        }
+    }
+
+    /// The border of a `<button>` before the border properties are applied.
+    fn button() -> Self {
+        let color = CssColor::RGBA(RGBA::new(118, 118, 118, 255));
+        let width = BorderSideWidth::Length(Length::Value(LengthValue::Px(2.0)));
+        let radius = Size2D(
+            LengthPercentage::Dimension(LengthValue::Px(3.0)),
+            LengthPercentage::Dimension(LengthValue::Px(3.0)),
+        );
+        Border {
+            colors: BorderColor {
+                top: color.clone(),
+                right: color.clone(),
+                bottom: color.clone(),
+                left: color,
+            },
+            width: BorderWidth {
+                top: width.clone(),
+                right: width.clone(),
+                bottom: width.clone(),
+                left: width,
+            },
+            radius: BorderRadius {
+                top_left: radius.clone(),
+                top_right: radius.clone(),
+                bottom_right: radius.clone(),
+                bottom_left: radius,
+            },
+        }
+    }
+}
+
 #[partial_derive_state]
 impl State for Border {
     type ChildDependencies = ();
     type ParentDependencies = ();
-    type NodeDependencies = ();
+    // Logical border radii depend on the writing mode
+    type NodeDependencies = (WritingMode,);
 
     const NODE_MASK: NodeMaskBuilder<'static> =
-        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&[
-            "border-color",
-            "border-top-color",
-            "border-right-color",
-            "border-bottom-color",
-            "border-left-color",
-            "border-radius",
-            "border-top-left-radius",
-            "border-top-right-radius",
-            "border-bottom-right-radius",
-            "border-bottom-left-radius",
-            "border-width",
-            "border-top-width",
-            "border-right-width",
-            "border-bottom-width",
-            "border-left-width",
-        ]));
+        NodeMaskBuilder::new()
+            .with_tag()
+            .with_attrs(AttributeMaskBuilder::Some(&[
+                "border-color",
+                "border-top-color",
+                "border-right-color",
+                "border-bottom-color",
+                "border-left-color",
+                "border-radius",
+                "border-top-left-radius",
+                "border-top-right-radius",
+                "border-bottom-right-radius",
+                "border-bottom-left-radius",
+                "border-start-start-radius",
+                "border-start-end-radius",
+                "border-end-start-radius",
+                "border-end-end-radius",
+                "border-width",
+                "border-top-width",
+                "border-right-width",
+                "border-bottom-width",
+                "border-left-width",
+            ]));
 
     fn update<'a>(
         &mut self,
         node_view: NodeView,
-        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        (writing_mode,): <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
         _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
         _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
         _: &SendAnyMap,
     ) -> bool {
-        let mut new = Border::default();
+        let mut new = if node_view.tag() == Some("button") {
+            Border::button()
+        } else {
+            Border::default()
+        };
         if let Some(attributes) = node_view.attributes() {
             for a in attributes {
-                let mut value = ParserInput::new(a.value.as_text().unwrap());
+                let Some(as_text) = a.value.as_text() else {
+                    continue;
+                };
+                let mut value = ParserInput::new(as_text);
                 let mut parser = Parser::new(&mut value);
-                match Property::parse(
+                // Declarations that fail to parse are skipped so the rest of the border still applies
+                let Ok(property) = Property::parse(
                     a.attribute.name.as_str().into(),
                     &mut parser,
                     &ParserOptions::default(),
-                )
-                .unwrap()
-                {
+                ) else {
+                    continue;
+                };
+                match property {
                     Property::BorderColor(c) => {
                         new.colors = c;
                     }
@@ -217,6 +464,18 @@ impl State for Border {
                     Property::BorderBottomLeftRadius(r, _) => {
                         new.radius.bottom_left = r;
                     }
+                    Property::BorderStartStartRadius(r) => {
+                        *new.corner_radius(writing_mode.corner(Start, Start)) = r;
+                    }
+                    Property::BorderStartEndRadius(r) => {
+                        *new.corner_radius(writing_mode.corner(Start, End)) = r;
+                    }
+                    Property::BorderEndStartRadius(r) => {
+                        *new.corner_radius(writing_mode.corner(End, Start)) = r;
+                    }
+                    Property::BorderEndEndRadius(r) => {
+                        *new.corner_radius(writing_mode.corner(End, End)) = r;
+                    }
                     Property::BorderWidth(width) => {
                         new.width = width;
                     }
@@ -278,54 +537,87 @@ impl Default for Border {
     }
 }
 
+/// The outline drawn outside of the border box.
+///
+/// `style` is `None` until `outline-style` (or the `outline` shorthand) is set. Focused elements without an outline style get the default focus ring, so setting an outline style themes the focus indicator and `outline-style: none` hides it.
 #[derive(Clone, PartialEq, Debug, Component)]
-pub(crate) struct FontSize(pub f32);
-pub const DEFAULT_FONT_SIZE: f32 = 16.0;
+pub(crate) struct Outline {
+    pub style: Option<OutlineStyle>,
+    pub width: BorderSideWidth,
+    pub color: CssColor,
+    pub offset: Length,
+}
 
-impl Default for FontSize {
+impl Default for Outline {
     fn default() -> Self {
-        FontSize(DEFAULT_FONT_SIZE)
+        Outline {
+            style: None,
+            width: BorderSideWidth::Medium,
+            color: CssColor::CurrentColor,
+            offset: Length::Value(LengthValue::Px(0.0)),
+        }
     }
 }
 
 #[partial_derive_state]
-impl State for FontSize {
+impl State for Outline {
     type ChildDependencies = ();
-    type ParentDependencies = (Self,);
+    type ParentDependencies = ();
     type NodeDependencies = ();
 
     const NODE_MASK: NodeMaskBuilder<'static> =
-        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&["font-size"]));
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&[
+            "outline",
+            "outline-color",
+            "outline-style",
+            "outline-width",
+            "outline-offset",
+        ]));
 
     fn update<'a>(
         &mut self,
         node_view: NodeView,
         _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
-        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
         _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
         _: &SendAnyMap,
     ) -> bool {
-        let new = if let Some(size_attr) = node_view.attributes().into_iter().flatten().next() {
-            let parent_size = if let Some(parent_size) = parent {
-                parent_size.0 .0.clone()
-            } else {
-                DEFAULT_FONT_SIZE
+        let mut new = Outline::default();
+        for attr in node_view.attributes().into_iter().flatten() {
+            let Some(as_text) = attr.value.as_text() else {
+                continue;
             };
-            if let Some(font_size) =
-                parse_font_size_from_attr(size_attr.value, parent_size, DEFAULT_FONT_SIZE)
-            {
-                font_size
-            } else {
-                DEFAULT_FONT_SIZE
+            // lightningcss doesn't know about outline-offset
+            if attr.attribute.name == "outline-offset" {
+                if let Ok(offset) = Length::parse_string(as_text) {
+                    new.offset = offset;
+                }
+                continue;
             }
-        } else if let Some(parent_size) = parent {
-            parent_size.0 .0.clone()
-        } else {
-            return false;
-        };
+            let mut value = ParserInput::new(as_text);
+            let mut parser = Parser::new(&mut value);
+            let Ok(property) = Property::parse(
+                attr.attribute.name.as_str().into(),
+                &mut parser,
+                &ParserOptions::default(),
+            ) else {
+                continue;
+            };
+            match property {
+                Property::Outline(outline) => {
+                    new.style = Some(outline.style);
+                    new.width = outline.width;
+                    new.color = outline.color;
+                }
+                Property::OutlineColor(color) => new.color = color,
+                Property::OutlineStyle(style) => new.style = Some(style),
+                Property::OutlineWidth(width) => new.width = width,
+                _ => {}
+            }
+        }
 
-        if self.0 != new {
-            *self = Self(new);
+        if self != &mut new {
+            *self = new;
             true
         } else {
             false
@@ -345,52 +637,1284 @@ impl State for FontSize {
     }
 }
 
-fn parse_font_size_from_attr(
-    css_value: &OwnedAttributeValue,
-    parent_font_size: f32,
-    root_font_size: f32,
-) -> Option<f32> {
-    match css_value {
-        OwnedAttributeValue::Text(n) => {
-            // css font-size parse.
-            // not support
-            // 1. calc,
-            // 3. relative font size. (smaller, larger)
-            match FontSizeProperty::parse_string(n) {
-                Ok(FontSizeProperty::Length(length)) => match length {
-                    DimensionPercentage::Dimension(l) => match l {
-                        LengthValue::Rem(v) => Some(v * root_font_size),
-                        LengthValue::Em(v) => Some(v * parent_font_size),
-                        _ => l.to_px().map(|v| v as f32),
-                    },
-                    // same with em.
-                    DimensionPercentage::Percentage(p) => Some(p.0 * parent_font_size),
-                    DimensionPercentage::Calc(_c) => None,
-                },
-                Ok(FontSizeProperty::Absolute(abs_val)) => {
-                    let factor = match abs_val {
-                        AbsoluteFontSize::XXSmall => 0.6,
-                        AbsoluteFontSize::XSmall => 0.75,
-                        AbsoluteFontSize::Small => 0.89, // 8/9
-                        AbsoluteFontSize::Medium => 1.0,
-                        AbsoluteFontSize::Large => 1.25,
-                        AbsoluteFontSize::XLarge => 1.5,
-                        AbsoluteFontSize::XXLarge => 2.0,
-                    };
-                    Some(factor * root_font_size)
+/// The `font-family` list. Named families are looked up in the fonts registered with [`crate::Config::with_font_file`] and the generic family is used if none of them are loaded. Code elements default to monospace.
+///
+/// It also holds the OpenType features set by `font-feature-settings`, `font-variant-ligatures` and `text-rendering`, and the weight, style and width set by `font-weight`, `font-style` and `font-stretch`, which are inherited separately from the family. `b` and `strong` are bold and `i` and `em` are italic by default.
+#[derive(Clone, PartialEq, Debug, Default, Component)]
+pub(crate) struct FontFamily {
+    /// Family names in order of preference, in lowercase.
+    pub names: Vec<String>,
+    pub generic: GenericFamily,
+    pub features: FontFeatures,
+    pub style: FontStyle,
+}
+
+impl FontFamily {
+    /// Returns the faces of the first named fonts that are registered or installed, then the installed font of the generic family.
+    pub fn resolve(&self, text_context: &TextContext) -> FontSelection {
+        text_context.fonts(&self.names, self.generic, self.style)
+    }
+}
+
+/// Elements that are bold by default.
+const BOLD_ELEMENTS: &[&str] = &["b", "strong"];
+
+/// Elements that are italic by default.
+const ITALIC_ELEMENTS: &[&str] = &["i", "em"];
+
+/// Elements that are rendered in a monospace font by default.
+const MONOSPACE_ELEMENTS: &[&str] = &["pre", "code", "kbd", "samp", "tt", "listing", "xmp"];
+
+/// Parses a `font-family` list like `"Fira Code", monospace`. The features and style are kept from `inherited`.
+fn parse_font_family(text: &str, inherited: &FontFamily) -> FontFamily {
+    let mut family = FontFamily {
+        names: Vec::new(),
+        generic: GenericFamily::SansSerif,
+        features: inherited.features.clone(),
+        style: inherited.style,
+    };
+    for name in text.split(',') {
+        let name = name.trim().trim_matches(|c| c == '"' || c == '\'');
+        match name.to_lowercase().as_str() {
+            "" => {}
+            "monospace" | "ui-monospace" => family.generic = GenericFamily::Monospace,
+            "serif" | "ui-serif" => family.generic = GenericFamily::Serif,
+            // the bundled font is used for every other generic family
+            "sans-serif" | "ui-sans-serif" | "cursive" | "fantasy" | "system-ui" => {}
+            name => family.names.push(name.to_string()),
+        }
+    }
+    family
+}
+
+/// Parses `font-feature-settings` like `"smcp", "liga" off, "salt" 2`.
+fn parse_font_feature_settings(text: &str) -> Option<Vec<([u8; 4], u32)>> {
+    let mut input = ParserInput::new(text);
+    let mut parser = Parser::new(&mut input);
+    if parser
+        .try_parse(|parser| parser.expect_ident_matching("normal"))
+        .is_ok()
+    {
+        return Some(Vec::new());
+    }
+    parser
+        .parse_comma_separated(|parser| {
+            let location = parser.current_source_location();
+            let tag = parser.expect_string()?.to_string();
+            let tag: [u8; 4] = tag
+                .as_bytes()
+                .try_into()
+                .map_err(|_| location.new_custom_error::<_, ()>(()))?;
+            let value = if let Ok(value) = parser.try_parse(|parser| parser.expect_integer()) {
+                value.max(0) as u32
+            } else if let Ok(ident) = parser.try_parse(|parser| parser.expect_ident_cloned()) {
+                match &*ident {
+                    "on" => 1,
+                    "off" => 0,
+                    _ => return Err(location.new_custom_error(())),
                 }
-                Ok(FontSizeProperty::Relative(rel_val)) => {
-                    let factor = match rel_val {
-                        RelativeFontSize::Smaller => 0.8,
-                        RelativeFontSize::Larger => 1.25,
-                    };
-                    Some(factor * parent_font_size)
+            } else {
+                1
+            };
+            Ok((tag, value))
+        })
+        .ok()
+}
+
+/// Parses `font-variant-ligatures` into the features it turns on or off.
+fn parse_font_variant_ligatures(text: &str) -> Option<Vec<([u8; 4], u32)>> {
+    let mut features = Vec::new();
+    for keyword in text.split_whitespace() {
+        match keyword {
+            "normal" => {}
+            "none" => {
+                for tag in [b"liga", b"clig", b"dlig", b"hlig", b"calt"] {
+                    features.push((*tag, 0));
                 }
-                _ => None,
             }
+            "common-ligatures" => features.extend([(*b"liga", 1), (*b"clig", 1)]),
+            "no-common-ligatures" => features.extend([(*b"liga", 0), (*b"clig", 0)]),
+            "discretionary-ligatures" => features.push((*b"dlig", 1)),
+            "no-discretionary-ligatures" => features.push((*b"dlig", 0)),
+            "historical-ligatures" => features.push((*b"hlig", 1)),
+            "no-historical-ligatures" => features.push((*b"hlig", 0)),
+            "contextual" => features.push((*b"calt", 1)),
+            "no-contextual" => features.push((*b"calt", 0)),
+            _ => return None,
         }
-        OwnedAttributeValue::Float(n) => Some(n.to_owned() as f32),
-        OwnedAttributeValue::Int(n) => Some(n.to_owned() as f32),
+    }
+    Some(features)
+}
+
+/// Returns the weight, style and width set by the `font` shorthand. Parts that are left out are reset to their initial values. The line height is applied by [`LineHeight`](crate::text::text_style::LineHeight).
+fn shorthand_style(font: &FontShorthand, inherited_weight: u16) -> FontStyle {
+    let initial = FontStyle::default();
+    let weight = font.weight.to_css_string(PrinterOptions::default()).ok();
+    let style = font.style.to_css_string(PrinterOptions::default()).ok();
+    let stretch = font.stretch.to_css_string(PrinterOptions::default()).ok();
+    FontStyle {
+        weight: weight
+            .and_then(|weight| parse_font_weight(&weight, inherited_weight))
+            .unwrap_or(initial.weight),
+        italic: style
+            .and_then(|style| parse_font_style(&style))
+            .unwrap_or(initial.italic),
+        stretch: stretch
+            .and_then(|stretch| parse_font_stretch(&stretch))
+            .unwrap_or(initial.stretch),
+    }
+}
+
+/// Parses `font-weight`. `bolder` and `lighter` are relative to the inherited weight.
+pub(crate) fn parse_font_weight(text: &str, inherited: u16) -> Option<u16> {
+    match text.trim() {
+        "normal" => Some(400),
+        "bold" => Some(700),
+        "bolder" => Some(match inherited {
+            0..=349 => 400,
+            350..=549 => 700,
+            550..=899 => 900,
+            weight => weight,
+        }),
+        "lighter" => Some(match inherited {
+            0..=99 => inherited,
+            100..=549 => 100,
+            550..=749 => 400,
+            _ => 700,
+        }),
+        number => number
+            .parse::<f32>()
+            .ok()
+            .filter(|weight| (1.0..=1000.0).contains(weight))
+            .map(|weight| weight.round() as u16),
+    }
+}
+
+/// Parses `font-style`. Oblique text is drawn like italic text whatever its angle.
+pub(crate) fn parse_font_style(text: &str) -> Option<bool> {
+    let text = text.trim();
+    match text {
+        "normal" => Some(false),
+        "italic" => Some(true),
+        _ => text.starts_with("oblique").then_some(true),
+    }
+}
+
+/// Parses `font-stretch` into a fraction of the normal width.
+fn parse_font_stretch(text: &str) -> Option<f32> {
+    let stretch = match text.trim() {
+        "ultra-condensed" => 0.5,
+        "extra-condensed" => 0.625,
+        "condensed" => 0.75,
+        "semi-condensed" => 0.875,
+        "normal" => 1.0,
+        "semi-expanded" => 1.125,
+        "expanded" => 1.25,
+        "extra-expanded" => 1.5,
+        "ultra-expanded" => 2.0,
+        percentage => {
+            let percentage: f32 = percentage.strip_suffix('%')?.trim().parse().ok()?;
+            if percentage < 0.0 {
+                return None;
+            }
+            percentage / 100.0
+        }
+    };
+    Some(stretch)
+}
+
+fn parse_text_rendering(text: &str) -> Option<TextRendering> {
+    match text.trim().to_lowercase().as_str() {
+        "auto" => Some(TextRendering::Auto),
+        "optimizespeed" => Some(TextRendering::OptimizeSpeed),
+        "optimizelegibility" => Some(TextRendering::OptimizeLegibility),
+        "geometricprecision" => Some(TextRendering::GeometricPrecision),
         _ => None,
     }
 }
+
+#[partial_derive_state]
+impl State for FontFamily {
+    type ChildDependencies = ();
+    type ParentDependencies = (Self,);
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new()
+            .with_tag()
+            .with_attrs(AttributeMaskBuilder::Some(&[
+                "font-family",
+                "font-feature-settings",
+                "font-variant-ligatures",
+                "text-rendering",
+                "font-weight",
+                "font-style",
+                "font-stretch",
+                "font",
+            ]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let mut new = parent.map(|(parent,)| parent.clone()).unwrap_or_default();
+        // bolder and lighter are relative to the weight of the parent
+        let inherited_weight = new.style.weight;
+        if let Some(tag) = node_view.tag() {
+            if BOLD_ELEMENTS.contains(&tag) {
+                new.style.weight = 700;
+            }
+            if ITALIC_ELEMENTS.contains(&tag) {
+                new.style.italic = true;
+            }
+        }
+        // the shorthand resets the properties it covers, and the longhands are applied after it
+        let shorthand = node_view
+            .attributes()
+            .into_iter()
+            .flatten()
+            .find(|attr| attr.attribute.name == "font")
+            .and_then(|attr| attr.value.as_text())
+            .and_then(|text| FontShorthand::parse_string(text).ok());
+        if let Some(font) = &shorthand {
+            new.style = shorthand_style(font, inherited_weight);
+            new.features.ligatures = Vec::new();
+        }
+        for attr in node_view.attributes().into_iter().flatten() {
+            if let Some(as_text) = attr.value.as_text() {
+                match attr.attribute.name.as_str() {
+                    "font-weight" => {
+                        if let Some(weight) = parse_font_weight(as_text, inherited_weight) {
+                            new.style.weight = weight;
+                        }
+                    }
+                    "font-style" => {
+                        if let Some(italic) = parse_font_style(as_text) {
+                            new.style.italic = italic;
+                        }
+                    }
+                    "font-stretch" => {
+                        if let Some(stretch) = parse_font_stretch(as_text) {
+                            new.style.stretch = stretch;
+                        }
+                    }
+                    "font-feature-settings" => {
+                        if let Some(settings) = parse_font_feature_settings(as_text) {
+                            new.features.settings = settings;
+                        }
+                    }
+                    "font-variant-ligatures" => {
+                        if let Some(ligatures) = parse_font_variant_ligatures(as_text) {
+                            new.features.ligatures = ligatures;
+                        }
+                    }
+                    "text-rendering" => {
+                        if let Some(rendering) = parse_text_rendering(as_text) {
+                            new.features.rendering = rendering;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if let Some(tag) = node_view.tag() {
+            if MONOSPACE_ELEMENTS.contains(&tag) {
+                new = FontFamily {
+                    names: Vec::new(),
+                    generic: GenericFamily::Monospace,
+                    ..new
+                };
+            }
+        }
+        let family_attr = node_view
+            .attributes()
+            .into_iter()
+            .flatten()
+            .find(|attr| attr.attribute.name == "font-family");
+        if let Some(as_text) = family_attr.and_then(|attr| attr.value.as_text()) {
+            new = parse_font_family(as_text, &new);
+        } else if let Some(font) = &shorthand {
+            let families: Vec<String> = font
+                .family
+                .iter()
+                .filter_map(|family| family.to_css_string(PrinterOptions::default()).ok())
+                .collect();
+            new = parse_font_family(&families.join(","), &new);
+        }
+
+        if self != &mut new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Component)]
+pub(crate) struct FontSize(pub f32);
+pub const DEFAULT_FONT_SIZE: f32 = 16.0;
+
+impl Default for FontSize {
+    fn default() -> Self {
+        FontSize(DEFAULT_FONT_SIZE)
+    }
+}
+
+/// The font size of the root element, which `rem` is relative to. It is computed before the state passes run and passed to them in the context.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct RootFontSize {
+    /// The top level element of the document. `rem` in its own font size is relative to the initial font size.
+    pub element: Option<NodeId>,
+    pub size: f32,
+}
+
+impl Default for RootFontSize {
+    fn default() -> Self {
+        RootFontSize {
+            element: None,
+            size: DEFAULT_FONT_SIZE,
+        }
+    }
+}
+
+impl RootFontSize {
+    /// Computes the font size of the first element in the document.
+    pub fn compute(rdom: &RealDom, viewport_size: &Size<u32>) -> Self {
+        let root = rdom.get(rdom.root_id()).unwrap();
+        let Some(element) = root
+            .children()
+            .into_iter()
+            .find(|child| matches!(&*child.node_type(), NodeType::Element(_)))
+        else {
+            return Self::default();
+        };
+        let size = match &*element.node_type() {
+            NodeType::Element(ElementNode { attributes, .. }) => {
+                let find = |name: &str| {
+                    attributes
+                        .iter()
+                        .find(|(attribute, _)| attribute.name == name)
+                        .map(|(_, value)| value)
+                };
+                compute_font_size(
+                    find("font-size"),
+                    find("font"),
+                    None,
+                    DEFAULT_FONT_SIZE,
+                    viewport_size,
+                )
+            }
+            _ => None,
+        };
+        RootFontSize {
+            element: Some(element.id()),
+            size: size.unwrap_or(DEFAULT_FONT_SIZE),
+        }
+    }
+
+    /// The size `rem` is relative to in the font size of a node.
+    fn for_font_size_of(&self, id: NodeId) -> f32 {
+        if self.element == Some(id) {
+            DEFAULT_FONT_SIZE
+        } else {
+            self.size
+        }
+    }
+}
+
+/// The font size of the root element once the state passes ran, which `rem` is relative to outside of font sizes.
+pub(crate) fn root_font_size(rdom: &RealDom) -> f32 {
+    rdom.get(rdom.root_id())
+        .and_then(|root| {
+            root.children()
+                .into_iter()
+                .find(|child| matches!(&*child.node_type(), NodeType::Element(_)))
+        })
+        .and_then(|element| element.get::<FontSize>().map(|font_size| font_size.0))
+        .unwrap_or(DEFAULT_FONT_SIZE)
+}
+
+#[partial_derive_state]
+impl State for FontSize {
+    type ChildDependencies = ();
+    type ParentDependencies = (Self,);
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&["font-size", "font"]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> bool {
+        let viewport_size = context
+            .get::<ViewportSize>()
+            .map(|viewport| viewport.0)
+            .unwrap_or(Size {
+                width: 0,
+                height: 0,
+            });
+        let root_font_size = context
+            .get::<RootFontSize>()
+            .copied()
+            .unwrap_or_default()
+            .for_font_size_of(node_view.node_id());
+        let parent_size = parent.map(|(parent,)| parent.0);
+        let attrs: Vec<_> = node_view.attributes().into_iter().flatten().collect();
+        let find = |name: &str| {
+            attrs
+                .iter()
+                .find(|attr| attr.attribute.name == name)
+                .map(|attr| attr.value)
+        };
+        let Some(new) = compute_font_size(
+            find("font-size"),
+            find("font"),
+            parent_size,
+            root_font_size,
+            &viewport_size,
+        ) else {
+            return false;
+        };
+
+        if self.0 != new {
+            *self = Self(new);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+/// Computes the font size of a node from its `font-size` attribute, or the size in its `font` shorthand, and the font size of its parent. Percentages, `em` and the relative keywords are relative to the parent, `rem` is relative to `root_font_size` and the absolute keywords are relative to `medium`. An invalid size is ignored, so the node keeps the size of its parent. Returns `None` for the root if it doesn't set a font size.
+fn compute_font_size(
+    size_attr: Option<&OwnedAttributeValue>,
+    font_attr: Option<&OwnedAttributeValue>,
+    parent_size: Option<f32>,
+    root_font_size: f32,
+    viewport_size: &Size<u32>,
+) -> Option<f32> {
+    let parent_font_size = parent_size.unwrap_or(DEFAULT_FONT_SIZE);
+    // system fonts like `caption` aren't parsed, so the shorthand is ignored for them
+    let shorthand = font_attr
+        .and_then(|font| font.as_text())
+        .and_then(|text| FontShorthand::parse_string(text).ok());
+    match (size_attr, shorthand) {
+        (Some(size_attr), _) => Some(
+            parse_font_size_from_attr(size_attr, parent_font_size, root_font_size, viewport_size)
+                .unwrap_or(parent_font_size),
+        ),
+        (None, Some(font)) => Some(
+            resolve_font_size(&font.size, parent_font_size, root_font_size, viewport_size)
+                .unwrap_or(parent_font_size),
+        ),
+        (None, None) => parent_size,
+    }
+}
+
+/// Computes the font size of every node again for a new viewport size or root font size. The state pass only runs when the attributes of a node change, so font sizes that use viewport units are updated here when the window is resized and sizes in `rem` when the font size of the root element changes. Returns the nodes whose font size changed.
+pub(crate) fn update_font_sizes(
+    rdom: &mut RealDom,
+    viewport_size: &Size<u32>,
+    root_font_size: &RootFontSize,
+) -> FxHashSet<NodeId> {
+    let mut changed = Vec::new();
+    let mut stack = vec![(rdom.root_id(), None)];
+    while let Some((id, parent_size)) = stack.pop() {
+        let node = rdom.get(id).unwrap();
+        let size = match &*node.node_type() {
+            NodeType::Element(ElementNode { attributes, .. }) => {
+                let find = |name: &str| {
+                    attributes
+                        .iter()
+                        .find(|(attribute, _)| attribute.name == name)
+                        .map(|(_, value)| value)
+                };
+                compute_font_size(
+                    find("font-size"),
+                    find("font"),
+                    parent_size,
+                    root_font_size.for_font_size_of(id),
+                    viewport_size,
+                )
+            }
+            _ => parent_size,
+        };
+        let old = node.get::<FontSize>().map(|font_size| font_size.0);
+        if let Some(size) = size {
+            if old != Some(size) {
+                changed.push((id, size));
+            }
+        }
+        for child in node.child_ids() {
+            stack.push((child, size.or(old)));
+        }
+    }
+    let mut changed_ids = FxHashSet::default();
+    for (id, size) in changed {
+        rdom.get_mut(id).unwrap().insert(FontSize(size));
+        changed_ids.insert(id);
+    }
+    changed_ids
+}
+
+fn parse_font_size_from_attr(
+    css_value: &OwnedAttributeValue,
+    parent_font_size: f32,
+    root_font_size: f32,
+    viewport_size: &Size<u32>,
+) -> Option<f32> {
+    match css_value {
+        OwnedAttributeValue::Text(n) => FontSizeProperty::parse_string(n).ok().and_then(|size| {
+            resolve_font_size(&size, parent_font_size, root_font_size, viewport_size)
+        }),
+        OwnedAttributeValue::Float(n) => Some(n.to_owned() as f32),
+        OwnedAttributeValue::Int(n) => Some(n.to_owned() as f32),
+        _ => None,
+    }
+}
+
+fn resolve_font_size(
+    size: &FontSizeProperty,
+    parent_font_size: f32,
+    root_font_size: f32,
+    viewport_size: &Size<u32>,
+) -> Option<f32> {
+    match size {
+        FontSizeProperty::Length(length) => {
+            resolve_font_relative(length, parent_font_size, root_font_size, viewport_size)
+        }
+        FontSizeProperty::Absolute(abs_val) => {
+            let factor = match abs_val {
+                AbsoluteFontSize::XXSmall => 0.6,
+                AbsoluteFontSize::XSmall => 0.75,
+                AbsoluteFontSize::Small => 0.89, // 8/9
+                AbsoluteFontSize::Medium => 1.0,
+                AbsoluteFontSize::Large => 1.25,
+                AbsoluteFontSize::XLarge => 1.5,
+                AbsoluteFontSize::XXLarge => 2.0,
+            };
+            // `medium` is the initial font size, not the font size of the root element
+            Some(factor * DEFAULT_FONT_SIZE)
+        }
+        FontSizeProperty::Relative(rel_val) => {
+            let factor = match rel_val {
+                RelativeFontSize::Smaller => 0.8,
+                RelativeFontSize::Larger => 1.25,
+            };
+            Some(factor * parent_font_size)
+        }
+    }
+}
+
+/// Resolves a length where `em` and percentages are relative to `font_size`.
+pub(crate) fn resolve_font_relative(
+    length: &LengthPercentage,
+    font_size: f32,
+    root_font_size: f32,
+    viewport_size: &Size<u32>,
+) -> Option<f32> {
+    match length {
+        DimensionPercentage::Dimension(l) => {
+            resolve_font_length(l, font_size, root_font_size, viewport_size)
+        }
+        // percentages are relative to the font size like em
+        DimensionPercentage::Percentage(p) => Some(p.0 * font_size),
+        DimensionPercentage::Calc(c) => {
+            resolve_font_calc(c, font_size, root_font_size, viewport_size)
+        }
+    }
+}
+
+/// Resolves a length in a font size. `em` and percentages are relative to the font size of the parent.
+fn resolve_font_length(
+    length: &LengthValue,
+    parent_font_size: f32,
+    root_font_size: f32,
+    viewport_size: &Size<u32>,
+) -> Option<f32> {
+    match length {
+        LengthValue::Rem(v) => Some(v * root_font_size),
+        LengthValue::Em(v) => Some(v * parent_font_size),
+        // the metrics of the parent font aren't known while the font size is computed
+        LengthValue::Ch(v) => Some(v * FontUnits::approximate(parent_font_size).ch),
+        LengthValue::Ex(v) => Some(v * FontUnits::approximate(parent_font_size).ex),
+        LengthValue::Vw(_) | LengthValue::Vh(_) | LengthValue::Vmin(_) | LengthValue::Vmax(_) => {
+            let rect = Size {
+                width: 0.0,
+                height: 0.0,
+            };
+            let context = ResolveContext {
+                viewport_size: *viewport_size,
+                font_size: parent_font_size,
+                root_font_size,
+                font_units: FontUnits::approximate(parent_font_size),
+            };
+            Some(length.resolve(Axis::Min, &rect, &context) as f32)
+        }
+        _ => length.to_px(),
+    }
+}
+
+/// Resolves `calc()`, `min()`, `max()` and `clamp()` in a font size.
+fn resolve_font_calc(
+    calc: &Calc<DimensionPercentage<LengthValue>>,
+    parent_font_size: f32,
+    root_font_size: f32,
+    viewport_size: &Size<u32>,
+) -> Option<f32> {
+    let resolve = |calc: &Calc<DimensionPercentage<LengthValue>>| {
+        resolve_font_calc(calc, parent_font_size, root_font_size, viewport_size)
+    };
+    let resolve_value = |value: &DimensionPercentage<LengthValue>| match value {
+        DimensionPercentage::Dimension(length) => {
+            resolve_font_length(length, parent_font_size, root_font_size, viewport_size)
+        }
+        DimensionPercentage::Percentage(p) => Some(p.0 * parent_font_size),
+        DimensionPercentage::Calc(calc) => resolve(calc),
+    };
+    match calc {
+        Calc::Value(value) => resolve_value(value),
+        Calc::Number(number) => Some(*number),
+        Calc::Sum(a, b) => Some(resolve(a)? + resolve(b)?),
+        Calc::Product(factor, value) => Some(factor * resolve(value)?),
+        Calc::Function(function) => match &**function {
+            MathFunction::Calc(calc) => resolve(calc),
+            MathFunction::Min(values) => values
+                .iter()
+                .map(resolve)
+                .collect::<Option<Vec<_>>>()?
+                .into_iter()
+                .reduce(f32::min),
+            MathFunction::Max(values) => values
+                .iter()
+                .map(resolve)
+                .collect::<Option<Vec<_>>>()?
+                .into_iter()
+                .reduce(f32::max),
+            MathFunction::Clamp(min, value, max) => {
+                Some(resolve(value)?.min(resolve(max)?).max(resolve(min)?))
+            }
+            _ => None,
+        },
+    }
+}
+
+/// The `box-shadow` property. Shadows are listed in the order they are declared, the first one is painted on top.
+#[derive(Clone, PartialEq, Debug, Default, Component)]
+pub(crate) struct BoxShadow {
+    pub shadows: Vec<Shadow>,
+}
+
+#[partial_derive_state]
+impl State for BoxShadow {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&["box-shadow"]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let mut new = BoxShadow::default();
+        for attr in node_view.attributes().into_iter().flatten() {
+            if let Some(as_text) = attr.value.as_text() {
+                let mut input = ParserInput::new(as_text);
+                let mut parser = Parser::new(&mut input);
+                if let Ok(shadows) = parser.parse_comma_separated(Shadow::parse) {
+                    new.shadows = shadows;
+                }
+            }
+        }
+
+        if self != &mut new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+/// The `text-shadow` property. It is inherited, so text nodes get the shadows of their element. The first shadow is painted on top.
+#[derive(Clone, PartialEq, Debug, Default, Component)]
+pub(crate) struct TextShadow {
+    pub shadows: Vec<TextShadowValue>,
+}
+
+#[partial_derive_state]
+impl State for TextShadow {
+    type ChildDependencies = ();
+    type ParentDependencies = (Self,);
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&["text-shadow"]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let mut new = parent.map_or_else(Self::default, |(parent,)| parent.clone());
+        for attr in node_view.attributes().into_iter().flatten() {
+            if let Some(as_text) = attr.value.as_text() {
+                if as_text.trim().eq_ignore_ascii_case("none") {
+                    new.shadows.clear();
+                    continue;
+                }
+                let mut input = ParserInput::new(as_text);
+                let mut parser = Parser::new(&mut input);
+                if let Ok(shadows) = parser.parse_comma_separated(TextShadowValue::parse) {
+                    new.shadows = shadows;
+                }
+            }
+        }
+
+        if self != &mut new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Default, Component)]
+pub(crate) struct Filter {
+    pub drop_shadows: Vec<DropShadow>,
+}
+
+#[partial_derive_state]
+impl State for Filter {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&["filter"]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let mut new = Filter::default();
+        if let Some(filter_attr) = node_view.attributes().into_iter().flatten().next() {
+            if let Some(as_text) = filter_attr.value.as_text() {
+                if let Ok(FilterList::Filters(filters)) = FilterList::parse_string(as_text) {
+                    for filter in filters {
+                        // TODO: support the rest of the filter functions
+                        if let FilterFunction::DropShadow(shadow) = filter {
+                            new.drop_shadows.push(shadow);
+                        }
+                    }
+                }
+            }
+        }
+
+        if self != &mut new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Component)]
+pub(crate) struct Overflow {
+    pub x: OverflowKeyword,
+    pub y: OverflowKeyword,
+}
+
+impl Default for Overflow {
+    fn default() -> Self {
+        Overflow {
+            x: OverflowKeyword::Visible,
+            y: OverflowKeyword::Visible,
+        }
+    }
+}
+
+impl Overflow {
+    /// Computes the used value of the overflow. If only one axis is `visible` or `clip`, it behaves like `auto` or `hidden` respectively.
+    fn computed(self) -> Self {
+        let scroll_container =
+            |keyword| !matches!(keyword, OverflowKeyword::Visible | OverflowKeyword::Clip);
+        let compute = |keyword, other| {
+            if scroll_container(other) {
+                match keyword {
+                    OverflowKeyword::Visible => OverflowKeyword::Auto,
+                    OverflowKeyword::Clip => OverflowKeyword::Hidden,
+                    keyword => keyword,
+                }
+            } else {
+                keyword
+            }
+        };
+        Overflow {
+            x: compute(self.x, self.y),
+            y: compute(self.y, self.x),
+        }
+    }
+
+    pub fn clips_x(&self) -> bool {
+        self.x != OverflowKeyword::Visible
+    }
+
+    pub fn clips_y(&self) -> bool {
+        self.y != OverflowKeyword::Visible
+    }
+
+    /// Returns true if the user can scroll the content horizontally.
+    pub fn scrolls_x(&self) -> bool {
+        matches!(self.x, OverflowKeyword::Auto | OverflowKeyword::Scroll)
+    }
+
+    /// Returns true if the user can scroll the content vertically.
+    pub fn scrolls_y(&self) -> bool {
+        matches!(self.y, OverflowKeyword::Auto | OverflowKeyword::Scroll)
+    }
+}
+
+#[partial_derive_state]
+impl State for Overflow {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&[
+            "overflow",
+            "overflow-x",
+            "overflow-y",
+        ]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let mut new = Overflow::default();
+        for attr in node_view.attributes().into_iter().flatten() {
+            if let Some(as_text) = attr.value.as_text() {
+                let mut value = ParserInput::new(as_text);
+                let mut parser = Parser::new(&mut value);
+                match Property::parse(
+                    attr.attribute.name.as_str().into(),
+                    &mut parser,
+                    &ParserOptions::default(),
+                ) {
+                    Ok(Property::Overflow(overflow)) => {
+                        new.x = overflow.x;
+                        new.y = overflow.y;
+                    }
+                    Ok(Property::OverflowX(x)) => new.x = x,
+                    Ok(Property::OverflowY(y)) => new.y = y,
+                    _ => {}
+                }
+            }
+        }
+        let new = new.computed();
+
+        if *self != new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VIEWPORT: Size<u32> = Size {
+        width: 800,
+        height: 600,
+    };
+
+    /// An element with a font size of 20px in a document whose root font size is 10px.
+    const CONTEXT: ResolveContext = ResolveContext {
+        viewport_size: VIEWPORT,
+        font_size: 20.0,
+        root_font_size: 10.0,
+        font_units: FontUnits { ch: 12.0, ex: 9.0 },
+    };
+
+    /// Adds a `div` with the given style attributes to `parent`.
+    fn element(rdom: &mut RealDom, parent: NodeId, style: &[(&str, &str)]) -> NodeId {
+        let id = rdom
+            .create_node(NodeType::Element(ElementNode {
+                tag: "div".to_string(),
+                attributes: style
+                    .iter()
+                    .map(|(name, value)| ((*name, "style").into(), value.to_string().into()))
+                    .collect(),
+                ..Default::default()
+            }))
+            .id();
+        rdom.get_mut(parent).unwrap().add_child(id);
+        id
+    }
+
+    fn length(value: &str) -> LengthPercentage {
+        LengthPercentage::parse_string(value).unwrap()
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-3,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn calc_subtracts_rem_from_percentage() {
+        let rect = Size {
+            width: 500.0,
+            height: 300.0,
+        };
+        let width = length("calc(100% - 2rem)").resolve(Axis::X, &rect, &CONTEXT);
+        assert_close(width, 500.0 - 20.0);
+        let height = length("calc(100% - 2rem)").resolve(Axis::Y, &rect, &CONTEXT);
+        assert_close(height, 300.0 - 20.0);
+    }
+
+    #[test]
+    fn clamp_with_calc_arguments() {
+        let rect = Size {
+            width: 100.0,
+            height: 100.0,
+        };
+        // the minimum wins when it is larger than the maximum
+        let clamped =
+            length("clamp(100px, 50%, calc(100% - 2rem))").resolve(Axis::X, &rect, &CONTEXT);
+        assert_close(clamped, 100.0);
+        let clamped = length("clamp(calc(10% + 5px), 50%, calc(100% - 2rem))").resolve(
+            Axis::X,
+            &rect,
+            &CONTEXT,
+        );
+        assert_close(clamped, 50.0);
+        let clamped =
+            length("clamp(10px, 200%, calc(100% - 2rem))").resolve(Axis::X, &rect, &CONTEXT);
+        assert_close(clamped, 100.0 - 20.0);
+    }
+
+    #[test]
+    fn calc_in_font_size() {
+        let size = resolve_font_relative(&length("calc(100% - 2rem)"), 40.0, 10.0, &VIEWPORT);
+        assert_eq!(size, Some(20.0));
+        let size = resolve_font_relative(
+            &length("clamp(calc(1rem + 2px), 200%, calc(100% + 1rem))"),
+            20.0,
+            10.0,
+            &VIEWPORT,
+        );
+        assert_eq!(size, Some(30.0));
+        let size = resolve_font_relative(
+            &length("clamp(calc(1rem + 2px), 10%, calc(100% + 1rem))"),
+            20.0,
+            10.0,
+            &VIEWPORT,
+        );
+        assert_eq!(size, Some(12.0));
+    }
+
+    #[test]
+    fn logical_border_radius_follows_the_direction() {
+        let mut rdom: RealDom = RealDom::new(vec![
+            WritingMode::to_type_erased(),
+            Border::to_type_erased(),
+        ]);
+        let root = rdom.root_id();
+        let radii = [
+            ("border-start-start-radius", "1px"),
+            ("border-start-end-radius", "2px"),
+            ("border-end-start-radius", "3px"),
+            ("border-end-end-radius", "4px"),
+        ];
+        let ltr = element(&mut rdom, root, &radii);
+        let rtl = element(&mut rdom, root, &[("direction", "rtl")]);
+        let rtl = element(&mut rdom, rtl, &radii);
+        rdom.update_state(SendAnyMap::new());
+
+        let radius = |id: NodeId| {
+            rdom.get(id)
+                .unwrap()
+                .get::<Border>()
+                .unwrap()
+                .radius
+                .clone()
+        };
+        let corner = |px: &str| Size2D(length(px), length(px));
+        let ltr = radius(ltr);
+        assert_eq!(ltr.top_left, corner("1px"));
+        assert_eq!(ltr.top_right, corner("2px"));
+        assert_eq!(ltr.bottom_left, corner("3px"));
+        assert_eq!(ltr.bottom_right, corner("4px"));
+        // the inline start is the right side in right to left text
+        let rtl = radius(rtl);
+        assert_eq!(rtl.top_right, corner("1px"));
+        assert_eq!(rtl.top_left, corner("2px"));
+        assert_eq!(rtl.bottom_right, corner("3px"));
+        assert_eq!(rtl.bottom_left, corner("4px"));
+    }
+
+    #[test]
+    fn overflow_axes_are_independent() {
+        let mut rdom: RealDom = RealDom::new(vec![Overflow::to_type_erased()]);
+        let root = rdom.root_id();
+        let mixed = element(
+            &mut rdom,
+            root,
+            &[("overflow-x", "scroll"), ("overflow-y", "hidden")],
+        );
+        let visible_x = element(
+            &mut rdom,
+            root,
+            &[("overflow-x", "visible"), ("overflow-y", "auto")],
+        );
+        let clip_x = element(
+            &mut rdom,
+            root,
+            &[("overflow-x", "clip"), ("overflow-y", "scroll")],
+        );
+        let shorthand = element(&mut rdom, root, &[("overflow", "visible hidden")]);
+        let clip_visible = element(&mut rdom, root, &[("overflow-x", "clip")]);
+        rdom.update_state(SendAnyMap::new());
+        let overflow = |id: NodeId| *rdom.get(id).unwrap().get::<Overflow>().unwrap();
+
+        // scrolls horizontally and clips vertically without scrolling
+        let mixed = overflow(mixed);
+        assert_eq!(mixed.x, OverflowKeyword::Scroll);
+        assert_eq!(mixed.y, OverflowKeyword::Hidden);
+        assert!(mixed.clips_x() && mixed.clips_y());
+        assert!(mixed.scrolls_x() && !mixed.scrolls_y());
+        // `visible` next to a scrolling axis computes to `auto` and `clip` to `hidden`
+        assert_eq!(overflow(visible_x).x, OverflowKeyword::Auto);
+        assert_eq!(overflow(clip_x).x, OverflowKeyword::Hidden);
+        assert_eq!(
+            overflow(shorthand),
+            Overflow {
+                x: OverflowKeyword::Auto,
+                y: OverflowKeyword::Hidden,
+            }
+        );
+        // `clip` is not a scroll container, so the other axis stays visible
+        let clip_visible = overflow(clip_visible);
+        assert_eq!(clip_visible.y, OverflowKeyword::Visible);
+        assert!(clip_visible.clips_x() && !clip_visible.clips_y());
+    }
+
+    #[test]
+    fn viewport_font_sizes_follow_the_viewport() {
+        let mut rdom: RealDom = RealDom::new(vec![FontSize::to_type_erased()]);
+        let root = rdom.root_id();
+        let clamped = element(&mut rdom, root, &[("font-size", "clamp(12px, 2vw, 24px)")]);
+        let nested = element(&mut rdom, clamped, &[("font-size", "50%")]);
+        let font_size =
+            |rdom: &RealDom, id: NodeId| rdom.get(id).unwrap().get::<FontSize>().unwrap().0;
+
+        let mut ctx = SendAnyMap::new();
+        ctx.insert(ViewportSize(VIEWPORT));
+        ctx.insert(RootFontSize::compute(&rdom, &VIEWPORT));
+        rdom.update_state(ctx);
+        // 2vw of 800px
+        assert_eq!(font_size(&rdom, clamped), 16.0);
+        assert_eq!(font_size(&rdom, nested), 8.0);
+
+        // resizing the window only recomputes the font sizes
+        for (width, expected) in [(400, 12.0), (1600, 24.0)] {
+            let viewport = Size { width, height: 600 };
+            let changed = update_font_sizes(
+                &mut rdom,
+                &viewport,
+                &RootFontSize::compute(&rdom, &viewport),
+            );
+            assert!(changed.contains(&clamped) && changed.contains(&nested));
+            assert_eq!(font_size(&rdom, clamped), expected);
+            assert_eq!(font_size(&rdom, nested), expected / 2.0);
+        }
+    }
+
+    #[test]
+    fn rem_is_relative_to_root_font_size() {
+        // the parent font size only affects em and percentages
+        assert_eq!(
+            resolve_font_relative(&length("2rem"), 10.0, 24.0, &VIEWPORT),
+            Some(48.0)
+        );
+        assert_eq!(
+            resolve_font_relative(&length("2em"), 10.0, 24.0, &VIEWPORT),
+            Some(20.0)
+        );
+        let size = OwnedAttributeValue::Text("1.5rem".to_string());
+        assert_eq!(
+            compute_font_size(Some(&size), None, Some(10.0), 24.0, &VIEWPORT),
+            Some(36.0)
+        );
+        let font = OwnedAttributeValue::Text("bold 0.5rem serif".to_string());
+        assert_eq!(
+            compute_font_size(None, Some(&font), Some(10.0), 24.0, &VIEWPORT),
+            Some(12.0)
+        );
+    }
+
+    #[test]
+    fn rem_and_em_outside_font_size() {
+        let rect = Size {
+            width: 0.0,
+            height: 0.0,
+        };
+        let px = |value: &str| length(value).resolve(Axis::X, &rect, &CONTEXT);
+        assert_close(px("2rem"), 20.0);
+        assert_close(px("2em"), 40.0);
+        assert_close(px("calc(1em + 1rem)"), 30.0);
+    }
+
+    #[test]
+    fn ch_and_ex_use_the_font_units() {
+        let rect = Size {
+            width: 0.0,
+            height: 0.0,
+        };
+        let px = |value: &str| length(value).resolve(Axis::X, &rect, &CONTEXT);
+        assert_close(px("2ch"), 24.0);
+        assert_close(px("2ex"), 18.0);
+        assert_close(px("calc(1ch + 1ex)"), 21.0);
+    }
+
+    #[test]
+    fn absolute_units_are_96px_per_inch() {
+        let rect = Size {
+            width: 0.0,
+            height: 0.0,
+        };
+        let px = |value: &str| length(value).resolve(Axis::X, &rect, &CONTEXT);
+        assert_close(px("1in"), 96.0);
+        assert_close(px("1pt"), 96.0 / 72.0);
+        assert_close(px("72pt"), 96.0);
+        assert_close(px("1pc"), 16.0);
+        assert_close(px("2.54cm"), 96.0);
+        assert_close(px("25.4mm"), 96.0);
+        assert_close(px("4Q"), 96.0 / 25.4);
+        assert_close(px("calc(1in - 12pt)"), 80.0);
+    }
+
+    #[test]
+    fn absolute_units_in_font_size() {
+        let size = |value: &str| {
+            parse_font_size_from_attr(
+                &OwnedAttributeValue::Text(value.to_string()),
+                DEFAULT_FONT_SIZE,
+                DEFAULT_FONT_SIZE,
+                &VIEWPORT,
+            )
+            .unwrap()
+        };
+        assert_close(size("12pt") as f64, 16.0);
+        assert_close(size("1pc") as f64, 16.0);
+        assert_close(size("0.5in") as f64, 48.0);
+    }
+}
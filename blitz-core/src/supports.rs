@@ -0,0 +1,213 @@
+//! `@supports` conditions.
+//!
+//! A condition is true when every declaration it tests is a property Blitz implements and the value is valid for it. `not`, `and` and `or` combine conditions like in CSS. `selector()` and other functions are false because there are no stylesheet selectors.
+
+use cssparser::{ParseError, Parser, ParserInput, Token};
+
+use crate::diagnostics::check_declaration;
+
+/// The style properties that are read by a pass. Shorthands are listed when the pass parses them.
+const SUPPORTED_PROPERTIES: &[&str] = &[
+    "accent-color",
+    "align-content",
+    "align-items",
+    "align-self",
+    "aspect-ratio",
+    "background-clip",
+    "background-color",
+    "background-image",
+    "background-position",
+    "background-repeat",
+    "background-size",
+    "border-bottom-color",
+    "border-bottom-left-radius",
+    "border-bottom-right-radius",
+    "border-bottom-width",
+    "border-collapse",
+    "border-color",
+    "border-end-end-radius",
+    "border-end-start-radius",
+    "border-left-color",
+    "border-left-width",
+    "border-radius",
+    "border-right-color",
+    "border-right-width",
+    "border-spacing",
+    "border-start-end-radius",
+    "border-start-start-radius",
+    "border-top-color",
+    "border-top-left-radius",
+    "border-top-right-radius",
+    "border-top-width",
+    "border-width",
+    "bottom",
+    "box-shadow",
+    "break-after",
+    "break-before",
+    "caption-side",
+    "color",
+    "column-gap",
+    "container",
+    "container-name",
+    "container-type",
+    "counter-increment",
+    "counter-reset",
+    "counter-set",
+    "cursor",
+    "direction",
+    "display",
+    "empty-cells",
+    "filter",
+    "flex",
+    "flex-basis",
+    "flex-direction",
+    "flex-grow",
+    "flex-shrink",
+    "flex-wrap",
+    "font",
+    "font-family",
+    "font-feature-settings",
+    "font-size",
+    "font-stretch",
+    "font-style",
+    "font-variant-ligatures",
+    "font-weight",
+    "gap",
+    "height",
+    "justify-content",
+    "left",
+    "letter-spacing",
+    "line-height",
+    "margin",
+    "margin-bottom",
+    "margin-left",
+    "margin-right",
+    "margin-top",
+    "max-height",
+    "max-width",
+    "min-height",
+    "min-width",
+    "object-fit",
+    "opacity",
+    "outline",
+    "outline-color",
+    "outline-offset",
+    "outline-style",
+    "outline-width",
+    "overflow",
+    "overflow-anchor",
+    "overflow-x",
+    "overflow-y",
+    "padding",
+    "padding-bottom",
+    "padding-left",
+    "padding-right",
+    "padding-top",
+    "page-break-after",
+    "page-break-before",
+    "position",
+    "quotes",
+    "right",
+    "row-gap",
+    "scroll-behavior",
+    "table-layout",
+    "text-align",
+    "text-align-last",
+    "text-decoration",
+    "text-decoration-color",
+    "text-decoration-line",
+    "text-decoration-style",
+    "text-decoration-thickness",
+    "text-overflow",
+    "text-rendering",
+    "text-shadow",
+    "text-transform",
+    "text-underline-offset",
+    "text-underline-position",
+    "top",
+    "transform",
+    "transform-origin",
+    "unicode-bidi",
+    "visibility",
+    "white-space",
+    "width",
+    "word-spacing",
+    "writing-mode",
+    "z-index",
+];
+
+/// Evaluates the condition of an `@supports` rule, like `(display: flex) and (not (gap: 1px))`. Conditions that fail to parse are false.
+pub(crate) fn supports(condition: &str) -> bool {
+    let mut input = ParserInput::new(condition);
+    let mut parser = Parser::new(&mut input);
+    parser
+        .parse_entirely(|parser| Ok::<_, ParseError<()>>(parse_condition(parser)))
+        .ok()
+        .flatten()
+        .unwrap_or(false)
+}
+
+/// Parses `not <in-parens>` or a list of conditions joined by `and` or `or`. Returns `None` if it doesn't parse.
+fn parse_condition(parser: &mut Parser) -> Option<bool> {
+    if parser
+        .try_parse(|parser| parser.expect_ident_matching("not"))
+        .is_ok()
+    {
+        return parse_in_parens(parser).map(|supported| !supported);
+    }
+    let mut supported = parse_in_parens(parser)?;
+    let mut operator: Option<String> = None;
+    while !parser.is_exhausted() {
+        let next = parser.expect_ident_cloned().ok()?.to_ascii_lowercase();
+        // `and` and `or` can't be mixed without parentheses
+        if !matches!(next.as_str(), "and" | "or") || operator.as_ref().is_some_and(|op| *op != next)
+        {
+            return None;
+        }
+        let other = parse_in_parens(parser)?;
+        supported = match next.as_str() {
+            "and" => supported && other,
+            _ => supported || other,
+        };
+        operator = Some(next);
+    }
+    Some(supported)
+}
+
+/// Parses a declaration or a condition in parentheses. Functions like `selector()` are false.
+fn parse_in_parens(parser: &mut Parser) -> Option<bool> {
+    match parser.next().ok()?.clone() {
+        Token::ParenthesisBlock => parser
+            .parse_nested_block(|block| {
+                let declaration = block.try_parse(|block| {
+                    let name = block.expect_ident_cloned()?;
+                    block.expect_colon()?;
+                    let start = block.position();
+                    while block.next().is_ok() {}
+                    Ok::<_, ParseError<()>>((name, block.slice_from(start).trim().to_string()))
+                });
+                let supported = match declaration {
+                    Ok((name, value)) => Some(supports_declaration(&name, &value)),
+                    Err(_) => parse_condition(block),
+                };
+                supported.ok_or_else(|| block.new_custom_error(()))
+            })
+            .ok(),
+        Token::Function(_) => {
+            parser
+                .parse_nested_block(|block| {
+                    while block.next().is_ok() {}
+                    Ok::<_, ParseError<()>>(())
+                })
+                .ok()?;
+            Some(false)
+        }
+        _ => None,
+    }
+}
+
+/// Returns true if the property is implemented and the value is valid for it.
+fn supports_declaration(name: &str, value: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    SUPPORTED_PROPERTIES.contains(&name.as_str()) && check_declaration(&name, value).is_none()
+}
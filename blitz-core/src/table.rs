@@ -0,0 +1,544 @@
+use std::sync::{Arc, Mutex};
+
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use lightningcss::traits::Parse;
+use lightningcss::values::length::Length;
+use rustc_hash::FxHashSet;
+use shipyard::Component;
+use taffy::prelude::*;
+
+use crate::layout::TaffyLayout;
+
+/// The part of a table an element is, set with the table values of `display`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub(crate) enum TableRole {
+    #[default]
+    None,
+    Table,
+    RowGroup,
+    Row,
+    Cell,
+    Caption,
+}
+
+impl TableRole {
+    /// The default role of table elements.
+    fn from_tag(tag: &str) -> Self {
+        match tag {
+            "table" => TableRole::Table,
+            "thead" | "tbody" | "tfoot" => TableRole::RowGroup,
+            "tr" => TableRole::Row,
+            "td" | "th" => TableRole::Cell,
+            "caption" => TableRole::Caption,
+            _ => TableRole::None,
+        }
+    }
+
+    fn from_display(display: &str) -> Self {
+        match display.trim() {
+            "table" | "inline-table" => TableRole::Table,
+            "table-row-group" | "table-header-group" | "table-footer-group" => TableRole::RowGroup,
+            "table-row" => TableRole::Row,
+            "table-cell" => TableRole::Cell,
+            "table-caption" => TableRole::Caption,
+            _ => TableRole::None,
+        }
+    }
+}
+
+/// The side of a table or figure a caption is placed on, set with `caption-side`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum CaptionSide {
+    Top,
+    Bottom,
+}
+
+impl CaptionSide {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            // captions are only placed in horizontal writing modes
+            "top" | "block-start" => Some(CaptionSide::Top),
+            "bottom" | "block-end" => Some(CaptionSide::Bottom),
+            _ => None,
+        }
+    }
+}
+
+/// The table properties of an element.
+///
+/// `border-collapse`, `border-spacing`, `empty-cells` and `caption-side` are inherited so rows, cells and captions can read the values set on the table.
+#[derive(Clone, Copy, PartialEq, Debug, Component)]
+pub(crate) struct TableStyle {
+    pub role: TableRole,
+    pub collapse: bool,
+    /// The horizontal and vertical space between cells when borders are not collapsed.
+    pub spacing: Size<f32>,
+    /// If `empty-cells: hide` is set. The borders and backgrounds of empty cells are hidden when borders are not collapsed.
+    pub hide_empty: bool,
+    /// If `table-layout: fixed` is set. Column widths only depend on the first row.
+    pub fixed: bool,
+    /// The number of columns a cell spans, set with the `colspan` attribute.
+    pub column_span: u16,
+    /// The number of rows a cell spans, set with the `rowspan` attribute. `0` spans the rest of the table.
+    pub row_span: u16,
+    /// `None` if `caption-side` isn't set. Table captions are placed on top by default and figure captions stay where they are in the document.
+    pub caption_side: Option<CaptionSide>,
+}
+
+impl Default for TableStyle {
+    fn default() -> Self {
+        TableStyle {
+            role: TableRole::None,
+            collapse: false,
+            spacing: Size {
+                width: 2.0,
+                height: 2.0,
+            },
+            hide_empty: false,
+            fixed: false,
+            column_span: 1,
+            row_span: 1,
+            caption_side: None,
+        }
+    }
+}
+
+impl TableStyle {
+    /// Maps the table box onto flexbox: tables and row groups stack rows vertically, rows place cells next to each other and stretch them to the tallest cell.
+    pub fn apply(&self, style: &mut Style) {
+        let spacing = if self.collapse {
+            Size::zero()
+        } else {
+            self.spacing
+        };
+        match self.role {
+            TableRole::None => {}
+            TableRole::Table => {
+                style.display = Display::Flex;
+                style.flex_direction = FlexDirection::Column;
+                style.gap.height = Dimension::Points(spacing.height);
+                style.padding = Rect {
+                    left: Dimension::Points(spacing.width),
+                    right: Dimension::Points(spacing.width),
+                    top: Dimension::Points(spacing.height),
+                    bottom: Dimension::Points(spacing.height),
+                };
+            }
+            TableRole::RowGroup => {
+                style.display = Display::Flex;
+                style.flex_direction = FlexDirection::Column;
+                style.gap.height = Dimension::Points(spacing.height);
+            }
+            TableRole::Row => {
+                style.display = Display::Flex;
+                style.flex_direction = FlexDirection::Row;
+                style.align_items = AlignItems::Stretch;
+                style.gap.width = Dimension::Points(spacing.width);
+            }
+            TableRole::Cell => {
+                style.flex_grow = 0.0;
+                style.flex_shrink = 0.0;
+            }
+            TableRole::Caption => {
+                style.flex_shrink = 0.0;
+            }
+        }
+    }
+}
+
+fn parse_spacing(value: &str) -> Option<Size<f32>> {
+    let mut lengths = value
+        .split_whitespace()
+        .map(|length| Length::parse_string(length).ok()?.to_px());
+    let width = lengths.next()??;
+    let height = match lengths.next() {
+        Some(height) => height?,
+        None => width,
+    };
+    Some(Size { width, height })
+}
+
+#[partial_derive_state]
+impl State for TableStyle {
+    type ChildDependencies = ();
+    type ParentDependencies = (Self,);
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new()
+            .with_tag()
+            .with_attrs(AttributeMaskBuilder::Some(&[
+                "display",
+                "border-collapse",
+                "border-spacing",
+                "empty-cells",
+                "table-layout",
+                "colspan",
+                "rowspan",
+                "caption-side",
+            ]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let inherited = parent.map(|(parent,)| *parent).unwrap_or_default();
+        let mut new = TableStyle {
+            role: node_view.tag().map(TableRole::from_tag).unwrap_or_default(),
+            collapse: inherited.collapse,
+            spacing: inherited.spacing,
+            hide_empty: inherited.hide_empty,
+            caption_side: inherited.caption_side,
+            ..Default::default()
+        };
+        for attr in node_view.attributes().into_iter().flatten() {
+            if let Some(as_text) = attr.value.as_text() {
+                match attr.attribute.name.as_str() {
+                    "display" => new.role = TableRole::from_display(as_text),
+                    "border-collapse" => match as_text.trim() {
+                        "collapse" => new.collapse = true,
+                        "separate" => new.collapse = false,
+                        _ => {}
+                    },
+                    "border-spacing" => {
+                        if let Some(spacing) = parse_spacing(as_text) {
+                            new.spacing = spacing;
+                        }
+                    }
+                    "empty-cells" => match as_text.trim() {
+                        "hide" => new.hide_empty = true,
+                        "show" => new.hide_empty = false,
+                        _ => {}
+                    },
+                    "table-layout" => new.fixed = as_text.trim() == "fixed",
+                    "caption-side" => {
+                        if let Some(side) = CaptionSide::parse(as_text) {
+                            new.caption_side = Some(side);
+                        }
+                    }
+                    // Browsers clamp spans to these limits
+                    "colspan" => {
+                        if let Ok(span) = as_text.trim().parse::<u16>() {
+                            new.column_span = span.clamp(1, 1000);
+                        }
+                    }
+                    "rowspan" => {
+                        if let Ok(span) = as_text.trim().parse::<u16>() {
+                            new.row_span = span.min(65534);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if self != &mut new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+/// Sizes the columns of every table in the document and places cells that span multiple columns or rows. This needs to run before the layout is computed.
+///
+/// Each column is as wide as the widest cell in it, measured at its max-content width. Cells that span multiple columns widen the columns evenly if they don't fit. With `table-layout: fixed` only the first row is measured. Returns the cells that were changed.
+pub(crate) fn update_table_layout(rdom: &RealDom, taffy: &Arc<Mutex<Taffy>>) -> FxHashSet<NodeId> {
+    let mut taffy = taffy.lock().unwrap();
+    let mut changed = FxHashSet::default();
+    for_each_table(rdom, |table, style| {
+        layout_table(table, style, &mut taffy, &mut changed)
+    });
+    changed
+}
+
+/// Stretches cells that span multiple rows over the rows they span. The rows only have a size after the layout is computed, so this needs to run after the layout and the layout needs to be computed again if any cell changed.
+pub(crate) fn update_row_spans(rdom: &RealDom, taffy: &Arc<Mutex<Taffy>>) -> FxHashSet<NodeId> {
+    let mut taffy = taffy.lock().unwrap();
+    let mut changed = FxHashSet::default();
+    for_each_table(rdom, |table, style| {
+        let (rows, cells) = place_cells(table);
+        for cell in cells.iter().filter(|cell| cell.row_span > 1) {
+            let Some(taffy_node) = cell
+                .node
+                .get::<TaffyLayout>()
+                .and_then(|layout| layout.node)
+            else {
+                continue;
+            };
+            let mut cell_style = *taffy.style(taffy_node).unwrap();
+            let height = row_span_height(&rows, cell, style, &taffy);
+            if cell_style.size.height != height {
+                cell_style.size.height = height;
+                taffy.set_style(taffy_node, cell_style).unwrap();
+                changed.insert(cell.node.id());
+            }
+        }
+    });
+    changed
+}
+
+fn for_each_table(rdom: &RealDom, mut f: impl FnMut(NodeRef, &TableStyle)) {
+    let mut stack = vec![rdom.get(rdom.root_id()).unwrap()];
+    while let Some(node) = stack.pop() {
+        let table = node
+            .get::<TableStyle>()
+            .map(|style| *style)
+            .filter(|style| style.role == TableRole::Table);
+        if let Some(style) = table {
+            f(node, &style);
+        }
+        // tables can be nested inside of cells
+        stack.extend(node.children());
+    }
+}
+
+fn collect_rows<'a>(node: NodeRef<'a>, rows: &mut Vec<NodeRef<'a>>) {
+    for child in node.children() {
+        match child.get::<TableStyle>().map(|style| style.role) {
+            Some(TableRole::Row) => rows.push(child),
+            Some(TableRole::RowGroup) => collect_rows(child, rows),
+            _ => {}
+        }
+    }
+}
+
+/// A cell placed in the grid of its table.
+struct PlacedCell<'a> {
+    node: NodeRef<'a>,
+    row: usize,
+    column: usize,
+    column_span: usize,
+    row_span: usize,
+}
+
+/// Places the cells of a table in a grid. Each cell takes the first column in its row that isn't covered by a cell spanning down from an earlier row.
+fn place_cells(table: NodeRef) -> (Vec<NodeRef>, Vec<PlacedCell>) {
+    let mut rows = Vec::new();
+    collect_rows(table, &mut rows);
+    // The number of rows each column is still covered for
+    let mut covered: Vec<usize> = Vec::new();
+    let mut cells = Vec::new();
+    for (row_index, row) in rows.iter().enumerate() {
+        let remaining_rows = rows.len() - row_index;
+        let mut column = 0;
+        for cell in row.children() {
+            let Some(style) = cell
+                .get::<TableStyle>()
+                .map(|style| *style)
+                .filter(|style| style.role == TableRole::Cell)
+            else {
+                continue;
+            };
+            while covered.get(column).copied().unwrap_or(0) > 0 {
+                column += 1;
+            }
+            let column_span = style.column_span.max(1) as usize;
+            // `rowspan="0"` spans to the last row
+            let row_span = match style.row_span {
+                0 => remaining_rows,
+                span => (span as usize).min(remaining_rows),
+            };
+            if covered.len() < column + column_span {
+                covered.resize(column + column_span, 0);
+            }
+            for slot in &mut covered[column..column + column_span] {
+                *slot = row_span;
+            }
+            cells.push(PlacedCell {
+                node: cell,
+                row: row_index,
+                column,
+                column_span,
+                row_span,
+            });
+            column += column_span;
+        }
+        for slot in &mut covered {
+            *slot = slot.saturating_sub(1);
+        }
+    }
+    (rows, cells)
+}
+
+/// Returns true if the border and background of a cell are hidden by `empty-cells: hide`. Cells are empty if they only contain whitespace.
+pub(crate) fn hides_empty_cell(node: NodeRef) -> bool {
+    let Some(style) = node.get::<TableStyle>().map(|style| *style) else {
+        return false;
+    };
+    style.role == TableRole::Cell
+        && style.hide_empty
+        && !style.collapse
+        && node
+            .children()
+            .into_iter()
+            .all(|child| match &*child.node_type() {
+                NodeType::Text(TextNode { text, .. }) => text.trim().is_empty(),
+                _ => false,
+            })
+}
+
+/// The space between cells. Collapsed borders have no space between them.
+fn cell_spacing(style: &TableStyle) -> Size<f32> {
+    if style.collapse {
+        Size::zero()
+    } else {
+        style.spacing
+    }
+}
+
+/// The height of a cell that spans multiple rows from the current layout of the rows.
+fn row_span_height(
+    rows: &[NodeRef],
+    cell: &PlacedCell,
+    style: &TableStyle,
+    taffy: &Taffy,
+) -> Dimension {
+    let spacing = cell_spacing(style);
+    let mut height = spacing.height * (cell.row_span - 1) as f32;
+    for row in &rows[cell.row..cell.row + cell.row_span] {
+        let Some(taffy_node) = row.get::<TaffyLayout>().and_then(|layout| layout.node) else {
+            return Dimension::Auto;
+        };
+        height += taffy.layout(taffy_node).unwrap().size.height;
+    }
+    Dimension::Points(height)
+}
+
+fn layout_table(
+    table: NodeRef,
+    style: &TableStyle,
+    taffy: &mut Taffy,
+    changed: &mut FxHashSet<NodeId>,
+) {
+    let (rows, cells) = place_cells(table);
+    let spacing = cell_spacing(style);
+    let columns = cells
+        .iter()
+        .map(|cell| cell.column + cell.column_span)
+        .max()
+        .unwrap_or(0);
+
+    let mut column_widths = vec![0.0f32; columns];
+    let mut spanning = Vec::new();
+    for cell in cells.iter().filter(|cell| !style.fixed || cell.row == 0) {
+        let Some(width) = measure_cell(cell.node, taffy) else {
+            continue;
+        };
+        if cell.column_span == 1 {
+            column_widths[cell.column] = column_widths[cell.column].max(width);
+        } else {
+            spanning.push((cell, width));
+        }
+    }
+    for (cell, width) in spanning {
+        let spanned = &mut column_widths[cell.column..cell.column + cell.column_span];
+        let available = spanned.iter().sum::<f32>() + spacing.width * (cell.column_span - 1) as f32;
+        if width > available {
+            let extra = (width - available) / cell.column_span as f32;
+            for column_width in spanned {
+                *column_width += extra;
+            }
+        }
+    }
+
+    // The start of each column relative to the row
+    let mut column_x = Vec::with_capacity(columns + 1);
+    let mut x = 0.0;
+    for width in &column_widths {
+        column_x.push(x);
+        x += width + spacing.width;
+    }
+    column_x.push(x);
+
+    // The column after the last cell in the flow of the current row
+    let mut flow_end = 0;
+    for (index, cell) in cells.iter().enumerate() {
+        if index == 0 || cells[index - 1].row != cell.row {
+            flow_end = 0;
+        }
+        let Some(layout) = cell.node.get::<TaffyLayout>() else {
+            continue;
+        };
+        let Some(taffy_node) = layout.node else {
+            continue;
+        };
+        let end = cell.column + cell.column_span;
+        let mut cell_style = layout.style;
+        cell_style.size.width =
+            Dimension::Points(column_x[end] - column_x[cell.column] - spacing.width);
+        if cell.row_span > 1 {
+            // Cells that span rows are taken out of the flow so they don't stretch the first row
+            cell_style.position_type = PositionType::Absolute;
+            cell_style.position.left = Dimension::Points(column_x[cell.column]);
+            cell_style.position.top = Dimension::Points(0.0);
+            cell_style.size.height = row_span_height(&rows, cell, style, taffy);
+        } else {
+            // Skip over the columns taken by cells that span down from earlier rows
+            let mut margin = column_x[cell.column] - column_x[flow_end];
+            // Collapsed borders overlap the border of the previous cell
+            if style.collapse && cell.column > 0 {
+                if let Dimension::Points(border) = cell_style.border.left {
+                    margin -= border;
+                }
+            }
+            cell_style.margin.left = Dimension::Points(margin);
+            flow_end = end;
+        }
+        if style.collapse && cell.row > 0 {
+            cell_style.margin.top = negate(cell_style.border.top);
+        }
+        if *taffy.style(taffy_node).unwrap() != cell_style {
+            taffy.set_style(taffy_node, cell_style).unwrap();
+            changed.insert(cell.node.id());
+        }
+    }
+}
+
+fn negate(dimension: Dimension) -> Dimension {
+    match dimension {
+        Dimension::Points(points) => Dimension::Points(-points),
+        _ => Dimension::Points(0.0),
+    }
+}
+
+/// The max-content width of a cell. An explicit width on the cell is used as is.
+fn measure_cell(cell: NodeRef, taffy: &mut Taffy) -> Option<f32> {
+    let layout = cell.get::<TaffyLayout>()?;
+    let taffy_node = layout.node?;
+    if let Dimension::Points(width) = layout.style.size.width {
+        return Some(width);
+    }
+    // Lay out the cell on its own with the style the author set before any column width was applied
+    if *taffy.style(taffy_node).ok()? != layout.style {
+        taffy.set_style(taffy_node, layout.style).ok()?;
+    }
+    taffy
+        .compute_layout(
+            taffy_node,
+            Size {
+                width: AvailableSpace::MaxContent,
+                height: AvailableSpace::MaxContent,
+            },
+        )
+        .ok()?;
+    Some(taffy.layout(taffy_node).ok()?.size.width)
+}
@@ -0,0 +1,82 @@
+//! Bidirectional text from `direction` and `unicode-bidi`.
+//!
+//! Text is broken into lines in logical order. Each line is then reordered into visual order with the Unicode Bidirectional Algorithm before it is shaped and drawn, so right-to-left runs are drawn reversed and neutral characters take the direction of the text around them. Every line is reordered on its own.
+
+use std::borrow::Cow;
+
+use unicode_bidi::{BidiInfo, Level};
+
+use crate::writing_mode::{Direction, UnicodeBidi};
+
+/// The direction a line is aligned with. `unicode-bidi: plaintext` takes the direction of the first strong character of the line instead of `direction`.
+pub(crate) fn line_direction(
+    text: &str,
+    direction: Direction,
+    unicode_bidi: UnicodeBidi,
+) -> Direction {
+    if unicode_bidi != UnicodeBidi::Plaintext {
+        return direction;
+    }
+    let info = BidiInfo::new(text, None);
+    match info.paragraphs.first() {
+        Some(paragraph) if paragraph.level.is_rtl() => Direction::Rtl,
+        Some(_) => Direction::Ltr,
+        None => direction,
+    }
+}
+
+/// Reorders a line from logical order into the order its characters are drawn from left to right. `direction` is the base direction of the line from [`line_direction`].
+///
+/// `bidi-override` draws every character in the base direction. Brackets in right-to-left runs are mirrored.
+pub(crate) fn visual_order(
+    text: &str,
+    direction: Direction,
+    unicode_bidi: UnicodeBidi,
+) -> Cow<str> {
+    if unicode_bidi == UnicodeBidi::Override {
+        return match direction {
+            Direction::Ltr => Cow::Borrowed(text),
+            Direction::Rtl => Cow::Owned(text.chars().rev().map(mirror).collect()),
+        };
+    }
+    let level = match direction {
+        Direction::Ltr => Level::ltr(),
+        Direction::Rtl => Level::rtl(),
+    };
+    let info = BidiInfo::new(text, Some(level));
+    if !info.has_rtl() {
+        return Cow::Borrowed(text);
+    }
+    let mut visual = String::with_capacity(text.len());
+    for paragraph in &info.paragraphs {
+        let (levels, runs) = info.visual_runs(paragraph, paragraph.range.clone());
+        for run in runs {
+            let run_text = &text[run.clone()];
+            if levels[run.start].is_rtl() {
+                visual.extend(run_text.chars().rev().map(mirror));
+            } else {
+                visual.push_str(run_text);
+            }
+        }
+    }
+    Cow::Owned(visual)
+}
+
+/// Returns the mirrored form of brackets, which point the other way in right-to-left text.
+fn mirror(ch: char) -> char {
+    match ch {
+        '(' => ')',
+        ')' => '(',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '<' => '>',
+        '>' => '<',
+        '«' => '»',
+        '»' => '«',
+        '‹' => '›',
+        '›' => '‹',
+        ch => ch,
+    }
+}
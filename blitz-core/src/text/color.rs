@@ -0,0 +1,64 @@
+//! Color glyphs from the `COLR` and `CPAL` tables of a font, like the glyphs of emoji fonts.
+//!
+//! Only version 0 of `COLR` is supported: a color glyph is a stack of outline glyphs that are each filled with one color of the palette. The gradients of version 1 and the bitmap glyphs of `CBDT` and `sbix` can't be drawn from outlines, so fonts that only have those are not used as emoji fallback.
+
+use vello::fello::raw::{tables::colr::Colr, types::GlyphId, FontRef, TableProvider};
+use vello::peniko::Color;
+
+/// Layers with this palette index are drawn with the text color.
+const FOREGROUND_INDEX: u16 = 0xFFFF;
+
+/// The color glyphs of a font and the colors of its default palette.
+pub(crate) struct ColorGlyphs<'a> {
+    colr: Colr<'a>,
+    palette: Vec<Color>,
+}
+
+impl<'a> ColorGlyphs<'a> {
+    /// Reads the color tables of a font. Returns `None` if the font has no version 0 color glyphs.
+    pub(crate) fn new(font: &FontRef<'a>) -> Option<Self> {
+        let colr = font.colr().ok()?;
+        colr.base_glyph_records()?.ok()?;
+        let cpal = font.cpal().ok()?;
+        // the first palette is the default one
+        let first = cpal.color_record_indices().first()?.get() as usize;
+        let palette = cpal
+            .color_records_array()?
+            .ok()?
+            .iter()
+            .skip(first)
+            .take(cpal.num_palette_entries() as usize)
+            .map(|record| Color::rgba8(record.red(), record.green(), record.blue(), record.alpha()))
+            .collect();
+        Some(ColorGlyphs { colr, palette })
+    }
+
+    /// Returns the layers of a color glyph from bottom to top, or `None` if the glyph isn't a color glyph. Layers without a color are drawn with the text color.
+    pub(crate) fn layers(&self, glyph: GlyphId) -> Option<Vec<(GlyphId, Option<Color>)>> {
+        let records = self.colr.base_glyph_records()?.ok()?;
+        let index = records
+            .binary_search_by_key(&glyph, |record| record.glyph_id())
+            .ok()?;
+        let base = &records[index];
+        let first = base.first_layer_index() as usize;
+        let layers = self.colr.layer_records()?.ok()?;
+        let layers = layers.get(first..first + base.num_layers() as usize)?;
+        Some(
+            layers
+                .iter()
+                .map(|layer| {
+                    let color = match layer.palette_index() {
+                        FOREGROUND_INDEX => None,
+                        index => self.palette.get(index as usize).copied(),
+                    };
+                    (layer.glyph_id(), color)
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Checks if a font has glyphs that can be drawn in color. Fonts with only bitmap emoji have none.
+pub(crate) fn has_color_glyphs(font: &FontRef) -> bool {
+    ColorGlyphs::new(font).is_some()
+}
@@ -0,0 +1,296 @@
+//! OpenType features from `font-feature-settings`, `font-variant-ligatures` and `text-rendering`.
+//!
+//! Text is not run through a full shaper, so only the single and ligature substitutions in the `GSUB` table and the pair adjustments (kerning) in the `GPOS` table of the font are applied. The features are looked up in every script of the font instead of the script of the text.
+
+use vello::fello::{
+    raw::{
+        tables::{
+            gpos::{PairPos, PositionLookup},
+            gsub::{LigatureSubstFormat1, SingleSubst, SubstitutionLookup},
+            layout::FeatureList,
+        },
+        types::{GlyphId, Tag},
+        FontRef, TableProvider,
+    },
+    MetadataProvider,
+};
+
+/// Features that are on unless they are turned off or `text-rendering` is `optimizeSpeed`.
+const DEFAULT_FEATURES: &[[u8; 4]] = &[*b"liga", *b"clig", *b"kern"];
+
+/// The `text-rendering` hint.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TextRendering {
+    #[default]
+    Auto,
+    /// Turns off kerning and ligatures so text is faster to shape.
+    OptimizeSpeed,
+    OptimizeLegibility,
+    GeometricPrecision,
+}
+
+/// The OpenType features text is drawn with. Each setting is a feature tag and a value, a value of 0 turns the feature off.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct FontFeatures {
+    /// Features set by `font-variant-ligatures`.
+    pub ligatures: Vec<([u8; 4], u32)>,
+    /// Features set by `font-feature-settings`. They take precedence over `font-variant-ligatures`.
+    pub settings: Vec<([u8; 4], u32)>,
+    /// Decides whether the default features are on. Features that are set explicitly are applied either way.
+    pub rendering: TextRendering,
+}
+
+impl FontFeatures {
+    fn enabled(&self, tag: Tag) -> bool {
+        let value = self
+            .settings
+            .iter()
+            .rev()
+            .chain(self.ligatures.iter().rev())
+            .find(|(feature, _)| Tag::new(feature) == tag)
+            .map(|(_, value)| *value);
+        match value {
+            Some(value) => value != 0,
+            None => {
+                self.rendering != TextRendering::OptimizeSpeed
+                    && DEFAULT_FEATURES
+                        .iter()
+                        .any(|feature| Tag::new(feature) == tag)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ShapedGlyph {
+    pub id: GlyphId,
+    pub ch: char,
+    /// The index of `ch` in the characters of the text.
+    pub cluster: usize,
+    /// The adjustment to the advance of the glyph from kerning, in ems.
+    pub kern: f32,
+}
+
+/// Maps text to glyphs and applies the substitutions of the enabled features.
+pub(crate) fn shape(font: &FontRef, text: &str, features: &FontFeatures) -> Vec<ShapedGlyph> {
+    let charmap = font.charmap();
+    let mut glyphs: Vec<ShapedGlyph> = text
+        .chars()
+        .enumerate()
+        .map(|(cluster, ch)| ShapedGlyph {
+            id: charmap.map(ch).unwrap_or_default(),
+            ch,
+            cluster,
+            kern: 0.0,
+        })
+        .collect();
+    apply_features(font, &mut glyphs, features);
+    apply_kerning(font, &mut glyphs, features);
+    glyphs
+}
+
+fn apply_features(font: &FontRef, glyphs: &mut Vec<ShapedGlyph>, features: &FontFeatures) {
+    let Ok(gsub) = font.gsub() else {
+        return;
+    };
+    let (Ok(feature_list), Ok(lookup_list)) = (gsub.feature_list(), gsub.lookup_list()) else {
+        return;
+    };
+
+    for index in lookup_indices(&feature_list, |tag| features.enabled(tag)) {
+        match lookup_list.lookups().get(index as usize) {
+            Ok(SubstitutionLookup::Single(lookup)) => {
+                let subtables: Vec<SingleSubst> = lookup.subtables().iter().flatten().collect();
+                for glyph in glyphs.iter_mut() {
+                    if let Some(id) = subtables
+                        .iter()
+                        .find_map(|subtable| single_substitute(subtable, glyph.id))
+                    {
+                        glyph.id = id;
+                    }
+                }
+            }
+            Ok(SubstitutionLookup::Ligature(lookup)) => {
+                let subtables: Vec<LigatureSubstFormat1> =
+                    lookup.subtables().iter().flatten().collect();
+                let mut i = 0;
+                while i < glyphs.len() {
+                    if let Some((id, len)) = subtables
+                        .iter()
+                        .find_map(|subtable| ligature_substitute(subtable, &glyphs[i..]))
+                    {
+                        glyphs[i].id = id;
+                        glyphs.drain(i + 1..i + len);
+                    }
+                    i += 1;
+                }
+            }
+            // other lookups need context or produce several glyphs, which this doesn't support
+            _ => {}
+        }
+    }
+}
+
+/// Returns the lookups of the enabled features. Lookups are applied in the order they appear in the font, not the order the features are listed.
+fn lookup_indices(feature_list: &FeatureList, enabled: impl Fn(Tag) -> bool) -> Vec<u16> {
+    let mut lookups: Vec<u16> = feature_list
+        .feature_records()
+        .iter()
+        .filter(|record| enabled(record.feature_tag()))
+        .filter_map(|record| record.feature(feature_list.offset_data()).ok())
+        .flat_map(|feature| {
+            feature
+                .lookup_list_indices()
+                .iter()
+                .map(|index| index.get())
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    lookups.sort_unstable();
+    lookups.dedup();
+    lookups
+}
+
+fn apply_kerning(font: &FontRef, glyphs: &mut [ShapedGlyph], features: &FontFeatures) {
+    let kern = Tag::new(b"kern");
+    if glyphs.len() < 2 || !features.enabled(kern) {
+        return;
+    }
+    let Ok(gpos) = font.gpos() else {
+        return;
+    };
+    let (Ok(feature_list), Ok(lookup_list)) = (gpos.feature_list(), gpos.lookup_list()) else {
+        return;
+    };
+    let units_per_em = font.head().map(|head| head.units_per_em()).unwrap_or(1000) as f32;
+
+    for index in lookup_indices(&feature_list, |tag| tag == kern) {
+        // only pair adjustments are supported
+        let Ok(PositionLookup::Pair(lookup)) = lookup_list.lookups().get(index as usize) else {
+            continue;
+        };
+        let subtables: Vec<PairPos> = lookup.subtables().iter().flatten().collect();
+        for i in 0..glyphs.len() - 1 {
+            let (first, second) = (glyphs[i].id, glyphs[i + 1].id);
+            if let Some(adjustment) = subtables
+                .iter()
+                .find_map(|subtable| pair_adjustment(subtable, first, second))
+            {
+                glyphs[i].kern += adjustment as f32 / units_per_em;
+            }
+        }
+    }
+}
+
+/// Returns the change to the advance of the first glyph of a pair in font units.
+fn pair_adjustment(subtable: &PairPos, first: GlyphId, second: GlyphId) -> Option<i16> {
+    match subtable {
+        PairPos::Format1(table) => {
+            let index = table.coverage().ok()?.get(first)?;
+            let set = table.pair_sets().get(index as usize).ok()?;
+            set.pair_value_records()
+                .iter()
+                .flatten()
+                .find(|record| record.second_glyph() == second)
+                .and_then(|record| record.value_record1().x_advance())
+        }
+        PairPos::Format2(table) => {
+            table.coverage().ok()?.get(first)?;
+            let class1 = table.class_def1().ok()?.get(first);
+            let class2 = table.class_def2().ok()?.get(second);
+            let record = table.class1_records().get(class1 as usize).ok()?;
+            let record = record.class2_records().get(class2 as usize).ok()?;
+            record.value_record1().x_advance()
+        }
+    }
+}
+
+fn single_substitute(subtable: &SingleSubst, id: GlyphId) -> Option<GlyphId> {
+    match subtable {
+        SingleSubst::Format1(table) => {
+            table.coverage().ok()?.get(id)?;
+            let substitute = id.to_u16() as i32 + table.delta_glyph_id() as i32;
+            Some(GlyphId::new(substitute as u16))
+        }
+        SingleSubst::Format2(table) => {
+            let index = table.coverage().ok()?.get(id)?;
+            table
+                .substitute_glyph_ids()
+                .get(index as usize)
+                .map(|id| id.get())
+        }
+    }
+}
+
+/// Returns the ligature that starts at the first glyph and the number of glyphs it replaces.
+fn ligature_substitute(
+    subtable: &LigatureSubstFormat1,
+    glyphs: &[ShapedGlyph],
+) -> Option<(GlyphId, usize)> {
+    let index = subtable.coverage().ok()?.get(glyphs[0].id)?;
+    let set = subtable.ligature_sets().get(index as usize).ok()?;
+    // ligatures are listed in order of preference
+    set.ligatures().iter().flatten().find_map(|ligature| {
+        let components = ligature.component_glyph_ids();
+        let matches = components.len() < glyphs.len()
+            && components
+                .iter()
+                .zip(&glyphs[1..])
+                .all(|(component, glyph)| component.get() == glyph.id);
+        matches.then(|| (ligature.ligature_glyph(), components.len() + 1))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::default_font;
+
+    fn glyph_ids(text: &str, features: &FontFeatures) -> Vec<GlyphId> {
+        shape(&default_font(), text, features)
+            .iter()
+            .map(|glyph| glyph.id)
+            .collect()
+    }
+
+    #[test]
+    fn text_rendering_toggles_kerning() {
+        let kerning = |rendering: TextRendering| {
+            let features = FontFeatures {
+                rendering,
+                ..Default::default()
+            };
+            shape(&default_font(), "AV", &features)[0].kern
+        };
+        assert!(kerning(TextRendering::OptimizeLegibility) < 0.0);
+        assert_eq!(kerning(TextRendering::OptimizeSpeed), 0.0);
+        // kerning that is set explicitly still applies under optimizeSpeed
+        let features = FontFeatures {
+            settings: vec![(*b"kern", 1)],
+            rendering: TextRendering::OptimizeSpeed,
+            ..Default::default()
+        };
+        assert!(shape(&default_font(), "AV", &features)[0].kern < 0.0);
+    }
+
+    #[test]
+    fn small_caps_change_the_glyphs() {
+        let small_caps = FontFeatures {
+            settings: vec![(*b"smcp", 1)],
+            ..Default::default()
+        };
+        let plain = glyph_ids("abc", &FontFeatures::default());
+        let substituted = glyph_ids("abc", &small_caps);
+        assert_eq!(substituted.len(), plain.len());
+        assert!(plain
+            .iter()
+            .zip(&substituted)
+            .all(|(plain, small_cap)| plain != small_cap));
+        // the feature can be turned back off by a later setting
+        let off = FontFeatures {
+            settings: vec![(*b"smcp", 1), (*b"smcp", 0)],
+            ..Default::default()
+        };
+        assert_eq!(glyph_ids("abc", &off), plain);
+    }
+}
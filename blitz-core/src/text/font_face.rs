@@ -0,0 +1,123 @@
+//! `@font-face` rules registered with [`Config::with_font_face_rules`](crate::Config::with_font_face_rules).
+//!
+//! Rules inside `@supports` are used when its condition holds for the properties Blitz implements, and rules inside `@layer` blocks are always used. Other at-rules and style rules are skipped with their blocks, so they don't stop the rest of the stylesheet from being read.
+//!
+//! Only fonts on disk are loaded: each rule uses the first `url()` in its `src` as a path, relative paths are relative to the working directory like the paths of [`Config::with_font_file`](crate::Config::with_font_file). `local()` sources are skipped because families that aren't registered are already looked up in the installed fonts. A range of weights like `font-weight: 100 900` is registered with its first weight.
+
+use std::path::PathBuf;
+
+use cssparser::{Delimiter, ParseError, Parser, ParserInput, Token};
+
+use super::{parse_unicode_range, FontFile, FontSource};
+use crate::style::{parse_font_style, parse_font_weight};
+use crate::supports::supports;
+
+/// Parses the `@font-face` rules in a stylesheet. Other rules are ignored, and so are rules without a `font-family` or a `url()` source.
+pub(crate) fn parse_font_face_rules(css: &str) -> Vec<FontFile> {
+    let mut input = ParserInput::new(css);
+    let mut parser = Parser::new(&mut input);
+    let mut files = Vec::new();
+    parse_rules(&mut parser, &mut files);
+    files
+}
+
+/// Reads the `@font-face` rules in a list of rules, and in the `@supports` and `@layer` blocks that apply.
+fn parse_rules(parser: &mut Parser, files: &mut Vec<FontFile>) {
+    while let Ok(token) = parser.next() {
+        let Token::AtKeyword(name) = token else {
+            continue;
+        };
+        let name = name.to_ascii_lowercase();
+        // the prelude ends at the block of the rule, or at the semicolon of a statement like `@import`
+        let start = parser.position();
+        let mut prelude_end = None;
+        loop {
+            let end = parser.position();
+            match parser.next() {
+                Ok(Token::CurlyBracketBlock) => {
+                    prelude_end = Some(end);
+                    break;
+                }
+                Ok(Token::Semicolon) | Err(_) => break,
+                Ok(_) => {}
+            }
+        }
+        let Some(prelude_end) = prelude_end else {
+            continue;
+        };
+        let prelude = parser.slice(start..prelude_end).trim().to_string();
+        let _ = parser.parse_nested_block(|block| {
+            match name.as_str() {
+                "font-face" => files.extend(parse_descriptors(block)),
+                "supports" if supports(&prelude) => parse_rules(block, files),
+                "layer" => parse_rules(block, files),
+                // the block of other rules is skipped
+                _ => while block.next().is_ok() {},
+            }
+            Ok::<_, ParseError<()>>(())
+        });
+    }
+}
+
+/// Reads the descriptors of one `@font-face` rule.
+fn parse_descriptors(block: &mut Parser) -> Option<FontFile> {
+    let mut family = None;
+    let mut path = None;
+    let mut unicode_range = None;
+    let mut weight = None;
+    let mut italic = None;
+    while !block.is_exhausted() {
+        let name = block.expect_ident_cloned().ok();
+        let value = block.parse_until_after(Delimiter::Semicolon, |value| {
+            value.expect_colon()?;
+            let start = value.position();
+            // the value is only read as text, so the tokens are skipped
+            while value.next().is_ok() {}
+            Ok::<_, ParseError<()>>(value.slice_from(start).trim().to_string())
+        });
+        let (Some(name), Ok(value)) = (name, value) else {
+            continue;
+        };
+        match &*name.to_ascii_lowercase() {
+            "font-family" => family = Some(value.trim_matches(['"', '\'']).to_string()),
+            "src" => path = parse_src(&value).map(PathBuf::from),
+            "unicode-range" => unicode_range = parse_unicode_range(&value),
+            "font-weight" => {
+                weight = value
+                    .split_whitespace()
+                    .next()
+                    .and_then(|weight| parse_font_weight(weight, 400))
+            }
+            "font-style" => italic = value.split_whitespace().next().and_then(parse_font_style),
+            _ => {}
+        }
+    }
+    let mut file = FontFile::new(family?, FontSource::Path(path?));
+    file.unicode_range = unicode_range;
+    file.weight = weight;
+    file.italic = italic;
+    Some(file)
+}
+
+/// Returns the first `url()` in a `src` descriptor.
+fn parse_src(value: &str) -> Option<String> {
+    let mut input = ParserInput::new(value);
+    let mut parser = Parser::new(&mut input);
+    while let Ok(token) = parser.next() {
+        let is_url_function = match token {
+            Token::UnquotedUrl(url) => return Some(url.to_string()),
+            Token::Function(name) => name.eq_ignore_ascii_case("url"),
+            _ => false,
+        };
+        if is_url_function {
+            return parser
+                .parse_nested_block(|url| {
+                    url.expect_string_cloned()
+                        .map(|url| url.to_string())
+                        .map_err(ParseError::<()>::from)
+                })
+                .ok();
+        }
+    }
+    None
+}
@@ -1,91 +1,819 @@
+use std::{
+    collections::HashMap,
+    ops::RangeInclusive,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
+
+use cssparser::{ParseError, Parser, ParserInput, UnicodeRange};
 use vello::{
-    fello::{raw::FontRef, MetadataProvider},
+    fello::{
+        raw::{types::GlyphId, FontRef},
+        MetadataProvider,
+    },
     glyph::GlyphContext,
     kurbo::Affine,
-    peniko::{Brush, Font},
+    peniko::{Blob, Brush, Font},
     SceneBuilder,
 };
 
+pub(crate) mod bidi;
+mod color;
+mod features;
+pub(crate) mod font_face;
+mod system;
+pub(crate) mod text_style;
+
+use color::{has_color_glyphs, ColorGlyphs};
+use features::shape;
+pub use features::{FontFeatures, TextRendering};
+use system::{
+    index_system_fonts, system_font_files, EMOJI_FAMILIES, FALLBACK_FAMILIES, MONOSPACE_FAMILIES,
+    SERIF_FAMILIES,
+};
+use text_style::TextSpacing;
+
 const FONT_DATA: &[u8] = include_bytes!("Roboto-Regular.ttf");
 
+/// Drawn at the end of text that is cut off by `text-overflow: ellipsis`.
+const ELLIPSIS: &str = "…";
+
+/// The smallest advance negative `letter-spacing` and `word-spacing` can shrink a glyph to, relative to its own advance. Tighter spacing would stack the glyphs on top of each other.
+const MIN_SPACED_ADVANCE: f64 = 0.25;
+
+/// The generic font family text is drawn with.
+///
+/// Serif and monospace text use the first installed font from a list of common system fonts. Only one font is bundled, so without them text is drawn with it like sans-serif text.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum GenericFamily {
+    Serif,
+    #[default]
+    SansSerif,
+    Monospace,
+}
+
+impl GenericFamily {
+    /// The system families tried in order before the bundled font.
+    fn system_families(self) -> &'static [&'static str] {
+        match self {
+            GenericFamily::Serif => SERIF_FAMILIES,
+            GenericFamily::SansSerif => &[],
+            GenericFamily::Monospace => MONOSPACE_FAMILIES,
+        }
+    }
+}
+
+/// Metrics of a font at a specific size. Offsets are relative to the baseline and positive values point up.
+#[derive(Clone, Copy, Debug)]
+pub struct FontMetrics {
+    pub ascent: f32,
+    pub descent: f32,
+    pub line_height: f32,
+    pub underline_offset: f32,
+    pub underline_thickness: f32,
+    pub strikeout_offset: f32,
+    pub strikeout_thickness: f32,
+    pub x_height: f32,
+}
+
+/// The size of the font relative units `ch` and `ex` in pixels.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FontUnits {
+    /// The advance of the `0` glyph.
+    pub ch: f32,
+    /// The height of a lowercase `x`.
+    pub ex: f32,
+}
+
+impl FontUnits {
+    /// The approximation CSS allows when the metrics of the font are not known: both units are half of an em.
+    pub fn approximate(font_size: f32) -> Self {
+        FontUnits {
+            ch: font_size / 2.0,
+            ex: font_size / 2.0,
+        }
+    }
+}
+
+/// Text broken into lines by [`TextContext::layout`].
+pub struct TextLayout {
+    pub lines: Vec<TextLine>,
+    pub line_height: f64,
+    /// The distance from the top of the text to the baseline of the first line.
+    pub baseline: f64,
+}
+
+impl TextLayout {
+    pub fn width(&self) -> f64 {
+        self.lines.iter().map(|line| line.width).fold(0.0, f64::max)
+    }
+
+    pub fn height(&self) -> f64 {
+        self.lines.len() as f64 * self.line_height
+    }
+}
+
+pub struct TextLine {
+    pub text: String,
+    pub width: f64,
+    /// If this line is the last line of a paragraph. Paragraphs end at forced line breaks and at the end of the text.
+    pub ends_paragraph: bool,
+}
+
+/// Text that asks for at least this weight is bold, and faces lighter than it are emboldened to draw it.
+const SYNTHESIZED_BOLD_THRESHOLD: u16 = 600;
+
+/// The slant of synthesized oblique text, `tan(14deg)`.
+const SYNTHESIZED_OBLIQUE_SKEW: f64 = 0.25;
+
+/// The weight, style and width text asks for, from `font-weight`, `font-style` and `font-stretch`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FontStyle {
+    /// Between 1 and 1000, 400 is normal and 700 is bold.
+    pub weight: u16,
+    /// If `font-style` is `italic` or `oblique`.
+    pub italic: bool,
+    /// The width as a fraction of the normal width. Faces are assumed to have the normal width, so it doesn't change the face that is picked yet.
+    pub stretch: f32,
+}
+
+impl Default for FontStyle {
+    fn default() -> Self {
+        FontStyle {
+            weight: 400,
+            italic: false,
+            stretch: 1.0,
+        }
+    }
+}
+
+/// A font file registered for a family. A family can have several faces that each cover part of Unicode, like a CJK font split into subsets, or that have a different weight or style.
+#[derive(Clone)]
+pub struct FontFace {
+    pub font: Font,
+    /// The characters the face is used for from its `unicode-range`. `None` covers every character.
+    pub unicode_range: Option<Vec<RangeInclusive<u32>>>,
+    /// The weight of the face, read from the bold flag of the font. 700 for bold faces and 400 otherwise.
+    pub weight: u16,
+    /// If the italic flag of the font is set.
+    pub italic: bool,
+}
+
+impl FontFace {
+    /// Reads the first font in a font file. Returns `None` if the data isn't a font.
+    fn new(data: Arc<Vec<u8>>, unicode_range: Option<Vec<RangeInclusive<u32>>>) -> Option<Self> {
+        if vello::fello::raw::FileRef::new(&data).is_err() {
+            return None;
+        }
+        let font = Font::new(Blob::new(data), 0);
+        // bit 0 of macStyle is bold and bit 1 is italic
+        let mac_style = to_font_ref(&font)
+            .and_then(|font| font.head().ok())
+            .map_or(0, |head| head.mac_style());
+        Some(FontFace {
+            font,
+            unicode_range,
+            weight: if mac_style & 1 != 0 { 700 } else { 400 },
+            italic: mac_style & 2 != 0,
+        })
+    }
+
+    fn covers(&self, ch: char) -> bool {
+        self.unicode_range
+            .as_ref()
+            .is_none_or(|ranges| ranges.iter().any(|range| range.contains(&(ch as u32))))
+    }
+}
+
+/// Where the data of a registered font comes from.
+#[derive(Clone)]
+pub(crate) enum FontSource {
+    Path(PathBuf),
+    Bytes(Arc<Vec<u8>>),
+}
+
+/// A font registered for a family, from the config, an `@font-face` rule or [`WindowHandle::register_font`](crate::WindowHandle::register_font).
+#[derive(Clone)]
+pub(crate) struct FontFile {
+    pub family: String,
+    pub source: FontSource,
+    /// The characters the font is used for from `unicode-range`. `None` covers every character.
+    pub unicode_range: Option<Vec<RangeInclusive<u32>>>,
+    /// The weight from `font-weight`. It is read from the font if it isn't set.
+    pub weight: Option<u16>,
+    /// If `font-style` is italic. It is read from the font if it isn't set.
+    pub italic: Option<bool>,
+}
+
+impl FontFile {
+    pub(crate) fn new(family: String, source: FontSource) -> Self {
+        FontFile {
+            family,
+            source,
+            unicode_range: None,
+            weight: None,
+            italic: None,
+        }
+    }
+
+    /// Reads the font. Returns `None` if the file can't be read or isn't a font.
+    fn read(self) -> Option<FontFace> {
+        let data = match self.source {
+            FontSource::Path(path) => Arc::new(std::fs::read(path).ok()?),
+            FontSource::Bytes(bytes) => bytes,
+        };
+        let mut face = FontFace::new(data, self.unicode_range)?;
+        face.weight = self.weight.unwrap_or(face.weight);
+        face.italic = self.italic.unwrap_or(face.italic);
+        Some(face)
+    }
+}
+
+/// Parses a `unicode-range` like `U+0000-00FF, U+4E00-9FFF, U+30??`.
+pub fn parse_unicode_range(text: &str) -> Option<Vec<RangeInclusive<u32>>> {
+    let mut input = ParserInput::new(text);
+    let mut parser = Parser::new(&mut input);
+    let ranges = parser
+        .parse_comma_separated(|parser| UnicodeRange::parse(parser).map_err(ParseError::<()>::from))
+        .ok()?;
+    Some(
+        ranges
+            .into_iter()
+            .map(|range| range.start..=range.end)
+            .collect(),
+    )
+}
+
+/// The fonts registered by the app, keyed by their lowercase family name. It can be cloned and shared between threads.
+///
+/// Families that aren't registered are looked up in the fonts installed on the system.
+#[derive(Clone, Default)]
+pub struct FontContext {
+    fonts: Arc<RwLock<HashMap<String, Vec<FontFace>>>>,
+    /// The faces of the system families that were looked up. Families that aren't installed have no faces.
+    system: Arc<RwLock<HashMap<String, Vec<FontFace>>>>,
+    /// The selections returned by [`FontContext::get`], keyed by the family names, the generic family, the weight and if the text is italic. They are cleared when a font is loaded.
+    selections: Arc<RwLock<HashMap<SelectionKey, FontSelection>>>,
+}
+
+type SelectionKey = (Vec<String>, GenericFamily, u16, bool);
+
+impl FontContext {
+    /// Load a font. Files are read on a background thread and text is drawn with the fallback font until `on_load` is called with the family name. Fonts from bytes are added right away, so they are used from the first frame.
+    ///
+    /// Files that can't be read or aren't fonts are skipped and the fallback font keeps being used. A font replaces the face of its family with the same weight, style and `unicode-range`.
+    pub(crate) fn load(&self, file: FontFile, on_load: impl FnOnce(String) + Send + 'static) {
+        let fonts = self.clone();
+        let in_background = matches!(file.source, FontSource::Path(_));
+        let load = move || {
+            let family = file.family.to_lowercase();
+            let Some(face) = file.read() else {
+                return;
+            };
+            let mut registered = fonts.fonts.write().unwrap();
+            let faces = registered.entry(family.clone()).or_default();
+            faces.retain(|registered| {
+                (
+                    registered.weight,
+                    registered.italic,
+                    &registered.unicode_range,
+                ) != (face.weight, face.italic, &face.unicode_range)
+            });
+            faces.push(face);
+            drop(registered);
+            fonts.selections.write().unwrap().clear();
+            on_load(family);
+        };
+        if in_background {
+            std::thread::spawn(load);
+        } else {
+            load();
+        }
+    }
+
+    /// Index the fonts installed on the system on a background thread. Installed families can't be found until `on_indexed` is called, then text has to be measured again with them.
+    pub(crate) fn index_system_fonts(&self, on_indexed: impl FnOnce() + Send + 'static) {
+        let fonts = self.clone();
+        index_system_fonts(move || {
+            // families that were looked up before the index was built were missing
+            fonts.system.write().unwrap().clear();
+            fonts.selections.write().unwrap().clear();
+            on_indexed();
+        });
+    }
+
+    /// Returns the faces of a family, from the registered fonts or else the installed fonts. Installed families are read the first time they are used.
+    fn family(&self, name: &str) -> Option<Vec<FontFace>> {
+        if let Some(faces) = self.fonts.read().unwrap().get(name) {
+            return Some(faces.clone());
+        }
+        if let Some(faces) = self.system.read().unwrap().get(name) {
+            return (!faces.is_empty()).then(|| faces.clone());
+        }
+        let faces: Vec<FontFace> = system_font_files(name)
+            .iter()
+            .filter_map(|path| FontFace::new(Arc::new(std::fs::read(path).ok()?), None))
+            .collect();
+        self.system
+            .write()
+            .unwrap()
+            .insert(name.to_string(), faces.clone());
+        (!faces.is_empty()).then_some(faces)
+    }
+
+    /// Returns the faces of a list of family names in order, followed by the first installed font of the generic family. Names that aren't registered or installed are skipped. The faces of each family are sorted by how well they match the style: faces with the right style come first, then faces with the closest weight.
+    ///
+    /// Selections are cached, so the font picked for each character is only looked up once for the same families and style.
+    pub fn get(&self, names: &[String], generic: GenericFamily, style: FontStyle) -> FontSelection {
+        let key = (names.to_vec(), generic, style.weight, style.italic);
+        if let Some(selection) = self.selections.read().unwrap().get(&key) {
+            return FontSelection {
+                style,
+                ..selection.clone()
+            };
+        }
+        let generic_faces = generic
+            .system_families()
+            .iter()
+            .find_map(|name| self.family(&name.to_lowercase()));
+        let faces = names
+            .iter()
+            .filter_map(|name| self.family(name))
+            .chain(generic_faces)
+            .flat_map(|mut faces| {
+                // the sort is stable, so faces that match equally well stay in the order they were loaded
+                faces.sort_by_key(|face| {
+                    (
+                        face.italic != style.italic,
+                        face.weight.abs_diff(style.weight),
+                    )
+                });
+                faces
+            })
+            .collect();
+        let selection = FontSelection {
+            faces,
+            fallback: self.fallback(),
+            style,
+            glyph_fonts: Default::default(),
+        };
+        self.selections
+            .write()
+            .unwrap()
+            .insert(key, selection.clone());
+        selection
+    }
+
+    /// Returns the faces with color glyphs of the first installed emoji family, followed by the faces of every installed family in [`FALLBACK_FAMILIES`].
+    fn fallback(&self) -> Vec<FontFace> {
+        let emoji = EMOJI_FAMILIES
+            .iter()
+            .filter_map(|name| self.family(&name.to_lowercase()))
+            .map(|faces| {
+                faces
+                    .into_iter()
+                    .filter(|face| {
+                        to_font_ref(&face.font).is_some_and(|font| has_color_glyphs(&font))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .find(|faces| !faces.is_empty())
+            .unwrap_or_default();
+        let system = FALLBACK_FAMILIES
+            .iter()
+            .filter_map(|name| self.family(&name.to_lowercase()))
+            .flatten();
+        emoji.into_iter().chain(system).collect()
+    }
+}
+
+/// The faces text is drawn with and the style it asks for. Bold and italic are synthesized for characters drawn with a face that doesn't have them.
+#[derive(Clone, Default)]
+pub struct FontSelection {
+    pub faces: Vec<FontFace>,
+    /// The emoji and system fonts used for characters that neither the faces nor the bundled font have a glyph for, like emoji or CJK text.
+    pub fallback: Vec<FontFace>,
+    pub style: FontStyle,
+    /// The index of the font each character is drawn with in the fonts of [`place_glyphs`]. It is shared by the clones of a cached selection.
+    glyph_fonts: Arc<RwLock<HashMap<char, usize>>>,
+}
+
+/// A glyph placed by [`place_glyphs`].
+struct PlacedGlyph {
+    /// The index of the font the glyph is drawn with.
+    font: usize,
+    id: GlyphId,
+    /// The index of the first character of the glyph in the text.
+    cluster: usize,
+    /// The position of the pen before the glyph, relative to the baseline of the first line.
+    x: f64,
+    y: f64,
+    /// The advance of the glyph, including word spacing.
+    advance: f64,
+    /// How far the glyph is drawn a second time to the right to synthesize bold, or 0.
+    embolden: f64,
+    /// The skew that synthesizes oblique text, or 0.
+    skew: f64,
+}
+
+/// Picks a font for each character and places the glyphs. Returns the fonts the glyphs refer to and the glyphs.
+///
+/// Each character is drawn with the first face whose `unicode-range` covers it and that has a glyph for it, and falls back to the bundled font and then to the fallback fonts of the selection. Runs are split wherever the font changes, so each run is shaped with a font that has its glyphs. Line breaks are measured with the first face, like the rest of the line metrics.
+fn place_glyphs<'a>(
+    selection: &'a FontSelection,
+    features: &FontFeatures,
+    size: f32,
+    text: &str,
+    spacing: TextSpacing,
+) -> (Vec<FontRef<'a>>, Vec<PlacedGlyph>) {
+    let style = selection.style;
+    let loaded: Vec<(&FontFace, FontRef)> = selection
+        .faces
+        .iter()
+        .filter_map(|face| Some((face, to_font_ref(&face.font)?)))
+        .collect();
+    let charmaps: Vec<_> = loaded.iter().map(|(_, font)| font.charmap()).collect();
+    let mut fonts: Vec<FontRef> = loaded.iter().map(|(_, font)| font.clone()).collect();
+    let fallback = fonts.len();
+    fonts.push(default_font());
+    let fallback_charmap = fonts[fallback].charmap();
+    let fallback_fonts: Vec<FontRef> = selection
+        .fallback
+        .iter()
+        .filter_map(|face| to_font_ref(&face.font))
+        .collect();
+    let fallback_charmaps: Vec<_> = fallback_fonts.iter().map(|font| font.charmap()).collect();
+    fonts.extend(fallback_fonts.iter().cloned());
+    let font_for = |ch: char| {
+        if let Some(&font) = selection.glyph_fonts.read().unwrap().get(&ch) {
+            return font;
+        }
+        // characters that no font has are drawn with the missing glyph of the bundled font
+        let font = loaded
+            .iter()
+            .zip(&charmaps)
+            .position(|((face, _), charmap)| face.covers(ch) && charmap.map(ch).is_some())
+            .or_else(|| fallback_charmap.map(ch).map(|_| fallback))
+            .or_else(|| {
+                fallback_charmaps
+                    .iter()
+                    .position(|charmap| charmap.map(ch).is_some())
+                    .map(|index| fallback + 1 + index)
+            })
+            .unwrap_or(fallback);
+        selection.glyph_fonts.write().unwrap().insert(ch, font);
+        font
+    };
+
+    // split the text into runs of characters that use the same font
+    let mut runs: Vec<(usize, usize, usize)> = Vec::new();
+    for (index, (byte, ch)) in text.char_indices().enumerate() {
+        let font = match runs.last() {
+            // line breaks don't have glyphs, they stay in the current run
+            Some(&(font, ..)) if ch == '\n' => font,
+            _ => font_for(ch),
+        };
+        if runs.last().map(|&(current, ..)| current) != Some(font) {
+            runs.push((font, byte, index));
+        }
+    }
+
+    let fello_size = vello::fello::Size::new(size);
+    let line_height = {
+        let metrics = fonts[0].metrics(fello_size, Default::default());
+        (metrics.ascent - metrics.descent + metrics.leading) as f64
+    };
+    let mut glyphs = Vec::new();
+    let mut pen_x = 0f64;
+    let mut pen_y = 0f64;
+    for (i, &(font, start, first_char)) in runs.iter().enumerate() {
+        let end = runs.get(i + 1).map_or(text.len(), |&(_, end, _)| end);
+        let font_ref = &fonts[font];
+        let glyph_metrics = font_ref.glyph_metrics(fello_size, Default::default());
+        // the bundled font and the fallback fonts are regular
+        let (weight, italic) = match loaded.get(font) {
+            Some((face, _)) => (face.weight, face.italic),
+            None => (400, false),
+        };
+        let embolden =
+            if style.weight >= SYNTHESIZED_BOLD_THRESHOLD && weight < SYNTHESIZED_BOLD_THRESHOLD {
+                size as f64 / 24.0
+            } else {
+                0.0
+            };
+        let skew = if style.italic && !italic {
+            SYNTHESIZED_OBLIQUE_SKEW
+        } else {
+            0.0
+        };
+        for shaped in shape(font_ref, &text[start..end], features) {
+            if shaped.ch == '\n' {
+                pen_y += line_height;
+                pen_x = 0.0;
+                continue;
+            }
+            let advance = glyph_metrics.advance_width(shaped.id).unwrap_or_default() as f64;
+            let kern = shaped.kern as f64 * size as f64;
+            let mut advance = advance + kern;
+            // emboldened glyphs are wider
+            advance += embolden;
+            let mut extra = spacing.letter;
+            if shaped.ch == ' ' {
+                extra += spacing.word;
+            }
+            advance = (advance + extra).max(advance * MIN_SPACED_ADVANCE);
+            glyphs.push(PlacedGlyph {
+                font,
+                id: shaped.id,
+                cluster: first_char + shaped.cluster,
+                x: pen_x,
+                y: pen_y,
+                advance,
+                embolden,
+                skew,
+            });
+            pen_x += advance;
+        }
+    }
+    (fonts, glyphs)
+}
+
 pub struct TextContext {
     gcx: GlyphContext,
+    fonts: FontContext,
 }
 
 impl Default for TextContext {
     fn default() -> Self {
+        Self::new(FontContext::default())
+    }
+}
+
+impl TextContext {
+    pub fn new(fonts: FontContext) -> Self {
         Self {
             gcx: GlyphContext::new(),
+            fonts,
         }
     }
-}
 
-impl TextContext {
+    /// Returns the faces of a list of family names in order and the installed font of the generic family, sorted by how well they match the style.
+    pub fn fonts(
+        &self,
+        names: &[String],
+        generic: GenericFamily,
+        style: FontStyle,
+    ) -> FontSelection {
+        self.fonts.get(names, generic, style)
+    }
+
+    /// Draw text at the baseline of the transform with extra space after each character and each space.
+    #[allow(clippy::too_many_arguments)]
     pub fn add(
         &mut self,
         builder: &mut SceneBuilder,
-        font: Option<&Font>,
+        fonts: &FontSelection,
+        features: &FontFeatures,
         size: f32,
         brush: Option<impl Into<Brush>>,
         transform: Affine,
         text: &str,
+        spacing: TextSpacing,
     ) {
-        let font = font.and_then(to_font_ref).unwrap_or_else(default_font);
-        let fello_size = vello::fello::Size::new(size);
-        let charmap = font.charmap();
-        let metrics = font.metrics(fello_size, Default::default());
-        let line_height = metrics.ascent - metrics.descent + metrics.leading;
-        let glyph_metrics = font.glyph_metrics(fello_size, Default::default());
-        let mut pen_x = 0f64;
-        let mut pen_y = 0f64;
+        let (font_refs, glyphs) = place_glyphs(fonts, features, size, text, spacing);
         let vars: [(&str, f32); 0] = [];
-        let mut provider = self.gcx.new_provider(&font, None, size, false, vars);
         let brush = brush.map(Into::into);
-        for ch in text.chars() {
-            if ch == '\n' {
-                pen_y += line_height as f64;
-                pen_x = 0.0;
-                continue;
+        // glyphs are drawn in runs that share a font
+        let mut start = 0;
+        while start < glyphs.len() {
+            let font = glyphs[start].font;
+            let end = glyphs[start..]
+                .iter()
+                .position(|glyph| glyph.font != font)
+                .map_or(glyphs.len(), |len| start + len);
+            let color_glyphs = ColorGlyphs::new(&font_refs[font]);
+            let mut provider = self
+                .gcx
+                .new_provider(&font_refs[font], None, size, false, vars);
+            for placed in &glyphs[start..end] {
+                // color glyphs are drawn as a stack of layers that each have their own color, other glyphs are one layer with the text color
+                let layers = color_glyphs
+                    .as_ref()
+                    .and_then(|color_glyphs| color_glyphs.layers(placed.id))
+                    .unwrap_or_else(|| vec![(placed.id, None)]);
+                for (id, color) in layers {
+                    let layer_brush = color.map(Brush::Solid);
+                    let Some(glyph) =
+                        provider.get(id.to_u16(), layer_brush.as_ref().or(brush.as_ref()))
+                    else {
+                        continue;
+                    };
+                    // the skew leans the tops of glyphs to the right after they are flipped so y points down
+                    let outline =
+                        Affine::skew(-placed.skew, 0.0) * Affine::scale_non_uniform(1.0, -1.0);
+                    let xform = transform * Affine::translate((placed.x, placed.y)) * outline;
+                    builder.append(&glyph, Some(xform));
+                    // bold is synthesized by drawing the glyph again slightly to the right
+                    if placed.embolden > 0.0 {
+                        let xform = transform
+                            * Affine::translate((placed.x + placed.embolden, placed.y))
+                            * outline;
+                        builder.append(&glyph, Some(xform));
+                    }
+                }
             }
-            let gid = charmap.map(ch).unwrap_or_default();
-            let advance = glyph_metrics.advance_width(gid).unwrap_or_default() as f64;
-            if let Some(glyph) = provider.get(gid.to_u16(), brush.as_ref()) {
-                let xform = transform
-                    * Affine::translate((pen_x, pen_y))
-                    * Affine::scale_non_uniform(1.0, -1.0);
-                builder.append(&glyph, Some(xform));
+            start = end;
+        }
+    }
+
+    /// Break text into lines. `line_height` is the height of each line from `line-height`, `None` uses the metrics of the font. The glyphs are centered vertically in their line.
+    ///
+    /// Lines end at line breaks, and at spaces where the next word doesn't fit in `wrap_width` if it is set. The space a line wraps at is not part of either line, so every line is followed by one character that isn't drawn.
+    #[allow(clippy::too_many_arguments)]
+    pub fn layout(
+        &self,
+        fonts: &FontSelection,
+        features: &FontFeatures,
+        size: f32,
+        text: &str,
+        line_height: Option<f64>,
+        spacing: TextSpacing,
+        wrap_width: Option<f64>,
+    ) -> TextLayout {
+        let metrics = self.get_font_metrics(fonts, size);
+        let content_height = (metrics.ascent - metrics.descent) as f64;
+        let line_height = line_height.unwrap_or(metrics.line_height as f64);
+        // half of the space that is left is added above the glyphs, and it is negative when the line is shorter than the glyphs
+        let half_leading = (line_height - content_height) / 2.0;
+        let measure = |line: &str| self.get_text_size(fonts, features, size, line, spacing).0;
+        let mut lines = Vec::new();
+        for paragraph in text.split('\n') {
+            let mut rest = paragraph;
+            loop {
+                let end = match wrap_width {
+                    Some(wrap_width) => break_line(rest, wrap_width, measure),
+                    None => rest.len(),
+                };
+                lines.push(TextLine {
+                    text: rest[..end].to_string(),
+                    width: measure(&rest[..end]),
+                    ends_paragraph: end == rest.len(),
+                });
+                if end == rest.len() {
+                    break;
+                }
+                rest = &rest[end + 1..];
             }
-            pen_x += advance;
+        }
+        TextLayout {
+            lines,
+            line_height,
+            baseline: half_leading + metrics.ascent as f64,
         }
     }
 
-    pub fn get_text_size<'a>(
-        &'a mut self,
-        font: Option<&'a Font>,
+    /// Returns the x position of the start of every character in a line of text, followed by the end of the line. Characters that were combined into a ligature split its advance evenly.
+    #[allow(clippy::too_many_arguments)]
+    pub fn caret_offsets(
+        &self,
+        fonts: &FontSelection,
+        features: &FontFeatures,
         size: f32,
         text: &str,
-    ) -> (f64, f64) {
-        let font = font.and_then(to_font_ref).unwrap_or_else(default_font);
-        let fello_size = vello::fello::Size::new(size);
-        let charmap = font.charmap();
-        let metrics = font.metrics(fello_size, Default::default());
-        let line_height = metrics.ascent - metrics.descent + metrics.leading;
-        let glyph_metrics = font.glyph_metrics(fello_size, Default::default());
-        let mut width = 0f64;
-        let mut height = line_height as f64;
-        for ch in text.chars() {
-            if ch == '\n' {
-                height += line_height as f64;
-                continue;
+        spacing: TextSpacing,
+    ) -> Vec<f64> {
+        let (_, glyphs) = place_glyphs(fonts, features, size, text, spacing);
+        let char_count = text.chars().count();
+        let mut offsets = Vec::with_capacity(char_count + 1);
+        for (i, glyph) in glyphs.iter().enumerate() {
+            let next_cluster = glyphs.get(i + 1).map_or(char_count, |next| next.cluster);
+            let chars = next_cluster - glyph.cluster;
+            for j in 0..chars {
+                offsets.push(glyph.x + glyph.advance * j as f64 / chars as f64);
             }
-            let gid = charmap.map(ch).unwrap_or_default();
-            let advance = glyph_metrics.advance_width(gid).unwrap_or_default() as f64;
-            width += advance;
         }
-        (width, height)
+        offsets.push(glyphs.last().map_or(0.0, |last| last.x + last.advance));
+        offsets
+    }
+
+    /// Cuts a line of text so it fits in `max_width` with an ellipsis after it. Returns `None` if the line already fits.
+    #[allow(clippy::too_many_arguments)]
+    pub fn ellipsize(
+        &self,
+        fonts: &FontSelection,
+        features: &FontFeatures,
+        size: f32,
+        text: &str,
+        spacing: TextSpacing,
+        max_width: f64,
+    ) -> Option<String> {
+        let offsets = self.caret_offsets(fonts, features, size, text, spacing);
+        if offsets.last().is_none_or(|width| *width <= max_width) {
+            return None;
+        }
+        let ellipsis_width = self
+            .get_text_size(fonts, features, size, ELLIPSIS, spacing)
+            .0;
+        // the number of characters that fit before the ellipsis, at least the ellipsis is drawn
+        let fits = offsets
+            .iter()
+            .rposition(|offset| offset + ellipsis_width <= max_width)
+            .unwrap_or(0);
+        let kept: String = text.chars().take(fits).collect();
+        Some(format!("{}{ELLIPSIS}", kept.trim_end()))
+    }
+
+    /// Returns the metrics of the first face, or of the bundled font if no face is loaded.
+    pub fn get_font_metrics(&self, fonts: &FontSelection, size: f32) -> FontMetrics {
+        let font = fonts
+            .faces
+            .iter()
+            .find_map(|face| to_font_ref(&face.font))
+            .unwrap_or_else(default_font);
+        let metrics = font.metrics(vello::fello::Size::new(size), Default::default());
+        // Fallbacks for fonts that don't provide decoration metrics
+        let default_thickness = size / 14.0;
+        let x_height = metrics.x_height.unwrap_or(size / 2.0);
+        FontMetrics {
+            ascent: metrics.ascent,
+            descent: metrics.descent,
+            line_height: metrics.ascent - metrics.descent + metrics.leading,
+            underline_offset: metrics
+                .underline
+                .as_ref()
+                .map(|underline| underline.offset)
+                .unwrap_or(-size / 10.0),
+            underline_thickness: metrics
+                .underline
+                .as_ref()
+                .map(|underline| underline.thickness)
+                .unwrap_or(default_thickness),
+            strikeout_offset: metrics
+                .strikeout
+                .as_ref()
+                .map(|strikeout| strikeout.offset)
+                .unwrap_or(x_height / 2.0),
+            strikeout_thickness: metrics
+                .strikeout
+                .as_ref()
+                .map(|strikeout| strikeout.thickness)
+                .unwrap_or(default_thickness),
+            x_height,
+        }
+    }
+
+    /// Measures `ch` and `ex` in the first face of the font.
+    pub fn get_font_units(
+        &self,
+        fonts: &FontSelection,
+        features: &FontFeatures,
+        size: f32,
+    ) -> FontUnits {
+        let ch = self
+            .get_text_size(fonts, features, size, "0", TextSpacing::default())
+            .0 as f32;
+        let approximate = FontUnits::approximate(size);
+        FontUnits {
+            ch: if ch > 0.0 { ch } else { approximate.ch },
+            ex: self.get_font_metrics(fonts, size).x_height,
+        }
+    }
+
+    pub fn get_text_size(
+        &self,
+        fonts: &FontSelection,
+        features: &FontFeatures,
+        size: f32,
+        text: &str,
+        spacing: TextSpacing,
+    ) -> (f64, f64) {
+        let line_height = self.get_font_metrics(fonts, size).line_height as f64;
+        let (_, glyphs) = place_glyphs(fonts, features, size, text, spacing);
+        let width = glyphs
+            .iter()
+            .map(|glyph| glyph.x + glyph.advance)
+            .fold(0.0, f64::max);
+        let lines = text.matches('\n').count() + 1;
+        (width, line_height * lines as f64)
     }
 }
 
+/// Returns the end of the first line of a paragraph that wraps at `width`: the last space the text before it fits in, or the first space if no word fits. Returns the length of the text if it fits or has no spaces.
+fn break_line(text: &str, width: f64, measure: impl Fn(&str) -> f64) -> usize {
+    if measure(text) <= width {
+        return text.len();
+    }
+    let mut fits = None;
+    // a line never wraps before its first word
+    for (space, _) in text.match_indices(' ').filter(|(space, _)| *space > 0) {
+        if measure(&text[..space]) > width {
+            return fits.unwrap_or(space);
+        }
+        fits = Some(space);
+    }
+    fits.unwrap_or(text.len())
+}
+
 fn to_font_ref(font: &Font) -> Option<FontRef> {
     use vello::fello::raw::FileRef;
     let file_ref = FileRef::new(font.data.as_ref()).ok()?;
@@ -98,3 +826,35 @@ fn to_font_ref(font: &Font) -> Option<FontRef> {
 fn default_font<'a>() -> FontRef<'a> {
     FontRef::new(FONT_DATA).unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emoji_fall_back_to_an_installed_emoji_font() {
+        let fonts = FontContext::default();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        fonts.index_system_fonts(move || {
+            let _ = sender.send(());
+        });
+        receiver.recv().unwrap();
+        let selection = fonts.get(&[], GenericFamily::default(), FontStyle::default());
+        // the emoji font is the first fallback face if one is installed, there is nothing to test without it
+        let has_emoji_font = selection
+            .fallback
+            .first()
+            .and_then(|face| to_font_ref(&face.font))
+            .is_some_and(|font| has_color_glyphs(&font));
+        if !has_emoji_font {
+            return;
+        }
+
+        let (font_refs, glyphs) =
+            place_glyphs(&selection, &FontFeatures::default(), 16.0, "\u{1f44d}");
+        assert_eq!(glyphs.len(), 1);
+        // the bundled font has no color glyphs, so the emoji isn't its missing glyph
+        assert!(has_color_glyphs(&font_refs[glyphs[0].font]));
+        assert_ne!(glyphs[0].id, GlyphId::new(0));
+    }
+}
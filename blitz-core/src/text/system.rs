@@ -0,0 +1,232 @@
+//! Fonts installed on the system.
+//!
+//! The font directories of the platform are indexed once on a background thread, so reading them doesn't delay the first frame. Families are matched by the names in the `name` table of each font, both the typographic family that groups every weight and the legacy family, in every language the font lists. Only the first font of a collection is used.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use once_cell::sync::OnceCell;
+
+/// The font files in the system font directories, keyed by [`family_key`]. It is empty until the index is built.
+static SYSTEM_FONTS: OnceCell<HashMap<String, Vec<PathBuf>>> = OnceCell::new();
+
+/// The name ids of the legacy family name and the typographic family name.
+const FAMILY_NAME_IDS: [u16; 2] = [1, 16];
+
+/// `name` tables are a few kilobytes. Larger tables are from broken files and are skipped.
+const MAX_NAME_TABLE_LEN: usize = 1 << 20;
+
+/// Builds the index of the installed fonts on a background thread and calls `on_indexed` when it is ready. The directories are only read once, later calls get the same index.
+pub(crate) fn index_system_fonts(on_indexed: impl FnOnce() + Send + 'static) {
+    std::thread::spawn(move || {
+        SYSTEM_FONTS.get_or_init(build_index);
+        on_indexed();
+    });
+}
+
+fn build_index() -> HashMap<String, Vec<PathBuf>> {
+    let mut fonts: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    let mut stack = font_dirs();
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.is_dir() {
+                stack.push(path);
+                continue;
+            }
+            let is_font = path.extension().is_some_and(|extension| {
+                let extension = extension.to_string_lossy().to_lowercase();
+                matches!(extension.as_str(), "ttf" | "otf" | "ttc")
+            });
+            if !is_font {
+                continue;
+            }
+            let mut keys: Vec<String> = family_names(&path)
+                .unwrap_or_default()
+                .iter()
+                .map(|name| family_key(name))
+                .collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                fonts.entry(key).or_default().push(path.clone());
+            }
+        }
+    }
+    fonts
+}
+
+/// Reads the family names of the first font in a file from its `name` table. Only the table directory and the `name` table are read.
+fn family_names(path: &Path) -> Option<Vec<String>> {
+    let mut file = File::open(path).ok()?;
+    let mut header = [0u8; 12];
+    file.read_exact(&mut header).ok()?;
+    if &header[0..4] == b"ttcf" {
+        // the offset of the first font follows the tag, the version and the number of fonts
+        let first_font = read_u32(&header, 8)?;
+        file.seek(SeekFrom::Start(first_font as u64)).ok()?;
+        file.read_exact(&mut header).ok()?;
+    }
+    let num_tables = read_u16(&header, 4)? as usize;
+    let mut records = vec![0u8; num_tables * 16];
+    file.read_exact(&mut records).ok()?;
+    let (offset, length) = records
+        .chunks_exact(16)
+        .find(|record| &record[0..4] == b"name")
+        .and_then(|record| Some((read_u32(record, 8)?, read_u32(record, 12)? as usize)))?;
+    if length > MAX_NAME_TABLE_LEN {
+        return None;
+    }
+    // table offsets are from the start of the file, also in collections
+    let mut table = vec![0u8; length];
+    file.seek(SeekFrom::Start(offset as u64)).ok()?;
+    file.read_exact(&mut table).ok()?;
+    Some(parse_family_names(&table))
+}
+
+/// Returns the family names in a `name` table. Names on the Unicode and Windows platforms are UTF-16, names on the Macintosh platform are only read if they are ASCII.
+fn parse_family_names(table: &[u8]) -> Vec<String> {
+    let (Some(count), Some(storage)) = (read_u16(table, 2), read_u16(table, 4)) else {
+        return Vec::new();
+    };
+    let mut names = Vec::new();
+    for index in 0..count as usize {
+        let record = 6 + index * 12;
+        let (Some(platform), Some(name_id), Some(length), Some(offset)) = (
+            read_u16(table, record),
+            read_u16(table, record + 6),
+            read_u16(table, record + 8),
+            read_u16(table, record + 10),
+        ) else {
+            break;
+        };
+        if !FAMILY_NAME_IDS.contains(&name_id) {
+            continue;
+        }
+        let start = storage as usize + offset as usize;
+        let Some(bytes) = table.get(start..start + length as usize) else {
+            continue;
+        };
+        let name = match platform {
+            0 | 3 => {
+                let units: Vec<u16> = bytes
+                    .chunks_exact(2)
+                    .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+                    .collect();
+                String::from_utf16_lossy(&units)
+            }
+            1 if bytes.is_ascii() => String::from_utf8_lossy(bytes).into_owned(),
+            _ => continue,
+        };
+        if !name.trim().is_empty() {
+            names.push(name);
+        }
+    }
+    names
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+/// The families tried in order for the `serif` generic family. The first one that is installed is used.
+pub(crate) const SERIF_FAMILIES: &[&str] = &[
+    "Times New Roman",
+    "Times",
+    "Noto Serif",
+    "DejaVu Serif",
+    "Liberation Serif",
+    "FreeSerif",
+    "Georgia",
+];
+
+/// The families tried in order for the `monospace` generic family. Without any of them the bundled font is used.
+pub(crate) const MONOSPACE_FAMILIES: &[&str] = &[
+    "Menlo",
+    "SF Mono",
+    "Consolas",
+    "Cascadia Mono",
+    "DejaVu Sans Mono",
+    "Liberation Mono",
+    "Noto Sans Mono",
+    "Ubuntu Mono",
+    "FreeMono",
+    "Courier New",
+    "Courier",
+];
+
+/// The families tried in order for characters that the fonts of the text, the bundled font and the emoji font don't have, like CJK text. Every installed family is used.
+pub(crate) const FALLBACK_FAMILIES: &[&str] = &[
+    "Noto Sans",
+    "Noto Sans CJK",
+    "Noto Sans CJK SC",
+    "DejaVu Sans",
+    "Droid Sans Fallback",
+    "Microsoft YaHei",
+    "PingFang",
+    "Hiragino Sans",
+    "Arial Unicode MS",
+];
+
+/// The families tried in order for characters that no other font has a glyph for, like emoji. Only fonts with color glyphs from the `COLR` table are used.
+pub(crate) const EMOJI_FAMILIES: &[&str] = &[
+    "Twemoji Mozilla",
+    "Segoe UI Emoji",
+    "Noto Color Emoji",
+    "Apple Color Emoji",
+];
+
+/// Returns the files of an installed family. Nothing is installed until the index is built.
+pub(crate) fn system_font_files(family: &str) -> &'static [PathBuf] {
+    SYSTEM_FONTS
+        .get()
+        .and_then(|fonts| fonts.get(&family_key(family)))
+        .map_or(&[], |files| files.as_slice())
+}
+
+/// Normalizes a family name so lookups ignore case, spaces and punctuation: `Noto Serif`, `noto serif` and `Noto-Serif` all become `notoserif`.
+fn family_key(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn font_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    if cfg!(target_os = "windows") {
+        if let Some(windir) = std::env::var_os("WINDIR") {
+            dirs.push(PathBuf::from(windir).join("Fonts"));
+        }
+        if let Some(local) = std::env::var_os("LOCALAPPDATA") {
+            dirs.push(PathBuf::from(local).join("Microsoft/Windows/Fonts"));
+        }
+    } else if cfg!(target_os = "macos") {
+        dirs.push(PathBuf::from("/System/Library/Fonts"));
+        dirs.push(PathBuf::from("/Library/Fonts"));
+        dirs.extend(home.map(|home| home.join("Library/Fonts")));
+    } else {
+        dirs.push(PathBuf::from("/usr/share/fonts"));
+        dirs.push(PathBuf::from("/usr/local/share/fonts"));
+        if let Some(home) = home {
+            dirs.push(home.join(".local/share/fonts"));
+            dirs.push(home.join(".fonts"));
+        }
+    }
+    dirs
+}
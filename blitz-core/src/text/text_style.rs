@@ -0,0 +1,759 @@
+use std::borrow::Cow;
+
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use lightningcss::properties::font::{Font as FontShorthand, LineHeight as LineHeightProperty};
+use lightningcss::properties::overflow::TextOverflow as TextOverflowProperty;
+use lightningcss::properties::text::{
+    Spacing, TextAlign as TextAlignProperty, TextAlignLast,
+    TextDecoration as TextDecorationProperty, TextDecorationLine, TextDecorationStyle,
+    TextDecorationThickness, TextTransform as TextTransformProperty, TextTransformCase,
+};
+use lightningcss::traits::Parse;
+use lightningcss::values::color::CssColor;
+use lightningcss::values::length::{Length, LengthPercentage};
+use lightningcss::values::percentage::DimensionPercentage;
+use shipyard::Component;
+use taffy::prelude::Size;
+
+use crate::style::{resolve_font_relative, FontSize, RootFontSize, DEFAULT_FONT_SIZE};
+use crate::util::ViewportSize;
+use crate::writing_mode::Direction;
+
+#[derive(Clone, PartialEq, Debug, Component)]
+pub(crate) struct TextDecoration {
+    pub line: TextDecorationLine,
+    pub style: TextDecorationStyle,
+    pub thickness: TextDecorationThickness,
+    pub color: CssColor,
+    /// The value of `text-underline-offset`. `None` is the `auto` value.
+    pub underline_offset: Option<LengthPercentage>,
+    /// If `text-underline-position` places the underline below the descenders.
+    pub underline_under: bool,
+}
+
+impl Default for TextDecoration {
+    fn default() -> Self {
+        TextDecoration {
+            line: TextDecorationLine::empty(),
+            style: TextDecorationStyle::Solid,
+            thickness: TextDecorationThickness::Auto,
+            color: CssColor::CurrentColor,
+            underline_offset: None,
+            underline_under: false,
+        }
+    }
+}
+
+#[partial_derive_state]
+impl State for TextDecoration {
+    type ChildDependencies = ();
+    // Decorations are propagated to all of the text inside the decorating element
+    type ParentDependencies = (Self,);
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&[
+            "text-decoration",
+            "text-decoration-line",
+            "text-decoration-style",
+            "text-decoration-thickness",
+            "text-decoration-color",
+            "text-underline-offset",
+            "text-underline-position",
+        ]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let mut new = parent.map(|(parent,)| parent.clone()).unwrap_or_default();
+
+        for attr in node_view.attributes().into_iter().flatten() {
+            if let Some(as_text) = attr.value.as_text() {
+                match attr.attribute.name.as_str() {
+                    "text-decoration" => {
+                        if let Ok(decoration) = TextDecorationProperty::parse_string(as_text) {
+                            new.line |= decoration.line;
+                            new.style = decoration.style;
+                            new.thickness = decoration.thickness;
+                            new.color = decoration.color;
+                        }
+                    }
+                    "text-decoration-line" => {
+                        if let Ok(line) = TextDecorationLine::parse_string(as_text) {
+                            new.line |= line;
+                        }
+                    }
+                    "text-decoration-style" => {
+                        if let Ok(style) = TextDecorationStyle::parse_string(as_text) {
+                            new.style = style;
+                        }
+                    }
+                    "text-decoration-thickness" => {
+                        if let Ok(thickness) = TextDecorationThickness::parse_string(as_text) {
+                            new.thickness = thickness;
+                        }
+                    }
+                    "text-decoration-color" => {
+                        if let Ok(color) = CssColor::parse_string(as_text) {
+                            new.color = color;
+                        }
+                    }
+                    "text-underline-offset" => {
+                        if as_text.trim() == "auto" {
+                            new.underline_offset = None;
+                        } else if let Ok(offset) = LengthPercentage::parse_string(as_text) {
+                            new.underline_offset = Some(offset);
+                        }
+                    }
+                    "text-underline-position" => {
+                        // the left and right values only apply to vertical text
+                        new.underline_under =
+                            as_text.split_whitespace().any(|value| value == "under");
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if self != &mut new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Component)]
+pub(crate) struct TextAlign {
+    pub align: TextAlignProperty,
+    pub align_last: TextAlignLast,
+}
+
+impl Default for TextAlign {
+    fn default() -> Self {
+        TextAlign {
+            align: TextAlignProperty::Start,
+            align_last: TextAlignLast::Auto,
+        }
+    }
+}
+
+impl TextAlign {
+    /// The physical alignment of a line of text. The last line of a paragraph is aligned with `text-align-last`, and `start` and `end` are resolved to a side with the direction of the text.
+    pub fn line_alignment(&self, ends_paragraph: bool, direction: Direction) -> TextAlignProperty {
+        let alignment = if ends_paragraph {
+            self.last_line_alignment()
+        } else {
+            self.align
+        };
+        match (alignment, direction) {
+            (TextAlignProperty::Start | TextAlignProperty::MatchParent, Direction::Ltr)
+            | (TextAlignProperty::End, Direction::Rtl) => TextAlignProperty::Left,
+            (TextAlignProperty::Start | TextAlignProperty::MatchParent, Direction::Rtl)
+            | (TextAlignProperty::End, Direction::Ltr) => TextAlignProperty::Right,
+            (alignment, _) => alignment,
+        }
+    }
+
+    fn last_line_alignment(&self) -> TextAlignProperty {
+        match self.align_last {
+            TextAlignLast::Auto => match self.align {
+                TextAlignProperty::Justify => TextAlignProperty::Start,
+                TextAlignProperty::JustifyAll => TextAlignProperty::Justify,
+                align => align,
+            },
+            TextAlignLast::Start => TextAlignProperty::Start,
+            TextAlignLast::End => TextAlignProperty::End,
+            TextAlignLast::Left => TextAlignProperty::Left,
+            TextAlignLast::Right => TextAlignProperty::Right,
+            TextAlignLast::Center => TextAlignProperty::Center,
+            TextAlignLast::Justify => TextAlignProperty::Justify,
+            TextAlignLast::MatchParent => TextAlignProperty::MatchParent,
+        }
+    }
+}
+
+#[partial_derive_state]
+impl State for TextAlign {
+    type ChildDependencies = ();
+    type ParentDependencies = (Self,);
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&[
+            "text-align",
+            "text-align-last",
+        ]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let root_font_size = context
+            .get::<RootFontSize>()
+            .map_or(DEFAULT_FONT_SIZE, |root| root.size);
+        let mut new = parent.map(|(parent,)| *parent).unwrap_or_default();
+
+        for attr in node_view.attributes().into_iter().flatten() {
+            if let Some(as_text) = attr.value.as_text() {
+                match attr.attribute.name.as_str() {
+                    "text-align" => {
+                        if let Ok(align) = TextAlignProperty::parse_string(as_text) {
+                            new.align = align;
+                        }
+                    }
+                    "text-align-last" => {
+                        if let Ok(align_last) = TextAlignLast::parse_string(as_text) {
+                            new.align_last = align_last;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if self != &mut new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+/// The `line-height` of text. Numbers are inherited as numbers so they scale with the font size of the descendants. Lengths and percentages are resolved to pixels on the element that sets them.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Component)]
+pub(crate) enum LineHeight {
+    /// Lines are as tall as the ascent, descent and line gap of the font.
+    #[default]
+    Normal,
+    /// A multiple of the font size.
+    Number(f32),
+    /// A height in pixels.
+    Length(f32),
+}
+
+impl LineHeight {
+    /// Returns the height of a line in pixels, or `None` for `normal`.
+    pub fn resolve(&self, font_size: f32) -> Option<f64> {
+        match self {
+            LineHeight::Normal => None,
+            LineHeight::Number(number) => Some((number * font_size) as f64),
+            LineHeight::Length(length) => Some(*length as f64),
+        }
+    }
+
+    fn from_property(
+        property: &LineHeightProperty,
+        font_size: f32,
+        root_font_size: f32,
+        viewport_size: &Size<u32>,
+    ) -> Option<Self> {
+        match property {
+            LineHeightProperty::Normal => Some(LineHeight::Normal),
+            LineHeightProperty::Number(number) => Some(LineHeight::Number(*number)),
+            LineHeightProperty::Length(length) => {
+                resolve_font_relative(length, font_size, root_font_size, viewport_size)
+                    .map(LineHeight::Length)
+            }
+        }
+    }
+}
+
+#[partial_derive_state]
+impl State for LineHeight {
+    type ChildDependencies = ();
+    type ParentDependencies = (Self,);
+    // lengths in em and percentages are relative to the font size of the element
+    type NodeDependencies = (FontSize,);
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&["line-height", "font"]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        (font_size,): <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> bool {
+        let viewport_size = context
+            .get::<ViewportSize>()
+            .map(|viewport| viewport.0)
+            .unwrap_or(Size {
+                width: 0,
+                height: 0,
+            });
+        let root_font_size = context
+            .get::<RootFontSize>()
+            .map_or(DEFAULT_FONT_SIZE, |root| root.size);
+        let mut new = parent.map(|(parent,)| *parent).unwrap_or_default();
+        let attrs: Vec<_> = node_view.attributes().into_iter().flatten().collect();
+        let find = |name: &str| {
+            attrs
+                .iter()
+                .find(|attr| attr.attribute.name == name)
+                .and_then(|attr| attr.value.as_text())
+        };
+        // the shorthand resets the line height, and the longhand is applied after it
+        if let Some(font) = find("font").and_then(|text| FontShorthand::parse_string(text).ok()) {
+            if let Some(line_height) = LineHeight::from_property(
+                &font.line_height,
+                font_size.0,
+                root_font_size,
+                &viewport_size,
+            ) {
+                new = line_height;
+            }
+        }
+        if let Some(line_height) = find("line-height")
+            .and_then(|text| LineHeightProperty::parse_string(text).ok())
+            .and_then(|property| {
+                LineHeight::from_property(&property, font_size.0, root_font_size, &viewport_size)
+            })
+        {
+            new = line_height;
+        }
+
+        if self != &mut new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+/// The extra space between characters from `letter-spacing` and between words from `word-spacing`, in pixels. Both are inherited as pixels, so lengths in `em` are resolved against the font size of the element that sets them.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Component)]
+pub struct TextSpacing {
+    /// Added after every character.
+    pub letter: f64,
+    /// Added after every space, on top of the letter spacing.
+    pub word: f64,
+}
+
+impl TextSpacing {
+    /// Returns the spacing with extra space after every space, like the space added to justify a line.
+    pub fn with_extra_word_spacing(self, extra: f64) -> Self {
+        TextSpacing {
+            word: self.word + extra,
+            ..self
+        }
+    }
+}
+
+/// Resolves a `letter-spacing` or `word-spacing` value. `normal` is no extra space.
+fn resolve_spacing(
+    spacing: &Spacing,
+    font_size: f32,
+    root_font_size: f32,
+    viewport_size: &Size<u32>,
+) -> Option<f64> {
+    match spacing {
+        Spacing::Normal => Some(0.0),
+        Spacing::Length(Length::Value(value)) => resolve_font_relative(
+            &DimensionPercentage::Dimension(value.clone()),
+            font_size,
+            root_font_size,
+            viewport_size,
+        )
+        .map(|length| length as f64),
+        // TODO: resolve calc() once font relative units can be resolved inside it
+        Spacing::Length(Length::Calc(_)) => None,
+    }
+}
+
+#[partial_derive_state]
+impl State for TextSpacing {
+    type ChildDependencies = ();
+    type ParentDependencies = (Self,);
+    // lengths in em are relative to the font size of the element
+    type NodeDependencies = (FontSize,);
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&[
+            "letter-spacing",
+            "word-spacing",
+        ]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        (font_size,): <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> bool {
+        let viewport_size = context
+            .get::<ViewportSize>()
+            .map(|viewport| viewport.0)
+            .unwrap_or(Size {
+                width: 0,
+                height: 0,
+            });
+        let mut new = parent.map(|(parent,)| *parent).unwrap_or_default();
+
+        for attr in node_view.attributes().into_iter().flatten() {
+            let Some(spacing) = attr
+                .value
+                .as_text()
+                .and_then(|text| Spacing::parse_string(text).ok())
+                .and_then(|spacing| {
+                    resolve_spacing(&spacing, font_size.0, root_font_size, &viewport_size)
+                })
+            else {
+                continue;
+            };
+            match attr.attribute.name.as_str() {
+                "letter-spacing" => new.letter = spacing,
+                "word-spacing" => new.word = spacing,
+                _ => {}
+            }
+        }
+
+        if self != &mut new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+/// The case `text-transform` draws text in. The text in the dom is not changed.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Component)]
+pub(crate) struct TextTransform(pub TextTransformCase);
+
+impl TextTransform {
+    /// Returns the text as it is drawn. Case mapping doesn't depend on the language, and can change the length of the text like `ß` becoming `SS`.
+    pub fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match self.0 {
+            TextTransformCase::None => Cow::Borrowed(text),
+            TextTransformCase::Uppercase => Cow::Owned(text.to_uppercase()),
+            TextTransformCase::Lowercase => Cow::Owned(text.to_lowercase()),
+            TextTransformCase::Capitalize => {
+                let mut capitalized = String::with_capacity(text.len());
+                let mut in_word = false;
+                for ch in text.chars() {
+                    // apostrophes don't end a word, so "don't" stays one word
+                    let word_char = ch.is_alphanumeric() || (in_word && matches!(ch, '\'' | '’'));
+                    if word_char && !in_word {
+                        capitalized.extend(ch.to_uppercase());
+                    } else {
+                        capitalized.push(ch);
+                    }
+                    in_word = word_char;
+                }
+                Cow::Owned(capitalized)
+            }
+        }
+    }
+}
+
+#[partial_derive_state]
+impl State for TextTransform {
+    type ChildDependencies = ();
+    type ParentDependencies = (Self,);
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&["text-transform"]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let mut new = parent.map(|(parent,)| *parent).unwrap_or_default();
+        if let Some(transform) = node_view
+            .attributes()
+            .into_iter()
+            .flatten()
+            .next()
+            .and_then(|attr| attr.value.as_text())
+            .and_then(|text| TextTransformProperty::parse_string(text).ok())
+        {
+            new = TextTransform(transform.case);
+        }
+
+        if self != &mut new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+/// How spaces and line breaks in text are drawn, from `white-space`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Component)]
+pub(crate) enum WhiteSpace {
+    /// Runs of whitespace collapse into one space and lines wrap.
+    #[default]
+    Normal,
+    /// Whitespace collapses like `normal` but lines never wrap.
+    Nowrap,
+    /// Whitespace and line breaks are kept and lines never wrap.
+    Pre,
+    /// Whitespace and line breaks are kept and lines wrap. `break-spaces` is drawn like it, and it is always used for `contenteditable` elements.
+    PreWrap,
+    /// Runs of spaces collapse but line breaks are kept, and lines wrap.
+    PreLine,
+}
+
+impl WhiteSpace {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "normal" => Some(WhiteSpace::Normal),
+            "nowrap" => Some(WhiteSpace::Nowrap),
+            "pre" => Some(WhiteSpace::Pre),
+            "pre-wrap" | "break-spaces" => Some(WhiteSpace::PreWrap),
+            "pre-line" => Some(WhiteSpace::PreLine),
+            _ => None,
+        }
+    }
+
+    /// The default of elements that keep their whitespace, from the default stylesheet.
+    fn from_tag(tag: &str) -> Option<Self> {
+        match tag {
+            "pre" | "listing" | "xmp" => Some(WhiteSpace::Pre),
+            "textarea" => Some(WhiteSpace::PreWrap),
+            _ => None,
+        }
+    }
+
+    /// Returns the text with its whitespace collapsed. Line breaks that are kept end a line when the text is laid out.
+    pub fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let collapses = |ch: char| match self {
+            WhiteSpace::Normal | WhiteSpace::Nowrap => ch.is_ascii_whitespace(),
+            WhiteSpace::PreLine => matches!(ch, ' ' | '\t'),
+            WhiteSpace::Pre | WhiteSpace::PreWrap => false,
+        };
+        if !text.chars().any(collapses) {
+            return Cow::Borrowed(text);
+        }
+        let mut collapsed = String::with_capacity(text.len());
+        for ch in text.chars() {
+            if collapses(ch) {
+                // spaces before a kept line break are removed, like spaces after it
+                if !collapsed.ends_with([' ', '\n']) {
+                    collapsed.push(' ');
+                }
+            } else {
+                if ch == '\n' && collapsed.ends_with(' ') {
+                    collapsed.pop();
+                }
+                collapsed.push(ch);
+            }
+        }
+        Cow::Owned(collapsed)
+    }
+
+    /// If lines wrap at the width of the content box of the parent.
+    pub fn wraps(&self) -> bool {
+        matches!(
+            self,
+            WhiteSpace::Normal | WhiteSpace::PreWrap | WhiteSpace::PreLine
+        )
+    }
+}
+
+#[partial_derive_state]
+impl State for WhiteSpace {
+    type ChildDependencies = ();
+    type ParentDependencies = (Self,);
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new()
+            .with_tag()
+            .with_attrs(AttributeMaskBuilder::Some(&[
+                "white-space",
+                "contenteditable",
+            ]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let mut white_space = None;
+        let mut editable = false;
+        for attr in node_view.attributes().into_iter().flatten() {
+            match attr.attribute.name.as_str() {
+                "white-space" => white_space = attr.value.as_text().and_then(WhiteSpace::parse),
+                "contenteditable" => {
+                    editable =
+                        attr.value.as_bool() != Some(false) && attr.value.as_text() != Some("false")
+                }
+                _ => {}
+            }
+        }
+        // editable text keeps every typed space and line break, so the caret can be placed after each character
+        let new = if editable {
+            WhiteSpace::PreWrap
+        } else {
+            white_space
+                .or_else(|| node_view.tag().and_then(WhiteSpace::from_tag))
+                .unwrap_or_else(|| parent.map(|(parent,)| *parent).unwrap_or_default())
+        };
+
+        if *self != new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+/// How text that overflows a block that clips its content ends, from `text-overflow`. It isn't inherited, text nodes use the value of their parent.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Component)]
+pub(crate) enum TextOverflow {
+    /// The text is cut off at the edge of the content box.
+    #[default]
+    Clip,
+    /// The characters that don't fit are replaced with `…`.
+    Ellipsis,
+}
+
+#[partial_derive_state]
+impl State for TextOverflow {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&["text-overflow"]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let new = match node_view
+            .attributes()
+            .into_iter()
+            .flatten()
+            .next()
+            .and_then(|attr| attr.value.as_text())
+            .and_then(|text| TextOverflowProperty::parse_string(text).ok())
+        {
+            Some(TextOverflowProperty::Ellipsis) => TextOverflow::Ellipsis,
+            _ => TextOverflow::Clip,
+        };
+
+        if self != &new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
@@ -0,0 +1,533 @@
+//! Editing the text of `<input>`, `<textarea>` and `contenteditable` elements.
+//!
+//! The focused field takes the text of key presses, moves its caret with the arrow keys, Home and End, and deletes with Backspace and Delete. Holding Shift while moving the caret selects text, and typing or deleting replaces the selection. Each edit writes the new text back to the dom and sends an `input` event with it, so the app sees the change like on the web: inputs and textareas store it in their `value` attribute, which is drawn as a generated box like the placeholder, and `contenteditable` elements store it in their first text child. An empty `contenteditable` element has no text child to store it in, so it only shows the text once the app renders it from the event.
+//!
+//! The caret blinks while the field is focused. IME composition is not supported yet, committed IME text is only sent as composition events. The caret of `contenteditable` elements is placed on the text as it is drawn, after `text-transform`, wrapping and `text-align`. Their whitespace is kept like `white-space: pre-wrap`, so every typed space moves the caret.
+
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+use dioxus_html::input_data::keyboard_types::Modifiers;
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use rustc_hash::FxHashSet;
+use shipyard::Component;
+use taffy::Taffy;
+use tao::{event::KeyEvent, keyboard::Key};
+use vello::kurbo::{Affine, Point, Rect, Vec2};
+use vello::peniko::{Color, Fill};
+use vello::SceneBuilder;
+
+use crate::application::DirtyNodes;
+use crate::focus::Focused;
+use crate::generated_content::GeneratedContent;
+use crate::layout::TaffyLayout;
+use crate::render::align_line;
+use crate::selection::SELECTION_COLOR;
+use crate::style::{FontFamily, FontSize, ForgroundColor};
+use crate::text::text_style::{LineHeight, TextAlign, TextSpacing, TextTransform, WhiteSpace};
+use crate::text::TextContext;
+use crate::util::{attribute, translate_color};
+use crate::writing_mode::{Direction, WritingMode};
+
+/// How long the caret stays visible or hidden while it blinks.
+const BLINK_INTERVAL: Duration = Duration::from_millis(530);
+
+/// The width of the caret.
+const CARET_WIDTH: f64 = 1.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum FieldKind {
+    /// An `<input>` that takes text. Its text is the `value` attribute.
+    Input,
+    /// A `<textarea>`. Its text is the `value` attribute and Enter starts a new line.
+    TextArea,
+    /// An element with `contenteditable`. Its text is its first text child and Enter starts a new line.
+    ContentEditable,
+}
+
+/// Returns true if an `<input>` with this `type` takes text.
+pub(crate) fn is_text_input_type(input_type: Option<&str>) -> bool {
+    matches!(
+        input_type.map(str::trim),
+        None | Some("text" | "search" | "email" | "url" | "tel" | "password" | "number")
+    )
+}
+
+/// If an element can be edited, from its tag and its `type`, `contenteditable`, `disabled` and `readonly` attributes.
+#[derive(Clone, PartialEq, Debug, Default, Component)]
+pub(crate) struct Editable {
+    /// `None` if the element is not a text field.
+    pub kind: Option<FieldKind>,
+    pub disabled: bool,
+    pub read_only: bool,
+}
+
+impl Editable {
+    /// Returns true if the user can change the text of the field.
+    pub fn is_editable(&self) -> bool {
+        self.kind.is_some() && !self.disabled && !self.read_only
+    }
+}
+
+#[partial_derive_state]
+impl State for Editable {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new()
+            .with_tag()
+            .with_attrs(AttributeMaskBuilder::Some(&[
+                "type",
+                "contenteditable",
+                "disabled",
+                "readonly",
+            ]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let mut new = Editable::default();
+        let mut input_type = None;
+        let mut content_editable = false;
+        for attr in node_view.attributes().into_iter().flatten() {
+            // boolean attributes are set by being present, dioxus sets them to "false" to remove them
+            let set = attr.value.as_bool() != Some(false) && attr.value.as_text() != Some("false");
+            match attr.attribute.name.as_str() {
+                "type" => input_type = attr.value.as_text().map(str::to_string),
+                "contenteditable" => content_editable = set,
+                "disabled" => new.disabled = set,
+                "readonly" => new.read_only = set,
+                _ => {}
+            }
+        }
+        new.kind = match node_view.tag() {
+            Some("input") if is_text_input_type(input_type.as_deref()) => Some(FieldKind::Input),
+            Some("textarea") => Some(FieldKind::TextArea),
+            Some(_) if content_editable => Some(FieldKind::ContentEditable),
+            _ => None,
+        };
+
+        if *self != new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+/// The caret and selection of a text field, as the number of characters before them. It is set on a field the first time it is focused.
+#[derive(Clone, Copy, PartialEq, Debug, Component)]
+pub(crate) struct Caret {
+    pub position: usize,
+    /// The other end of the selection. Nothing is selected when it is at the caret.
+    pub anchor: usize,
+    /// When the caret last moved. It is visible for a blink interval after every move.
+    pub moved: Instant,
+}
+
+impl Caret {
+    fn at(position: usize) -> Self {
+        Caret {
+            position,
+            anchor: position,
+            moved: Instant::now(),
+        }
+    }
+
+    /// The selected characters.
+    pub fn selection(&self) -> Range<usize> {
+        self.position.min(self.anchor)..self.position.max(self.anchor)
+    }
+
+    /// Returns true while the blinking caret is shown.
+    pub fn is_visible(&self) -> bool {
+        (self.moved.elapsed().as_millis() / BLINK_INTERVAL.as_millis()) % 2 == 0
+    }
+
+    /// Keeps the caret inside text that was changed by the app.
+    fn clamp(self, len: usize) -> Self {
+        Caret {
+            position: self.position.min(len),
+            anchor: self.anchor.min(len),
+            moved: self.moved,
+        }
+    }
+}
+
+/// Returns the text of a field.
+pub(crate) fn field_text(node: NodeRef, kind: FieldKind) -> String {
+    match kind {
+        FieldKind::Input | FieldKind::TextArea => attribute(node, "value").unwrap_or_default(),
+        FieldKind::ContentEditable => first_text_child(node)
+            .and_then(|child| match &*node.real_dom().get(child)?.node_type() {
+                NodeType::Text(TextNode { text, .. }) => Some(text.clone()),
+                _ => None,
+            })
+            .unwrap_or_default(),
+    }
+}
+
+/// Returns the text selected in a field. The text of password inputs can't be copied.
+pub(crate) fn selected_field_text(node: NodeRef) -> Option<String> {
+    let kind = node.get::<Editable>()?.kind?;
+    let selection = node.get::<Caret>()?.selection();
+    let password = kind == FieldKind::Input
+        && attribute(node, "type").is_some_and(|input_type| input_type.trim() == "password");
+    if selection.is_empty() || password {
+        return None;
+    }
+    let text = field_text(node, kind);
+    Some(text[byte_index(&text, selection.start)..byte_index(&text, selection.end)].to_string())
+}
+
+/// Writes the text of a field back to the dom. A `contenteditable` element without text is left for the app to fill.
+fn set_field_text(rdom: &mut RealDom, id: NodeId, kind: FieldKind, text: String) {
+    match kind {
+        FieldKind::Input | FieldKind::TextArea => {
+            let mut node = rdom.get_mut(id).unwrap();
+            if let NodeTypeMut::Element(mut element) = node.node_type_mut() {
+                element.set_attribute("value".to_string(), text);
+            }
+        }
+        // nodes created here would be unknown to the virtual dom, so the app adds the first text from the `input` event
+        FieldKind::ContentEditable => {
+            if let Some(child) = first_text_child(rdom.get(id).unwrap()) {
+                let mut child = rdom.get_mut(child).unwrap();
+                if let NodeTypeMut::Text(mut child_text) = child.node_type_mut() {
+                    *child_text = text;
+                }
+            }
+        }
+    }
+}
+
+fn first_text_child(node: NodeRef) -> Option<NodeId> {
+    node.children()
+        .into_iter()
+        .find(|child| matches!(&*child.node_type(), NodeType::Text(_)))
+        .map(|child| child.id())
+}
+
+/// Returns the index of the byte after `chars` characters.
+fn byte_index(text: &str, chars: usize) -> usize {
+    text.char_indices()
+        .nth(chars)
+        .map_or(text.len(), |(index, _)| index)
+}
+
+/// Tracks the carets of the text fields and edits the focused field.
+///
+/// Like [`crate::mouse::PseudoClassState`], the caret is inserted directly on the field and only the field is marked dirty.
+#[derive(Default)]
+pub(crate) struct EditingState {
+    /// The field the caret was last drawn in and if it was visible, to redraw the field when the caret blinks.
+    shown: Option<(NodeId, bool)>,
+    dirty: FxHashSet<NodeId>,
+}
+
+impl EditingState {
+    /// Applies a key press to a field. Returns the new text of the field if it changed, which is sent with an `input` event.
+    pub fn key(
+        &mut self,
+        rdom: &mut RealDom,
+        id: NodeId,
+        event: &KeyEvent,
+        modifiers: Modifiers,
+    ) -> Option<String> {
+        let node = rdom.get(id)?;
+        let editable = (*node.get::<Editable>()?).clone();
+        let kind = editable.kind.filter(|_| editable.is_editable())?;
+        let mut text = field_text(node, kind);
+        let len = text.chars().count();
+        let mut caret = node
+            .get::<Caret>()
+            .map_or(Caret::at(len), |caret| caret.clamp(len));
+        let selection = caret.selection();
+        let extend = modifiers.contains(Modifiers::SHIFT);
+        let shortcut =
+            modifiers.contains(Modifiers::CONTROL) || modifiers.contains(Modifiers::META);
+
+        // the characters to remove and the text to insert in their place
+        let mut edit: Option<(Range<usize>, String)> = None;
+        let mut move_to = None;
+        match &event.logical_key {
+            Key::ArrowLeft if !extend && !selection.is_empty() => move_to = Some(selection.start),
+            Key::ArrowRight if !extend && !selection.is_empty() => move_to = Some(selection.end),
+            Key::ArrowLeft => move_to = Some(caret.position.saturating_sub(1)),
+            Key::ArrowRight => move_to = Some((caret.position + 1).min(len)),
+            Key::Home => move_to = Some(0),
+            Key::End => move_to = Some(len),
+            Key::Backspace if selection.is_empty() => {
+                if caret.position > 0 {
+                    edit = Some((caret.position - 1..caret.position, String::new()));
+                }
+            }
+            Key::Delete if selection.is_empty() => {
+                if caret.position < len {
+                    edit = Some((caret.position..caret.position + 1, String::new()));
+                }
+            }
+            Key::Backspace | Key::Delete => edit = Some((selection, String::new())),
+            Key::Enter if kind != FieldKind::Input => edit = Some((selection, "\n".to_string())),
+            _ => {
+                let typed = event
+                    .text
+                    .filter(|typed| !shortcut && !typed.chars().any(char::is_control))?;
+                edit = Some((selection, typed.to_string()));
+            }
+        }
+
+        if let Some(position) = move_to {
+            caret.position = position;
+            if !extend {
+                caret.anchor = position;
+            }
+        }
+        let changed = edit.map(|(range, inserted)| {
+            let start = byte_index(&text, range.start);
+            let end = byte_index(&text, range.end);
+            text.replace_range(start..end, &inserted);
+            caret = Caret::at(range.start + inserted.chars().count());
+            set_field_text(rdom, id, kind, text.clone());
+            text
+        });
+        caret.moved = Instant::now();
+        rdom.get_mut(id).unwrap().insert(caret);
+        self.dirty.insert(id);
+        changed
+    }
+
+    /// Puts the caret at the end of the focused field the first time it is focused and redraws the field when the caret blinks. Returns when the caret blinks next so the event loop can wake up for it.
+    pub fn blink(&mut self, rdom: &mut RealDom, focused: Option<NodeId>) -> Option<Instant> {
+        let field = focused.and_then(|id| {
+            let node = rdom.get(id)?;
+            let kind = node.get::<Editable>()?.kind?;
+            Some((id, kind, node.get::<Caret>().map(|caret| *caret)))
+        });
+        let Some((id, kind, caret)) = field else {
+            self.shown = None;
+            return None;
+        };
+        let caret = match caret {
+            Some(caret) => caret,
+            None => {
+                let len = field_text(rdom.get(id).unwrap(), kind).chars().count();
+                let caret = Caret::at(len);
+                rdom.get_mut(id).unwrap().insert(caret);
+                caret
+            }
+        };
+        let shown = Some((id, caret.is_visible()));
+        if self.shown != shown {
+            self.shown = shown;
+            self.dirty.insert(id);
+        }
+        let blinks = caret.moved.elapsed().as_millis() / BLINK_INTERVAL.as_millis();
+        Some(caret.moved + BLINK_INTERVAL * (blinks as u32 + 1))
+    }
+
+    pub fn clean(&mut self) -> DirtyNodes {
+        DirtyNodes::Some(std::mem::take(&mut self.dirty))
+    }
+}
+
+/// Draws the selection and the blinking caret of a focused text field over its text. `content_pos` is where the content of the field is placed and `content_box` is the content box, where the caret of an empty field goes.
+pub(crate) fn render_caret(
+    taffy: &Taffy,
+    node: NodeRef,
+    generated: &GeneratedContent,
+    content_pos: Point,
+    content_box: Rect,
+    text_context: &mut TextContext,
+    scene_builder: &mut SceneBuilder,
+) {
+    let Some(kind) = node.get::<Editable>().and_then(|editable| editable.kind) else {
+        return;
+    };
+    let focused = node.get::<Focused>().is_some_and(|focused| focused.0);
+    let Some(caret) = node.get::<Caret>().map(|caret| *caret).filter(|_| focused) else {
+        return;
+    };
+    // the text is drawn in the value box of inputs as it is, and in the text child of contenteditable elements like other text
+    let drawn = match kind {
+        FieldKind::Input | FieldKind::TextArea => generated.value.as_ref().map(|value| {
+            let location = taffy.layout(value.node).unwrap().location;
+            DrawnText::unchanged(
+                content_pos + Vec2::new(location.x as f64, location.y as f64),
+                value.text.clone(),
+            )
+        }),
+        FieldKind::ContentEditable => first_text_child(node).and_then(|child| {
+            let child = node.real_dom().get(child)?;
+            let location = taffy
+                .layout(child.get::<TaffyLayout>()?.node?)
+                .unwrap()
+                .location;
+            let NodeType::Text(TextNode { text, .. }) = &*child.node_type() else {
+                return None;
+            };
+            let transform = *child.get::<TextTransform>()?;
+            let white_space = *child.get::<WhiteSpace>()?;
+            let direction = child.get::<WritingMode>()?.direction;
+            Some(DrawnText {
+                origin: content_pos + Vec2::new(location.x as f64, location.y as f64),
+                text: white_space.apply(&transform.apply(text)).into_owned(),
+                raw: text.clone(),
+                transform,
+                white_space,
+                align: Some((*child.get::<TextAlign>()?, direction)),
+            })
+        }),
+    };
+    let drawn = drawn.unwrap_or_else(|| DrawnText::unchanged(content_box.origin(), String::new()));
+    let caret = caret.clamp(drawn.raw.chars().count());
+
+    let font_size = node.get::<FontSize>().unwrap().0;
+    let font_family = node.get::<FontFamily>().unwrap();
+    let fonts = font_family.resolve(text_context);
+    let features = &font_family.features;
+    let line_height = node.get::<LineHeight>().unwrap().resolve(font_size);
+    let spacing = *node.get::<TextSpacing>().unwrap();
+    let content_x = (content_box.x0, content_box.width());
+    let wrap_width = drawn.white_space.wraps().then_some(content_box.width());
+    let text_layout = text_context.layout(
+        &fonts,
+        features,
+        font_size,
+        &drawn.text,
+        line_height,
+        spacing,
+        wrap_width,
+    );
+    // where each line starts in the drawn text, where it is drawn and the x positions of its character boundaries
+    let mut line_start = 0;
+    let lines: Vec<(usize, f64, Vec<f64>)> = text_layout
+        .lines
+        .iter()
+        .map(|line| {
+            let (x, word_spacing) = match drawn.align {
+                Some((text_align, direction)) => {
+                    let alignment = text_align.line_alignment(line.ends_paragraph, direction);
+                    align_line(line, alignment, drawn.origin.x, content_x)
+                }
+                None => (drawn.origin.x, 0.0),
+            };
+            let offsets = text_context.caret_offsets(
+                &fonts,
+                features,
+                font_size,
+                &line.text,
+                spacing.with_extra_word_spacing(word_spacing),
+            );
+            let start = line_start;
+            line_start += line.text.chars().count() + 1;
+            (start, x, offsets)
+        })
+        .collect();
+    let line_top = |index: usize| drawn.origin.y + index as f64 * text_layout.line_height;
+
+    let selection = caret.selection();
+    if !selection.is_empty() {
+        let start = drawn.offset(selection.start);
+        let end = drawn.offset(selection.end);
+        // the selection is highlighted line by line
+        for (index, (line_start, x, offsets)) in lines.iter().enumerate() {
+            let line_end = line_start + offsets.len() - 1;
+            let from = start.max(*line_start);
+            let to = end.min(line_end);
+            let selects_break = (start..end).contains(&line_end);
+            if from < to || selects_break {
+                // a selected line break is shown as a little space at the end of the line
+                let end_x = if selects_break {
+                    x + offsets[to - line_start] + font_size as f64 / 4.0
+                } else {
+                    x + offsets[to - line_start]
+                };
+                let top = line_top(index);
+                let rect = Rect::new(
+                    x + offsets[from - line_start],
+                    top,
+                    end_x,
+                    top + text_layout.line_height,
+                );
+                scene_builder.fill(
+                    Fill::NonZero,
+                    Affine::IDENTITY,
+                    SELECTION_COLOR,
+                    None,
+                    &rect,
+                );
+            }
+        }
+    }
+
+    if caret.is_visible() {
+        let position = drawn.offset(caret.position);
+        let index = lines
+            .iter()
+            .rposition(|(line_start, ..)| *line_start <= position)
+            .unwrap_or(0);
+        let (line_start, x, offsets) = &lines[index];
+        let x = x + offsets[(position - line_start).min(offsets.len() - 1)];
+        let top = line_top(index);
+        let color: Color = translate_color(&node.get::<ForgroundColor>().unwrap().0);
+        let rect = Rect::new(x, top, x + CARET_WIDTH, top + text_layout.line_height);
+        scene_builder.fill(Fill::NonZero, Affine::IDENTITY, color, None, &rect);
+    }
+}
+
+/// The text of a field as it is drawn, with the text it was made from.
+struct DrawnText {
+    /// Where the first line is drawn.
+    origin: Point,
+    text: String,
+    raw: String,
+    transform: TextTransform,
+    white_space: WhiteSpace,
+    /// The alignment of the lines and the direction of the text, `None` for the value box which is drawn at its start.
+    align: Option<(TextAlign, Direction)>,
+}
+
+impl DrawnText {
+    /// Text that is drawn as it is on one line.
+    fn unchanged(origin: Point, text: String) -> Self {
+        DrawnText {
+            origin,
+            raw: text.clone(),
+            text,
+            transform: TextTransform::default(),
+            white_space: WhiteSpace::Pre,
+            align: None,
+        }
+    }
+
+    /// Converts the number of characters before a position in the raw text to the drawn text, whose case mapping can change its length.
+    fn offset(&self, chars: usize) -> usize {
+        let prefix = &self.raw[..byte_index(&self.raw, chars)];
+        self.white_space
+            .apply(&self.transform.apply(prefix))
+            .chars()
+            .count()
+    }
+}
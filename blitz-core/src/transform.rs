@@ -0,0 +1,259 @@
+//! The `transform` and `transform-origin` properties.
+//!
+//! Transforms don't change the layout. The translation of the whole transform moves the element and its descendants like relative positioning, so it is also used to find where elements are for hit testing. The rest of the transform, like rotations and scales, is applied around the `transform-origin` when the element is painted and isn't taken into account by hit testing yet. Percentages resolve against the size of the element on the same axis.
+
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use lightningcss::properties::transform::{Transform as TransformFunction, TransformList};
+use lightningcss::traits::Parse;
+use lightningcss::values::length::LengthPercentage;
+use lightningcss::values::percentage::NumberOrPercentage;
+use lightningcss::values::position::{
+    HorizontalPositionKeyword, Position, PositionComponent, VerticalPositionKeyword,
+};
+use shipyard::Component;
+use taffy::prelude::{Layout, Size};
+use vello::kurbo::{Affine, Point, Vec2};
+
+use crate::util::{Axis, Resolve, ResolveContext};
+
+#[derive(Clone, PartialEq, Debug, Component)]
+pub(crate) struct Transform {
+    pub functions: Vec<TransformFunction>,
+    /// The point the transform is applied around, the center of the element by default.
+    pub origin: Position,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform {
+            functions: Vec::new(),
+            origin: Position::center(),
+        }
+    }
+}
+
+impl Transform {
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty()
+    }
+
+    /// Returns the transform functions composed into one matrix. `size` is the size of the element.
+    pub fn matrix(&self, size: &Size<f32>, context: &ResolveContext) -> Affine {
+        let x = |length: &LengthPercentage| length.resolve(Axis::X, size, context);
+        let y = |length: &LengthPercentage| length.resolve(Axis::Y, size, context);
+        // the functions apply from right to left, so each one is multiplied on the right
+        self.functions
+            .iter()
+            .map(|function| match function {
+                TransformFunction::Translate(tx, ty)
+                | TransformFunction::Translate3d(tx, ty, _) => {
+                    Affine::translate(Vec2::new(x(tx), y(ty)))
+                }
+                TransformFunction::TranslateX(tx) => Affine::translate(Vec2::new(x(tx), 0.0)),
+                TransformFunction::TranslateY(ty) => Affine::translate(Vec2::new(0.0, y(ty))),
+                TransformFunction::Scale(sx, sy) | TransformFunction::Scale3d(sx, sy, _) => {
+                    Affine::scale_non_uniform(number(sx), number(sy))
+                }
+                TransformFunction::ScaleX(sx) => Affine::scale_non_uniform(number(sx), 1.0),
+                TransformFunction::ScaleY(sy) => Affine::scale_non_uniform(1.0, number(sy)),
+                TransformFunction::Rotate(angle) | TransformFunction::RotateZ(angle) => {
+                    Affine::rotate(angle.to_radians() as f64)
+                }
+                TransformFunction::Skew(ax, ay) => Affine::skew(
+                    (ax.to_radians() as f64).tan(),
+                    (ay.to_radians() as f64).tan(),
+                ),
+                TransformFunction::SkewX(ax) => Affine::skew((ax.to_radians() as f64).tan(), 0.0),
+                TransformFunction::SkewY(ay) => Affine::skew(0.0, (ay.to_radians() as f64).tan()),
+                TransformFunction::Matrix(m) => Affine::new(
+                    [m.a, m.b, m.c, m.d, m.e, m.f].map(|coefficient| coefficient as f64),
+                ),
+                // 3d transforms other than translations and scales can't be drawn in 2d
+                _ => Affine::IDENTITY,
+            })
+            .fold(Affine::IDENTITY, |total, function| total * function)
+    }
+
+    /// Returns how far the element is moved by the transform.
+    pub fn translation(&self, size: &Size<f32>, context: &ResolveContext) -> Vec2 {
+        self.matrix(size, context).translation()
+    }
+
+    /// Returns the part of the transform that isn't a translation, applied around the transform origin. `pos` is where the element is placed after its translation.
+    ///
+    /// Returns `None` if the transform only translates the element.
+    pub fn paint_transform(
+        &self,
+        pos: Point,
+        size: &Size<f32>,
+        context: &ResolveContext,
+    ) -> Option<Affine> {
+        let linear = self.matrix(size, context).with_translation(Vec2::ZERO);
+        if linear == Affine::IDENTITY {
+            return None;
+        }
+        let origin = pos.to_vec2() + self.origin_offset(size, context);
+        Some(Affine::translate(origin) * linear * Affine::translate(-origin))
+    }
+
+    /// Returns the transform origin relative to the top left corner of the element.
+    fn origin_offset(&self, size: &Size<f32>, context: &ResolveContext) -> Vec2 {
+        let width = size.width as f64;
+        let height = size.height as f64;
+        let x = match &self.origin.x {
+            PositionComponent::Center => width / 2.0,
+            PositionComponent::Length(length) => length.resolve(Axis::X, size, context),
+            PositionComponent::Side { side, offset } => {
+                let offset = offset
+                    .as_ref()
+                    .map_or(0.0, |offset| offset.resolve(Axis::X, size, context));
+                match side {
+                    HorizontalPositionKeyword::Left => offset,
+                    HorizontalPositionKeyword::Right => width - offset,
+                }
+            }
+        };
+        let y = match &self.origin.y {
+            PositionComponent::Center => height / 2.0,
+            PositionComponent::Length(length) => length.resolve(Axis::Y, size, context),
+            PositionComponent::Side { side, offset } => {
+                let offset = offset
+                    .as_ref()
+                    .map_or(0.0, |offset| offset.resolve(Axis::Y, size, context));
+                match side {
+                    VerticalPositionKeyword::Top => offset,
+                    VerticalPositionKeyword::Bottom => height - offset,
+                }
+            }
+        };
+        Vec2::new(x, y)
+    }
+}
+
+fn number(value: &NumberOrPercentage) -> f64 {
+    let number: f32 = value.into();
+    number as f64
+}
+
+/// Returns how far the element is translated by its `transform`.
+pub(crate) fn translation(node: NodeRef, layout: &Layout, viewport_size: &Size<u32>) -> Vec2 {
+    node.get::<Transform>()
+        .map(|transform| {
+            transform.translation(&layout.size, &ResolveContext::of(node, viewport_size))
+        })
+        .unwrap_or_default()
+}
+
+/// Returns the rotation, scale or skew of an element around its transform origin. `pos` is where the element is placed.
+pub(crate) fn paint_transform(
+    node: NodeRef,
+    layout: &Layout,
+    pos: Point,
+    viewport_size: &Size<u32>,
+) -> Option<Affine> {
+    node.get::<Transform>().and_then(|transform| {
+        let context = ResolveContext::of(node, viewport_size);
+        transform.paint_transform(pos, &layout.size, &context)
+    })
+}
+
+#[partial_derive_state]
+impl State for Transform {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&[
+            "transform",
+            "transform-origin",
+        ]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let mut new = Transform::default();
+        for attr in node_view.attributes().into_iter().flatten() {
+            if let Some(as_text) = attr.value.as_text() {
+                match attr.attribute.name.as_str() {
+                    "transform" => {
+                        if let Ok(list) = TransformList::parse_string(as_text) {
+                            new.functions = list.0;
+                        }
+                    }
+                    "transform-origin" => {
+                        if let Ok(origin) = Position::parse_string(as_text) {
+                            new.origin = origin;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if *self != new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::FontUnits;
+
+    const CONTEXT: ResolveContext = ResolveContext {
+        viewport_size: Size {
+            width: 800,
+            height: 600,
+        },
+        font_size: 16.0,
+        root_font_size: 16.0,
+        font_units: FontUnits { ch: 8.0, ex: 8.0 },
+    };
+
+    fn transform(value: &str) -> Transform {
+        Transform {
+            functions: TransformList::parse_string(value).unwrap().0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn percentages_translate_by_the_size_on_each_axis() {
+        let size = Size {
+            width: 200.0,
+            height: 100.0,
+        };
+        assert_eq!(
+            transform("translate(50%, 25%)").translation(&size, &CONTEXT),
+            Vec2::new(100.0, 25.0)
+        );
+        // the element is centered on the point it was placed at
+        let pos = Point::new(400.0, 300.0);
+        let moved = pos + transform("translate(-50%, -50%)").translation(&size, &CONTEXT);
+        let center = moved + Vec2::new(size.width as f64 / 2.0, size.height as f64 / 2.0);
+        assert_eq!(center, pos);
+    }
+}
@@ -1,12 +1,17 @@
+use dioxus_native_core::prelude::*;
 use lightningcss::properties::border::BorderSideWidth;
 use lightningcss::values;
 use taffy::prelude::Size;
-use values::calc::{Calc, MathFunction};
+use values::calc::{Calc, MathFunction, RoundingStrategy};
 use values::color::CssColor;
 use values::length::{Length, LengthValue};
 use values::percentage::DimensionPercentage;
 use vello::peniko::Color;
 
+use crate::layout::TaffyLayout;
+use crate::style::{root_font_size, FontSize, DEFAULT_FONT_SIZE};
+use crate::text::FontUnits;
+
 #[allow(dead_code)]
 #[derive(Clone, Copy)]
 pub(crate) enum Axis {
@@ -18,82 +23,195 @@ pub(crate) enum Axis {
     Max,
 }
 
+/// The size of the window. It is passed to state passes that resolve viewport units.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct ViewportSize(pub Size<u32>);
+
+/// What a length is resolved against besides the box it is in: the window for viewport units and the fonts of the element for font relative units.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct ResolveContext {
+    pub viewport_size: Size<u32>,
+    /// The font size of the element, which `em` is relative to.
+    pub font_size: f32,
+    /// The font size of the root element, which `rem` is relative to.
+    pub root_font_size: f32,
+    /// The size of `ch` and `ex` in the font of the element.
+    pub font_units: FontUnits,
+}
+
+impl ResolveContext {
+    /// The context of an element whose font size has been computed by the state passes and whose font has been measured by the layout pass. Fonts that haven't been measured use the approximate units.
+    pub fn of(node: NodeRef, viewport_size: &Size<u32>) -> Self {
+        let font_size = node
+            .get::<FontSize>()
+            .map_or(DEFAULT_FONT_SIZE, |font_size| font_size.0);
+        ResolveContext {
+            viewport_size: *viewport_size,
+            font_size,
+            root_font_size: root_font_size(node.real_dom()),
+            font_units: node
+                .get::<TaffyLayout>()
+                .and_then(|layout| layout.font_units)
+                .unwrap_or_else(|| FontUnits::approximate(font_size)),
+        }
+    }
+}
+
+/// Returns true if the node is an element with the given tag.
+pub(crate) fn has_tag(node: NodeRef, tag: &str) -> bool {
+    matches!(&*node.node_type(), NodeType::Element(element) if element.tag == tag)
+}
+
+/// Returns the text value of an attribute of an element.
+pub(crate) fn attribute(node: NodeRef, name: &str) -> Option<String> {
+    let NodeType::Element(element) = &*node.node_type() else {
+        return None;
+    };
+    element
+        .attributes
+        .iter()
+        .find(|(attribute, _)| attribute.name == name)
+        .and_then(|(_, value)| value.as_text().map(str::to_string))
+}
+
+/// Converts a CSS color to sRGB. Colors in other spaces like `hsl()`, `lab()` or `color()` are converted by lightningcss. Colors that can't be converted are drawn black instead of stopping the renderer.
 pub(crate) fn translate_color(color: &CssColor) -> Color {
-    let rgb = color.to_rgb();
-    if let CssColor::RGBA(rgba) = rgb {
-        Color::rgba(
+    // `currentColor` has no value of its own
+    let rgb = match color {
+        CssColor::CurrentColor => None,
+        CssColor::RGBA(rgba) => Some(*rgba),
+        _ => match color.to_rgb() {
+            CssColor::RGBA(rgba) => Some(rgba),
+            _ => None,
+        },
+    };
+    match rgb {
+        Some(rgba) => Color::rgba(
             rgba.red as f64 / 255.0,
             rgba.green as f64 / 255.0,
             rgba.blue as f64 / 255.0,
             rgba.alpha as f64 / 255.0,
-        )
-    } else {
-        panic!("translation failed");
+        ),
+        None => Color::BLACK,
+    }
+}
+
+/// Converts a color where `currentColor` is the computed `color` of the element.
+pub(crate) fn translate_current_color(color: &CssColor, current_color: &CssColor) -> Color {
+    match color {
+        CssColor::CurrentColor => translate_color(current_color),
+        color => translate_color(color),
     }
 }
 
 pub(crate) trait Resolve {
-    fn resolve(&self, axis: Axis, rect: &Size<f32>, viewport_size: &Size<u32>) -> f64;
+    fn resolve(&self, axis: Axis, rect: &Size<f32>, context: &ResolveContext) -> f64;
 }
 
 impl<T: Resolve> Resolve for Calc<T> {
-    fn resolve(&self, axis: Axis, rect: &Size<f32>, viewport_size: &Size<u32>) -> f64 {
+    fn resolve(&self, axis: Axis, rect: &Size<f32>, context: &ResolveContext) -> f64 {
         match self {
-            values::calc::Calc::Value(v) => v.resolve(axis, rect, viewport_size),
+            values::calc::Calc::Value(v) => v.resolve(axis, rect, context),
             values::calc::Calc::Number(px) => *px as f64,
             values::calc::Calc::Sum(v1, v2) => {
-                v1.resolve(axis, rect, viewport_size) + v2.resolve(axis, rect, viewport_size)
-            }
-            values::calc::Calc::Product(v1, v2) => {
-                *v1 as f64 * v2.resolve(axis, rect, viewport_size)
+                v1.resolve(axis, rect, context) + v2.resolve(axis, rect, context)
             }
-            values::calc::Calc::Function(f) => f.resolve(axis, rect, viewport_size),
+            values::calc::Calc::Product(v1, v2) => *v1 as f64 * v2.resolve(axis, rect, context),
+            values::calc::Calc::Function(f) => f.resolve(axis, rect, context),
         }
     }
 }
 
 impl<T: Resolve> Resolve for MathFunction<T> {
-    fn resolve(&self, axis: Axis, rect: &Size<f32>, viewport_size: &Size<u32>) -> f64 {
-        match self {
-            values::calc::MathFunction::Calc(c) => c.resolve(axis, rect, viewport_size),
-            values::calc::MathFunction::Min(v) => v
-                .iter()
-                .map(|v| v.resolve(axis, rect, viewport_size))
-                .min_by(|f1, f2| f1.partial_cmp(f2).unwrap())
-                .unwrap(),
-            values::calc::MathFunction::Max(v) => v
-                .iter()
-                .map(|v| v.resolve(axis, rect, viewport_size))
-                .max_by(|f1, f2| f1.partial_cmp(f2).unwrap())
-                .unwrap(),
-            values::calc::MathFunction::Clamp(min, val, max) => min
-                .resolve(axis, rect, viewport_size)
-                .max(val.resolve(axis, rect, viewport_size).min(max.resolve(
-                    axis,
-                    rect,
-                    viewport_size,
-                ))),
-            _ => todo!(),
+    fn resolve(&self, axis: Axis, rect: &Size<f32>, context: &ResolveContext) -> f64 {
+        let resolve = |calc: &Calc<T>| calc.resolve(axis, rect, context);
+        let value = match self {
+            MathFunction::Calc(c) => resolve(c),
+            MathFunction::Min(v) => v.iter().map(resolve).fold(f64::INFINITY, f64::min),
+            MathFunction::Max(v) => v.iter().map(resolve).fold(f64::NEG_INFINITY, f64::max),
+            MathFunction::Clamp(min, val, max) => resolve(min).max(resolve(val).min(resolve(max))),
+            MathFunction::Round(strategy, value, interval) => {
+                round(*strategy, resolve(value), resolve(interval))
+            }
+            // the remainder has the sign of the dividend and the modulus has the sign of the divisor
+            MathFunction::Rem(dividend, divisor) => resolve(dividend) % resolve(divisor),
+            MathFunction::Mod(dividend, divisor) => {
+                let divisor = resolve(divisor);
+                ((resolve(dividend) % divisor) + divisor) % divisor
+            }
+            MathFunction::Abs(v) => resolve(v).abs(),
+            MathFunction::Sign(v) => {
+                let v = resolve(v);
+                if v == 0.0 {
+                    0.0
+                } else {
+                    v.signum()
+                }
+            }
+            MathFunction::Hypot(v) => v.iter().map(resolve).map(|v| v * v).sum::<f64>().sqrt(),
+        };
+        // a division by zero makes the value NaN or infinite, which can't be laid out
+        if value.is_finite() {
+            value
+        } else {
+            0.0
         }
     }
 }
 
+/// Rounds a value to a multiple of the interval like `round()`. Values halfway between two multiples are rounded up.
+fn round(strategy: RoundingStrategy, value: f64, interval: f64) -> f64 {
+    if interval == 0.0 {
+        return f64::NAN;
+    }
+    let multiple = value / interval;
+    let rounded = match strategy {
+        RoundingStrategy::Nearest => (multiple + 0.5).floor(),
+        RoundingStrategy::Up => multiple.ceil(),
+        RoundingStrategy::Down => multiple.floor(),
+        RoundingStrategy::ToZero => multiple.trunc(),
+    };
+    rounded * interval
+}
+
 impl Resolve for BorderSideWidth {
-    fn resolve(&self, axis: Axis, rect: &Size<f32>, viewport_size: &Size<u32>) -> f64 {
+    fn resolve(&self, axis: Axis, rect: &Size<f32>, context: &ResolveContext) -> f64 {
         match self {
             BorderSideWidth::Thin => 2.0,
             BorderSideWidth::Medium => 4.0,
             BorderSideWidth::Thick => 6.0,
-            BorderSideWidth::Length(l) => l.resolve(axis, rect, viewport_size),
+            BorderSideWidth::Length(l) => l.resolve(axis, rect, context),
         }
     }
 }
 
+/// The number of css pixels in an inch. Physical units are defined relative to it, not to the resolution of the screen.
+pub(crate) const PX_PER_IN: f64 = 96.0;
+
+/// Converts an absolute length to css pixels. Returns `None` for units that depend on the font or the viewport.
+pub(crate) fn absolute_length_px(length: &LengthValue) -> Option<f64> {
+    use values::length::LengthValue::*;
+    match length {
+        Px(px) => Some(*px as f64),
+        In(inches) => Some(*inches as f64 * PX_PER_IN),
+        Cm(cm) => Some(*cm as f64 * PX_PER_IN / 2.54),
+        Mm(mm) => Some(*mm as f64 * PX_PER_IN / 25.4),
+        // a quarter of a millimeter
+        Q(q) => Some(*q as f64 * PX_PER_IN / 101.6),
+        Pt(pt) => Some(*pt as f64 * PX_PER_IN / 72.0),
+        Pc(pc) => Some(*pc as f64 * PX_PER_IN / 6.0),
+        _ => None,
+    }
+}
+
 impl Resolve for LengthValue {
-    fn resolve(&self, _axis: Axis, _rect: &Size<f32>, viewport_size: &Size<u32>) -> f64 {
+    fn resolve(&self, _axis: Axis, _rect: &Size<f32>, context: &ResolveContext) -> f64 {
         use values::length::LengthValue::*;
+        if let Some(px) = absolute_length_px(self) {
+            return px;
+        }
+        let viewport_size = &context.viewport_size;
         match self {
-            Px(px) => *px as f64,
             Vw(vw) => *vw as f64 * viewport_size.width as f64 / 100.0,
             Vh(vh) => *vh as f64 * viewport_size.height as f64 / 100.0,
             Vmin(vmin) => {
@@ -102,31 +220,35 @@ impl Resolve for LengthValue {
             Vmax(vmax) => {
                 *vmax as f64 * viewport_size.height.max(viewport_size.width) as f64 / 100.0
             }
-            _ => todo!(),
+            Em(em) => *em as f64 * context.font_size as f64,
+            Rem(rem) => *rem as f64 * context.root_font_size as f64,
+            Ch(ch) => (*ch * context.font_units.ch) as f64,
+            Ex(ex) => (*ex * context.font_units.ex) as f64,
+            _ => 0.0,
         }
     }
 }
 
 impl Resolve for Length {
-    fn resolve(&self, axis: Axis, rect: &Size<f32>, viewport_size: &Size<u32>) -> f64 {
+    fn resolve(&self, axis: Axis, rect: &Size<f32>, context: &ResolveContext) -> f64 {
         match self {
-            Length::Value(l) => l.resolve(axis, rect, viewport_size),
-            Length::Calc(c) => c.resolve(axis, rect, viewport_size),
+            Length::Value(l) => l.resolve(axis, rect, context),
+            Length::Calc(c) => c.resolve(axis, rect, context),
         }
     }
 }
 
 impl<T: Resolve> Resolve for DimensionPercentage<T> {
-    fn resolve(&self, axis: Axis, rect: &Size<f32>, viewport_size: &Size<u32>) -> f64 {
+    fn resolve(&self, axis: Axis, rect: &Size<f32>, context: &ResolveContext) -> f64 {
         match self {
-            DimensionPercentage::Dimension(v) => v.resolve(axis, rect, viewport_size),
+            DimensionPercentage::Dimension(v) => v.resolve(axis, rect, context),
             DimensionPercentage::Percentage(p) => match axis {
                 Axis::X => (rect.width * p.0).into(),
                 Axis::Y => (rect.height * p.0).into(),
                 Axis::Min => (rect.width.min(rect.height) * p.0).into(),
                 Axis::Max => (rect.width.max(rect.height) * p.0).into(),
             },
-            DimensionPercentage::Calc(c) => c.resolve(axis, rect, viewport_size),
+            DimensionPercentage::Calc(c) => c.resolve(axis, rect, context),
         }
     }
 }
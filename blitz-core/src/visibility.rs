@@ -0,0 +1,87 @@
+//! The `visibility` property.
+//!
+//! Hidden elements keep their place in the layout but are not painted and can't be hit by the mouse or focused with Tab. `visibility` is inherited, so the descendants of a hidden element are hidden too unless they set `visibility: visible` themselves, in which case they are painted as usual. `collapse` removes table rows and row groups from the layout like `display: none`, but the cells in them still count towards the widths of the columns. On other elements it is the same as `hidden`.
+
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use shipyard::Component;
+
+use crate::table::TableRole;
+
+/// The `visibility` of a node.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Component)]
+pub(crate) enum Visibility {
+    #[default]
+    Visible,
+    Hidden,
+    Collapse,
+}
+
+impl Visibility {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "visible" => Some(Visibility::Visible),
+            "hidden" => Some(Visibility::Hidden),
+            "collapse" => Some(Visibility::Collapse),
+            _ => None,
+        }
+    }
+
+    /// If a table box with this visibility is removed from the layout.
+    pub fn collapses(&self, role: TableRole) -> bool {
+        *self == Visibility::Collapse && matches!(role, TableRole::Row | TableRole::RowGroup)
+    }
+}
+
+#[partial_derive_state]
+impl State for Visibility {
+    type ChildDependencies = ();
+    type ParentDependencies = (Self,);
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&["visibility"]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let new = node_view
+            .attributes()
+            .into_iter()
+            .flatten()
+            .next()
+            .and_then(|attr| attr.value.as_text())
+            .and_then(Visibility::parse)
+            .unwrap_or_else(|| parent.map(|(parent,)| *parent).unwrap_or_default());
+
+        if *self != new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+/// Returns true if the node is painted and can be interacted with.
+pub(crate) fn is_visible(node: NodeRef) -> bool {
+    node.get::<Visibility>()
+        .is_none_or(|visibility| *visibility == Visibility::Visible)
+}
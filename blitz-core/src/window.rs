@@ -0,0 +1,178 @@
+use std::sync::Arc;
+
+use ::image::RgbaImage;
+use dioxus_native_core::prelude::NodeId;
+use tao::event_loop::EventLoopProxy;
+use tokio::sync::{
+    mpsc::{unbounded_channel, UnboundedSender},
+    oneshot,
+};
+
+use crate::text::{FontFile, FontSource};
+use crate::{Redraw, ScrollAlignment, StyleWarning};
+
+/// A handle to the window the app is rendered in. It can be cloned and used from any thread.
+#[derive(Clone)]
+pub struct WindowHandle {
+    /// `None` for documents rendered without a window.
+    proxy: Option<EventLoopProxy<Redraw>>,
+    commands: UnboundedSender<WindowCommand>,
+}
+
+pub(crate) enum WindowCommand {
+    Screenshot(oneshot::Sender<RgbaImage>),
+    Print(PageSize, oneshot::Sender<Vec<RgbaImage>>),
+    Pause,
+    Resume,
+    ScrollIntoView(NodeId, ScrollAlignment),
+    PushFocusTrap(NodeId),
+    PopFocusTrap,
+    SetPointerCapture(NodeId),
+    ReleasePointerCapture,
+    StyleWarnings(oneshot::Sender<Vec<StyleWarning>>),
+    HitTest(f64, f64, oneshot::Sender<Option<NodeId>>),
+    RegisterFont(FontFile),
+    Focus(NodeId),
+}
+
+/// The size of a printed page in pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PageSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl PageSize {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+
+    /// An A4 page (210mm by 297mm) at the given dots per inch.
+    pub fn a4(dpi: f32) -> Self {
+        let dots_per_mm = dpi / 25.4;
+        Self {
+            width: (210.0 * dots_per_mm).round() as u32,
+            height: (297.0 * dots_per_mm).round() as u32,
+        }
+    }
+}
+
+impl WindowHandle {
+    pub(crate) fn new(
+        proxy: EventLoopProxy<Redraw>,
+        commands: UnboundedSender<WindowCommand>,
+    ) -> Self {
+        Self {
+            proxy: Some(proxy),
+            commands,
+        }
+    }
+
+    /// A handle for a document rendered without a window. Commands are ignored, so screenshots and the other methods that wait for the window return `None`.
+    pub(crate) fn headless() -> Self {
+        let (commands, _) = unbounded_channel();
+        Self {
+            proxy: None,
+            commands,
+        }
+    }
+
+    /// Capture the current contents of the window.
+    ///
+    /// The frame is rendered to an offscreen texture and read back without blocking the event loop. Returns `None` if the frame couldn't be rendered or the window was closed before the capture finished.
+    pub async fn screenshot(&self) -> Option<RgbaImage> {
+        let (sender, receiver) = oneshot::channel();
+        self.send(WindowCommand::Screenshot(sender));
+        receiver.await.ok()
+    }
+
+    /// Render the document into pages of the given size, one image per page.
+    ///
+    /// Pages are split at the page height and at forced breaks from `break-before: page` and `break-after: page`. Returns `None` if a page couldn't be rendered or the window was closed before the pages were rendered.
+    pub async fn print(&self, page_size: PageSize) -> Option<Vec<RgbaImage>> {
+        let (sender, receiver) = oneshot::channel();
+        self.send(WindowCommand::Print(page_size, sender));
+        receiver.await.ok()
+    }
+
+    /// Stop rendering and processing input until [`Self::resume`] is called. The dom is still updated in the background, but nothing is painted.
+    ///
+    /// Input received while paused is dropped or queued depending on [`Config::with_paused_input`](crate::Config::with_paused_input).
+    pub fn pause(&self) {
+        self.send(WindowCommand::Pause);
+    }
+
+    /// Resume rendering after [`Self::pause`]. Queued input is processed before the next frame.
+    pub fn resume(&self) {
+        self.send(WindowCommand::Resume);
+    }
+
+    /// Scroll the containers around a node so it is in view, aligned in each container like `scrollIntoView` on the web. Containers with `scroll-behavior: smooth` animate to the new position.
+    pub fn scroll_into_view(&self, node: NodeId, alignment: ScrollAlignment) {
+        self.send(WindowCommand::ScrollIntoView(node, alignment));
+    }
+
+    /// Keep focus inside a container, like an open modal. Tab navigation cycles through the focusable elements in the container and clicking elements outside of it doesn't move focus there. If focus is outside of the container it moves to the first focusable element inside.
+    ///
+    /// Traps nest: only the last one pushed is active until it is popped.
+    pub fn push_focus_trap(&self, container: NodeId) {
+        self.send(WindowCommand::PushFocusTrap(container));
+    }
+
+    /// Remove the last focus trap pushed with [`Self::push_focus_trap`]. Focus goes back to the element that had it when the trap was pushed.
+    pub fn pop_focus_trap(&self) {
+        self.send(WindowCommand::PopFocusTrap);
+    }
+
+    /// Send mouse moves and button events to a node while a button is held, even when the pointer leaves it, like `setPointerCapture` on the web. This keeps drags on sliders and resize handles working when the pointer moves faster than the element.
+    ///
+    /// The capture ends when every button is released or [`Self::release_pointer_capture`] is called. It is ignored if no button is held when it arrives.
+    pub fn set_pointer_capture(&self, node: NodeId) {
+        self.send(WindowCommand::SetPointerCapture(node));
+    }
+
+    /// End the capture started with [`Self::set_pointer_capture`]. Mouse events go to the element under the pointer again.
+    pub fn release_pointer_capture(&self) {
+        self.send(WindowCommand::ReleasePointerCapture);
+    }
+
+    /// Register a font for a `font-family` name from its data while the app is running, like a font the app downloaded. It replaces the face of the family with the same weight and style. Text that uses the family is measured and drawn again with it.
+    pub fn register_font(&self, family: impl Into<String>, bytes: impl Into<Vec<u8>>) {
+        self.send(WindowCommand::RegisterFont(FontFile::new(
+            family.into(),
+            FontSource::Bytes(Arc::new(bytes.into())),
+        )));
+    }
+
+    /// Move the focus to an element, like `focus()` on the web. It is ignored if the element can't be focused or is outside of the active focus trap.
+    pub fn focus(&self, node: NodeId) {
+        self.send(WindowCommand::Focus(node));
+    }
+
+    /// Get the style attributes that are ignored because their value is invalid or the property doesn't exist, in document order.
+    ///
+    /// The list is always empty unless [`Config::with_style_diagnostics`](crate::Config::with_style_diagnostics) is on. Returns `None` if the window was closed.
+    pub async fn style_warnings(&self) -> Option<Vec<StyleWarning>> {
+        let (sender, receiver) = oneshot::channel();
+        self.send(WindowCommand::StyleWarnings(sender));
+        receiver.await.ok()
+    }
+
+    /// Find the topmost element at a point in the window, the element a click there would land on. Elements stacked with `z-index`, transforms, scrolling and overflow clips are taken into account.
+    ///
+    /// Returns `None` if there is no element at the point or the window was closed.
+    pub async fn hit_test(&self, x: f64, y: f64) -> Option<NodeId> {
+        let (sender, receiver) = oneshot::channel();
+        self.send(WindowCommand::HitTest(x, y, sender));
+        receiver.await.ok().flatten()
+    }
+
+    fn send(&self, command: WindowCommand) {
+        if self.commands.send(command).is_ok() {
+            // wake up the event loop so the command is handled
+            if let Some(proxy) = &self.proxy {
+                let _ = proxy.send_event(Redraw);
+            }
+        }
+    }
+}
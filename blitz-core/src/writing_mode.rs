@@ -0,0 +1,186 @@
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use shipyard::Component;
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub(crate) enum Direction {
+    #[default]
+    Ltr,
+    Rtl,
+}
+
+/// How the text of an element is reordered, set by `unicode-bidi`. Each text node is reordered on its own, so `embed` and `isolate` behave like `normal` and `isolate-override` like `bidi-override`.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub(crate) enum UnicodeBidi {
+    #[default]
+    Normal,
+    /// Every character is drawn in the direction of the element.
+    Override,
+    /// The direction of each line is taken from its first strong character.
+    Plaintext,
+}
+
+/// The direction blocks are stacked in, set by the `writing-mode` property.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub(crate) enum BlockFlow {
+    #[default]
+    HorizontalTb,
+    VerticalRl,
+    VerticalLr,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum PhysicalSide {
+    Top,
+    Right,
+    Bottom,
+    Left,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum PhysicalCorner {
+    TopLeft,
+    TopRight,
+    BottomRight,
+    BottomLeft,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum LogicalEdge {
+    Start,
+    End,
+}
+
+/// The writing mode and direction of an element. This is used to map logical properties (like `margin-inline-start` or `border-start-end-radius`) to physical sides and corners.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Component)]
+pub(crate) struct WritingMode {
+    pub direction: Direction,
+    pub block_flow: BlockFlow,
+    /// Not inherited by child elements, text nodes use the value of their element.
+    pub unicode_bidi: UnicodeBidi,
+}
+
+impl WritingMode {
+    pub fn block_side(&self, edge: LogicalEdge) -> PhysicalSide {
+        let start = match self.block_flow {
+            BlockFlow::HorizontalTb => PhysicalSide::Top,
+            BlockFlow::VerticalRl => PhysicalSide::Right,
+            BlockFlow::VerticalLr => PhysicalSide::Left,
+        };
+        match edge {
+            LogicalEdge::Start => start,
+            LogicalEdge::End => start.opposite(),
+        }
+    }
+
+    pub fn inline_side(&self, edge: LogicalEdge) -> PhysicalSide {
+        let start = match (self.block_flow, self.direction) {
+            (BlockFlow::HorizontalTb, Direction::Ltr) => PhysicalSide::Left,
+            (BlockFlow::HorizontalTb, Direction::Rtl) => PhysicalSide::Right,
+            (_, Direction::Ltr) => PhysicalSide::Top,
+            (_, Direction::Rtl) => PhysicalSide::Bottom,
+        };
+        match edge {
+            LogicalEdge::Start => start,
+            LogicalEdge::End => start.opposite(),
+        }
+    }
+
+    /// Maps a logical corner to a physical corner. Logical corners are named block edge first, so `border-start-end-radius` is `corner(Start, End)`.
+    pub fn corner(&self, block: LogicalEdge, inline: LogicalEdge) -> PhysicalCorner {
+        match (self.block_side(block), self.inline_side(inline)) {
+            (PhysicalSide::Top, PhysicalSide::Left) | (PhysicalSide::Left, PhysicalSide::Top) => {
+                PhysicalCorner::TopLeft
+            }
+            (PhysicalSide::Top, PhysicalSide::Right) | (PhysicalSide::Right, PhysicalSide::Top) => {
+                PhysicalCorner::TopRight
+            }
+            (PhysicalSide::Bottom, PhysicalSide::Right)
+            | (PhysicalSide::Right, PhysicalSide::Bottom) => PhysicalCorner::BottomRight,
+            (PhysicalSide::Bottom, PhysicalSide::Left)
+            | (PhysicalSide::Left, PhysicalSide::Bottom) => PhysicalCorner::BottomLeft,
+            // the block and inline axes are always perpendicular
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl PhysicalSide {
+    fn opposite(self) -> Self {
+        match self {
+            PhysicalSide::Top => PhysicalSide::Bottom,
+            PhysicalSide::Right => PhysicalSide::Left,
+            PhysicalSide::Bottom => PhysicalSide::Top,
+            PhysicalSide::Left => PhysicalSide::Right,
+        }
+    }
+}
+
+#[partial_derive_state]
+impl State for WritingMode {
+    type ChildDependencies = ();
+    type ParentDependencies = (Self,);
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new()
+            .with_tag()
+            .with_attrs(AttributeMaskBuilder::Some(&[
+                "direction",
+                "writing-mode",
+                "unicode-bidi",
+            ]));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: &SendAnyMap,
+    ) -> bool {
+        let mut new = parent.map(|(parent,)| *parent).unwrap_or_default();
+        if node_view.tag().is_some() {
+            new.unicode_bidi = UnicodeBidi::Normal;
+        }
+
+        for attr in node_view.attributes().into_iter().flatten() {
+            if let Some(as_text) = attr.value.as_text() {
+                match (attr.attribute.name.as_str(), as_text.trim()) {
+                    ("direction", "ltr") => new.direction = Direction::Ltr,
+                    ("direction", "rtl") => new.direction = Direction::Rtl,
+                    ("writing-mode", "horizontal-tb") => new.block_flow = BlockFlow::HorizontalTb,
+                    ("writing-mode", "vertical-rl") => new.block_flow = BlockFlow::VerticalRl,
+                    ("writing-mode", "vertical-lr") => new.block_flow = BlockFlow::VerticalLr,
+                    ("unicode-bidi", "normal" | "embed" | "isolate") => {
+                        new.unicode_bidi = UnicodeBidi::Normal
+                    }
+                    ("unicode-bidi", "bidi-override" | "isolate-override") => {
+                        new.unicode_bidi = UnicodeBidi::Override
+                    }
+                    ("unicode-bidi", "plaintext") => new.unicode_bidi = UnicodeBidi::Plaintext,
+                    _ => {}
+                }
+            }
+        }
+
+        if self != &mut new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
@@ -1,11 +1,18 @@
+use std::cell::RefCell;
 use std::ops::Deref;
-use std::sync::Arc;
+use std::rc::Rc;
+use std::sync::{Arc, RwLock};
 
-use dioxus::core::{Component, VirtualDom};
+use dioxus::core::{Component, ScopeState, VirtualDom};
+use dioxus::prelude::use_state;
 use dioxus_native_core::prelude::*;
 
 use blitz_core::EventData;
-use blitz_core::{render, Config, Driver};
+use blitz_core::{
+    render, render_to_image, ComputedLayout, ContainerQuery, Driver, RgbaImage, WindowHandle,
+};
+
+pub use blitz_core::Config;
 
 pub async fn launch(app: Component<()>) {
     launch_cfg(app, Config::default()).await
@@ -21,34 +28,135 @@ pub async fn launch_cfg_with_props<Props: 'static + Send>(
     cfg: Config,
 ) {
     render(
-        move |rdom, _| {
-            let mut vdom = VirtualDom::new_with_props(app, props);
-            let muts = vdom.rebuild();
-            let mut rdom = rdom.write().unwrap();
-            let mut dioxus_state = DioxusState::create(&mut rdom);
-            dioxus_state.apply_mutations(&mut rdom, muts);
-            DioxusRenderer {
-                vdom,
-                dioxus_state,
-                #[cfg(all(feature = "hot-reload", debug_assertions))]
-                hot_reload_rx: {
-                    let (hot_reload_tx, hot_reload_rx) =
-                        tokio::sync::mpsc::unbounded_channel::<dioxus_hot_reload::HotReloadMsg>();
-                    dioxus_hot_reload::connect(move |msg| {
-                        let _ = hot_reload_tx.send(msg);
-                    });
-                    hot_reload_rx
-                },
-            }
-        },
+        move |rdom, _, handle| create_renderer(app, props, rdom, handle, true),
         cfg,
     )
     .await;
 }
 
+/// Render the first frame of an app to an image without opening a window. The same app at the same size always renders the same image, so it can be compared against a saved snapshot.
+///
+/// Returns `None` if no GPU is available.
+pub async fn render_app_to_image(app: Component<()>, width: u32, height: u32) -> Option<RgbaImage> {
+    render_to_image(
+        move |rdom, _, handle| create_renderer(app, (), rdom, handle, false),
+        width,
+        height,
+        Config::default(),
+    )
+    .await
+}
+
+/// Build the virtual dom of an app and apply its first render to the real dom. Hot reloading is only connected for apps shown in a window.
+fn create_renderer<Props: 'static>(
+    app: Component<Props>,
+    props: Props,
+    rdom: &RwLock<RealDom>,
+    handle: &WindowHandle,
+    #[allow(unused_variables)] hot_reload: bool,
+) -> DioxusRenderer {
+    let mut vdom = VirtualDom::new_with_props(app, props);
+    vdom.base_scope().provide_context(handle.clone());
+    let layout_callbacks = LayoutCallbacks::default();
+    vdom.base_scope().provide_context(layout_callbacks.clone());
+    let muts = vdom.rebuild();
+    let mut rdom = rdom.write().unwrap();
+    let mut dioxus_state = DioxusState::create(&mut rdom);
+    dioxus_state.apply_mutations(&mut rdom, muts);
+    DioxusRenderer {
+        vdom,
+        dioxus_state,
+        layout_callbacks,
+        #[cfg(all(feature = "hot-reload", debug_assertions))]
+        hot_reload_rx: {
+            let (hot_reload_tx, hot_reload_rx) =
+                tokio::sync::mpsc::unbounded_channel::<dioxus_hot_reload::HotReloadMsg>();
+            if hot_reload {
+                dioxus_hot_reload::connect(move |msg| {
+                    let _ = hot_reload_tx.send(msg);
+                });
+            }
+            hot_reload_rx
+        },
+    }
+}
+
+/// Get a handle to the window the app is rendered in.
+pub fn use_window(cx: &ScopeState) -> &WindowHandle {
+    cx.use_hook(|| cx.consume_context::<WindowHandle>())
+        .as_ref()
+        .expect("use_window must be called inside of a blitz app")
+}
+
+type LayoutCallback = Box<dyn FnMut(&ComputedLayout)>;
+
+/// The callbacks registered with [`use_after_layout`], one slot for each component that registered one.
+#[derive(Clone, Default)]
+struct LayoutCallbacks(Rc<RefCell<Vec<Rc<RefCell<LayoutCallback>>>>>);
+
+/// Removes the slot of the callback when the component that registered it is dropped.
+struct LayoutCallbackHandle {
+    callbacks: LayoutCallbacks,
+    callback: Rc<RefCell<LayoutCallback>>,
+}
+
+impl Drop for LayoutCallbackHandle {
+    fn drop(&mut self) {
+        self.callbacks
+            .0
+            .borrow_mut()
+            .retain(|callback| !Rc::ptr_eq(callback, &self.callback));
+    }
+}
+
+/// Run a callback every time the layout is computed, before the frame is painted.
+///
+/// The callback can read the position and size of elements and update state. Any changes are rendered in the same frame, which makes it possible to position elements relative to each other without a frame of lag. The callback passed in the latest render is the one that runs.
+pub fn use_after_layout(cx: &ScopeState, callback: impl FnMut(&ComputedLayout) + 'static) {
+    let handle = cx.use_hook(|| {
+        let callbacks = cx
+            .consume_context::<LayoutCallbacks>()
+            .expect("use_after_layout must be called inside of a blitz app");
+        let callback: Rc<RefCell<LayoutCallback>> = Rc::new(RefCell::new(Box::new(|_| {})));
+        callbacks.0.borrow_mut().push(callback.clone());
+        LayoutCallbackHandle {
+            callbacks,
+            callback,
+        }
+    });
+    // the closure of this render sees the current props and state
+    *handle.callback.borrow_mut() = Box::new(callback);
+}
+
+/// Evaluate a container query like `(min-width: 400px)` for the element with the `id`. The query is checked against the nearest ancestor with a `container-type` every time the layout changes.
+///
+/// Returns false until the first layout and if the query can't be parsed.
+pub fn use_container_query(cx: &ScopeState, id: &str, query: &str) -> bool {
+    let matches = use_state(cx, || false);
+    // the query is only parsed again when it changes
+    let parsed = cx.use_hook(|| (query.to_string(), ContainerQuery::parse(query)));
+    if parsed.0 != query {
+        *parsed = (query.to_string(), ContainerQuery::parse(query));
+    }
+    let query = parsed.1.clone();
+    let id = id.to_string();
+    let setter = matches.clone();
+    use_after_layout(cx, move |layout| {
+        let new = query
+            .as_ref()
+            .map(|query| layout.query_container(&id, query))
+            .unwrap_or(false);
+        if *setter.current() != new {
+            setter.set(new);
+        }
+    });
+    **matches
+}
+
 struct DioxusRenderer {
     vdom: VirtualDom,
     dioxus_state: DioxusState,
+    layout_callbacks: LayoutCallbacks,
     #[cfg(all(feature = "hot-reload", debug_assertions))]
     hot_reload_rx: tokio::sync::mpsc::UnboundedReceiver<dioxus_hot_reload::HotReloadMsg>,
 }
@@ -73,6 +181,14 @@ impl Driver for DioxusRenderer {
         }
     }
 
+    fn after_layout(&mut self, layout: &ComputedLayout) {
+        // the list is copied so callbacks can't conflict with components that register or drop theirs
+        let callbacks = self.layout_callbacks.0.borrow().clone();
+        for callback in callbacks {
+            (callback.borrow_mut())(layout);
+        }
+    }
+
     fn poll_async(&mut self) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + '_>> {
         #[cfg(all(feature = "hot-reload", debug_assertions))]
         return Box::pin(async {